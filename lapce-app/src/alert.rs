@@ -37,6 +37,8 @@ pub struct AlertBoxData {
     pub title: RwSignal<String>,
     pub msg: RwSignal<String>,
     pub buttons: RwSignal<Vec<AlertButton>>,
+    /// Run when the alert is dismissed without picking a button
+    pub on_cancel: RwSignal<Option<Rc<dyn Fn()>>>,
     pub config: ReadSignal<Arc<LapceConfig>>,
 }
 
@@ -47,9 +49,18 @@ pub fn new(cx: Scope, common: Rc<CommonData>) -> Self {
             title: cx.create_rw_signal("".to_string()),
             msg: cx.create_rw_signal("".to_string()),
             buttons: cx.create_rw_signal(Vec::new()),
+            on_cancel: cx.create_rw_signal(None),
             config: common.config,
         }
     }
+
+    /// Run the cancel action of the shown alert, if it has one
+    pub fn run_cancel(&self) {
+        if let Some(on_cancel) = self.on_cancel.get_untracked() {
+            self.on_cancel.set(None);
+            on_cancel();
+        }
+    }
 }
 
 pub fn alert_box(alert_data: AlertBoxData) -> impl View {
@@ -58,6 +69,7 @@ pub fn alert_box(alert_data: AlertBoxData) -> impl View {
     let title = alert_data.title;
     let msg = alert_data.msg;
     let buttons = alert_data.buttons;
+    let cancel_data = alert_data.clone();
     let button_id = AtomicU64::new(0);
 
     container({
@@ -117,6 +129,7 @@ pub fn alert_box(alert_data: AlertBoxData) -> impl View {
                 .style(|s| s.flex_col().width_pct(100.0).margin_top(10.0)),
                 label(|| "Cancel".to_string())
                     .on_click_stop(move |_| {
+                        cancel_data.run_cancel();
                         active.set(false);
                     })
                     .style(move |s| {
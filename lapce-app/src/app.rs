@@ -66,7 +66,7 @@
 use tracing_subscriber::{filter::Targets, reload::Handle};
 
 use crate::{
-    about, alert,
+    about, alert, breakpoint,
     code_action::CodeActionStatus,
     command::{
         CommandKind, InternalCommand, LapceCommand, LapceWorkbenchCommand,
@@ -307,8 +307,9 @@ fn create_windows(
 
         // Split user input into known existing directors and
         // file paths that exist or not
-        let (dirs, files): (Vec<&PathObject>, Vec<&PathObject>) =
-            paths.iter().partition(|p| p.is_dir);
+        let (dirs, files): (Vec<&PathObject>, Vec<&PathObject>) = paths
+            .iter()
+            .partition(|p| p.is_dir || LapceWorkspace::is_workspace_file(&p.path));
 
         let files: Vec<PathObject> = files.into_iter().cloned().collect();
         let mut files = if files.is_empty() { None } else { Some(files) };
@@ -344,11 +345,10 @@ fn create_windows(
                     maximised: false,
                     tabs: TabsInfo {
                         active_tab: 0,
-                        workspaces: vec![LapceWorkspace {
-                            kind: workspace_type,
-                            path: Some(dir.path.to_owned()),
-                            last_open: 0,
-                        }],
+                        workspaces: vec![LapceWorkspace::from_path(
+                            workspace_type,
+                            dir.path.to_owned(),
+                        )],
                     },
                 };
 
@@ -1226,6 +1226,7 @@ fn editor_tab_content(
     let workspace = common.workspace.clone();
     let editors = main_split.editors;
     let diff_editors = main_split.diff_editors;
+    let last_language = main_split.last_language.read_only();
     let config = common.config;
     let focus = common.focus;
     let items = move || {
@@ -1284,6 +1285,7 @@ fn editor_tab_content(
                 });
                 if let Some(diff_editor_data) = diff_editor_data {
                     let focus_right = diff_editor_data.focus_right;
+                    let focus_result = diff_editor_data.focus_result;
                     let diff_editor_tab_id = diff_editor_data.editor_tab_id;
                     let diff_editor_scope = diff_editor_data.scope;
                     let is_active = move |tracked: bool| {
@@ -1308,6 +1310,22 @@ fn editor_tab_content(
                             false
                         }
                     };
+                    // Whether the pane on the `right` or the `result` pane has
+                    // the focus
+                    let has_focus =
+                        move |tracked: bool, right: bool, result: bool| {
+                            let (focus_right, focus_result) = if tracked {
+                                (focus_right.get(), focus_result.get())
+                            } else {
+                                (
+                                    focus_right.get_untracked(),
+                                    focus_result.get_untracked(),
+                                )
+                            };
+                            is_active(tracked)
+                                && focus_result == result
+                                && (result || focus_right == right)
+                        };
                     let left_viewport = diff_editor_data.left.viewport();
                     let left_scroll_to = diff_editor_data.left.scroll_to();
                     let right_viewport = diff_editor_data.right.viewport();
@@ -1330,25 +1348,19 @@ fn editor_tab_content(
                         create_rw_signal(diff_editor_data.left.clone());
                     let right_editor =
                         create_rw_signal(diff_editor_data.right.clone());
-                    stack((
+                    let sides = stack((
                         container(
                             editor_container_view(
                                 window_tab_data.clone(),
                                 workspace.clone(),
-                                move |track| {
-                                    is_active(track)
-                                        && if track {
-                                            !focus_right.get()
-                                        } else {
-                                            !focus_right.get_untracked()
-                                        }
-                                },
+                                move |track| has_focus(track, false, false),
                                 left_editor,
                             )
                             .debug_name("Left Editor"),
                         )
                         .on_event_cont(EventListener::PointerDown, move |_| {
                             focus_right.set(false);
+                            focus_result.set(false);
                         })
                         .style(move |s| {
                             s.height_full()
@@ -1363,20 +1375,14 @@ fn editor_tab_content(
                             editor_container_view(
                                 window_tab_data.clone(),
                                 workspace.clone(),
-                                move |track| {
-                                    is_active(track)
-                                        && if track {
-                                            focus_right.get()
-                                        } else {
-                                            focus_right.get_untracked()
-                                        }
-                                },
+                                move |track| has_focus(track, true, false),
                                 right_editor,
                             )
                             .debug_name("Right Editor"),
                         )
                         .on_event_cont(EventListener::PointerDown, move |_| {
                             focus_right.set(true);
+                            focus_result.set(false);
                         })
                         .style(|s| s.height_full().flex_grow(1.0).flex_basis(0.0)),
                         diff_show_more_section_view(
@@ -1384,17 +1390,53 @@ fn editor_tab_content(
                             &diff_editor_data.right,
                         ),
                     ))
-                    .style(|s: Style| s.size_full())
-                    .on_cleanup(move || {
-                        diff_editor_scope.dispose();
-                    })
-                    .into_any()
+                    .style(|s| s.width_full().flex_grow(1.0).flex_basis(0.0));
+                    // A merge editor shows the file being merged below the
+                    // current and incoming versions
+                    let view = if let Some(result) = diff_editor_data.result.clone()
+                    {
+                        let result_editor = create_rw_signal(result);
+                        stack((
+                            sides,
+                            container(
+                                editor_container_view(
+                                    window_tab_data.clone(),
+                                    workspace.clone(),
+                                    move |track| has_focus(track, false, true),
+                                    result_editor,
+                                )
+                                .debug_name("Result Editor"),
+                            )
+                            .on_event_cont(EventListener::PointerDown, move |_| {
+                                focus_result.set(true);
+                            })
+                            .style(move |s| {
+                                s.width_full()
+                                    .flex_grow(1.0)
+                                    .flex_basis(0.0)
+                                    .border_top(1.0)
+                                    .border_color(
+                                        config.get().color(LapceColor::LAPCE_BORDER),
+                                    )
+                            }),
+                        ))
+                        .style(|s| s.flex_col())
+                        .into_any()
+                    } else {
+                        sides.into_any()
+                    };
+                    view.style(|s: Style| s.size_full())
+                        .on_cleanup(move || {
+                            diff_editor_scope.dispose();
+                        })
+                        .into_any()
                 } else {
                     text("empty diff editor").into_any()
                 }
             }
             EditorTabChild::Settings(_) => {
-                settings_view(plugin.installed, editors, common).into_any()
+                settings_view(plugin.installed, last_language, editors, common)
+                    .into_any()
             }
             EditorTabChild::ThemeColorSettings(_) => {
                 theme_color_settings_view(editors, common).into_any()
@@ -2372,15 +2414,7 @@ fn palette_item(
             let text = name.to_string();
             let kind = *kind;
 
-            let path = location.path.clone();
-            let full_path = location.path.clone();
-            let path = if let Some(workspace_path) = workspace.path.as_ref() {
-                path.strip_prefix(workspace_path)
-                    .unwrap_or(&full_path)
-                    .to_path_buf()
-            } else {
-                path
-            };
+            let path = workspace.relative_path(&location.path);
 
             let hint = path.to_string_lossy().to_string();
             let text_indices: Vec<usize> = item
@@ -2569,9 +2603,13 @@ fn palette_item(
         | PaletteItemContent::SshHost { .. }
         | PaletteItemContent::Language { .. }
         | PaletteItemContent::LineEnding { .. }
+        | PaletteItemContent::Encoding { .. }
+        | PaletteItemContent::Snippet { .. }
         | PaletteItemContent::ColorTheme { .. }
         | PaletteItemContent::SCMReference { .. }
+        | PaletteItemContent::SCMStash { .. }
         | PaletteItemContent::TerminalProfile { .. }
+        | PaletteItemContent::DebugProcess { .. }
         | PaletteItemContent::IconTheme { .. } => {
             let text = item.filter_text;
             let indices = item.indices;
@@ -3066,9 +3104,11 @@ fn completion(window_tab_data: Rc<WindowTabData>) -> impl View {
             move |(i, item)| {
                 stack((
                     container(
-                        text(
-                            item.item.kind.map(completion_kind_to_str).unwrap_or(""),
-                        )
+                        text(if item.user_snippet {
+                            "u"
+                        } else {
+                            item.item.kind.map(completion_kind_to_str).unwrap_or("")
+                        })
                         .style(move |s| {
                             s.width_full()
                                 .justify_content(Some(JustifyContent::Center))
@@ -3336,6 +3376,7 @@ fn window_tab(window_tab_data: Rc<WindowTabData>) -> impl View {
         hover(window_tab_data.clone()),
         code_action(window_tab_data.clone()),
         rename(window_tab_data.clone()),
+        breakpoint::breakpoint_edit_popup(window_tab_data.clone()),
         palette(window_tab_data.clone()),
         about::about_popup(window_tab_data.clone()),
         alert::alert_box(window_tab_data.alert_data.clone()),
@@ -3862,6 +3903,16 @@ pub fn launch() {
             tracing::error!("{:?}", err);
         }
     }
+    if let Some(path) = LapceConfig::languages_file() {
+        if let Err(err) = watcher.watch(&path, notify::RecursiveMode::Recursive) {
+            tracing::error!("{:?}", err);
+        }
+    }
+    if let Some(path) = Directory::snippets_directory() {
+        if let Err(err) = watcher.watch(&path, notify::RecursiveMode::Recursive) {
+            tracing::error!("{:?}", err);
+        }
+    }
     if let Some(path) = Directory::plugins_directory() {
         if let Err(err) = watcher.watch(&path, notify::RecursiveMode::Recursive) {
             tracing::error!("{:?}", err);
@@ -4221,6 +4272,9 @@ pub fn window_menu(
                 .entry(MenuItem::new(t!("Open Folder")).action(move || {
                     workbench_command.send(LapceWorkbenchCommand::OpenFolder);
                 }))
+                .entry(MenuItem::new(t!("Open Workspace File")).action(move || {
+                    workbench_command.send(LapceWorkbenchCommand::OpenWorkspaceFile);
+                }))
                 .separator()
                 .entry(MenuItem::new(t!("Save")).action(move || {
                     lapce_command.send(LapceCommand {
@@ -0,0 +1,328 @@
+use std::{path::PathBuf, rc::Rc};
+
+use floem::{
+    event::EventListener,
+    keyboard::Modifiers,
+    reactive::{RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
+    style::{CursorStyle, Display, Position},
+    views::{container, label, stack, Decorators},
+    View,
+};
+use lapce_core::{
+    command::{EditCommand, FocusCommand},
+    mode::Mode,
+    selection::Selection,
+};
+use lapce_xi_rope::Rope;
+
+use crate::{
+    command::{CommandExecuted, CommandKind, LapceCommand},
+    config::color::LapceColor,
+    debug::{LapceBreakpoint, RunDebugData},
+    editor::EditorData,
+    keypress::{condition::Condition, KeyPressFocus},
+    main_split::Editors,
+    text_input::TextInputBuilder,
+    window_tab::{CommonData, Focus, WindowTabData},
+};
+
+/// Which part of a breakpoint is being edited
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakpointEditKind {
+    Condition,
+    HitCount,
+    LogMessage,
+}
+
+impl BreakpointEditKind {
+    pub fn title(&self) -> &'static str {
+        match self {
+            BreakpointEditKind::Condition => "Expression",
+            BreakpointEditKind::HitCount => "Hit Count",
+            BreakpointEditKind::LogMessage => "Log Message",
+        }
+    }
+
+    pub fn placeholder(&self) -> &'static str {
+        match self {
+            BreakpointEditKind::Condition => {
+                "Break when expression evaluates to true"
+            }
+            BreakpointEditKind::HitCount => {
+                "Break when hit count condition is met, e.g. >= 5"
+            }
+            BreakpointEditKind::LogMessage => {
+                "Message to log when breakpoint is hit, {} are interpolated"
+            }
+        }
+    }
+
+    fn value(&self, breakpoint: &LapceBreakpoint) -> Option<String> {
+        match self {
+            BreakpointEditKind::Condition => breakpoint.condition.clone(),
+            BreakpointEditKind::HitCount => breakpoint.hit_condition.clone(),
+            BreakpointEditKind::LogMessage => breakpoint.log_message.clone(),
+        }
+    }
+
+    fn set_value(&self, breakpoint: &mut LapceBreakpoint, value: Option<String>) {
+        match self {
+            BreakpointEditKind::Condition => breakpoint.condition = value,
+            BreakpointEditKind::HitCount => breakpoint.hit_condition = value,
+            BreakpointEditKind::LogMessage => breakpoint.log_message = value,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct BreakpointEditData {
+    pub active: RwSignal<bool>,
+    pub kind: RwSignal<BreakpointEditKind>,
+    pub path: RwSignal<PathBuf>,
+    /// The breakpoint with the changes made so far, written back on confirm
+    pub breakpoint: RwSignal<LapceBreakpoint>,
+    pub editor: EditorData,
+    pub debug: RunDebugData,
+    pub common: Rc<CommonData>,
+}
+
+impl KeyPressFocus for BreakpointEditData {
+    fn get_mode(&self) -> Mode {
+        Mode::Insert
+    }
+
+    fn check_condition(&self, condition: Condition) -> bool {
+        matches!(condition, Condition::ModalFocus)
+    }
+
+    fn run_command(
+        &self,
+        command: &LapceCommand,
+        count: Option<usize>,
+        mods: Modifiers,
+    ) -> CommandExecuted {
+        match &command.kind {
+            CommandKind::Focus(FocusCommand::ModalClose) => {
+                self.cancel();
+            }
+            CommandKind::Edit(EditCommand::InsertNewLine) => {
+                self.confirm();
+            }
+            CommandKind::Edit(_)
+            | CommandKind::Move(_)
+            | CommandKind::MultiSelection(_) => {
+                self.editor.run_command(command, count, mods);
+            }
+            CommandKind::Workbench(_)
+            | CommandKind::Scroll(_)
+            | CommandKind::Focus(_)
+            | CommandKind::MotionMode(_) => {}
+        }
+        CommandExecuted::Yes
+    }
+
+    fn receive_char(&self, c: &str) {
+        self.editor.receive_char(c);
+    }
+}
+
+impl BreakpointEditData {
+    pub fn new(
+        cx: Scope,
+        editors: Editors,
+        debug: RunDebugData,
+        common: Rc<CommonData>,
+    ) -> Self {
+        Self {
+            active: cx.create_rw_signal(false),
+            kind: cx.create_rw_signal(BreakpointEditKind::Condition),
+            path: cx.create_rw_signal(PathBuf::new()),
+            breakpoint: cx.create_rw_signal(LapceBreakpoint::new(0, 0)),
+            editor: editors.make_local(cx, common.clone()),
+            debug,
+            common,
+        }
+    }
+
+    pub fn start(
+        &self,
+        path: PathBuf,
+        line: usize,
+        offset: usize,
+        kind: BreakpointEditKind,
+    ) {
+        let breakpoint = self
+            .debug
+            .breakpoints
+            .with_untracked(|breakpoints| {
+                breakpoints
+                    .get(&path)
+                    .and_then(|breakpoints| breakpoints.get(&line))
+                    .cloned()
+            })
+            .unwrap_or_else(|| LapceBreakpoint::new(line, offset));
+        self.path.set(path);
+        self.breakpoint.set(breakpoint);
+        self.load_kind(kind);
+        self.active.set(true);
+        self.common.focus.set(Focus::BreakpointEdit);
+    }
+
+    /// Keep the text typed for the current kind and switch to editing `kind`
+    pub fn switch_kind(&self, kind: BreakpointEditKind) {
+        if self.kind.get_untracked() == kind {
+            return;
+        }
+        self.store_kind();
+        self.load_kind(kind);
+    }
+
+    fn load_kind(&self, kind: BreakpointEditKind) {
+        let text = self
+            .breakpoint
+            .with_untracked(|breakpoint| kind.value(breakpoint))
+            .unwrap_or_default();
+        self.editor.doc().reload(Rope::from(&text), true);
+        self.editor
+            .cursor()
+            .update(|cursor| cursor.set_insert(Selection::region(0, text.len())));
+        self.kind.set(kind);
+    }
+
+    fn store_kind(&self) {
+        let text = self
+            .editor
+            .doc()
+            .buffer
+            .with_untracked(|buffer| buffer.to_string());
+        let text = text.trim();
+        let value = if text.is_empty() {
+            None
+        } else {
+            Some(text.to_string())
+        };
+        let kind = self.kind.get_untracked();
+        self.breakpoint
+            .update(|breakpoint| kind.set_value(breakpoint, value));
+    }
+
+    fn cancel(&self) {
+        self.active.set(false);
+        if let Focus::BreakpointEdit = self.common.focus.get_untracked() {
+            self.common.focus.set(Focus::Workbench);
+        }
+    }
+
+    pub fn confirm(&self) {
+        self.store_kind();
+        let path = self.path.get_untracked();
+        let breakpoint = self.breakpoint.get_untracked();
+        self.debug.breakpoints.update(|breakpoints| {
+            breakpoints
+                .entry(path.clone())
+                .or_default()
+                .insert(breakpoint.line, breakpoint);
+        });
+        self.debug.send_breakpoints(&self.common.proxy, &path);
+        self.cancel();
+    }
+}
+
+pub fn breakpoint_edit_popup(window_tab_data: Rc<WindowTabData>) -> impl View {
+    let data = window_tab_data.breakpoint_edit.clone();
+    let active = data.active;
+    let kind = data.kind;
+    let path = data.path;
+    let breakpoint = data.breakpoint;
+    let config = data.common.config;
+    let focus = data.common.focus;
+    let workspace = data.common.workspace.clone();
+
+    let kind_button = move |k: BreakpointEditKind| {
+        let data = data.clone();
+        label(move || k.title())
+            .on_click_stop(move |_| {
+                data.switch_kind(k);
+            })
+            .style(move |s| {
+                let config = config.get();
+                s.padding_horiz(8.0)
+                    .border_radius(6.0)
+                    .margin_right(4.0)
+                    .cursor(CursorStyle::Pointer)
+                    .apply_if(kind.get() == k, |s| {
+                        s.background(
+                            config.color(LapceColor::PANEL_CURRENT_BACKGROUND),
+                        )
+                    })
+                    .hover(|s| {
+                        s.background(
+                            config.color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                        )
+                    })
+            })
+    };
+
+    let input = TextInputBuilder::new()
+        .is_focused(move || focus.get() == Focus::BreakpointEdit)
+        .build_editor(window_tab_data.breakpoint_edit.editor.clone())
+        .placeholder(move || kind.get().placeholder().to_string())
+        .style(|s| s.width_full());
+
+    container(
+        stack((
+            stack((
+                label(move || {
+                    let path = workspace.relative_path(&path.get());
+                    let line = breakpoint.with(|b| b.line);
+                    format!("{}:{}", path.display(), line + 1)
+                })
+                .style(move |s| {
+                    s.text_ellipsis()
+                        .flex_grow(1.0)
+                        .min_width(0.0)
+                        .color(config.get().color(LapceColor::EDITOR_DIM))
+                }),
+                kind_button(BreakpointEditKind::Condition),
+                kind_button(BreakpointEditKind::HitCount),
+                kind_button(BreakpointEditKind::LogMessage),
+            ))
+            .style(|s| s.items_center().width_full().padding(5.0)),
+            container(input).style(move |s| {
+                let config = config.get();
+                s.width_full()
+                    .height(25.0)
+                    .items_center()
+                    .border_top(1.0)
+                    .border_color(config.color(LapceColor::LAPCE_BORDER))
+                    .background(config.color(LapceColor::EDITOR_BACKGROUND))
+            }),
+        ))
+        .on_event_stop(EventListener::PointerDown, move |_| {})
+        .style(move |s| {
+            let config = config.get();
+            s.width(config.ui.palette_width() as f64)
+                .max_width_full()
+                .margin_top(4.0)
+                .border(1.0)
+                .border_radius(6.0)
+                .border_color(config.color(LapceColor::LAPCE_BORDER))
+                .flex_col()
+                .background(config.color(LapceColor::PALETTE_BACKGROUND))
+                .pointer_events_auto()
+        }),
+    )
+    .style(move |s| {
+        s.display(if active.get() {
+            Display::Flex
+        } else {
+            Display::None
+        })
+        .position(Position::Absolute)
+        .size_full()
+        .flex_col()
+        .items_center()
+        .pointer_events_none()
+    })
+    .debug_name("Breakpoint Edit Layer")
+}
@@ -1,6 +1,6 @@
 use std::rc::Rc;
 
-use lapce_rpc::dap_types::{ConfigSource, RunDebugConfig};
+use lapce_rpc::dap_types::{ConfigSource, DebugRequest, RunDebugConfig};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -82,11 +82,16 @@ fn get_rust_command_config(
             Some(RunDebugConfig {
                 ty: None,
                 name: cargo_args.label,
+                request: DebugRequest::Launch,
                 program: cargo_args.kind,
                 args: Some(cargo_args.args.cargo_args),
                 cwd: None,
                 env: None,
                 prelaunch: None,
+                process_id: None,
+                host: None,
+                port: None,
+                adapter_args: None,
                 debug_command: None,
                 dap_id: Default::default(),
                 tracing_output: mode == RunDebugMode::Debug,
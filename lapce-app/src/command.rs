@@ -17,12 +17,14 @@
     terminal::{TermId, TerminalProfile},
 };
 use lsp_types::{CodeActionOrCommand, Position, WorkspaceEdit};
+use serde::Deserialize;
 use serde_json::Value;
 use strum::{EnumMessage, IntoEnumIterator};
 use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
 
 use crate::{
     alert::AlertButton,
+    breakpoint::BreakpointEditKind,
     debug::RunDebugMode,
     doc::Doc,
     editor::location::EditorLocation,
@@ -175,6 +177,10 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Open Folder")]
     OpenFolder,
 
+    #[strum(serialize = "open_workspace_file")]
+    #[strum(message = "Open Workspace File")]
+    OpenWorkspaceFile,
+
     #[strum(serialize = "close_folder")]
     #[strum(message = "Close Folder")]
     CloseFolder,
@@ -516,6 +522,90 @@ pub enum LapceWorkbenchCommand {
     #[strum(serialize = "source_control_discard_workspace_changes")]
     SourceControlDiscardWorkspaceChanges,
 
+    #[strum(message = "Source Control: Stage File")]
+    #[strum(serialize = "source_control_stage_active_file")]
+    SourceControlStageActiveFile,
+
+    #[strum(message = "Source Control: Unstage File")]
+    #[strum(serialize = "source_control_unstage_active_file")]
+    SourceControlUnstageActiveFile,
+
+    #[strum(message = "Source Control: Stage Hunk")]
+    #[strum(serialize = "source_control_stage_hunk")]
+    SourceControlStageHunk,
+
+    #[strum(message = "Source Control: Unstage Hunk")]
+    #[strum(serialize = "source_control_unstage_hunk")]
+    SourceControlUnstageHunk,
+
+    #[strum(message = "Source Control: Fetch")]
+    #[strum(serialize = "source_control_fetch")]
+    SourceControlFetch,
+
+    #[strum(message = "Source Control: Pull")]
+    #[strum(serialize = "source_control_pull")]
+    SourceControlPull,
+
+    #[strum(message = "Source Control: Pull (Merge)")]
+    #[strum(serialize = "source_control_pull_merge")]
+    SourceControlPullMerge,
+
+    #[strum(message = "Source Control: Pull (Rebase)")]
+    #[strum(serialize = "source_control_pull_rebase")]
+    SourceControlPullRebase,
+
+    #[strum(message = "Source Control: Push")]
+    #[strum(serialize = "source_control_push")]
+    SourceControlPush,
+
+    #[strum(message = "Source Control: Create Branch")]
+    #[strum(serialize = "source_control_create_branch")]
+    SourceControlCreateBranch,
+
+    #[strum(message = "Source Control: Rename Branch")]
+    #[strum(serialize = "source_control_rename_branch")]
+    SourceControlRenameBranch,
+
+    #[strum(message = "Source Control: Delete Branch")]
+    #[strum(serialize = "source_control_delete_branch")]
+    SourceControlDeleteBranch,
+
+    #[strum(message = "Source Control: Delete Branch (Force)")]
+    #[strum(serialize = "source_control_force_delete_branch")]
+    SourceControlForceDeleteBranch,
+
+    #[strum(message = "Source Control: Merge Branch")]
+    #[strum(serialize = "source_control_merge_branch")]
+    SourceControlMergeBranch,
+
+    #[strum(message = "Source Control: Stash Changes")]
+    #[strum(serialize = "source_control_stash")]
+    SourceControlStash,
+
+    #[strum(message = "Source Control: Apply Stash")]
+    #[strum(serialize = "source_control_stash_apply")]
+    SourceControlStashApply,
+
+    #[strum(message = "Source Control: Pop Stash")]
+    #[strum(serialize = "source_control_stash_pop")]
+    SourceControlStashPop,
+
+    #[strum(message = "Source Control: Drop Stash")]
+    #[strum(serialize = "source_control_stash_drop")]
+    SourceControlStashDrop,
+
+    #[strum(message = "Source Control: Show History")]
+    #[strum(serialize = "show_git_history")]
+    ShowGitHistory,
+
+    #[strum(message = "Source Control: Show File History")]
+    #[strum(serialize = "show_file_history")]
+    ShowFileHistory,
+
+    #[strum(message = "Source Control: Open Changes of Line's Commit")]
+    #[strum(serialize = "source_control_open_blame_commit")]
+    SourceControlOpenBlameCommit,
+
     #[strum(serialize = "export_current_theme_settings")]
     #[strum(message = "Export current settings to a theme file")]
     ExportCurrentThemeSettings,
@@ -532,6 +622,18 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Change current file line ending")]
     ChangeFileLineEnding,
 
+    #[strum(serialize = "reopen_with_encoding")]
+    #[strum(message = "Reopen current file with encoding")]
+    ReopenWithEncoding,
+
+    #[strum(serialize = "save_with_encoding")]
+    #[strum(message = "Save current file with encoding")]
+    SaveWithEncoding,
+
+    #[strum(serialize = "insert_snippet")]
+    #[strum(message = "Insert Snippet")]
+    InsertSnippet,
+
     #[strum(serialize = "next_editor_tab")]
     #[strum(message = "Next Editor Tab")]
     NextEditorTab,
@@ -544,6 +646,14 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Toggle Inlay Hints")]
     ToggleInlayHints,
 
+    #[strum(serialize = "toggle_inline_blame")]
+    #[strum(message = "Toggle Inline Blame")]
+    ToggleInlineBlame,
+
+    #[strum(serialize = "toggle_blame_gutter")]
+    #[strum(message = "Toggle Blame Gutter")]
+    ToggleBlameGutter,
+
     #[strum(serialize = "restart_to_update")]
     RestartToUpdate,
 
@@ -585,6 +695,14 @@ pub enum LapceWorkbenchCommand {
     #[strum(serialize = "previous_error")]
     PreviousError,
 
+    #[strum(message = "Next Search Result")]
+    #[strum(serialize = "next_search_result")]
+    NextSearchResult,
+
+    #[strum(message = "Previous Search Result")]
+    #[strum(serialize = "previous_search_result")]
+    PreviousSearchResult,
+
     #[strum(message = "Diff Files")]
     #[strum(serialize = "diff_files")]
     DiffFiles,
@@ -600,6 +718,123 @@ pub enum LapceWorkbenchCommand {
     #[strum(serialize = "add_run_debug_config")]
     #[strum(message = "Add Run Debug Config")]
     AddRunDebugConfig,
+
+    #[strum(serialize = "fold")]
+    #[strum(message = "Fold")]
+    Fold,
+
+    #[strum(serialize = "unfold")]
+    #[strum(message = "Unfold")]
+    Unfold,
+
+    #[strum(serialize = "toggle_fold")]
+    #[strum(message = "Toggle Fold")]
+    ToggleFold,
+
+    #[strum(serialize = "fold_all")]
+    #[strum(message = "Fold All")]
+    FoldAll,
+
+    #[strum(serialize = "unfold_all")]
+    #[strum(message = "Unfold All")]
+    UnfoldAll,
+
+    #[strum(serialize = "fold_level_1")]
+    #[strum(message = "Fold Level 1")]
+    FoldLevel1,
+
+    #[strum(serialize = "fold_level_2")]
+    #[strum(message = "Fold Level 2")]
+    FoldLevel2,
+
+    #[strum(serialize = "fold_level_3")]
+    #[strum(message = "Fold Level 3")]
+    FoldLevel3,
+
+    /// Inserts the `text` passed in parameter at the cursors of the active editor.
+    #[strum(serialize = "insert_text")]
+    InsertText,
+
+    /// Runs the shell `command` passed in parameter in the active terminal.
+    #[strum(serialize = "run_shell_command")]
+    RunShellCommand,
+
+    /// Moves to the 1-based `line`, and optionally `column`, passed in parameter.
+    #[strum(serialize = "go_to_line")]
+    GoToLine,
+
+    /// Runs the `commands` passed in parameter one after another.
+    #[strum(serialize = "run_commands")]
+    RunCommands,
+
+    #[strum(serialize = "ex_command_line")]
+    #[strum(message = "Ex Command Line")]
+    ExCommandLine,
+
+    /// Runs the vim ex command passed in parameter, such as `w` or `%s/a/b/g`.
+    #[strum(serialize = "run_ex_command")]
+    RunExCommand,
+}
+
+/// The args of `open_file`, which skips the file dialog when given
+#[derive(Clone, Debug, Deserialize)]
+pub struct OpenFileArgs {
+    /// Relative paths are resolved against the workspace folder
+    pub path: PathBuf,
+}
+
+/// The args of `insert_text`
+#[derive(Clone, Debug, Deserialize)]
+pub struct InsertTextArgs {
+    pub text: String,
+}
+
+/// The args of `run_shell_command`
+#[derive(Clone, Debug, Deserialize)]
+pub struct ShellCommandArgs {
+    pub command: String,
+}
+
+/// The args of `go_to_line`
+#[derive(Clone, Debug, Deserialize)]
+pub struct GoToLineArgs {
+    pub line: usize,
+    pub column: Option<usize>,
+}
+
+/// The args of `run_commands`
+#[derive(Clone, Debug, Deserialize)]
+pub struct RunCommandsArgs {
+    pub commands: Vec<CommandArg>,
+}
+
+/// A command of `run_commands`, either a bare command name or a table with the
+/// command and its args
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum CommandArg {
+    Name(String),
+    WithArgs {
+        command: String,
+        #[serde(default)]
+        args: Option<Value>,
+    },
+}
+
+impl CommandArg {
+    pub fn command(&self) -> &str {
+        match self {
+            CommandArg::Name(command) => command,
+            CommandArg::WithArgs { command, .. } => command,
+        }
+    }
+
+    pub fn args(&self) -> Option<&Value> {
+        match self {
+            CommandArg::Name(_) => None,
+            CommandArg::WithArgs { args, .. } => args.as_ref(),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -617,6 +852,16 @@ pub enum InternalCommand {
     MakeConfirmed,
     OpenFileChanges {
         path: PathBuf,
+        /// Show the staged changes rather than the ones in the working file
+        staged: bool,
+    },
+    OpenCommitFileChanges {
+        path: PathBuf,
+        commit: String,
+    },
+    /// Open the merge editor of a file with conflicts
+    OpenMergeEditor {
+        path: PathBuf,
     },
     ReloadFileExplorer,
     /// Test whether a file/directory can be created at that path
@@ -710,6 +955,12 @@ pub enum InternalCommand {
         start: usize,
         position: Position,
     },
+    EditBreakpoint {
+        path: PathBuf,
+        line: usize,
+        offset: usize,
+        kind: BreakpointEditKind,
+    },
     Search {
         pattern: Option<String>,
     },
@@ -10,12 +10,16 @@
 };
 use lapce_rpc::{plugin::PluginId, proxy::ProxyRpcHandler};
 use lsp_types::{
-    CompletionItem, CompletionResponse, CompletionTextEdit, InsertTextFormat,
-    Position,
+    CompletionItem, CompletionItemKind, CompletionResponse, CompletionTextEdit,
+    Documentation, InsertTextFormat, Position,
 };
 use nucleo::Utf32Str;
 
-use crate::{config::LapceConfig, editor::EditorData, snippet::Snippet};
+use crate::{
+    config::LapceConfig,
+    editor::EditorData,
+    snippet::{Snippet, SnippetTab, UserSnippet},
+};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum CompletionStatus {
@@ -28,6 +32,8 @@ pub enum CompletionStatus {
 pub struct ScoredCompletionItem {
     pub item: CompletionItem,
     pub plugin_id: PluginId,
+    /// Whether the item comes from a snippet file rather than a language server
+    pub user_snippet: bool,
     pub score: u32,
     pub label_score: u32,
     pub indices: Vec<usize>,
@@ -52,6 +58,11 @@ pub struct CompletionData {
     pub input: String,
     /// `(Input, CompletionItems)`
     pub input_items: im::HashMap<String, im::Vector<ScoredCompletionItem>>,
+    /// The user snippets offered alongside the items of the language servers,
+    /// or the choices of a snippet tabstop
+    pub snippet_items: im::Vector<ScoredCompletionItem>,
+    /// The snippet tabstop whose choices are listed instead of completion items
+    pub choice_tab: Option<SnippetTab>,
     /// The filtered items that are being displayed to the user
     pub filtered_items: im::Vector<ScoredCompletionItem>,
     /// The size of the completion element.  
@@ -78,6 +89,8 @@ pub fn new(cx: Scope, config: ReadSignal<Arc<LapceConfig>>) -> Self {
             active,
             input: "".to_string(),
             input_items: im::HashMap::new(),
+            snippet_items: im::Vector::new(),
+            choice_tab: None,
             filtered_items: im::Vector::new(),
             layout_rect: Rect::ZERO,
             matcher: cx
@@ -111,6 +124,7 @@ pub fn receive(
             .map(|i| ScoredCompletionItem {
                 item: i.to_owned(),
                 plugin_id,
+                user_snippet: false,
                 score: 0,
                 label_score: 0,
                 indices: Vec::new(),
@@ -120,6 +134,62 @@ pub fn receive(
         self.filter_items();
     }
 
+    /// Offer `snippets` in the completion list, once for each of their prefixes.
+    pub fn set_snippets<'a>(
+        &mut self,
+        snippets: impl Iterator<Item = &'a UserSnippet>,
+    ) {
+        self.snippet_items = snippets
+            .flat_map(|snippet| {
+                snippet.prefixes.iter().map(|prefix| ScoredCompletionItem {
+                    item: user_snippet_item(snippet, prefix),
+                    plugin_id: PluginId(0),
+                    user_snippet: true,
+                    score: 0,
+                    label_score: 0,
+                    indices: Vec::new(),
+                })
+            })
+            .collect();
+        self.filter_items();
+    }
+
+    /// List the choices of the snippet tabstop `tab`, which replace all of its
+    /// ranges once picked.
+    pub fn show_choices(
+        &mut self,
+        editor_id: EditorId,
+        path: PathBuf,
+        tab: SnippetTab,
+    ) {
+        self.status = CompletionStatus::Done;
+        self.request_id += 1;
+        self.latest_editor_id = Some(editor_id);
+        self.path = path;
+        self.offset = tab.ranges.first().map(|(start, _)| *start).unwrap_or(0);
+        self.active.set(0);
+        self.input.clear();
+        self.input_items.clear();
+        self.snippet_items = tab
+            .choices
+            .iter()
+            .map(|choice| ScoredCompletionItem {
+                item: CompletionItem {
+                    label: choice.clone(),
+                    kind: Some(CompletionItemKind::VALUE),
+                    ..Default::default()
+                },
+                plugin_id: PluginId(0),
+                user_snippet: false,
+                score: 0,
+                label_score: 0,
+                indices: Vec::new(),
+            })
+            .collect();
+        self.choice_tab = Some(tab);
+        self.filter_items();
+    }
+
     /// Request for completion items wit the current request id.
     pub fn request(
         &mut self,
@@ -145,6 +215,8 @@ pub fn cancel(&mut self) {
         self.active.set(0);
         self.input.clear();
         self.input_items.clear();
+        self.snippet_items.clear();
+        self.choice_tab = None;
         self.filtered_items.clear();
     }
 
@@ -162,13 +234,16 @@ pub fn update_input(&mut self, input: String) {
     }
 
     fn all_items(&self) -> im::Vector<ScoredCompletionItem> {
-        self.input_items
+        let mut items = self
+            .input_items
             .get(&self.input)
             .cloned()
             .filter(|items| !items.is_empty())
             .unwrap_or_else(move || {
                 self.input_items.get("").cloned().unwrap_or_default()
-            })
+            });
+        items.append(self.snippet_items.clone());
+        items
     }
 
     pub fn filter_items(&mut self) {
@@ -310,7 +385,7 @@ pub fn update_document_completion(
 
         let config = self.config.get_untracked();
 
-        if !config.editor.enable_completion_lens {
+        if !config.editor.enable_completion_lens || self.choice_tab.is_some() {
             doc.clear_completion_lens();
             return;
         }
@@ -339,6 +414,19 @@ pub fn update_document_completion(
     }
 }
 
+/// The completion item that inserts `snippet` when `prefix` is typed
+fn user_snippet_item(snippet: &UserSnippet, prefix: &str) -> CompletionItem {
+    CompletionItem {
+        label: prefix.to_string(),
+        kind: Some(CompletionItemKind::SNIPPET),
+        detail: Some(snippet.name.clone()),
+        documentation: snippet.description.clone().map(Documentation::String),
+        insert_text: Some(snippet.body.clone()),
+        insert_text_format: Some(InsertTextFormat::SNIPPET),
+        ..Default::default()
+    }
+}
+
 /// Get the text of the completion lens for the given completion item.  
 /// Returns `None` if the completion lens should be hidden.
 /// Returns `Some(None)` if the completion lens should be shown, but not changed.
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -7,9 +7,12 @@
 use ::core::slice;
 use floem::{peniko::Color, prelude::palette::css};
 use itertools::Itertools;
-use lapce_core::directory::Directory;
-use lapce_proxy::plugin::wasi::find_all_volts;
-use lapce_rpc::plugin::VoltID;
+use lapce_core::{
+    directory::Directory,
+    language::{LanguageDefinition, LapceLanguage},
+};
+use lapce_proxy::plugin::wasi::{find_all_volts, find_language_definitions};
+use lapce_rpc::plugin::{LspServerConfig, VoltID};
 use lsp_types::{CompletionItemKind, SymbolKind};
 use once_cell::sync::Lazy;
 use parking_lot::RwLock;
@@ -24,11 +27,15 @@
     editor::{EditorConfig, WrapStyle, SCALE_OR_SIZE_LIMIT},
     icon::LapceIcons,
     icon_theme::IconThemeConfig,
+    language::LanguageConfig,
     svg::SvgStore,
     terminal::TerminalConfig,
     ui::UIConfig,
 };
-use crate::workspace::{LapceWorkspace, LapceWorkspaceType};
+use crate::{
+    snippet::UserSnippet,
+    workspace::{LapceWorkspace, LapceWorkspaceType},
+};
 
 pub mod color;
 pub mod color_theme;
@@ -36,6 +43,7 @@
 pub mod editor;
 pub mod icon;
 pub mod icon_theme;
+pub mod language;
 pub mod svg;
 pub mod terminal;
 pub mod ui;
@@ -104,6 +112,14 @@ pub struct LapceConfig {
     pub color_theme: ColorThemeConfig,
     #[serde(default)]
     pub icon_theme: IconThemeConfig,
+    /// The `[language.<name>]` sections, keyed by the name as written. They are
+    /// kept sorted so that sections naming the same language apply in a fixed
+    /// order.
+    #[serde(default)]
+    pub language: BTreeMap<String, LanguageConfig>,
+    /// The language servers declared in `[lsp.<name>]` sections
+    #[serde(default)]
+    pub lsp: HashMap<String, LspServerConfig>,
     #[serde(flatten)]
     pub plugins: HashMap<String, HashMap<String, serde_json::Value>>,
     #[serde(skip)]
@@ -126,6 +142,16 @@ pub struct LapceConfig {
     /// The couple names for the wrap style
     #[serde(skip)]
     wrap_style_list: im::Vector<String>,
+    /// The editor config with the `[language.<name>.editor]` overrides applied
+    #[serde(skip)]
+    language_editor: HashMap<LapceLanguage, EditorConfig>,
+    /// The `[language.<name>.editor]` values of every language that has any
+    #[serde(skip)]
+    language_overrides:
+        HashMap<LapceLanguage, serde_json::Map<String, serde_json::Value>>,
+    /// The snippets of the enabled volts, the user and the workspace
+    #[serde(skip)]
+    snippets: Vec<UserSnippet>,
 }
 
 impl LapceConfig {
@@ -134,6 +160,11 @@ pub fn load(
         disabled_volts: &[VoltID],
         extra_plugin_paths: &[PathBuf],
     ) -> Self {
+        LapceLanguage::register(Self::load_language_definitions(
+            disabled_volts,
+            extra_plugin_paths,
+        ));
+
         let config = Self::merge_config(workspace, None, None);
         let mut lapce_config: LapceConfig = match config.try_deserialize() {
             Ok(config) => config,
@@ -174,6 +205,9 @@ pub fn load(
 
         lapce_config.terminal.get_indexed_colors();
 
+        lapce_config.snippets =
+            Self::load_snippets(workspace, disabled_volts, extra_plugin_paths);
+
         lapce_config
     }
 
@@ -297,11 +331,76 @@ fn resolve_theme(&mut self, workspace: &LapceWorkspace) {
                 self.icon_theme.path = icon_theme_path.clone().unwrap_or_default();
             }
             self.plugins = new.plugins;
+            self.language = new.language;
         }
+        self.resolve_language_editors();
         self.resolve_colors(Some(&default_lapce_config));
         self.update_id();
     }
 
+    /// Build the editor config of every language that has overrides, by
+    /// layering its `[language.<name>.editor]` values on top of `[editor]`.
+    ///
+    /// When several sections name the same language, such as `go` and `Go`,
+    /// they are applied in the order of their names, so the last one wins for a
+    /// setting they both set.
+    fn resolve_language_editors(&mut self) {
+        self.language_editor.clear();
+        self.language_overrides.clear();
+
+        for (name, language_config) in &self.language {
+            if language_config.editor.is_empty() {
+                continue;
+            }
+            let Some(language) = LapceLanguage::from_name(name) else {
+                error!("Unknown language `{name}` in language settings");
+                continue;
+            };
+            self.language_overrides
+                .entry(language)
+                .or_default()
+                .extend(language_config.editor.clone());
+        }
+
+        let Ok(serde_json::Value::Object(editor)) =
+            serde_json::to_value(&self.editor)
+        else {
+            return;
+        };
+        for (language, overrides) in &self.language_overrides {
+            let mut merged = editor.clone();
+            merged.extend(overrides.clone());
+            match serde_json::from_value::<EditorConfig>(serde_json::Value::Object(
+                merged,
+            )) {
+                Ok(config) => {
+                    self.language_editor.insert(*language, config);
+                }
+                Err(err) => {
+                    error!(
+                        "Failed to apply editor settings for `{}`: {err}",
+                        language.name()
+                    );
+                }
+            }
+        }
+    }
+
+    /// The editor config for documents of `language`, which is `[editor]` with
+    /// any `[language.<name>.editor]` overrides applied.
+    pub fn editor_for(&self, language: LapceLanguage) -> &EditorConfig {
+        self.language_editor.get(&language).unwrap_or(&self.editor)
+    }
+
+    /// The editor settings that `[language.<name>.editor]` overrides for
+    /// `language`, keyed by their kebab-case field name.
+    pub fn language_editor_overrides(
+        &self,
+        language: LapceLanguage,
+    ) -> Option<&serde_json::Map<String, serde_json::Value>> {
+        self.language_overrides.get(&language)
+    }
+
     fn load_color_themes(
         disabled_volts: &[VoltID],
         extra_plugin_paths: &[PathBuf],
@@ -508,6 +607,60 @@ fn load_plugin_color_themes(
         themes
     }
 
+    /// The `[[language]]` definitions of the enabled volts and of the user's
+    /// `languages.toml`, which comes last so that it replaces the definitions
+    /// of a volt for the same language.
+    fn load_language_definitions(
+        disabled_volts: &[VoltID],
+        extra_plugin_paths: &[PathBuf],
+    ) -> Vec<LanguageDefinition> {
+        find_language_definitions(
+            disabled_volts,
+            extra_plugin_paths,
+            Self::languages_file().as_deref(),
+        )
+    }
+
+    /// The snippet files of the enabled volts, then of the snippets directory
+    /// and then of the workspace's `.lapce/snippets`
+    fn load_snippets(
+        workspace: &LapceWorkspace,
+        disabled_volts: &[VoltID],
+        extra_plugin_paths: &[PathBuf],
+    ) -> Vec<UserSnippet> {
+        let mut snippets = Vec::new();
+        for meta in find_all_volts(extra_plugin_paths) {
+            if disabled_volts.contains(&meta.id()) {
+                continue;
+            }
+            if let Some(files) = meta.snippets.as_ref() {
+                for path in files {
+                    snippets.extend(UserSnippet::load(Path::new(path)));
+                }
+            }
+        }
+        if let Some(dir) = Directory::snippets_directory() {
+            snippets.extend(UserSnippet::load_dir(&dir));
+        }
+        if workspace.kind == LapceWorkspaceType::Local {
+            for root in workspace.roots() {
+                snippets
+                    .extend(UserSnippet::load_dir(&root.join(".lapce/snippets")));
+            }
+        }
+        snippets
+    }
+
+    /// The snippets that apply to documents of `language`
+    pub fn snippets_for(
+        &self,
+        language: LapceLanguage,
+    ) -> impl Iterator<Item = &UserSnippet> {
+        self.snippets
+            .iter()
+            .filter(move |snippet| snippet.applies_to(language))
+    }
+
     fn load_plugin_icon_themes(
         disabled_volts: &[VoltID],
         extra_plugin_paths: &[PathBuf],
@@ -594,6 +747,22 @@ pub fn keymaps_file() -> Option<PathBuf> {
         Some(path)
     }
 
+    pub fn languages_file() -> Option<PathBuf> {
+        let path = Directory::config_directory()?.join("languages.toml");
+
+        if !path.exists() {
+            if let Err(err) = std::fs::OpenOptions::new()
+                .create_new(true)
+                .write(true)
+                .open(&path)
+            {
+                tracing::error!("{:?}", err);
+            }
+        }
+
+        Some(path)
+    }
+
     pub fn ui_svg(&self, icon: &'static str) -> String {
         let svg = self.icon_theme.ui.get(icon).and_then(|path| {
             let path = self.icon_theme.path.join(path);
@@ -196,6 +196,14 @@ pub struct EditorConfig {
         desc = "Set the completion lens font size. If 0 it uses the inlay hint font size."
     )]
     pub completion_lens_font_size: usize,
+    #[field_names(
+        desc = "If the editor should display the author and commit that last changed the line at the end of the cursor line"
+    )]
+    pub blame_end_of_line: bool,
+    #[field_names(
+        desc = "If the editor should display the commit that last changed each line in the gutter"
+    )]
+    pub blame_gutter: bool,
     #[field_names(
         desc = "Set the cursor blink interval (in milliseconds). Set to 0 to completely disable."
     )]
@@ -17,6 +17,10 @@ impl LapceIcons {
     pub const DEBUG: &'static str = "debug";
     pub const DEBUG_ALT: &'static str = "debug_alt";
     pub const DEBUG_BREAKPOINT: &'static str = "debug_breakpoint";
+    pub const DEBUG_BREAKPOINT_CONDITIONAL: &'static str =
+        "debug_breakpoint_conditional";
+    pub const DEBUG_LOGPOINT: &'static str = "debug_logpoint";
+    pub const DEBUG_BREAKPOINT_EDIT: &'static str = "debug_breakpoint_edit";
     pub const DEBUG_SMALL: &'static str = "debug_small";
     pub const DEBUG_RESTART: &'static str = "debug_restart";
     pub const DEBUG_CONTINUE: &'static str = "debug_continue";
@@ -52,6 +56,8 @@ impl LapceIcons {
     pub const SCM_DIFF_ADDED: &'static str = "scm.diff.added";
     pub const SCM_DIFF_REMOVED: &'static str = "scm.diff.removed";
     pub const SCM_DIFF_RENAMED: &'static str = "scm.diff.renamed";
+    pub const SCM_DIFF_CONFLICTED: &'static str = "scm.diff.conflicted";
+    pub const SCM_MERGE: &'static str = "scm.merge";
     pub const SCM_CHANGE_ADD: &'static str = "scm.change.add";
     pub const SCM_CHANGE_REMOVE: &'static str = "scm.change.remove";
 
@@ -100,6 +106,7 @@ impl LapceIcons {
     pub const SEARCH_REGEX: &'static str = "search.regex";
     pub const SEARCH_REPLACE: &'static str = "search.replace";
     pub const SEARCH_REPLACE_ALL: &'static str = "search.replace_all";
+    pub const SEARCH_IGNORED: &'static str = "search.ignored";
 
     pub const FILE_TYPE_CODE: &'static str = "file-code";
     pub const FILE_TYPE_MEDIA: &'static str = "file-media";
@@ -115,6 +122,9 @@ impl LapceIcons {
 
     pub const IMPLEMENTATION: &'static str = "implementation";
 
+    pub const GIT_HISTORY: &'static str = "git_history";
+    pub const GIT_HISTORY_REFRESH: &'static str = "git_history.refresh";
+
     pub const SYMBOL_KIND_ARRAY: &'static str = "symbol_kind.array";
     pub const SYMBOL_KIND_BOOLEAN: &'static str = "symbol_kind.boolean";
     pub const SYMBOL_KIND_CLASS: &'static str = "symbol_kind.class";
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+/// Settings from a `[language.<name>]` section, which apply only to documents
+/// of that language.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct LanguageConfig {
+    /// Overrides of `[editor]` settings, keyed by their kebab-case field name
+    #[serde(default)]
+    pub editor: serde_json::Map<String, serde_json::Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use lapce_core::language::LapceLanguage;
+
+    use crate::config::{LapceConfig, DEFAULT_CONFIG};
+
+    #[test]
+    fn test_language_editor_overrides() {
+        let settings = r#"
+[editor]
+tab-width = 4
+format-on-save = false
+
+[language.go.editor]
+tab-width = 8
+format-on-save = true
+
+# sorts before `go`, so `go` wins where both set a value
+[language.Go.editor]
+tab-width = 2
+"#;
+        let config = config::Config::builder()
+            .add_source(DEFAULT_CONFIG.clone())
+            .add_source(config::File::from_str(settings, config::FileFormat::Toml))
+            .build()
+            .unwrap();
+        let mut lapce_config: LapceConfig = config.try_deserialize().unwrap();
+        lapce_config.resolve_language_editors();
+
+        let go = lapce_config.editor_for(LapceLanguage::Go);
+        assert_eq!(go.tab_width, 8);
+        assert!(go.format_on_save);
+
+        let rust = lapce_config.editor_for(LapceLanguage::Rust);
+        assert_eq!(rust.tab_width, 4);
+        assert!(!rust.format_on_save);
+
+        assert!(lapce_config
+            .language_editor_overrides(LapceLanguage::Go)
+            .is_some_and(|overrides| overrides.contains_key("tab-width")));
+        assert!(lapce_config
+            .language_editor_overrides(LapceLanguage::Rust)
+            .is_none());
+    }
+}
@@ -196,7 +196,10 @@ fn insert_recent_workspace(&self, workspace: LapceWorkspace) -> Result<()> {
 
         let mut exits = false;
         for w in workspaces.iter_mut() {
-            if w.path == workspace.path && w.kind == workspace.kind {
+            if w.path == workspace.path
+                && w.file == workspace.file
+                && w.kind == workspace.kind
+            {
                 w.last_open = std::time::SystemTime::now()
                     .duration_since(std::time::UNIX_EPOCH)
                     .unwrap()
@@ -399,12 +402,14 @@ pub fn save_doc_position(
         path: PathBuf,
         cursor_offset: usize,
         scroll_offset: Vec2,
+        folded_lines: Vec<usize>,
     ) {
         let info = DocInfo {
             workspace: workspace.clone(),
             path,
             scroll_offset: (scroll_offset.x, scroll_offset.y),
             cursor_offset,
+            folded_lines,
         };
         if let Err(err) = self.save_tx.send(SaveEvent::Doc(info)) {
             tracing::error!("{:?}", err);
@@ -1,29 +1,42 @@
 use std::{
     collections::{BTreeMap, HashMap},
     fmt::Display,
-    path::PathBuf,
+    path::{Path, PathBuf},
     rc::Rc,
     time::Instant,
 };
 
 use floem::{
     ext_event::create_ext_action,
+    keyboard::Modifiers,
     reactive::{Memo, RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
     views::VirtualVector,
 };
+use lapce_core::{
+    command::{EditCommand, MoveCommand},
+    mode::Mode,
+    selection::Selection,
+};
 use lapce_rpc::{
     dap_types::{
-        self, DapId, RunDebugConfig, SourceBreakpoint, StackFrame, Stopped,
-        ThreadId, Variable,
+        self, DapId, EvaluateContext, EvaluateResponse, Output, RunDebugConfig,
+        SourceBreakpoint, StackFrame, Stopped, ThreadId, Variable,
     },
-    proxy::ProxyResponse,
+    proxy::{ProxyResponse, ProxyRpcHandler},
     terminal::TermId,
 };
+use lapce_xi_rope::Rope;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    command::InternalCommand,
-    editor::location::{EditorLocation, EditorPosition},
+    command::{CommandExecuted, CommandKind, InternalCommand, LapceCommand},
+    config::icon::LapceIcons,
+    editor::{
+        location::{EditorLocation, EditorPosition},
+        EditorData,
+    },
+    keypress::{condition::Condition, KeyPressFocus},
+    main_split::Editors,
     window_tab::CommonData,
 };
 
@@ -57,26 +70,251 @@ pub struct RunDebugConfigs {
     pub configs: Vec<RunDebugConfig>,
 }
 
+/// The input of the debug panel that receives the keyboard
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugInput {
+    Console,
+    Watch,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugConsoleKind {
+    /// Output of the debuggee or the debug adapter
+    Output,
+    /// Output on stderr, or an expression that failed to evaluate
+    Error,
+    /// An expression entered in the console
+    Input,
+    /// The value of an expression entered in the console
+    Result,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DebugConsoleEntry {
+    pub kind: DebugConsoleKind,
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DebugWatch {
+    pub expression: String,
+    /// The value from the last time the debuggee stopped, or the error message
+    pub value: Option<Result<String, String>>,
+}
+
 #[derive(Clone)]
 pub struct RunDebugData {
     pub active_term: RwSignal<Option<TermId>>,
     pub daps: RwSignal<im::HashMap<DapId, DapData>>,
     pub breakpoints: RwSignal<BTreeMap<PathBuf, BTreeMap<usize, LapceBreakpoint>>>,
+    pub input_focus: RwSignal<DebugInput>,
+    pub console_editor: EditorData,
+    pub watch_editor: EditorData,
+    pub console_history: RwSignal<Vec<String>>,
+    /// The position in `console_history` when browsing it with up and down
+    pub history_index: RwSignal<Option<usize>>,
+    pub watches: RwSignal<im::Vector<DebugWatch>>,
+}
+
+impl KeyPressFocus for RunDebugData {
+    fn get_mode(&self) -> Mode {
+        Mode::Insert
+    }
+
+    fn check_condition(&self, condition: Condition) -> bool {
+        matches!(condition, Condition::PanelFocus)
+    }
+
+    fn run_command(
+        &self,
+        command: &LapceCommand,
+        count: Option<usize>,
+        mods: Modifiers,
+    ) -> CommandExecuted {
+        let is_console = self.input_focus.get_untracked() == DebugInput::Console;
+        match &command.kind {
+            CommandKind::Edit(EditCommand::InsertNewLine) => {
+                match self.input_focus.get_untracked() {
+                    DebugInput::Console => self.submit_console(),
+                    DebugInput::Watch => self.add_watch(),
+                }
+                CommandExecuted::Yes
+            }
+            CommandKind::Move(MoveCommand::Up) if is_console => {
+                self.browse_history(true);
+                CommandExecuted::Yes
+            }
+            CommandKind::Move(MoveCommand::Down) if is_console => {
+                self.browse_history(false);
+                CommandExecuted::Yes
+            }
+            CommandKind::Edit(_)
+            | CommandKind::Move(_)
+            | CommandKind::MultiSelection(_) => {
+                self.active_editor().run_command(command, count, mods)
+            }
+            CommandKind::Workbench(_)
+            | CommandKind::Scroll(_)
+            | CommandKind::Focus(_)
+            | CommandKind::MotionMode(_) => CommandExecuted::No,
+        }
+    }
+
+    fn receive_char(&self, c: &str) {
+        self.active_editor().receive_char(c);
+    }
 }
 
 impl RunDebugData {
-    pub fn new(
-        cx: Scope,
-        breakpoints: RwSignal<BTreeMap<PathBuf, BTreeMap<usize, LapceBreakpoint>>>,
-    ) -> Self {
+    pub fn new(cx: Scope, editors: Editors, common: Rc<CommonData>) -> Self {
         let active_term: RwSignal<Option<TermId>> = cx.create_rw_signal(None);
-        let daps: RwSignal<im::HashMap<DapId, DapData>> =
-            cx.create_rw_signal(im::HashMap::new());
 
         Self {
             active_term,
-            daps,
-            breakpoints,
+            daps: common.daps,
+            breakpoints: common.breakpoints,
+            input_focus: cx.create_rw_signal(DebugInput::Console),
+            console_editor: editors.make_local(cx, common.clone()),
+            watch_editor: editors.make_local(cx, common),
+            console_history: cx.create_rw_signal(Vec::new()),
+            history_index: cx.create_rw_signal(None),
+            watches: cx.create_rw_signal(im::Vector::new()),
+        }
+    }
+
+    fn active_editor(&self) -> &EditorData {
+        match self.input_focus.get_untracked() {
+            DebugInput::Console => &self.console_editor,
+            DebugInput::Watch => &self.watch_editor,
+        }
+    }
+
+    /// The debug session of the active run and debug terminal
+    pub fn active_dap(&self, tracked: bool) -> Option<DapData> {
+        let active_term = if tracked {
+            self.active_term.get()?
+        } else {
+            self.active_term.get_untracked()?
+        };
+        let find = |daps: &im::HashMap<DapId, DapData>| {
+            daps.values()
+                .find(|dap| dap.term_id == active_term)
+                .cloned()
+        };
+        if tracked {
+            self.daps.with(find)
+        } else {
+            self.daps.with_untracked(find)
+        }
+    }
+
+    fn take_input(editor: &EditorData) -> Option<String> {
+        let text = editor.doc().buffer.with_untracked(|b| b.to_string());
+        let text = text.trim().to_string();
+        if text.is_empty() {
+            return None;
+        }
+        editor.reset();
+        Some(text)
+    }
+
+    fn submit_console(&self) {
+        let Some(expression) = Self::take_input(&self.console_editor) else {
+            return;
+        };
+        self.history_index.set(None);
+        self.console_history.update(|history| {
+            if history.last() != Some(&expression) {
+                history.push(expression.clone());
+            }
+        });
+
+        let Some(dap) = self.active_dap(false) else {
+            return;
+        };
+        dap.push_console(DebugConsoleKind::Input, expression.clone());
+        let console = dap.console;
+        dap.evaluate(expression, EvaluateContext::Repl, move |result| {
+            let entry = match result {
+                Ok(resp) => DebugConsoleEntry {
+                    kind: DebugConsoleKind::Result,
+                    text: resp.result,
+                },
+                Err(err) => DebugConsoleEntry {
+                    kind: DebugConsoleKind::Error,
+                    text: err,
+                },
+            };
+            console.update(|console| console.push_back(entry));
+        });
+    }
+
+    fn browse_history(&self, up: bool) {
+        let history = self.console_history.get_untracked();
+        if history.is_empty() {
+            return;
+        }
+        let index = match (self.history_index.get_untracked(), up) {
+            (None, true) => Some(history.len() - 1),
+            (None, false) => return,
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) if i + 1 < history.len() => Some(i + 1),
+            (Some(_), false) => None,
+        };
+        self.history_index.set(index);
+
+        let text = index.map(|i| history[i].clone()).unwrap_or_default();
+        self.console_editor.doc().reload(Rope::from(&text), true);
+        self.console_editor
+            .cursor()
+            .update(|cursor| cursor.set_insert(Selection::caret(text.len())));
+    }
+
+    fn add_watch(&self) {
+        let Some(expression) = Self::take_input(&self.watch_editor) else {
+            return;
+        };
+        self.watches.update(|watches| {
+            watches.push_back(DebugWatch {
+                expression,
+                value: None,
+            })
+        });
+        if let Some(dap) = self.active_dap(false) {
+            if dap.stopped.get_untracked() {
+                self.evaluate_watches(&dap);
+            }
+        }
+    }
+
+    pub fn remove_watch(&self, index: usize) {
+        self.watches.update(|watches| {
+            if index < watches.len() {
+                watches.remove(index);
+            }
+        });
+    }
+
+    /// Evaluate all the watch expressions in the selected frame of `dap`
+    pub fn evaluate_watches(&self, dap: &DapData) {
+        let watches = self.watches;
+        let expressions: Vec<String> = watches.with_untracked(|watches| {
+            watches.iter().map(|w| w.expression.clone()).collect()
+        });
+        for (i, expression) in expressions.into_iter().enumerate() {
+            dap.evaluate(
+                expression.clone(),
+                EvaluateContext::Watch,
+                move |result| {
+                    watches.update(|watches| {
+                        if let Some(watch) = watches.get_mut(i) {
+                            if watch.expression == expression {
+                                watch.value = Some(result.map(|r| r.result));
+                            }
+                        }
+                    });
+                },
+            );
         }
     }
 
@@ -88,25 +326,41 @@ pub fn source_breakpoints(&self) -> HashMap<PathBuf, Vec<SourceBreakpoint>> {
                 (
                     path.to_path_buf(),
                     breakpoints
-                        .iter()
-                        .filter_map(|(_, b)| {
-                            if b.active {
-                                Some(SourceBreakpoint {
-                                    line: b.line + 1,
-                                    column: None,
-                                    condition: None,
-                                    hit_condition: None,
-                                    log_message: None,
-                                })
-                            } else {
-                                None
-                            }
-                        })
+                        .values()
+                        .filter(|b| b.active)
+                        .map(|b| b.source_breakpoint())
                         .collect(),
                 )
             })
             .collect()
     }
+
+    /// Send the active breakpoints of `path` to all the running debug adapters
+    pub fn send_breakpoints(&self, proxy: &ProxyRpcHandler, path: &Path) {
+        let source_breakpoints: Vec<SourceBreakpoint> =
+            self.breakpoints.with_untracked(|breakpoints| {
+                breakpoints
+                    .get(path)
+                    .map(|breakpoints| {
+                        breakpoints
+                            .values()
+                            .filter(|b| b.active)
+                            .map(|b| b.source_breakpoint())
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            });
+        let daps: Vec<DapId> = self
+            .daps
+            .with_untracked(|daps| daps.keys().cloned().collect());
+        for dap_id in daps {
+            proxy.dap_set_breakpoints(
+                dap_id,
+                path.to_path_buf(),
+                source_breakpoints.clone(),
+            );
+        }
+    }
 }
 
 #[derive(Clone, PartialEq)]
@@ -125,6 +379,75 @@ pub struct LapceBreakpoint {
     pub offset: usize,
     pub dap_line: Option<usize>,
     pub active: bool,
+    /// Only break when this expression evaluates to true
+    #[serde(default)]
+    pub condition: Option<String>,
+    /// Only break once the hit count satisfies this expression, e.g. `>= 5`
+    #[serde(default)]
+    pub hit_condition: Option<String>,
+    /// Log this message instead of breaking, with `{expr}` interpolated
+    #[serde(default)]
+    pub log_message: Option<String>,
+}
+
+impl LapceBreakpoint {
+    pub fn new(line: usize, offset: usize) -> Self {
+        Self {
+            id: None,
+            verified: false,
+            message: None,
+            line,
+            offset,
+            dap_line: None,
+            active: true,
+            condition: None,
+            hit_condition: None,
+            log_message: None,
+        }
+    }
+
+    pub fn source_breakpoint(&self) -> SourceBreakpoint {
+        SourceBreakpoint {
+            line: self.line + 1,
+            column: None,
+            condition: self.condition.clone(),
+            hit_condition: self.hit_condition.clone(),
+            log_message: self.log_message.clone(),
+        }
+    }
+
+    pub fn is_logpoint(&self) -> bool {
+        self.log_message.is_some()
+    }
+
+    pub fn is_conditional(&self) -> bool {
+        self.condition.is_some() || self.hit_condition.is_some()
+    }
+
+    pub fn icon(&self) -> &'static str {
+        if self.is_logpoint() {
+            LapceIcons::DEBUG_LOGPOINT
+        } else if self.is_conditional() {
+            LapceIcons::DEBUG_BREAKPOINT_CONDITIONAL
+        } else {
+            LapceIcons::DEBUG_BREAKPOINT
+        }
+    }
+
+    /// A short description of the condition, hit count and log message
+    pub fn detail(&self) -> String {
+        let mut detail = Vec::new();
+        if let Some(condition) = self.condition.as_ref() {
+            detail.push(condition.clone());
+        }
+        if let Some(hit_condition) = self.hit_condition.as_ref() {
+            detail.push(format!("hit {hit_condition}"));
+        }
+        if let Some(log_message) = self.log_message.as_ref() {
+            detail.push(format!("log \"{log_message}\""));
+        }
+        detail.join(", ")
+    }
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -190,6 +513,9 @@ pub struct DapData {
     pub variables_id: RwSignal<usize>,
     pub variables: RwSignal<DapVariable>,
     pub breakline: Memo<Option<(usize, PathBuf)>>,
+    /// The stack frame that expressions are evaluated in
+    pub frame_id: RwSignal<Option<usize>>,
+    pub console: RwSignal<im::Vector<DebugConsoleEntry>>,
     pub common: Rc<CommonData>,
 }
 
@@ -243,6 +569,8 @@ pub fn new(
                 children_expanded_count: 0,
             }),
             breakline,
+            frame_id: cx.create_rw_signal(None),
+            console: cx.create_rw_signal(im::Vector::new()),
             common,
         }
     }
@@ -265,6 +593,7 @@ pub fn stopped(
         for (thread_id, frames) in stack_traces {
             let is_main_thread = main_thread_id.as_ref() == Some(thread_id);
             if is_main_thread {
+                self.frame_id.set(frames.first().map(|frame| frame.id));
                 if let Some(frame) = frames.first() {
                     if let Some(path) =
                         frame.source.as_ref().and_then(|source| source.path.clone())
@@ -333,6 +662,50 @@ pub fn stopped(
         });
     }
 
+    pub fn output(&self, output: &Output) {
+        let kind = if output.category.as_deref() == Some("stderr") {
+            DebugConsoleKind::Error
+        } else {
+            DebugConsoleKind::Output
+        };
+        let text = output.output.trim_end_matches(['\r', '\n']);
+        if !text.is_empty() {
+            self.push_console(kind, text.to_string());
+        }
+    }
+
+    fn push_console(&self, kind: DebugConsoleKind, text: String) {
+        self.console.update(|console| {
+            console.push_back(DebugConsoleEntry { kind, text });
+        });
+    }
+
+    /// Evaluate `expression` in the selected stack frame
+    pub fn evaluate(
+        &self,
+        expression: String,
+        context: EvaluateContext,
+        f: impl FnOnce(Result<EvaluateResponse, String>) + 'static,
+    ) {
+        let send = create_ext_action(self.common.scope, move |result| {
+            let result = match result {
+                Ok(ProxyResponse::DapEvaluateResponse { result }) => Ok(result),
+                Ok(_) => Err("unexpected response".to_string()),
+                Err(err) => Err(err.message),
+            };
+            f(result);
+        });
+        self.common.proxy.dap_evaluate(
+            self.dap_id,
+            expression,
+            self.frame_id.get_untracked(),
+            context,
+            move |result| {
+                send(result);
+            },
+        );
+    }
+
     pub fn toggle_expand(&self, parent: Vec<usize>, reference: usize) {
         self.variables_id.update(|id| {
             *id += 1;
@@ -1,6 +1,6 @@
 use std::{
     borrow::Cow,
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::HashMap,
     ops::Range,
     path::{Path, PathBuf},
@@ -42,9 +42,10 @@
     },
     char_buffer::CharBuffer,
     command::EditCommand,
+    conflict::{parse_conflicts, ConflictBlock, ConflictResolution},
     cursor::{Cursor, CursorAffinity},
     editor::{Action, EditConf, EditType},
-    indent::IndentStyle,
+    indent::{auto_detect_indent_style, IndentStyle},
     language::LapceLanguage,
     line_ending::LineEnding,
     mode::MotionMode,
@@ -55,10 +56,12 @@
     syntax::{edit::SyntaxEdit, BracketParser, Syntax},
     word::{get_char_property, CharClassification, WordCursor},
 };
+use lapce_proxy::buffer::encoding_from_name;
 use lapce_rpc::{
-    buffer::BufferId,
+    buffer::{BufferId, EditorConfigProperties, EndOfLine},
     plugin::PluginId,
     proxy::ProxyResponse,
+    source_control::BlameHunk,
     style::{LineStyle, LineStyles, Style},
 };
 use lapce_xi_rope::{
@@ -73,9 +76,14 @@
 use smallvec::SmallVec;
 
 use crate::{
-    command::{CommandKind, LapceCommand},
+    alert::AlertButton,
+    command::{CommandKind, InternalCommand, LapceCommand},
     config::{color::LapceColor, LapceConfig},
-    editor::{compute_screen_lines, gutter::FoldingRanges, EditorData},
+    editor::{
+        compute_screen_lines,
+        gutter::{FoldingRange, FoldingRanges},
+        EditorData,
+    },
     find::{Find, FindProgress, FindResult},
     history::DocumentHistory,
     keypress::KeyPressFocus,
@@ -88,6 +96,10 @@
     workspace::LapceWorkspace,
 };
 
+/// How long the buffer has to stay unchanged after an edit before its blame is
+/// retrieved again
+const BLAME_DEBOUNCE_MS: u64 = 500;
+
 #[derive(Clone, Debug)]
 pub struct DiagnosticData {
     pub expanded: RwSignal<bool>,
@@ -153,6 +165,9 @@ pub struct DocInfo {
     pub path: PathBuf,
     pub scroll_offset: (f64, f64),
     pub cursor_offset: usize,
+    /// The start lines of the folded ranges
+    #[serde(default)]
+    pub folded_lines: Vec<usize>,
 }
 
 /// (Offset -> (Plugin the code actions are from, Code Actions))
@@ -170,6 +185,10 @@ pub struct Doc {
     /// Whether the buffer's content has been loaded/initialized into the buffer.
     pub loaded: RwSignal<bool>,
     pub buffer: RwSignal<Buffer>,
+    /// The name of the encoding the file is read and saved with
+    pub encoding: RwSignal<String>,
+    /// The `.editorconfig` properties that apply to the file
+    pub editorconfig: RwSignal<EditorConfigProperties>,
     pub syntax: RwSignal<Syntax>,
     semantic_styles: RwSignal<Option<Spans<Style>>>,
     /// Inlay hints for the document
@@ -192,10 +211,22 @@ pub struct Doc {
     pub code_lens: RwSignal<AllCodeLens>,
 
     pub folding_ranges: RwSignal<FoldingRanges>,
+    /// The folded lines restored from the db, applied once the ranges are known
+    pub folds_to_restore: RwSignal<Vec<usize>>,
+    /// Whether the folding ranges came from a language server, rather than from
+    /// the syntax tree or the indentation
+    lsp_folding: Rc<Cell<bool>>,
 
     /// Stores information about different versions of the document from source control.
     histories: RwSignal<im::HashMap<String, DocumentHistory>>,
     pub head_changes: RwSignal<im::Vector<DiffLines>>,
+    /// The commits that last changed the lines, if blame is shown
+    pub blame: RwSignal<im::Vector<BlameHunk>>,
+    /// Whether git reports unresolved conflicts in the file
+    pub conflicted: RwSignal<bool>,
+    /// The conflict blocks left in the file, which are only looked for when it
+    /// is conflicted
+    pub conflicts: RwSignal<im::Vector<ConflictBlock>>,
 
     line_styles: Rc<RefCell<LineStyles>>,
     pub parser: Rc<RefCell<BracketParser>>,
@@ -229,6 +260,8 @@ pub fn new(
             scope: cx,
             buffer_id: BufferId::next(),
             buffer: cx.create_rw_signal(Buffer::new("")),
+            encoding: cx.create_rw_signal("UTF-8".to_string()),
+            editorconfig: cx.create_rw_signal(EditorConfigProperties::default()),
             syntax: cx.create_rw_signal(syntax),
             line_styles: Rc::new(RefCell::new(HashMap::new())),
             parser: Rc::new(RefCell::new(BracketParser::new(
@@ -251,6 +284,9 @@ pub fn new(
             loaded: cx.create_rw_signal(false),
             histories: cx.create_rw_signal(im::HashMap::new()),
             head_changes: cx.create_rw_signal(im::Vector::new()),
+            blame: cx.create_rw_signal(im::Vector::new()),
+            conflicted: cx.create_rw_signal(false),
+            conflicts: cx.create_rw_signal(im::Vector::new()),
             sticky_headers: Rc::new(RefCell::new(HashMap::new())),
             code_actions: cx.create_rw_signal(im::HashMap::new()),
             find_result: FindResult::new(cx),
@@ -260,6 +296,8 @@ pub fn new(
             code_lens: cx.create_rw_signal(im::HashMap::new()),
             document_symbol_data: cx.create_rw_signal(None),
             folding_ranges: cx.create_rw_signal(FoldingRanges::default()),
+            folds_to_restore: cx.create_rw_signal(Vec::new()),
+            lsp_folding: Rc::new(Cell::new(false)),
         }
     }
 
@@ -279,6 +317,8 @@ pub fn new_content(
             scope: cx,
             buffer_id: BufferId::next(),
             buffer: cx.create_rw_signal(Buffer::new("")),
+            encoding: cx.create_rw_signal("UTF-8".to_string()),
+            editorconfig: cx.create_rw_signal(EditorConfigProperties::default()),
             syntax: cx.create_rw_signal(Syntax::plaintext()),
             line_styles: Rc::new(RefCell::new(HashMap::new())),
             parser: Rc::new(RefCell::new(BracketParser::new(
@@ -301,6 +341,9 @@ pub fn new_content(
             content: cx.create_rw_signal(content),
             histories: cx.create_rw_signal(im::HashMap::new()),
             head_changes: cx.create_rw_signal(im::Vector::new()),
+            blame: cx.create_rw_signal(im::Vector::new()),
+            conflicted: cx.create_rw_signal(false),
+            conflicts: cx.create_rw_signal(im::Vector::new()),
             sticky_headers: Rc::new(RefCell::new(HashMap::new())),
             loaded: cx.create_rw_signal(true),
             find_result: FindResult::new(cx),
@@ -311,6 +354,8 @@ pub fn new_content(
             code_lens: cx.create_rw_signal(im::HashMap::new()),
             document_symbol_data: cx.create_rw_signal(None),
             folding_ranges: cx.create_rw_signal(FoldingRanges::default()),
+            folds_to_restore: cx.create_rw_signal(Vec::new()),
+            lsp_folding: Rc::new(Cell::new(false)),
         }
     }
 
@@ -330,6 +375,8 @@ pub fn new_history(
             scope: cx,
             buffer_id: BufferId::next(),
             buffer: cx.create_rw_signal(Buffer::new("")),
+            encoding: cx.create_rw_signal("UTF-8".to_string()),
+            editorconfig: cx.create_rw_signal(EditorConfigProperties::default()),
             syntax: cx.create_rw_signal(syntax),
             line_styles: Rc::new(RefCell::new(HashMap::new())),
             parser: Rc::new(RefCell::new(BracketParser::new(
@@ -354,6 +401,9 @@ pub fn new_history(
             loaded: cx.create_rw_signal(true),
             histories: cx.create_rw_signal(im::HashMap::new()),
             head_changes: cx.create_rw_signal(im::Vector::new()),
+            blame: cx.create_rw_signal(im::Vector::new()),
+            conflicted: cx.create_rw_signal(false),
+            conflicts: cx.create_rw_signal(im::Vector::new()),
             code_actions: cx.create_rw_signal(im::HashMap::new()),
             find_result: FindResult::new(cx),
             preedit: PreeditData::new(cx),
@@ -362,6 +412,8 @@ pub fn new_history(
             code_lens: cx.create_rw_signal(im::HashMap::new()),
             document_symbol_data: cx.create_rw_signal(None),
             folding_ranges: cx.create_rw_signal(FoldingRanges::default()),
+            folds_to_restore: cx.create_rw_signal(Vec::new()),
+            lsp_folding: Rc::new(Cell::new(false)),
         }
     }
 
@@ -424,6 +476,12 @@ pub fn set_syntax(&self, syntax: Syntax) {
         });
     }
 
+    /// The language of the document, which selects the
+    /// `[language.<name>.editor]` settings that apply to it.
+    pub fn language(&self) -> LapceLanguage {
+        self.syntax.with_untracked(|syntax| syntax.language)
+    }
+
     /// Set the syntax highlighting this document should use.
     pub fn set_language(&self, language: LapceLanguage) {
         self.syntax.set(Syntax::from_language(language));
@@ -440,13 +498,34 @@ pub fn loaded(&self) -> bool {
 
     //// Initialize the content with some text, this marks the document as loaded.
     pub fn init_content(&self, content: Rope) {
+        let editorconfig = self.editorconfig.get_untracked();
         batch(|| {
             self.syntax.with_untracked(|syntax| {
                 self.buffer.update(|buffer| {
+                    // An indentation set by `.editorconfig` wins over the one of
+                    // the file itself. Detecting it while the buffer is still
+                    // empty always falls back to the given style.
+                    let detected = auto_detect_indent_style(&content)
+                        .unwrap_or_else(|| {
+                            IndentStyle::from_str(syntax.language.indent_unit())
+                        });
+                    let style = editorconfig_indent_style(&editorconfig, detected);
+                    buffer.detect_indent(|| style);
                     buffer.init_content(content);
-                    buffer.detect_indent(|| {
-                        IndentStyle::from_str(syntax.language.indent_unit())
-                    });
+                    match editorconfig.end_of_line {
+                        Some(EndOfLine::Lf) => {
+                            buffer.set_line_ending(LineEnding::Lf)
+                        }
+                        Some(EndOfLine::CrLf) => {
+                            buffer.set_line_ending(LineEnding::CrLf)
+                        }
+                        Some(EndOfLine::Cr) => {
+                            tracing::warn!(
+                                "end_of_line = cr from .editorconfig isn't supported"
+                            );
+                        }
+                        None => {}
+                    }
                 });
             });
             self.loaded.set(true);
@@ -505,6 +584,7 @@ pub fn do_insert(
         }
 
         let old_cursor = cursor.mode.clone();
+        let editor_config = config.editor_for(self.language());
         let deltas = self.syntax.with_untracked(|syntax| {
             self.buffer
                 .try_update(|buffer| {
@@ -515,8 +595,8 @@ pub fn do_insert(
                         &|buffer, c, offset| {
                             syntax_prev_unmatched(buffer, syntax, c, offset)
                         },
-                        config.editor.auto_closing_matching_pairs,
-                        config.editor.auto_surround,
+                        editor_config.auto_closing_matching_pairs,
+                        editor_config.auto_surround,
                     )
                 })
                 .unwrap()
@@ -604,6 +684,7 @@ pub fn apply_deltas(&self, deltas: &[(Rope, RopeDelta, InvalLines)]) {
                 self.update_diagnostics(delta);
                 self.update_completion_lens(delta);
                 self.update_find_result(delta);
+                self.update_folding_ranges(delta, &inval.old_text);
                 if let DocContent::File { path, .. } = self.content.get_untracked() {
                     self.update_breakpoints(delta, &path, &inval.old_text);
                     self.common.proxy.update(
@@ -625,6 +706,7 @@ pub fn apply_deltas(&self, deltas: &[(Rope, RopeDelta, InvalLines)]) {
             })
             .collect();
         self.on_update(Some(edits));
+        self.check_blame();
     }
 
     pub fn is_pristine(&self) -> bool {
@@ -647,6 +729,7 @@ pub fn line_ending(&self) -> LineEnding {
         batch(|| {
             self.trigger_syntax_change(edits);
             self.trigger_head_change();
+            self.update_conflicts();
             self.check_auto_save();
             self.get_inlay_hints();
             self.find_result.reset();
@@ -708,7 +791,7 @@ fn check_auto_save(&self) {
             let doc = self.clone();
             let scope = self.scope;
             let proxy = self.common.proxy.clone();
-            let format = config.editor.format_on_save;
+            let format = config.editor_for(self.language()).format_on_save;
             exec_after(
                 Duration::from_millis(config.editor.autosave_interval),
                 move |_| {
@@ -784,6 +867,11 @@ fn update_inlay_hints(&self, delta: &RopeDelta) {
         let send = create_ext_action(self.scope, move |syntax| {
             if doc.buffer.with_untracked(|b| b.rev()) == rev {
                 doc.syntax.set(syntax);
+                if !doc.lsp_folding.get() {
+                    let folding = doc.fallback_folding_ranges();
+                    doc.folding_ranges
+                        .update(|ranges| ranges.set_ranges(folding));
+                }
                 doc.do_bracket_colorization();
                 doc.clear_style_cache();
                 doc.clear_sticky_headers_cache();
@@ -1089,41 +1177,88 @@ pub fn init_diagnostics(&self) {
         self.clear_code_actions();
     }
 
+    /// Request the folding ranges from the language server, falling back to the
+    /// syntax tree and then to the indentation when there is no server.
     pub fn get_folding_range(&self) {
-        // let cx = self.scope;
-        // let doc = self.clone();
-        // let rev = self.rev();
-        // if let DocContent::File { path, .. } = doc.content.get_untracked() {
-        //     let send = create_ext_action(cx, {
-        //         move |result| {
-        //             if rev != doc.rev() {
-        //                 return;
-        //             }
-        //             if let Ok(ProxyResponse::LspFoldingRangeResponse {
-        //                 resp, ..
-        //             }) = result
-        //             {
-        //                 let folding = resp
-        //                     .unwrap_or_default()
-        //                     .into_iter()
-        //                     .map(|x| {
-        //                         crate::editor::gutter::FoldingRange::from_lsp(x)
-        //                     })
-        //                     .sorted_by(|x, y| x.start.line.cmp(&y.start.line))
-        //                     .collect();
-        //                 doc.folding_ranges.update(|symbol| {
-        //                     symbol.0 = folding;
-        //                 });
-        //             }
-        //         }
-        //     });
-
-        //     self.common
-        //         .proxy
-        //         .get_lsp_folding_range(path, move |result| {
-        //             send(result);
-        //         });
-        // }
+        if !self.loaded() {
+            return;
+        }
+
+        let path =
+            if let DocContent::File { path, .. } = self.content.get_untracked() {
+                path
+            } else {
+                return;
+            };
+
+        let cx = self.scope;
+        let doc = self.clone();
+        let rev = self.rev();
+        let send = create_ext_action(cx, move |result| {
+            if rev != doc.rev() {
+                return;
+            }
+            let folding = match result {
+                Ok(ProxyResponse::LspFoldingRangeResponse {
+                    resp: Some(resp),
+                    ..
+                }) if !resp.is_empty() => {
+                    doc.lsp_folding.set(true);
+                    resp.into_iter()
+                        .map(FoldingRange::from_lsp)
+                        .sorted_by(|x, y| x.start.line.cmp(&y.start.line))
+                        .collect()
+                }
+                _ => {
+                    doc.lsp_folding.set(false);
+                    doc.fallback_folding_ranges()
+                }
+            };
+            doc.folding_ranges.update(|ranges| {
+                ranges.set_ranges(folding);
+                let folds = doc.folds_to_restore.get_untracked();
+                if !folds.is_empty() {
+                    ranges.fold_lines(&folds);
+                }
+            });
+            doc.folds_to_restore.set(Vec::new());
+        });
+
+        self.common
+            .proxy
+            .get_lsp_folding_range(path, move |result| {
+                send(result);
+            });
+    }
+
+    /// The folding ranges of the syntax tree, or of the indentation if the
+    /// language has no syntax tree.
+    fn fallback_folding_ranges(&self) -> Vec<FoldingRange> {
+        let ranges = self.syntax.with_untracked(|syntax| {
+            if syntax.rev != self.rev() {
+                return None;
+            }
+            syntax.folding_ranges()
+        });
+        if let Some(ranges) = ranges.filter(|r| !r.is_empty()) {
+            return ranges
+                .into_iter()
+                .map(|(start, end)| FoldingRange::new(start, end))
+                .collect();
+        }
+
+        let tab_width = self
+            .editorconfig
+            .with_untracked(|editorconfig| editorconfig.tab_width)
+            .unwrap_or_else(|| {
+                let language = self.language();
+                self.common
+                    .config
+                    .with_untracked(|config| config.editor_for(language).tab_width)
+            });
+        self.buffer.with_untracked(|buffer| {
+            FoldingRanges::from_indentation(buffer.text(), tab_width).0
+        })
     }
 
     /// Get the current completion lens text
@@ -1151,6 +1286,29 @@ pub fn clear_completion_lens(&self) {
         }
     }
 
+    /// Update the folding ranges' lines after an edit so that the folds stay on the
+    /// same code until the ranges are requested again.
+    fn update_folding_ranges(&self, delta: &RopeDelta, old_text: &Rope) {
+        if self.folding_ranges.with_untracked(|r| r.0.is_empty()) {
+            return;
+        }
+
+        let line_of = |line: u32| {
+            let offset = old_text.offset_of_line(line as usize);
+            let offset = Transformer::new(delta).transform(offset, false);
+            self.buffer
+                .with_untracked(|buffer| buffer.line_of_offset(offset))
+                as u32
+        };
+        self.folding_ranges.update(|ranges| {
+            for range in ranges.0.iter_mut() {
+                range.start.line = line_of(range.start.line);
+                range.end.line = line_of(range.end.line);
+            }
+            ranges.0.retain(|range| range.start.line < range.end.line);
+        });
+    }
+
     fn update_breakpoints(&self, delta: &RopeDelta, path: &Path, old_text: &Rope) {
         if self
             .common
@@ -1358,6 +1516,133 @@ pub fn retrieve_head(&self) {
                 });
             });
         }
+
+        let config = self.common.config.get_untracked();
+        if config.editor.blame_end_of_line || config.editor.blame_gutter {
+            self.retrieve_blame();
+        }
+    }
+
+    /// Retrieve the commits that last changed the lines of the file
+    pub fn retrieve_blame(&self) {
+        let DocContent::File { path, .. } = self.content.get_untracked() else {
+            return;
+        };
+
+        let rev = self.rev();
+        let buffer = self.buffer;
+        let blame = self.blame;
+        let send = create_ext_action(self.scope, move |result| {
+            if let Ok(ProxyResponse::GitBlameResponse { hunks }) = result {
+                // The lines may have moved if the buffer changed since
+                if buffer.with_untracked(|buffer| buffer.rev()) == rev {
+                    blame.set(hunks.into());
+                }
+            }
+        });
+        self.common.proxy.git_blame(path, move |result| {
+            send(result);
+        });
+    }
+
+    /// Retrieve the blame again once the buffer stops changing for a while, as
+    /// the lines it was retrieved for moved
+    fn check_blame(&self) {
+        let config = self.common.config.get_untracked();
+        if !config.editor.blame_end_of_line && !config.editor.blame_gutter {
+            return;
+        }
+
+        let rev = self.rev();
+        let doc = self.clone();
+        exec_after(Duration::from_millis(BLAME_DEBOUNCE_MS), move |_| {
+            let current_rev = doc
+                .buffer
+                .try_with_untracked(|b| b.as_ref().map(|b| b.rev()));
+            if current_rev == Some(rev) {
+                doc.retrieve_blame();
+            }
+        });
+    }
+
+    /// The blame of the commit that last changed `line`
+    pub fn blame_of_line(&self, line: usize) -> Option<BlameHunk> {
+        self.blame.with_untracked(|blame| {
+            let index = blame
+                .binary_search_by(|hunk| {
+                    if hunk.contains_line(line) {
+                        std::cmp::Ordering::Equal
+                    } else if hunk.start_line > line {
+                        std::cmp::Ordering::Greater
+                    } else {
+                        std::cmp::Ordering::Less
+                    }
+                })
+                .ok()?;
+            blame.get(index).cloned()
+        })
+    }
+
+    /// Mark whether git reports unresolved conflicts in the file
+    pub fn set_conflicted(&self, conflicted: bool) {
+        if self.conflicted.get_untracked() != conflicted {
+            self.conflicted.set(conflicted);
+            self.update_conflicts();
+        }
+    }
+
+    fn update_conflicts(&self) {
+        let conflicts: im::Vector<ConflictBlock> = if self.conflicted.get_untracked()
+        {
+            self.buffer.with_untracked(|buffer| {
+                parse_conflicts(buffer.text().lines(..)).into()
+            })
+        } else {
+            im::Vector::new()
+        };
+        if self.conflicts.with_untracked(|c| c != &conflicts) {
+            self.conflicts.set(conflicts);
+        }
+    }
+
+    /// The conflict block `line` is part of
+    pub fn conflict_at_line(&self, line: usize) -> Option<ConflictBlock> {
+        self.conflicts.with_untracked(|conflicts| {
+            conflicts
+                .iter()
+                .find(|conflict| conflict.contains_line(line))
+                .copied()
+        })
+    }
+
+    /// Replace the conflict block `conflict` with the lines of the side(s)
+    /// `resolution` keeps
+    pub fn resolve_conflict(
+        &self,
+        conflict: ConflictBlock,
+        resolution: ConflictResolution,
+    ) {
+        let (range, text) = self.buffer.with_untracked(|buffer| {
+            let text = conflict
+                .kept_lines(resolution)
+                .into_iter()
+                .map(|lines| {
+                    buffer
+                        .slice_to_cow(
+                            buffer.offset_of_line(lines.start)
+                                ..buffer.offset_of_line(lines.end),
+                        )
+                        .to_string()
+                })
+                .collect::<String>();
+            let range = buffer.offset_of_line(conflict.start)
+                ..buffer.offset_of_line(conflict.end + 1);
+            (range, text)
+        });
+        self.do_raw_edit(
+            &[(Selection::region(range.start, range.end), text.as_str())],
+            EditType::Other,
+        );
     }
 
     pub fn trigger_head_change(&self) {
@@ -1408,6 +1693,8 @@ pub fn save(&self, after_action: impl FnOnce() + 'static) {
         if let DocContent::File { path, .. } = content {
             let rev = self.rev();
             let buffer = self.buffer;
+            let doc = self.clone();
+            let saved_path = path.clone();
             let send = create_ext_action(self.scope, move |result| {
                 if let Ok(ProxyResponse::SaveResponse {}) = result {
                     let current_rev = buffer.with_untracked(|buffer| buffer.rev());
@@ -1415,6 +1702,19 @@ pub fn save(&self, after_action: impl FnOnce() + 'static) {
                         buffer.update(|buffer| {
                             buffer.set_pristine();
                         });
+                        let config = doc.common.config.get_untracked();
+                        if config.editor.blame_end_of_line
+                            || config.editor.blame_gutter
+                        {
+                            doc.retrieve_blame();
+                        }
+                        // Staging a conflicted file marks it resolved, once no
+                        // conflict markers are left
+                        if doc.conflicted.get_untracked()
+                            && doc.conflicts.with_untracked(|c| c.is_empty())
+                        {
+                            doc.common.proxy.git_stage_files(vec![saved_path]);
+                        }
                         after_action();
                     }
                 }
@@ -1426,6 +1726,141 @@ pub fn save(&self, after_action: impl FnOnce() + 'static) {
         }
     }
 
+    /// Trim trailing whitespace and add a final newline, for the files where
+    /// `.editorconfig` asks for it
+    pub fn apply_editorconfig_on_save(&self) {
+        let editorconfig = self.editorconfig.get_untracked();
+        let trim = editorconfig.trim_trailing_whitespace == Some(true);
+        let final_newline = editorconfig.insert_final_newline == Some(true);
+        if !trim && !final_newline {
+            return;
+        }
+
+        let edits = self.buffer.with_untracked(|buffer| {
+            let mut edits: Vec<(Selection, &'static str)> = Vec::new();
+            if trim {
+                for line in 0..buffer.num_lines() {
+                    let start = buffer.offset_of_line(line);
+                    let end = buffer.line_end_offset(line, true);
+                    let content = buffer.slice_to_cow(start..end);
+                    let trimmed = content.trim_end().len();
+                    if trimmed < content.len() {
+                        edits.push((Selection::region(start + trimmed, end), ""));
+                    }
+                }
+            }
+
+            let len = buffer.len();
+            if final_newline
+                && len > 0
+                && !buffer.slice_to_cow(len - 1..len).ends_with('\n')
+            {
+                let newline = buffer.line_ending().get_chars();
+                // Trimming the last line would otherwise be a second edit at the
+                // very same end offset
+                match edits.last_mut() {
+                    Some((selection, text)) if selection.max_offset() == len => {
+                        *text = newline;
+                    }
+                    _ => edits.push((Selection::caret(len), newline)),
+                }
+            }
+            edits
+        });
+        if !edits.is_empty() {
+            self.do_raw_edit(&edits, EditType::Other);
+        }
+    }
+
+    /// Read the file again with another encoding, asking first when that would
+    /// drop unsaved changes. The encoding can be any label `encoding_rs` knows,
+    /// such as `latin1`, and is kept under its canonical name.
+    pub fn reopen_with_encoding(&self, encoding: &str) {
+        let DocContent::File { path, .. } = self.content.get_untracked() else {
+            return;
+        };
+        let encoding = match encoding_from_name(encoding) {
+            Ok(encoding) => encoding.name(),
+            Err(err) => {
+                tracing::error!("{err}");
+                return;
+            }
+        };
+        if self.is_pristine() {
+            self.reload_with_encoding(path, encoding.to_string());
+            return;
+        }
+
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let doc = self.clone();
+        let encoding = encoding.to_string();
+        let internal_command = self.common.internal_command;
+        internal_command.send(InternalCommand::ShowAlert {
+            title: format!("Do you want to reopen {name} with {encoding}?"),
+            msg: "Your unsaved changes will be lost.".to_string(),
+            buttons: vec![AlertButton {
+                text: "Reopen".to_string(),
+                action: Rc::new(move || {
+                    internal_command.send(InternalCommand::HideAlert);
+                    doc.reload_with_encoding(path.clone(), encoding.clone());
+                }),
+            }],
+        });
+    }
+
+    fn reload_with_encoding(&self, path: PathBuf, encoding: String) {
+        let doc = self.clone();
+        let new_encoding = encoding.clone();
+        let send = create_ext_action(self.scope, move |result| {
+            if let Ok(ProxyResponse::ReopenWithEncodingResponse { content }) = result
+            {
+                doc.encoding.set(new_encoding);
+                doc.content.update(|content| {
+                    if let DocContent::File { read_only, .. } = content {
+                        *read_only = false;
+                    }
+                });
+                doc.reload(Rope::from(content), true);
+            }
+        });
+        self.common
+            .proxy
+            .reopen_with_encoding(path, encoding, move |result| {
+                send(result);
+            });
+    }
+
+    /// Save the file with another encoding, which is then kept for later saves
+    /// under its canonical name
+    pub fn save_with_encoding(&self, encoding: &str) {
+        let DocContent::File { path, .. } = self.content.get_untracked() else {
+            return;
+        };
+        let encoding = match encoding_from_name(encoding) {
+            Ok(encoding) => encoding.name(),
+            Err(err) => {
+                tracing::error!("{err}");
+                return;
+            }
+        };
+        let doc = self.clone();
+        let new_encoding = encoding.to_string();
+        let send = create_ext_action(self.scope, move |result| {
+            if let Ok(ProxyResponse::Success {}) = result {
+                doc.encoding.set(new_encoding);
+                doc.save(|| {});
+            }
+        });
+        self.common
+            .proxy
+            .set_encoding(path, encoding.to_string(), move |result| {
+                send(result);
+            });
+    }
+
     pub fn set_inline_completion(
         &self,
         inline_completion: String,
@@ -1580,6 +2015,7 @@ fn compute_screen_lines(
             editor.lines(),
             editor.text_prov(),
             editor.config_id(),
+            &editor_data.folded_vlines(),
         )
     }
 
@@ -1985,12 +2421,20 @@ fn indent_line(&self, _: EditorId, line: usize, line_content: &str) -> usize {
     }
 
     fn tab_width(&self, _: EditorId, _line: usize) -> usize {
-        self.config.with_untracked(|config| config.editor.tab_width)
+        self.doc
+            .editorconfig
+            .with_untracked(|editorconfig| editorconfig.tab_width)
+            .unwrap_or_else(|| {
+                let language = self.doc.language();
+                self.config
+                    .with_untracked(|config| config.editor_for(language).tab_width)
+            })
     }
 
     fn atomic_soft_tabs(&self, _: EditorId, _line: usize) -> bool {
+        let language = self.doc.language();
         self.config
-            .with_untracked(|config| config.editor.atomic_soft_tabs)
+            .with_untracked(|config| config.editor_for(language).atomic_soft_tabs)
     }
 
     fn apply_attr_styles(
@@ -2166,6 +2610,26 @@ fn syntax_prev_unmatched(
     }
 }
 
+/// The indentation `.editorconfig` asks for over the `detected` one of the file.
+/// With only `indent_size` set, the detected spaces take that size, while the
+/// detected tabs keep it as their width through `tab_width`.
+fn editorconfig_indent_style(
+    editorconfig: &EditorConfigProperties,
+    detected: IndentStyle,
+) -> IndentStyle {
+    let spaces = |size: Option<usize>| {
+        IndentStyle::Spaces(size.unwrap_or(4).clamp(1, 8) as u8)
+    };
+    match (editorconfig.indent_with_tabs, detected) {
+        (Some(true), _) => IndentStyle::Tabs,
+        (Some(false), _) => spaces(editorconfig.indent_size),
+        (None, IndentStyle::Spaces(_)) if editorconfig.indent_size.is_some() => {
+            spaces(editorconfig.indent_size)
+        }
+        (None, detected) => detected,
+    }
+}
+
 fn should_blink(
     focus: RwSignal<Focus>,
     keyboard_focus: RwSignal<Option<ViewId>>,
@@ -2241,3 +2705,32 @@ fn extra_styles_for_range(
             })
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use lapce_core::indent::IndentStyle;
+    use lapce_rpc::buffer::EditorConfigProperties;
+
+    use super::editorconfig_indent_style;
+
+    #[test]
+    fn test_editorconfig_indent_style() {
+        let style = |indent_with_tabs, indent_size, detected| {
+            let editorconfig = EditorConfigProperties {
+                indent_with_tabs,
+                indent_size,
+                ..Default::default()
+            };
+            editorconfig_indent_style(&editorconfig, detected)
+        };
+        let spaces = IndentStyle::Spaces;
+
+        assert_eq!(style(Some(true), Some(2), spaces(4)), IndentStyle::Tabs);
+        assert_eq!(style(Some(false), Some(2), IndentStyle::Tabs), spaces(2));
+        assert_eq!(style(Some(false), None, IndentStyle::Tabs), spaces(4));
+        // Only `indent_size`, which applies to the detected style
+        assert_eq!(style(None, Some(2), spaces(4)), spaces(2));
+        assert_eq!(style(None, Some(2), IndentStyle::Tabs), IndentStyle::Tabs);
+        assert_eq!(style(None, None, spaces(8)), spaces(8));
+    }
+}
@@ -20,9 +20,10 @@
     },
     views::editor::{
         command::CommandExecuted,
+        core::register::Clipboard,
         id::EditorId,
         movement,
-        text::Document,
+        text::{Document, SystemClipboard},
         view::{
             DiffSection, DiffSectionKind, LineInfo, ScreenLines, ScreenLinesBase,
         },
@@ -36,19 +37,25 @@
     buffer::{
         diff::DiffLines,
         rope_text::{RopeText, RopeTextVal},
-        InvalLines,
+        Buffer, InvalLines,
     },
     command::{
         EditCommand, FocusCommand, MotionModeCommand, MultiSelectionCommand,
         ScrollCommand,
     },
-    cursor::{Cursor, CursorMode},
+    cursor::{Cursor, CursorAffinity, CursorMode},
     editor::EditType,
+    language::LapceLanguage,
     mode::{Mode, MotionMode},
     rope_text_pos::RopeTextPosition,
     selection::{InsertDrift, SelRegion, Selection},
 };
-use lapce_rpc::{buffer::BufferId, plugin::PluginId, proxy::ProxyResponse};
+use lapce_rpc::{
+    buffer::{BufferId, EndOfLine},
+    dap_types::EvaluateContext,
+    plugin::PluginId,
+    proxy::ProxyResponse,
+};
 use lapce_xi_rope::{Rope, RopeDelta, Transformer};
 use lsp_types::{
     CodeActionResponse, CompletionItem, CompletionTextEdit, GotoDefinitionResponse,
@@ -56,11 +63,14 @@
     MarkedString, MarkupKind, Range, TextEdit,
 };
 use nucleo::Utf32Str;
+use regex::RegexBuilder;
 use serde::{Deserialize, Serialize};
+use strum::EnumMessage;
 use view::StickyHeaderInfo;
 
 use self::{
     diff::DiffInfo,
+    gutter::FoldedVLines,
     location::{EditorLocation, EditorPosition},
 };
 use crate::{
@@ -70,6 +80,8 @@
     db::LapceDb,
     doc::{Doc, DocContent},
     editor_tab::EditorTabChild,
+    ex_command::{regex_pattern, regex_replacement, ExCommandKind},
+    find::FindSearchString,
     id::{DiffEditorId, EditorTabId},
     inline_completion::{InlineCompletionItem, InlineCompletionStatus},
     keypress::{condition::Condition, KeyPressFocus},
@@ -83,7 +95,8 @@
         implementation_view::{init_implementation_root, map_to_location},
         kind::PanelKind,
     },
-    snippet::Snippet,
+    snippet::{random_variable, time_variable, Snippet, SnippetTab},
+    source_control::{local_date, time_ago},
     tracing::*,
     window_tab::{CommonData, Focus, WindowTabData},
 };
@@ -204,7 +217,7 @@ pub struct OnScreenFind {
     pub regions: Vec<SelRegion>,
 }
 
-pub type SnippetIndex = Vec<(usize, (usize, usize))>;
+pub type SnippetIndex = Vec<SnippetTab>;
 
 /// Shares data between cloned instances as long as the signals aren't swapped out.
 #[derive(Clone, Debug)]
@@ -423,10 +436,11 @@ fn run_edit_command(&self, cmd: &EditCommand) -> CommandExecuted {
         let text = self.editor.rope_text();
         let is_local = doc.content.with_untracked(|content| content.is_local());
         let modal = self.editor.es.with_untracked(|s| s.modal()) && !is_local;
+        let language = doc.language();
         let smart_tab = self
             .common
             .config
-            .with_untracked(|config| config.editor.smart_tab);
+            .with_untracked(|config| config.editor_for(language).smart_tab);
         let doc_before_edit = text.text().clone();
         let mut cursor = self.editor.cursor.get_untracked();
         let mut register = self.common.register.get_untracked();
@@ -692,19 +706,18 @@ fn run_move_command(
                 register,
             )
         });
+        self.skip_folded_lines(&mut cursor, movement, mods);
 
         self.editor.cursor.set(cursor);
 
         if self.snippet.with_untracked(|s| s.is_some()) {
             self.snippet.update(|snippet| {
                 let offset = self.editor.cursor.get_untracked().offset();
-                let mut within_region = false;
-                for (_, (start, end)) in snippet.as_mut().unwrap() {
-                    if offset >= *start && offset <= *end {
-                        within_region = true;
-                        break;
-                    }
-                }
+                let within_region = snippet
+                    .as_ref()
+                    .unwrap()
+                    .iter()
+                    .any(|tab| tab.contains(offset));
                 if !within_region {
                     *snippet = None;
                 }
@@ -957,75 +970,10 @@ pub fn run_focus_command(
                 self.cancel_inline_completion();
             }
             FocusCommand::JumpToNextSnippetPlaceholder => {
-                self.snippet.update(|snippet| {
-                    if let Some(snippet_mut) = snippet.as_mut() {
-                        let mut current = 0;
-                        let offset = self.cursor().get_untracked().offset();
-                        for (i, (_, (start, end))) in snippet_mut.iter().enumerate()
-                        {
-                            if *start <= offset && offset <= *end {
-                                current = i;
-                                break;
-                            }
-                        }
-
-                        let last_placeholder = current + 1 >= snippet_mut.len() - 1;
-
-                        if let Some((_, (start, end))) = snippet_mut.get(current + 1)
-                        {
-                            let mut selection =
-                                lapce_core::selection::Selection::new();
-                            let region = lapce_core::selection::SelRegion::new(
-                                *start, *end, None,
-                            );
-                            selection.add_region(region);
-                            self.cursor().update(|cursor| {
-                                cursor.set_insert(selection);
-                            });
-                        }
-
-                        if last_placeholder {
-                            *snippet = None;
-                        }
-                        // self.update_signature();
-                        self.cancel_completion();
-                        self.cancel_inline_completion();
-                    }
-                });
+                self.jump_snippet_tab(true);
             }
             FocusCommand::JumpToPrevSnippetPlaceholder => {
-                self.snippet.update(|snippet| {
-                    if let Some(snippet_mut) = snippet.as_mut() {
-                        let mut current = 0;
-                        let offset = self.cursor().get_untracked().offset();
-                        for (i, (_, (start, end))) in snippet_mut.iter().enumerate()
-                        {
-                            if *start <= offset && offset <= *end {
-                                current = i;
-                                break;
-                            }
-                        }
-
-                        if current > 0 {
-                            if let Some((_, (start, end))) =
-                                snippet_mut.get(current - 1)
-                            {
-                                let mut selection =
-                                    lapce_core::selection::Selection::new();
-                                let region = lapce_core::selection::SelRegion::new(
-                                    *start, *end, None,
-                                );
-                                selection.add_region(region);
-                                self.cursor().update(|cursor| {
-                                    cursor.set_insert(selection);
-                                });
-                            }
-                            // self.update_signature();
-                            self.cancel_completion();
-                            self.cancel_inline_completion();
-                        }
-                    }
-                });
+                self.jump_snippet_tab(false);
             }
             FocusCommand::GotoDefinition => {
                 self.go_to_definition();
@@ -1713,14 +1661,16 @@ fn has_inline_completions(&self) -> bool {
     }
 
     pub fn select_completion(&self) {
-        let item = self
+        let (item, choice_tab) = self
             .common
             .completion
-            .with_untracked(|c| c.current_item().cloned());
+            .with_untracked(|c| (c.current_item().cloned(), c.choice_tab.clone()));
         self.cancel_completion();
         let doc = self.doc();
         if let Some(item) = item {
-            if item.item.data.is_some() {
+            if let Some(tab) = choice_tab {
+                self.pick_snippet_choice(&tab, &item.item.label);
+            } else if item.item.data.is_some() {
                 let editor = self.clone();
                 let rev = doc.buffer.with_untracked(|buffer| buffer.rev());
                 let path = doc.content.with_untracked(|c| c.path().cloned());
@@ -1785,6 +1735,15 @@ fn update_completion(&self, display_if_empty_input: bool) {
             return;
         }
 
+        // Typing over a snippet tabstop hides its choices
+        if self
+            .common
+            .completion
+            .with_untracked(|c| c.choice_tab.is_some())
+        {
+            self.cancel_completion();
+        }
+
         let doc = self.doc();
         let path = match if doc.loaded() {
             doc.content.with_untracked(|c| c.path().cloned())
@@ -1885,10 +1844,16 @@ fn update_completion(&self, display_if_empty_input: bool) {
                     self.id(),
                     &self.common.proxy,
                     path,
-                    input,
+                    input.clone(),
                     position,
                 );
             }
+
+            // Snippets are not offered after a `.` or `:`
+            if !input.is_empty() || display_if_empty_input {
+                let config = self.common.config.get_untracked();
+                completion.set_snippets(config.snippets_for(doc.language()));
+            }
         });
     }
 
@@ -1968,6 +1933,17 @@ fn apply_completion_item(&self, item: &CompletionItem) -> anyhow::Result<()> {
         let end_offset = buffer.next_code_boundary(offset);
         let selection = Selection::region(start_offset, end_offset);
 
+        if let (lsp_types::InsertTextFormat::SNIPPET, Some(insert_text)) =
+            (text_format, item.insert_text.as_deref())
+        {
+            return self.completion_apply_snippet(
+                insert_text,
+                &selection,
+                additional_edit,
+                start_offset,
+            );
+        }
+
         self.do_edit(
             &selection,
             &[
@@ -1989,7 +1965,8 @@ pub fn completion_apply_snippet(
         additional_edit: Vec<(Selection, &str)>,
         start_offset: usize,
     ) -> anyhow::Result<()> {
-        let snippet = Snippet::from_str(snippet)?;
+        let mut snippet = Snippet::from_str(snippet)?;
+        snippet.resolve_variables(&|name| self.snippet_variable(name));
         let text = snippet.text();
         let mut cursor = self.cursor().get_untracked();
         let old_cursor = cursor.mode.clone();
@@ -2009,7 +1986,7 @@ pub fn completion_apply_snippet(
 
         let mut transformer = Transformer::new(&delta);
         let offset = transformer.transform(start_offset, false);
-        let snippet_tabs = snippet.tabs(offset);
+        let snippet_tabs = snippet.tab_stops(offset);
 
         let doc = self.doc();
         if snippet_tabs.is_empty() {
@@ -2023,11 +2000,8 @@ pub fn completion_apply_snippet(
             return Ok(());
         }
 
-        let mut selection = lapce_core::selection::Selection::new();
-        let (_tab, (start, end)) = &snippet_tabs[0];
-        let region = lapce_core::selection::SelRegion::new(*start, *end, None);
-        selection.add_region(region);
-        cursor.set_insert(selection);
+        let first_tab = snippet_tabs[0].clone();
+        cursor.set_insert(first_tab.selection());
 
         doc.buffer.update(|buffer| {
             buffer.set_cursor_before(old_cursor);
@@ -2036,16 +2010,32 @@ pub fn completion_apply_snippet(
         self.cursor().set(cursor);
         self.apply_deltas(&[(b_text, delta, inval_lines)]);
         self.add_snippet_placeholders(snippet_tabs);
+        if !first_tab.choices.is_empty() {
+            self.show_snippet_choices(first_tab);
+        }
         Ok(())
     }
 
-    fn add_snippet_placeholders(
-        &self,
-        new_placeholders: Vec<(usize, (usize, usize))>,
-    ) {
+    /// Insert `snippet` in place of the selection, with its placeholders
+    /// reachable like those of a completion item's snippet
+    pub fn insert_snippet(&self, snippet: &str) -> anyhow::Result<()> {
+        let selection = self.doc().buffer.with_untracked(|buffer| {
+            self.cursor().get_untracked().edit_selection(buffer)
+        });
+        let start_offset = selection.first().map(|r| r.min()).unwrap_or(0);
+        self.completion_apply_snippet(snippet, &selection, Vec::new(), start_offset)
+    }
+
+    fn add_snippet_placeholders(&self, new_placeholders: Vec<SnippetTab>) {
         self.snippet.update(|snippet| {
             if snippet.is_none() {
-                if new_placeholders.len() > 1 {
+                // A single tabstop still needs the snippet session to show its
+                // choices or to update its transforms when it is left
+                if new_placeholders.len() > 1
+                    || new_placeholders.iter().any(|tab| {
+                        !tab.choices.is_empty() || !tab.transforms.is_empty()
+                    })
+                {
                     *snippet = Some(new_placeholders);
                 }
                 return;
@@ -2053,14 +2043,11 @@ fn add_snippet_placeholders(
 
             let placeholders = snippet.as_mut().unwrap();
 
-            let mut current = 0;
             let offset = self.cursor().get_untracked().offset();
-            for (i, (_, (start, end))) in placeholders.iter().enumerate() {
-                if *start <= offset && offset <= *end {
-                    current = i;
-                    break;
-                }
-            }
+            let current = placeholders
+                .iter()
+                .position(|tab| tab.contains(offset))
+                .unwrap_or(0);
 
             let v = placeholders.split_off(current);
             placeholders.extend_from_slice(&new_placeholders);
@@ -2068,6 +2055,171 @@ fn add_snippet_placeholders(
         });
     }
 
+    /// Move to the next or previous tabstop of the snippet being filled in,
+    /// ending the snippet session once its last tabstop is reached
+    fn jump_snippet_tab(&self, next: bool) {
+        let Some(tabs) = self.snippet.get_untracked() else {
+            return;
+        };
+        let offset = self.cursor().get_untracked().offset();
+        let current = tabs
+            .iter()
+            .position(|tab| tab.contains(offset))
+            .unwrap_or(0);
+        let target = if next {
+            current + 1
+        } else if current > 0 {
+            current - 1
+        } else {
+            return;
+        };
+
+        // self.update_signature();
+        self.cancel_completion();
+        self.cancel_inline_completion();
+        self.apply_snippet_transforms(current);
+
+        // The transforms may have moved the tabstops
+        let Some(tabs) = self.snippet.get_untracked() else {
+            return;
+        };
+        if let Some(tab) = tabs.get(target) {
+            self.cursor().update(|cursor| {
+                cursor.set_insert(tab.selection());
+            });
+            if !tab.choices.is_empty() {
+                self.show_snippet_choices(tab.clone());
+            }
+        }
+        if next && target + 1 >= tabs.len() {
+            self.snippet.set(None);
+        }
+    }
+
+    /// Replace the transformed mirrors of the snippet tabstop at `index` with
+    /// the transform of the text typed in it
+    fn apply_snippet_transforms(&self, index: usize) {
+        let Some(tab) = self.snippet.with_untracked(|s| {
+            s.as_ref().and_then(|tabs| tabs.get(index).cloned())
+        }) else {
+            return;
+        };
+        let Some((start, end)) = tab.ranges.first().copied() else {
+            return;
+        };
+        if tab.transforms.is_empty() {
+            return;
+        }
+
+        let doc = self.doc();
+        let text = doc
+            .buffer
+            .with_untracked(|buffer| buffer.slice_to_cow(start..end).to_string());
+        let new_texts: Vec<String> = tab
+            .transforms
+            .iter()
+            .map(|(_, transform)| transform.apply(&text))
+            .collect();
+        let edits: Vec<(Selection, &str)> = tab
+            .transforms
+            .iter()
+            .zip(&new_texts)
+            .map(|(((start, end), _), new_text)| {
+                (Selection::region(*start, *end), new_text.as_str())
+            })
+            .collect();
+        if let Some(delta) = doc.do_raw_edit(&edits, EditType::Completion) {
+            self.apply_deltas(&[delta]);
+        }
+    }
+
+    /// List the choices of a snippet tabstop in the completion dropdown
+    fn show_snippet_choices(&self, tab: SnippetTab) {
+        let path = self
+            .doc()
+            .content
+            .with_untracked(|content| content.path().cloned())
+            .unwrap_or_default();
+        self.common.completion.update(|completion| {
+            completion.show_choices(self.id(), path, tab);
+        });
+    }
+
+    /// Replace every range of a snippet tabstop with the picked choice
+    fn pick_snippet_choice(&self, tab: &SnippetTab, choice: &str) {
+        let selection = tab.selection();
+        self.do_edit(&selection, &[(selection.clone(), choice)]);
+    }
+
+    /// The value of a snippet variable, `None` for an unknown one
+    fn snippet_variable(&self, name: &str) -> Option<String> {
+        let doc = self.doc();
+        let cursor = self.cursor().get_untracked();
+        let offset = cursor.offset();
+        let path = doc
+            .content
+            .with_untracked(|content| content.path().cloned());
+        let workspace = &self.common.workspace;
+        let workspace_folder = path
+            .as_deref()
+            .and_then(|path| workspace.root_of(path))
+            .or_else(|| workspace.path.clone());
+        let value = match name {
+            "TM_SELECTED_TEXT" => doc.buffer.with_untracked(|buffer| {
+                cursor
+                    .edit_selection(buffer)
+                    .regions()
+                    .iter()
+                    .map(|region| {
+                        buffer.slice_to_cow(region.min()..region.max()).to_string()
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }),
+            "TM_CURRENT_LINE" => doc.buffer.with_untracked(|buffer| {
+                let line = buffer.line_of_offset(offset);
+                buffer
+                    .line_content(line)
+                    .trim_end_matches(['\r', '\n'])
+                    .to_string()
+            }),
+            "TM_CURRENT_WORD" => doc.buffer.with_untracked(|buffer| {
+                let start = buffer.prev_code_boundary(offset);
+                let end = buffer.next_code_boundary(offset);
+                buffer.slice_to_cow(start..end).to_string()
+            }),
+            "TM_LINE_INDEX" => doc
+                .buffer
+                .with_untracked(|buffer| buffer.line_of_offset(offset).to_string()),
+            "TM_LINE_NUMBER" => doc.buffer.with_untracked(|buffer| {
+                (buffer.line_of_offset(offset) + 1).to_string()
+            }),
+            "TM_FILENAME" => {
+                path.as_ref()?.file_name()?.to_string_lossy().to_string()
+            }
+            "TM_FILENAME_BASE" => {
+                path.as_ref()?.file_stem()?.to_string_lossy().to_string()
+            }
+            "TM_DIRECTORY" => path.as_ref()?.parent()?.to_string_lossy().to_string(),
+            "TM_FILEPATH" => path.as_ref()?.to_string_lossy().to_string(),
+            "RELATIVE_FILEPATH" => workspace
+                .relative_path(path.as_ref()?)
+                .to_string_lossy()
+                .to_string(),
+            "WORKSPACE_NAME" => {
+                workspace_folder?.file_name()?.to_string_lossy().to_string()
+            }
+            "WORKSPACE_FOLDER" => workspace_folder?.to_string_lossy().to_string(),
+            "CLIPBOARD" => SystemClipboard::new().get_string()?,
+            "LINE_COMMENT" => doc.language().comment_token().to_string(),
+            _ => {
+                return time_variable(name, &chrono::Local::now())
+                    .or_else(|| random_variable(name));
+            }
+        };
+        Some(value)
+    }
+
     pub fn do_edit(
         &self,
         selection: &Selection,
@@ -2111,6 +2263,63 @@ pub fn do_text_edit(&self, edits: &[TextEdit]) {
         self.do_edit(&selection, &edits);
     }
 
+    /// Replace the selections with `text` as is, without auto closing or indenting
+    pub fn insert_text(&self, text: &str) {
+        let selection = self.doc().buffer.with_untracked(|buffer| {
+            self.cursor().get_untracked().edit_selection(buffer)
+        });
+        self.do_edit(&selection, &[(&selection, text)]);
+    }
+
+    /// The first and last lines of the selection, which the `'<,'>` range of ex
+    /// commands refers to
+    pub fn selection_lines(&self) -> Option<(usize, usize)> {
+        let (start, end) =
+            self.cursor().with_untracked(|cursor| match &cursor.mode {
+                CursorMode::Visual { start, end, .. } => {
+                    Some((*start.min(end), *start.max(end)))
+                }
+                CursorMode::Insert(selection) => {
+                    let start = selection.regions().first()?.min();
+                    let end = selection.regions().last()?.max();
+                    (start != end).then_some((start, end))
+                }
+                CursorMode::Normal(_) => None,
+            })?;
+        Some(self.doc().buffer.with_untracked(|buffer| {
+            (buffer.line_of_offset(start), buffer.line_of_offset(end))
+        }))
+    }
+
+    /// Run an ex command that edits the lines from `start` to `end`, such as `:s`,
+    /// `:g`, `:d` or `:sort`
+    pub fn run_ex_edit(
+        &self,
+        kind: &ExCommandKind,
+        start: usize,
+        end: usize,
+    ) -> anyhow::Result<()> {
+        let doc = self.doc();
+        let search = self.common.find.search_string.get_untracked();
+        let (edits, offset) = doc.buffer.with_untracked(|buffer| {
+            let edits = ex_edits(buffer, kind, start, end, search.as_ref())?;
+            let offset = buffer.offset_of_line(start.min(buffer.last_line()));
+            anyhow::Ok((edits, offset))
+        })?;
+        if edits.is_empty() {
+            return Ok(());
+        }
+
+        let edits = edits
+            .iter()
+            .map(|(range, text)| {
+                (Selection::region(range.start, range.end), text.as_str())
+            })
+            .collect::<Vec<_>>();
+        self.do_edit(&Selection::caret(offset), &edits);
+        Ok(())
+    }
+
     fn apply_deltas(&self, deltas: &[(Rope, RopeDelta, InvalLines)]) {
         if !deltas.is_empty() && !self.confirmed.get_untracked() {
             self.confirmed.set(true);
@@ -2127,22 +2336,16 @@ fn update_snippet_offset(&self, delta: &RopeDelta) {
         if self.snippet.with_untracked(|s| s.is_some()) {
             self.snippet.update(|snippet| {
                 let mut transformer = Transformer::new(delta);
-                *snippet = Some(
-                    snippet
-                        .as_ref()
-                        .unwrap()
-                        .iter()
-                        .map(|(tab, (start, end))| {
-                            (
-                                *tab,
-                                (
-                                    transformer.transform(*start, false),
-                                    transformer.transform(*end, true),
-                                ),
-                            )
-                        })
-                        .collect(),
-                );
+                for tab in snippet.iter_mut().flatten() {
+                    let ranges = tab
+                        .ranges
+                        .iter_mut()
+                        .chain(tab.transforms.iter_mut().map(|(range, _)| range));
+                    for (start, end) in ranges {
+                        *start = transformer.transform(*start, false);
+                        *end = transformer.transform(*end, true);
+                    }
+                }
             });
         }
     }
@@ -2334,6 +2537,7 @@ pub fn save(
         }
 
         let config = self.common.config.get_untracked();
+        let editor_config = config.editor_for(doc.language());
         let DocContent::File { path, .. } = content else {
             return;
         };
@@ -2341,12 +2545,25 @@ pub fn save(
         // If we are disallowing formatting (such as due to a manual save without formatting),
         // then we skip normalizing line endings as a common reason for that is large files.
         // (but if the save is typical, even if config format_on_save is false, we normalize)
-        if allow_formatting && config.editor.normalize_line_endings {
+        // An `end_of_line` in `.editorconfig` is normalized to even when the
+        // setting is off
+        let editorconfig_eol = doc.editorconfig.with_untracked(|editorconfig| {
+            matches!(
+                editorconfig.end_of_line,
+                Some(EndOfLine::Lf | EndOfLine::CrLf)
+            )
+        });
+        if allow_formatting
+            && (editor_config.normalize_line_endings || editorconfig_eol)
+        {
             self.run_edit_command(&EditCommand::NormalizeLineEndings);
         }
+        if allow_formatting {
+            doc.apply_editorconfig_on_save();
+        }
 
         let rev = doc.rev();
-        let format_on_save = allow_formatting && config.editor.format_on_save;
+        let format_on_save = allow_formatting && editor_config.format_on_save;
         if format_on_save {
             let editor = self.clone();
             let send = create_ext_action(self.scope, move |result| {
@@ -2493,6 +2710,85 @@ fn replace_all(&self, text: &str) {
         }
     }
 
+    /// Change the folded ranges for one of the fold commands, moving the cursor
+    /// out of the lines that become hidden.
+    pub fn run_fold_command(&self, cmd: &LapceWorkbenchCommand) {
+        let doc = self.doc();
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        let line = doc.buffer.with_untracked(|b| b.line_of_offset(offset));
+        doc.folding_ranges.update(|ranges| match cmd {
+            LapceWorkbenchCommand::Fold => ranges.fold(line),
+            LapceWorkbenchCommand::Unfold => ranges.unfold(line),
+            LapceWorkbenchCommand::ToggleFold => ranges.toggle_fold(line),
+            LapceWorkbenchCommand::FoldAll => ranges.fold_all(),
+            LapceWorkbenchCommand::UnfoldAll => ranges.unfold_all(),
+            LapceWorkbenchCommand::FoldLevel1 => ranges.fold_level(1),
+            LapceWorkbenchCommand::FoldLevel2 => ranges.fold_level(2),
+            LapceWorkbenchCommand::FoldLevel3 => ranges.fold_level(3),
+            _ => {}
+        });
+
+        let visible_line =
+            doc.folding_ranges.with_untracked(|r| r.visible_line(line));
+        if visible_line != line {
+            let offset = doc.buffer.with_untracked(|b| {
+                b.first_non_blank_character_on_line(visible_line)
+            });
+            self.cursor()
+                .update(|cursor| cursor.set_offset(offset, false, false));
+        }
+    }
+
+    /// Keep the cursor out of the lines hidden in folded ranges: moving up or down
+    /// steps over them, any other movement landing in one unfolds it.
+    fn skip_folded_lines(
+        &self,
+        cursor: &mut Cursor,
+        movement: &lapce_core::movement::Movement,
+        mods: Modifiers,
+    ) {
+        let doc = self.doc();
+        let hidden = |cursor: &Cursor| {
+            let line = doc
+                .buffer
+                .with_untracked(|b| b.line_of_offset(cursor.offset()));
+            doc.folding_ranges
+                .with_untracked(|r| r.hidden_end(line).is_some())
+                .then_some(line)
+        };
+        if hidden(cursor).is_none() {
+            return;
+        }
+
+        if matches!(
+            movement,
+            lapce_core::movement::Movement::Up
+                | lapce_core::movement::Movement::Down
+        ) {
+            while hidden(cursor).is_some() {
+                let offset = cursor.offset();
+                self.common.register.update(|register| {
+                    movement::move_cursor(
+                        &self.editor,
+                        &*doc,
+                        cursor,
+                        movement,
+                        1,
+                        mods.shift(),
+                        register,
+                    )
+                });
+                if cursor.offset() == offset {
+                    break;
+                }
+            }
+        }
+
+        if let Some(line) = hidden(cursor) {
+            doc.folding_ranges.update(|r| r.unfold(line));
+        }
+    }
+
     pub fn save_doc_position(&self) {
         let doc = self.doc();
         let path = match if doc.loaded() {
@@ -2506,6 +2802,7 @@ pub fn save_doc_position(&self) {
 
         let cursor_offset = self.cursor().with_untracked(|c| c.offset());
         let scroll_offset = self.viewport().with_untracked(|v| v.origin().to_vec2());
+        let folded_lines = doc.folding_ranges.with_untracked(|r| r.folded_lines());
 
         let db: Arc<LapceDb> = use_context().unwrap();
         db.save_doc_position(
@@ -2513,6 +2810,7 @@ pub fn save_doc_position(&self) {
             path,
             cursor_offset,
             scroll_offset,
+            folded_lines,
         );
     }
 
@@ -2859,12 +3157,81 @@ pub fn pointer_move(&self, pointer_event: &PointerMoveEvent) {
                         }
                     });
                 mouse_hover_timer.set(timer_token);
+            } else if let Some(line) = self.inline_blame_line(offset) {
+                // Past the end of the cursor line, where the blame is shown
+                let start_offset = self
+                    .doc()
+                    .buffer
+                    .with_untracked(|buffer| buffer.prev_code_boundary(offset));
+                self.schedule_blame_hover(line, start_offset);
             } else {
                 self.common.mouse_hover_timer.set(TimerToken::INVALID);
             }
         }
     }
 
+    /// The cursor line if the inline blame is shown and `offset` is on it
+    fn inline_blame_line(&self, offset: usize) -> Option<usize> {
+        if !self.common.config.get_untracked().editor.blame_end_of_line {
+            return None;
+        }
+        let doc = self.doc();
+        let cursor_offset = self.cursor().with_untracked(|c| c.offset());
+        let (line, cursor_line) = doc.buffer.with_untracked(|buffer| {
+            (
+                buffer.line_of_offset(offset),
+                buffer.line_of_offset(cursor_offset),
+            )
+        });
+        (line == cursor_line).then_some(line)
+    }
+
+    /// Show the blame of `line` at `offset` after the hover delay
+    pub fn schedule_blame_hover(&self, line: usize, offset: usize) {
+        let hover_delay = self.common.config.get_untracked().editor.hover_delay;
+        let editor = self.clone();
+        let mouse_hover_timer = self.common.mouse_hover_timer;
+        let timer_token =
+            exec_after(Duration::from_millis(hover_delay), move |token| {
+                if mouse_hover_timer.try_get_untracked() == Some(token)
+                    && editor.editor_tab_id.try_get_untracked().is_some()
+                {
+                    editor.show_blame_hover(line, offset);
+                }
+            });
+        mouse_hover_timer.set(timer_token);
+    }
+
+    fn show_blame_hover(&self, line: usize, offset: usize) {
+        let Some(hunk) = self.doc().blame_of_line(line) else {
+            return;
+        };
+        let text = match hunk.short_commit() {
+            Some(short_commit) => {
+                let now = chrono::Utc::now().timestamp();
+                format!(
+                    "**{}** <{}>, {} ({})\n\n`{short_commit}`\n\n{}\n\n---\n\n\
+                     Run \"{}\" to show the changes",
+                    hunk.author,
+                    hunk.email,
+                    time_ago(hunk.time, now),
+                    local_date(hunk.time),
+                    hunk.message.trim_end(),
+                    LapceWorkbenchCommand::SourceControlOpenBlameCommit
+                        .get_message()
+                        .unwrap_or_default(),
+                )
+            }
+            None => hunk.author.clone(),
+        };
+        let config = self.common.config.get_untracked();
+        let hover_data = &self.common.hover;
+        hover_data.content.set(parse_markdown(&text, 1.8, &config));
+        hover_data.offset.set(offset);
+        hover_data.editor_id.set(self.id());
+        hover_data.active.set(true);
+    }
+
     #[instrument]
     pub fn pointer_up(&self, pointer_event: &PointerInputEvent) {
         self.editor.pointer_up(pointer_event);
@@ -2954,6 +3321,15 @@ fn right_click(&self, pointer_event: &PointerInputEvent) {
                     Some(CommandKind::Workbench(
                         LapceWorkbenchCommand::SourceControlOpenActiveFileRemoteUrl,
                     )),
+                    Some(CommandKind::Workbench(
+                        LapceWorkbenchCommand::SourceControlStageHunk,
+                    )),
+                    Some(CommandKind::Workbench(
+                        LapceWorkbenchCommand::SourceControlOpenBlameCommit,
+                    )),
+                    Some(CommandKind::Workbench(
+                        LapceWorkbenchCommand::ShowFileHistory,
+                    )),
                     None,
                     Some(CommandKind::Edit(EditCommand::ClipboardCut)),
                     Some(CommandKind::Edit(EditCommand::ClipboardCopy)),
@@ -2964,6 +3340,23 @@ fn right_click(&self, pointer_event: &PointerInputEvent) {
                     )),
                 ]
             }
+        } else if doc.content.with_untracked(|content| {
+            matches!(
+                content,
+                DocContent::History(history) if history.version == "index"
+            )
+        }) {
+            vec![
+                Some(CommandKind::Workbench(
+                    LapceWorkbenchCommand::SourceControlUnstageHunk,
+                )),
+                None,
+                Some(CommandKind::Edit(EditCommand::ClipboardCopy)),
+                None,
+                Some(CommandKind::Workbench(
+                    LapceWorkbenchCommand::PaletteCommand,
+                )),
+            ]
         } else {
             vec![
                 Some(CommandKind::Edit(EditCommand::ClipboardCut)),
@@ -3002,6 +3395,56 @@ fn right_click(&self, pointer_event: &PointerInputEvent) {
 
     #[instrument]
     fn update_hover(&self, offset: usize) {
+        if !self.update_debug_hover(offset) {
+            self.update_lsp_hover(offset);
+        }
+    }
+
+    /// Evaluate the identifier under the mouse if a debug session is paused,
+    /// falling back to the language server hover if it can't be evaluated
+    fn update_debug_hover(&self, offset: usize) -> bool {
+        let Some(dap) = self.common.daps.with_untracked(|daps| {
+            daps.values()
+                .find(|dap| dap.stopped.get_untracked())
+                .cloned()
+        }) else {
+            return false;
+        };
+        let expression = self.doc().buffer.with_untracked(|buffer| {
+            let (start, end) = buffer.select_word(offset);
+            buffer.slice_to_cow(start..end).to_string()
+        });
+        if expression.is_empty()
+            || !expression.chars().all(|c| c.is_alphanumeric() || c == '_')
+        {
+            return false;
+        }
+
+        let config = self.common.config;
+        let hover_data = self.common.hover.clone();
+        let editor = self.clone();
+        dap.evaluate(expression.clone(), EvaluateContext::Hover, move |result| {
+            match result {
+                Ok(resp) => {
+                    let content = parse_markdown(
+                        &format!("```\n{expression} = {}\n```", resp.result),
+                        1.8,
+                        &config.get_untracked(),
+                    );
+                    hover_data.content.set(content);
+                    hover_data.offset.set(offset);
+                    hover_data.editor_id.set(editor.id());
+                    hover_data.active.set(true);
+                }
+                Err(_) => {
+                    editor.update_lsp_hover(offset);
+                }
+            }
+        });
+        true
+    }
+
+    fn update_lsp_hover(&self, offset: usize) {
         let doc = self.doc();
         let path = doc
             .content
@@ -3038,9 +3481,28 @@ pub fn reset(&self) {
             .update(|cursor| cursor.set_offset(0, false, false));
     }
 
+    /// The visual lines hidden by the folded ranges of the document
+    pub fn folded_vlines(&self) -> FoldedVLines {
+        let doc = self.doc();
+        let folded = doc.folding_ranges.with_untracked(|r| r.get_folded_range());
+        if folded.0.is_empty() {
+            return FoldedVLines::default();
+        }
+        let last_line = doc.buffer.with_untracked(|b| b.last_line());
+        FoldedVLines::new(&folded, last_line, |line| {
+            if line > last_line {
+                return self.editor.last_vline().get() + 1;
+            }
+            let offset = doc.buffer.with_untracked(|b| b.offset_of_line(line));
+            self.editor
+                .vline_of_offset(offset, CursorAffinity::Forward)
+                .get()
+        })
+    }
+
     pub fn visual_line(&self, line: usize) -> usize {
         self.kind.with_untracked(|kind| match kind {
-            EditorViewKind::Normal => line,
+            EditorViewKind::Normal => self.folded_vlines().visual_vline(line),
             EditorViewKind::Diff(diff) => {
                 let is_right = diff.is_right;
                 let mut last_change: Option<&DiffLines> = None;
@@ -3124,7 +3586,7 @@ pub fn visual_line(&self, line: usize) -> usize {
 
     pub fn actual_line(&self, visual_line: usize, bottom_affinity: bool) -> usize {
         self.kind.with_untracked(|kind| match kind {
-            EditorViewKind::Normal => visual_line,
+            EditorViewKind::Normal => self.folded_vlines().actual_vline(visual_line),
             EditorViewKind::Diff(diff) => {
                 let is_right = diff.is_right;
                 let mut actual_line: usize = 0;
@@ -3285,6 +3747,27 @@ fn check_condition(&self, condition: Condition) -> bool {
         }
     }
 
+    fn context_value(&self, key: &str) -> Option<String> {
+        match key {
+            "language_id" => {
+                let language = self.doc().language();
+                let id: &str = match language {
+                    LapceLanguage::User(_) => language.name(),
+                    _ => language.into(),
+                };
+                Some(id.to_lowercase())
+            }
+            "file_extension" => {
+                let path = self
+                    .doc()
+                    .content
+                    .with_untracked(|content| content.path().cloned())?;
+                Some(path.extension()?.to_string_lossy().to_string())
+            }
+            _ => None,
+        }
+    }
+
     #[instrument]
     fn run_command(
         &self,
@@ -3511,6 +3994,132 @@ fn show_inline_completion(cmd: &EditCommand) -> bool {
     )
 }
 
+/// The edits of an ex command applied to the lines from `start` to `end`, as
+/// ranges of the text in order and their replacements
+fn ex_edits(
+    buffer: &Buffer,
+    kind: &ExCommandKind,
+    start: usize,
+    end: usize,
+    search: Option<&FindSearchString>,
+) -> anyhow::Result<Vec<(std::ops::Range<usize>, String)>> {
+    let mut edits = Vec::new();
+    if let ExCommandKind::Global {
+        pattern,
+        invert,
+        command,
+    } = kind
+    {
+        let regex = RegexBuilder::new(&regex_pattern(pattern)).build()?;
+        // Consecutive lines are deleted at once, as deleting the last line takes
+        // the line break before it, which belongs to the line before when that
+        // one is deleted too
+        let delete = matches!(command.as_ref(), ExCommandKind::Delete);
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for line in start..=end {
+            let content = buffer.line_content(line);
+            if regex.is_match(content.trim_end_matches(['\r', '\n'])) == *invert {
+                continue;
+            }
+            match runs.last_mut() {
+                Some((_, last)) if delete && *last + 1 == line => *last = line,
+                _ => runs.push((line, line)),
+            }
+        }
+        for (first, last) in runs {
+            ex_line_edits(buffer, command, first, last, search, &mut edits)?;
+        }
+    } else {
+        ex_line_edits(buffer, kind, start, end, search, &mut edits)?;
+    }
+    Ok(edits)
+}
+
+/// Add the edits of an ex command other than `:g` applied to the lines from
+/// `start` to `end`
+fn ex_line_edits(
+    buffer: &Buffer,
+    kind: &ExCommandKind,
+    start: usize,
+    end: usize,
+    search: Option<&FindSearchString>,
+    edits: &mut Vec<(std::ops::Range<usize>, String)>,
+) -> anyhow::Result<()> {
+    let line_start = buffer.offset_of_line(start);
+    let line_end = buffer.line_end_offset(end, true);
+    match kind {
+        ExCommandKind::Delete => {
+            let range = if end < buffer.last_line() {
+                line_start..buffer.offset_of_line(end + 1)
+            } else if start > 0 {
+                // Take the line break before the last line instead
+                buffer.line_end_offset(start - 1, true)..buffer.len()
+            } else {
+                0..buffer.len()
+            };
+            edits.push((range, String::new()));
+        }
+        ExCommandKind::Substitute(substitute) => {
+            let pattern = if substitute.pattern.is_empty() {
+                // `:s//replacement/` replaces the current search, which is
+                // already in the regex syntax
+                let search = search.ok_or_else(|| anyhow::anyhow!("no pattern"))?;
+                match &search.regex {
+                    Some(_) => search.content.clone(),
+                    None => regex::escape(&search.content),
+                }
+            } else {
+                regex_pattern(&substitute.pattern)
+            };
+            let regex = RegexBuilder::new(&pattern)
+                .case_insensitive(substitute.ignore_case)
+                .build()?;
+
+            let replacement = regex_replacement(&substitute.replacement);
+            let limit = if substitute.global { usize::MAX } else { 1 };
+            for line in start..=end {
+                let offset = buffer.offset_of_line(line);
+                let content = buffer.line_content(line);
+                let content = content.trim_end_matches(['\r', '\n']);
+                for captures in regex.captures_iter(content).take(limit) {
+                    let Some(matched) = captures.get(0) else {
+                        continue;
+                    };
+                    // Expanded from the captures of the match in its line, so
+                    // that anchors and word boundaries see the whole line
+                    let mut text = String::new();
+                    captures.expand(&replacement, &mut text);
+                    edits.push((
+                        offset + matched.start()..offset + matched.end(),
+                        text,
+                    ));
+                }
+            }
+        }
+        ExCommandKind::Sort(options) => {
+            let mut lines: Vec<String> = (start..=end)
+                .map(|line| {
+                    buffer
+                        .line_content(line)
+                        .trim_end_matches(['\r', '\n'])
+                        .to_string()
+                })
+                .collect();
+            options.sort(&mut lines);
+            let text = lines.join(buffer.line_ending().get_chars());
+            edits.push((line_start..line_end, text));
+        }
+        ExCommandKind::GoToLine
+        | ExCommandKind::Write
+        | ExCommandKind::Quit { .. }
+        | ExCommandKind::WriteQuit
+        | ExCommandKind::Edit { .. }
+        | ExCommandKind::Global { .. }
+        | ExCommandKind::NoHighlight => {}
+    }
+    Ok(())
+}
+
 // TODO(minor): Should we just put this on view, since it only requires those values?
 pub(crate) fn compute_screen_lines(
     config: ReadSignal<Arc<LapceConfig>>,
@@ -3520,6 +4129,7 @@ pub(crate) fn compute_screen_lines(
     lines: &Lines,
     text_prov: impl TextLayoutProvider + Clone,
     config_id: ConfigId,
+    folded: &FoldedVLines,
 ) -> ScreenLines {
     // TODO: this should probably be a get since we need to depend on line-height
     let config = config.get();
@@ -3549,7 +4159,18 @@ pub(crate) fn compute_screen_lines(
             let mut rvlines = Vec::new();
             let mut info = HashMap::new();
 
-            let Some(min_info) = *min_info else {
+            // The lines hidden in folded ranges don't take up space on screen, so
+            // the first visible line has to skip over the ones folded before it.
+            let folded_ranges = doc.folding_ranges.with(|r| r.get_folded_range());
+            let min_info = if folded.is_empty() {
+                *min_info
+            } else {
+                let start_vline = VLine(folded.actual_vline(min_vline.get()));
+                lines
+                    .iter_vlines(text_prov.clone(), false, start_vline)
+                    .next()
+            };
+            let Some(min_info) = min_info else {
                 return ScreenLines {
                     lines: Rc::new(rvlines),
                     info: Rc::new(info),
@@ -3561,44 +4182,52 @@ pub(crate) fn compute_screen_lines(
             // TODO: the original was min_line..max_line + 1, are we iterating too little now?
             // the iterator is from min_vline..max_vline
             let count = max_vline.get() - min_vline.get();
-            let iter = lines.iter_rvlines_init(
-                text_prov,
-                cache_rev,
-                config_id,
-                min_info.rvline,
-                false,
-            );
+            let last_line = doc.buffer.with_untracked(|b| b.last_line());
+            let mut start_rvline = min_info.rvline;
+            let mut y_idx = min_vline.get();
+            'folds: loop {
+                let iter = lines.iter_rvlines_init(
+                    text_prov.clone(),
+                    cache_rev,
+                    config_id,
+                    start_rvline,
+                    false,
+                );
 
-            // let range = doc.folding_ranges.get().get_folded_range();
-            // let mut init_index = 0;
+                for vline_info in iter {
+                    if rvlines.len() >= count {
+                        break 'folds;
+                    }
 
-            for (i, vline_info) in iter.enumerate() {
-                if rvlines.len() >= count {
-                    break;
+                    if let Some(end) =
+                        folded_ranges.hidden_end(vline_info.rvline.line)
+                    {
+                        if end >= last_line {
+                            break 'folds;
+                        }
+                        // continue after the folded range
+                        start_rvline = lines.rvline_of_line(&text_prov, end + 1);
+                        continue 'folds;
+                    }
+                    rvlines.push(vline_info.rvline);
+
+                    let vline_y = y_idx * line_height;
+                    let line_y =
+                        vline_y - vline_info.rvline.line_index * line_height;
+                    y_idx += 1;
+
+                    // Add the information to make it cheap to get in the future.
+                    // This y positions are shifted by the baseline y0
+                    info.insert(
+                        vline_info.rvline,
+                        LineInfo {
+                            y: line_y as f64 - y0,
+                            vline_y: vline_y as f64 - y0,
+                            vline_info,
+                        },
+                    );
                 }
-
-                // let (folded, next_index) =
-                //     range.contain_line(init_index, vline_info.rvline.line as u32);
-                // init_index = next_index;
-                // if folded {
-                //     continue;
-                // }
-                rvlines.push(vline_info.rvline);
-
-                let y_idx = min_vline.get() + i;
-                let vline_y = y_idx * line_height;
-                let line_y = vline_y - vline_info.rvline.line_index * line_height;
-
-                // Add the information to make it cheap to get in the future.
-                // This y positions are shifted by the baseline y0
-                info.insert(
-                    vline_info.rvline,
-                    LineInfo {
-                        y: line_y as f64 - y0,
-                        vline_y: vline_y as f64 - y0,
-                        vline_info,
-                    },
-                );
+                break;
             }
 
             ScreenLines {
@@ -3897,3 +4526,48 @@ fn find_hint(mut pre_hint_len: u32, index: u32, hint: &InlayHint) -> FindHintRs
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use lapce_core::buffer::Buffer;
+
+    use super::ex_edits;
+    use crate::ex_command::ExCommand;
+
+    /// Run the ex `command` on the lines from `start` to `end` of `text`
+    fn run_ex(text: &str, command: &str, start: usize, end: usize) -> String {
+        let buffer = Buffer::new(text);
+        let kind = ExCommand::parse(command).unwrap().kind;
+        let edits = ex_edits(&buffer, &kind, start, end, None).unwrap();
+        let mut text = text.to_string();
+        for (range, replacement) in edits.into_iter().rev() {
+            text.replace_range(range, &replacement);
+        }
+        text
+    }
+
+    #[test]
+    fn test_ex_edits() {
+        // Deleting the last line takes the line break before it
+        assert_eq!(run_ex("a\nb\nc", "d", 2, 2), "a\nb");
+        assert_eq!(run_ex("a\nb\nc", "d", 1, 2), "a");
+        assert_eq!(run_ex("a\nb", "d", 0, 1), "");
+
+        // Including when the lines before it are deleted too
+        assert_eq!(run_ex("a\nx\nb\nx\nx", "g/x/d", 0, 4), "a\nb");
+        assert_eq!(run_ex("x\nx\nx", "g/x/d", 0, 2), "");
+        assert_eq!(run_ex("x\na\nx", "v/x/d", 0, 2), "x\nx");
+
+        // Without `g` only the first match of each line is replaced
+        assert_eq!(run_ex("aa\naa\naa", "s/a/b/", 0, 1), "ba\nba\naa");
+        assert_eq!(run_ex("aa\naa", "s/a/b/g", 0, 1), "bb\nbb");
+        assert_eq!(
+            run_ex("foo(1) bar(2)", "s/\\(\\w\\+\\)(\\(\\d\\))/\\2:\\1/g", 0, 0),
+            "1:foo 2:bar"
+        );
+        // Anchors match against the whole line, not the isolated match
+        assert_eq!(run_ex("ab\nab", "s/^a/x/", 0, 1), "xb\nab");
+        assert_eq!(run_ex("a\na", "s/^/# /", 0, 1), "# a\n# a");
+        assert_eq!(run_ex("a\nb\na", "g/a/s/$/;/", 0, 2), "a;\nb\na;");
+    }
+}
@@ -14,8 +14,7 @@
     diff::{expand_diff_lines, rope_diff, DiffExpand, DiffLines},
     rope_text::RopeText,
 };
-use lapce_rpc::{buffer::BufferId, proxy::ProxyResponse};
-use lapce_xi_rope::Rope;
+use lapce_rpc::buffer::BufferId;
 use serde::{Deserialize, Serialize};
 
 use super::{EditorData, EditorViewKind};
@@ -38,6 +37,9 @@ pub struct DiffInfo {
 pub struct DiffEditorInfo {
     pub left_content: DocContent,
     pub right_content: DocContent,
+    /// The file being merged, for a merge editor
+    #[serde(default)]
+    pub result_content: Option<DocContent>,
 }
 
 impl DiffEditorInfo {
@@ -61,7 +63,7 @@ pub fn to_data(
                 DocContent::Local => {
                     Rc::new(Doc::new_local(cx, data.editors, common.clone()))
                 }
-                DocContent::History(history) => {
+                DocContent::History(_) => {
                     let doc = Doc::new_history(
                         cx,
                         content.clone(),
@@ -69,26 +71,7 @@ pub fn to_data(
                         common.clone(),
                     );
                     let doc = Rc::new(doc);
-
-                    {
-                        let doc = doc.clone();
-                        let send = create_ext_action(cx, move |result| {
-                            if let Ok(ProxyResponse::BufferHeadResponse {
-                                content,
-                                ..
-                            }) = result
-                            {
-                                doc.init_content(Rope::from(content));
-                            }
-                        });
-                        common.proxy.get_buffer_head(
-                            history.path.clone(),
-                            move |result| {
-                                send(result);
-                            },
-                        );
-                    }
-
+                    data.load_history_doc(&doc);
                     doc
                 }
                 DocContent::Scratch { name, .. } => {
@@ -113,6 +96,7 @@ pub fn to_data(
 
         let left_doc = new_doc(&self.left_content);
         let right_doc = new_doc(&self.right_content);
+        let result_doc = self.result_content.as_ref().map(new_doc);
 
         let diff_editor_data = DiffEditorData::new(
             cx,
@@ -120,6 +104,7 @@ pub fn to_data(
             editor_tab_id,
             left_doc,
             right_doc,
+            result_doc,
             data.editors,
             data.common.clone(),
         );
@@ -139,8 +124,13 @@ pub struct DiffEditorData {
     pub scope: Scope,
     pub left: EditorData,
     pub right: EditorData,
+    /// The editable file below the two sides, which makes this a merge editor
+    /// comparing the current and incoming versions of a conflicted file
+    pub result: Option<EditorData>,
     pub confirmed: RwSignal<bool>,
     pub focus_right: RwSignal<bool>,
+    /// Whether the result has the focus, rather than one of the sides
+    pub focus_result: RwSignal<bool>,
 }
 
 impl DiffEditorData {
@@ -150,6 +140,7 @@ pub fn new(
         editor_tab_id: EditorTabId,
         left_doc: Rc<Doc>,
         right_doc: Rc<Doc>,
+        result_doc: Option<Rc<Doc>>,
         editors: Editors,
         common: Rc<CommonData>,
     ) -> Self {
@@ -157,7 +148,7 @@ pub fn new(
         let confirmed = cx.create_rw_signal(false);
 
         // TODO: ensure that left/right are cleaned up
-        let [left, right] = [left_doc, right_doc].map(|doc| {
+        let make_editor = |doc| {
             editors.make_from_doc(
                 cx,
                 doc,
@@ -166,7 +157,9 @@ pub fn new(
                 Some(confirmed),
                 common.clone(),
             )
-        });
+        };
+        let [left, right] = [left_doc, right_doc].map(make_editor);
+        let result = result_doc.map(make_editor);
 
         let data = Self {
             id,
@@ -174,6 +167,8 @@ pub fn new(
             scope: cx,
             left,
             right,
+            focus_result: cx.create_rw_signal(result.is_some()),
+            result,
             confirmed,
             focus_right: cx.create_rw_signal(true),
         };
@@ -187,6 +182,10 @@ pub fn diff_editor_info(&self) -> DiffEditorInfo {
         DiffEditorInfo {
             left_content: self.left.doc().content.get_untracked(),
             right_content: self.right.doc().content.get_untracked(),
+            result_content: self
+                .result
+                .as_ref()
+                .map(|result| result.doc().content.get_untracked()),
         }
     }
 
@@ -200,7 +199,7 @@ pub fn copy(
         let cx = cx.create_child();
         let confirmed = cx.create_rw_signal(true);
 
-        let [left, right] = [&self.left, &self.right].map(|editor_data| {
+        let copy_editor = |editor_data: &EditorData| {
             editors
                 .make_copy(
                     editor_data.id(),
@@ -210,15 +209,19 @@ pub fn copy(
                     Some(confirmed),
                 )
                 .unwrap()
-        });
+        };
+        let [left, right] = [&self.left, &self.right].map(copy_editor);
+        let result = self.result.as_ref().map(copy_editor);
 
         let diff_editor = DiffEditorData {
             scope: cx,
             id: diff_editor_id,
             editor_tab_id: cx.create_rw_signal(editor_tab_id),
             focus_right: cx.create_rw_signal(true),
+            focus_result: cx.create_rw_signal(result.is_some()),
             left,
             right,
+            result,
             confirmed,
         };
 
@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use floem::{
     context::PaintCx,
     peniko::kurbo::{Point, Rect, Size},
@@ -7,10 +9,14 @@
 };
 use im::HashMap;
 use lapce_core::{buffer::rope_text::RopeText, mode::Mode};
+use lapce_xi_rope::Rope;
 use serde::{Deserialize, Serialize};
 
 use super::{view::changes_colors_screen, EditorData};
-use crate::config::{color::LapceColor, LapceConfig};
+use crate::{
+    config::{color::LapceColor, LapceConfig},
+    source_control::local_date,
+};
 
 pub struct EditorGutterView {
     id: ViewId,
@@ -204,6 +210,75 @@ fn debug_name(&self) -> std::borrow::Cow<'static, str> {
     }
 }
 
+/// The column left of the line numbers showing the commit that last changed
+/// the lines
+pub struct BlameGutterView {
+    id: ViewId,
+    editor: EditorData,
+}
+
+pub fn blame_gutter_view(editor: EditorData) -> BlameGutterView {
+    BlameGutterView {
+        id: ViewId::new(),
+        editor,
+    }
+}
+
+impl View for BlameGutterView {
+    fn id(&self) -> ViewId {
+        self.id
+    }
+
+    fn paint(&mut self, cx: &mut floem::context::PaintCx) {
+        let viewport = self.editor.viewport().get_untracked();
+        let screen_lines = self.editor.screen_lines();
+        let config = self.editor.common.config.get_untracked();
+        let line_height = config.editor.line_height() as f64;
+        let doc = self.editor.doc();
+
+        let family: Vec<FamilyOwned> =
+            FamilyOwned::parse_list(&config.editor.font_family).collect();
+        let attrs = Attrs::new()
+            .family(&family)
+            .color(config.color(LapceColor::EDITOR_DIM))
+            .font_size(config.editor.font_size() as f32);
+        let attrs_list = AttrsList::new(attrs);
+
+        screen_lines.with_untracked(|screen_lines| {
+            // Each commit is shown once, on the first visible line it changed
+            let mut last_start = None;
+            for (line, y) in screen_lines.iter_lines_y() {
+                let Some(hunk) = doc.blame_of_line(line) else {
+                    last_start = None;
+                    continue;
+                };
+                if last_start == Some(hunk.start_line) {
+                    continue;
+                }
+                last_start = Some(hunk.start_line);
+
+                let author: String = hunk.author.chars().take(12).collect();
+                let text = if hunk.commit.is_some() {
+                    format!("{} {author}", local_date(hunk.time))
+                } else {
+                    author
+                };
+                let mut text_layout = TextLayout::new();
+                text_layout.set_text(&text, attrs_list.clone());
+                let height = text_layout.size().height;
+                cx.draw_text(
+                    &text_layout,
+                    Point::new(10.0, y + (line_height - height) / 2.0 - viewport.y0),
+                );
+            }
+        });
+    }
+
+    fn debug_name(&self) -> std::borrow::Cow<'static, str> {
+        "Blame Gutter".into()
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct FoldingRanges(pub Vec<FoldingRange>);
 
@@ -211,6 +286,149 @@ fn debug_name(&self) -> std::borrow::Cow<'static, str> {
 pub struct FoldedRanges(pub Vec<FoldedRange>);
 
 impl FoldingRanges {
+    /// Foldable ranges from the indentation of the lines, for when there is
+    /// neither a language server nor a syntax tree to get them from
+    pub fn from_indentation(text: &Rope, tab_width: usize) -> Self {
+        let mut ranges = Vec::new();
+        // (indent, line) of the lines that the following lines may be nested in
+        let mut stack: Vec<(usize, usize)> = Vec::new();
+        let mut last_non_blank = 0;
+        for (line, content) in text.lines(..).enumerate() {
+            let Some(indent) = indent_width(&content, tab_width) else {
+                continue;
+            };
+            while let Some(&(start_indent, start)) = stack.last() {
+                if start_indent < indent {
+                    break;
+                }
+                stack.pop();
+                if last_non_blank > start {
+                    ranges.push(FoldingRange::new(start, last_non_blank));
+                }
+            }
+            stack.push((indent, line));
+            last_non_blank = line;
+        }
+        while let Some((_, start)) = stack.pop() {
+            if last_non_blank > start {
+                ranges.push(FoldingRange::new(start, last_non_blank));
+            }
+        }
+        ranges.sort_by_key(|r| r.start.line);
+        Self(ranges)
+    }
+
+    /// Replace the ranges with new ones, keeping ranges starting on the same
+    /// line folded
+    pub fn set_ranges(&mut self, mut ranges: Vec<FoldingRange>) {
+        let folded = self.folded_lines();
+        ranges.sort_by_key(|r| r.start.line);
+        for range in ranges.iter_mut() {
+            if folded.contains(&(range.start.line as usize)) {
+                range.status = FoldingRangeStatus::Fold;
+            }
+        }
+        self.0 = ranges;
+    }
+
+    /// The start lines of the folded ranges
+    pub fn folded_lines(&self) -> Vec<usize> {
+        self.0
+            .iter()
+            .filter(|r| r.status.is_folded())
+            .map(|r| r.start.line as usize)
+            .collect()
+    }
+
+    /// Fold the ranges starting on `lines`
+    pub fn fold_lines(&mut self, lines: &[usize]) {
+        for range in self.0.iter_mut() {
+            if lines.contains(&(range.start.line as usize)) {
+                range.status = FoldingRangeStatus::Fold;
+            }
+        }
+    }
+
+    /// Fold the innermost unfolded range containing `line`
+    pub fn fold(&mut self, line: usize) {
+        if let Some(range) = self
+            .0
+            .iter_mut()
+            .filter(|r| !r.status.is_folded() && r.contains_line(line))
+            .last()
+        {
+            range.status = FoldingRangeStatus::Fold;
+        }
+    }
+
+    /// Unfold the ranges starting on or hiding `line`
+    pub fn unfold(&mut self, line: usize) {
+        for range in self.0.iter_mut() {
+            if range.contains_line(line) {
+                range.status = FoldingRangeStatus::Unfold;
+            }
+        }
+    }
+
+    pub fn toggle_fold(&mut self, line: usize) {
+        if self
+            .0
+            .iter()
+            .any(|r| r.status.is_folded() && r.contains_line(line))
+        {
+            self.unfold(line);
+        } else {
+            self.fold(line);
+        }
+    }
+
+    pub fn fold_all(&mut self) {
+        for range in self.0.iter_mut() {
+            range.status = FoldingRangeStatus::Fold;
+        }
+    }
+
+    pub fn unfold_all(&mut self) {
+        for range in self.0.iter_mut() {
+            range.status = FoldingRangeStatus::Unfold;
+        }
+    }
+
+    /// Fold the ranges nested `level` deep, where the outermost ones are
+    /// level 1, and unfold the ones around them
+    pub fn fold_level(&mut self, level: usize) {
+        // the end lines of the ranges containing the current one
+        let mut parents: Vec<u32> = Vec::new();
+        for range in self.0.iter_mut() {
+            while parents.last().is_some_and(|end| *end < range.start.line) {
+                parents.pop();
+            }
+            let depth = parents.len() + 1;
+            if depth == level {
+                range.status = FoldingRangeStatus::Fold;
+            } else if depth < level {
+                range.status = FoldingRangeStatus::Unfold;
+            }
+            parents.push(range.end.line);
+        }
+    }
+
+    /// The end line of the folded range hiding `line`, if it's hidden
+    pub fn hidden_end(&self, line: usize) -> Option<usize> {
+        self.get_folded_range().hidden_end(line)
+    }
+
+    /// The first line of the folded range hiding `line`, or `line` itself if
+    /// it's not hidden
+    pub fn visible_line(&self, line: usize) -> usize {
+        self.get_folded_range()
+            .0
+            .iter()
+            .find(|r| (r.start.line as usize) < line && line <= r.end.line as usize)
+            .map(|r| r.start.line as usize)
+            .unwrap_or(line)
+    }
+
     pub fn get_folded_range(&self) -> FoldedRanges {
         let mut range = Vec::new();
         let mut limit_line = 0;
@@ -270,7 +488,29 @@ pub fn to_display_items(&self) -> Vec<FoldingDisplayItem> {
     }
 }
 
+/// The width of the indentation of a line, or `None` for a blank line
+fn indent_width(content: &str, tab_width: usize) -> Option<usize> {
+    let mut width = 0;
+    for c in content.chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width += tab_width,
+            '\r' | '\n' => return None,
+            _ => return Some(width),
+        }
+    }
+    None
+}
+
 impl FoldedRanges {
+    /// The end line of the folded range hiding `line`, if it's hidden
+    pub fn hidden_end(&self, line: usize) -> Option<usize> {
+        self.0
+            .iter()
+            .find(|r| (r.start.line as usize) < line && line <= r.end.line as usize)
+            .map(|r| r.end.line as usize)
+    }
+
     pub fn contain_line(&self, start_index: usize, line: u32) -> (bool, usize) {
         if start_index >= self.0.len() {
             return (false, start_index);
@@ -279,7 +519,7 @@ pub fn contain_line(&self, start_index: usize, line: u32) -> (bool, usize) {
         for range in self.0[start_index..].iter() {
             if range.start.line >= line {
                 return (false, last_index);
-            } else if range.start.line < line && range.end.line > line {
+            } else if range.start.line < line && range.end.line >= line {
                 return (true, last_index);
             } else if range.end.line < line {
                 last_index += 1;
@@ -289,6 +529,63 @@ pub fn contain_line(&self, start_index: usize, line: u32) -> (bool, usize) {
     }
 }
 
+/// The visual lines hidden by folded ranges, for mapping between the visual
+/// lines of the text and the visual lines shown on screen
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FoldedVLines(Vec<Range<usize>>);
+
+impl FoldedVLines {
+    /// `vline_of_line` gives the first visual line of a buffer line, with
+    /// `last_line + 1` mapping to just past the last visual line
+    pub fn new(
+        folded: &FoldedRanges,
+        last_line: usize,
+        vline_of_line: impl Fn(usize) -> usize,
+    ) -> Self {
+        let ranges = folded
+            .0
+            .iter()
+            .filter(|r| (r.start.line as usize) < last_line)
+            .map(|r| {
+                let start = r.start.line as usize + 1;
+                let end = (r.end.line as usize + 1).min(last_line + 1);
+                vline_of_line(start)..vline_of_line(end)
+            })
+            .filter(|r| !r.is_empty())
+            .collect();
+        Self(ranges)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The on screen index of `vline`. Hidden lines map to the line they are
+    /// folded into.
+    pub fn visual_vline(&self, vline: usize) -> usize {
+        let mut hidden = 0;
+        for range in &self.0 {
+            if range.start >= vline {
+                break;
+            }
+            hidden += range.end.min(vline) - range.start;
+        }
+        vline - hidden
+    }
+
+    /// The visual line shown at the on screen index `visual_vline`
+    pub fn actual_vline(&self, visual_vline: usize) -> usize {
+        let mut vline = visual_vline;
+        for range in &self.0 {
+            if range.start > vline {
+                break;
+            }
+            vline += range.len();
+        }
+        vline
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FoldedRange {
     pub start: FoldingPosition,
@@ -304,6 +601,22 @@ pub struct FoldingRange {
 }
 
 impl FoldingRange {
+    /// A range hiding the lines after `start_line` up to and including `end_line`
+    pub fn new(start_line: usize, end_line: usize) -> Self {
+        Self {
+            start: FoldingPosition {
+                line: start_line as u32,
+                character: None,
+            },
+            end: FoldingPosition {
+                line: end_line as u32,
+                character: None,
+            },
+            status: FoldingRangeStatus::Unfold,
+            collapsed_text: None,
+        }
+    }
+
     pub fn from_lsp(value: lsp_types::FoldingRange) -> Self {
         let lsp_types::FoldingRange {
             start_line,
@@ -329,6 +642,11 @@ pub fn from_lsp(value: lsp_types::FoldingRange) -> Self {
             collapsed_text,
         }
     }
+
+    /// Whether `line` is the start line of the range or hidden by it
+    pub fn contains_line(&self, line: usize) -> bool {
+        self.start.line as usize <= line && line <= self.end.line as usize
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Copy)]
@@ -347,14 +665,14 @@ pub enum FoldingRangeStatus {
 
 impl FoldingRangeStatus {
     pub fn click(&mut self) {
-        // match self {
-        //     FoldingRangeStatus::Fold => {
-        //         *self = FoldingRangeStatus::Unfold;
-        //     }
-        //     FoldingRangeStatus::Unfold => {
-        //         *self = FoldingRangeStatus::Fold;
-        //     }
-        // }
+        match self {
+            FoldingRangeStatus::Fold => {
+                *self = FoldingRangeStatus::Unfold;
+            }
+            FoldingRangeStatus::Unfold => {
+                *self = FoldingRangeStatus::Fold;
+            }
+        }
     }
     pub fn is_folded(&self) -> bool {
         *self == Self::Fold
@@ -393,3 +711,40 @@ fn from(value: lsp_types::FoldingRangeKind) -> Self {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use lapce_xi_rope::Rope;
+
+    use super::{FoldedVLines, FoldingRanges};
+
+    #[test]
+    fn test_indentation_folding_ranges() {
+        let text = Rope::from(
+            "fn main() {\n    if a {\n        b();\n\n    }\n}\n\nfn other() {}\n",
+        );
+        let ranges = FoldingRanges::from_indentation(&text, 4);
+        let lines: Vec<(u32, u32)> = ranges
+            .0
+            .iter()
+            .map(|r| (r.start.line, r.end.line))
+            .collect();
+        assert_eq!(lines, vec![(0, 4), (1, 2)]);
+    }
+
+    #[test]
+    fn test_fold_level() {
+        let text = Rope::from("a\n  b\n    c\n  d\n    e\nf\n");
+        let mut ranges = FoldingRanges::from_indentation(&text, 4);
+        ranges.fold_level(2);
+        assert_eq!(ranges.folded_lines(), vec![1, 3]);
+
+        let folded = ranges.get_folded_range();
+        let vlines = FoldedVLines::new(&folded, 6, |line| line);
+        assert_eq!(vlines.visual_vline(1), 1);
+        assert_eq!(vlines.visual_vline(3), 2);
+        assert_eq!(vlines.visual_vline(5), 3);
+        assert_eq!(vlines.actual_vline(2), 3);
+        assert_eq!(vlines.actual_vline(3), 5);
+    }
+}
@@ -21,6 +21,8 @@ pub enum EditorPosition {
     Line(usize),
     Position(Position),
     Offset(usize),
+    /// A line along with a byte column within that line
+    LineColumn(usize, usize),
 }
 
 impl EditorPosition {
@@ -29,6 +31,11 @@ pub fn to_offset(&self, text: &impl RopeText) -> usize {
             EditorPosition::Line(n) => text.first_non_blank_character_on_line(*n),
             EditorPosition::Position(position) => text.offset_of_position(position),
             EditorPosition::Offset(offset) => *offset,
+            EditorPosition::LineColumn(line, column) => {
+                let line_start = text.offset_of_line(*line);
+                let line_end = text.offset_line_end(line_start, true);
+                (line_start + column).min(line_end)
+            }
         }
     }
 }
@@ -1,13 +1,12 @@
-use std::{
-    cmp, collections::BTreeMap, ops::DerefMut, path::PathBuf, rc::Rc, sync::Arc,
-};
+use std::{cmp, ops::DerefMut, path::PathBuf, rc::Rc, sync::Arc};
 
 use floem::{
-    action::{set_ime_allowed, set_ime_cursor_area},
+    action::{set_ime_allowed, set_ime_cursor_area, show_context_menu},
     context::{PaintCx, StyleCx},
     event::{Event, EventListener, EventPropagation},
     keyboard::Modifiers,
     kurbo::Stroke,
+    menu::{Menu, MenuItem},
     peniko::{
         kurbo::{Line, Point, Rect, Size},
         Color,
@@ -19,6 +18,7 @@
     },
     style::{CursorColor, CursorStyle, Style, TextColor},
     taffy::prelude::NodeId,
+    text::{Attrs, AttrsList, FamilyOwned, TextLayout},
     views::{
         clip, container, dyn_stack,
         editor::{
@@ -43,24 +43,32 @@
 use itertools::Itertools;
 use lapce_core::{
     buffer::{diff::DiffLines, rope_text::RopeText, Buffer},
+    conflict::{ConflictBlock, ConflictResolution},
     cursor::{CursorAffinity, CursorMode},
     selection::SelRegion,
 };
-use lapce_rpc::{
-    dap_types::{DapId, SourceBreakpoint},
-    plugin::PluginId,
-};
+use lapce_rpc::{plugin::PluginId, proxy::ProxyRpcHandler};
 use lapce_xi_rope::find::CaseMatching;
 use lsp_types::CodeLens;
 
-use super::{gutter::editor_gutter_view, DocSignal, EditorData};
+use super::{
+    gutter::{blame_gutter_view, editor_gutter_view},
+    DocSignal, EditorData,
+};
 use crate::{
     app::clickable_icon,
+    breakpoint::BreakpointEditKind,
     command::InternalCommand,
-    config::{color::LapceColor, editor::WrapStyle, icon::LapceIcons, LapceConfig},
-    debug::{DapData, LapceBreakpoint},
-    doc::DocContent,
+    config::{
+        color::LapceColor,
+        editor::{EditorConfig, WrapStyle},
+        icon::LapceIcons,
+        LapceConfig,
+    },
+    debug::{LapceBreakpoint, RunDebugData},
+    doc::{Doc, DocContent},
     editor::gutter::FoldingDisplayItem,
+    source_control::time_ago,
     text_input::TextInputBuilder,
     window_tab::{CommonData, Focus, WindowTabData},
     workspace::LapceWorkspace,
@@ -73,15 +81,15 @@ pub struct StickyHeaderInfo {
     pub y_diff: f64,
 }
 
-fn editor_wrap(config: &LapceConfig) -> WrapMethod {
+fn editor_wrap(config: &EditorConfig) -> WrapMethod {
     /// Minimum width that we'll allow the view to be wrapped at.
     const MIN_WRAPPED_WIDTH: f32 = 100.0;
 
-    match config.editor.wrap_style {
+    match config.wrap_style {
         WrapStyle::None => WrapMethod::None,
         WrapStyle::EditorWidth => WrapMethod::EditorWidth,
         WrapStyle::WrapWidth => WrapMethod::WrapWidth {
-            width: (config.editor.wrap_width as f32).max(MIN_WRAPPED_WIDTH),
+            width: (config.wrap_width as f32).max(MIN_WRAPPED_WIDTH),
         },
     }
 }
@@ -93,6 +101,7 @@ pub fn editor_style(
 ) -> Style {
     let config = config.get();
     let doc = doc.get();
+    let editor_config = config.editor_for(doc.language());
 
     s.set(
         IndentStyleProp,
@@ -121,19 +130,19 @@ pub fn editor_style(
         PreeditUnderlineColor,
         config.color(LapceColor::EDITOR_FOREGROUND),
     )
-    .set(ShowIndentGuide, config.editor.show_indent_guide)
+    .set(ShowIndentGuide, editor_config.show_indent_guide)
     .set(Modal, config.core.modal)
     .set(
         ModalRelativeLine,
         config.editor.modal_mode_relative_line_numbers,
     )
-    .set(SmartTab, config.editor.smart_tab)
-    .set(WrapProp, editor_wrap(&config))
+    .set(SmartTab, editor_config.smart_tab)
+    .set(WrapProp, editor_wrap(editor_config))
     .set(
         CursorSurroundingLines,
         config.editor.cursor_surrounding_lines,
     )
-    .set(RenderWhitespaceProp, config.editor.render_whitespace)
+    .set(RenderWhitespaceProp, editor_config.render_whitespace)
 }
 
 pub struct EditorView {
@@ -169,6 +178,10 @@ pub fn editor_view(
         hide_cursor.track();
         let occurrences = doc.with(|doc| doc.find_result.occurrences);
         occurrences.track();
+        let blame = doc.with(|doc| doc.blame);
+        blame.track();
+        let conflicts = doc.with(|doc| doc.conflicts);
+        conflicts.track();
         id.request_paint();
     });
 
@@ -352,6 +365,54 @@ fn paint_diff_sections(
         }
     }
 
+    /// Highlight the current and incoming sides of the conflict blocks a merge
+    /// left in the file, with their marker lines stronger
+    fn paint_conflicts(
+        &self,
+        cx: &mut PaintCx,
+        viewport: Rect,
+        screen_lines: &ScreenLines,
+        config: &LapceConfig,
+    ) {
+        let conflicts = self.editor.doc().conflicts.get_untracked();
+        if conflicts.is_empty() {
+            return;
+        }
+        let line_height = config.editor.line_height() as f64;
+        for (line, y) in screen_lines.iter_lines_y() {
+            let Some(conflict) = conflicts
+                .iter()
+                .find(|conflict| conflict.contains_line(line))
+            else {
+                continue;
+            };
+            let color = if line < conflict.base.unwrap_or(conflict.separator) {
+                LapceColor::SOURCE_CONTROL_ADDED
+            } else if line < conflict.separator {
+                LapceColor::SOURCE_CONTROL_CONFLICTED
+            } else if line > conflict.separator {
+                LapceColor::SOURCE_CONTROL_MODIFIED
+            } else {
+                continue;
+            };
+            let is_marker = line == conflict.start
+                || line == conflict.end
+                || Some(line) == conflict.base;
+            cx.fill(
+                &Rect::from_origin_size(
+                    (viewport.x0, y),
+                    (viewport.width(), line_height),
+                ),
+                config.color(color).multiply_alpha(if is_marker {
+                    0.4
+                } else {
+                    0.2
+                }),
+                0.0,
+            );
+        }
+    }
+
     fn paint_diff_no_code(
         &self,
         cx: &mut PaintCx,
@@ -713,6 +774,70 @@ fn paint_sticky_headers(
         }
     }
 
+    /// Show who last changed the cursor line after the end of it
+    fn paint_inline_blame(
+        &self,
+        cx: &mut PaintCx,
+        screen_lines: &ScreenLines,
+        config: &LapceConfig,
+    ) {
+        if !config.editor.blame_end_of_line
+            || !self.editor.kind.get_untracked().is_normal()
+        {
+            return;
+        }
+
+        let doc = self.editor.doc();
+        let offset = self.editor.cursor().with_untracked(|c| c.offset());
+        let line = doc.buffer.with_untracked(|b| b.line_of_offset(offset));
+        let Some(hunk) = doc.blame_of_line(line) else {
+            return;
+        };
+        let Some(info) = screen_lines.info_for_line(line) else {
+            return;
+        };
+
+        let text = if hunk.commit.is_some() {
+            let now = chrono::Utc::now().timestamp();
+            format!(
+                "{}, {} \u{2022} {}",
+                hunk.author,
+                time_ago(hunk.time, now),
+                hunk.summary()
+            )
+        } else {
+            hunk.author.clone()
+        };
+
+        // The blame goes after the last visual line of a wrapped line
+        let line_layout = self.editor.editor.text_layout(line);
+        let (line_end, line_index) = line_layout
+            .text
+            .layout_runs()
+            .enumerate()
+            .last()
+            .map(|(i, run)| {
+                let end = run.glyphs.last().map(|g| g.x + g.w).unwrap_or(0.0);
+                (end as f64, i)
+            })
+            .unwrap_or((0.0, 0));
+
+        let family: Vec<FamilyOwned> =
+            FamilyOwned::parse_list(&config.editor.font_family).collect();
+        let attrs = Attrs::new()
+            .family(&family)
+            .color(config.color(LapceColor::EDITOR_DIM))
+            .font_size(config.editor.inlay_hint_font_size() as f32);
+        let mut text_layout = TextLayout::new();
+        text_layout.set_text(&text, AttrsList::new(attrs));
+
+        let line_height = config.editor.line_height() as f64;
+        let y = info.vline_y
+            + line_index as f64 * line_height
+            + (line_height - text_layout.size().height) / 2.0;
+        cx.draw_text(&text_layout, Point::new(line_end + 40.0, y));
+    }
+
     fn paint_scroll_bar(
         &self,
         cx: &mut PaintCx,
@@ -1142,6 +1267,7 @@ fn paint(&mut self, cx: &mut PaintCx) {
         FloemEditorView::paint_selection(cx, ed, &screen_lines);
         let screen_lines = ed.screen_lines.get_untracked();
         self.paint_diff_sections(cx, viewport, &screen_lines, &config);
+        self.paint_conflicts(cx, viewport, &screen_lines, &config);
         let screen_lines = ed.screen_lines.get_untracked();
         self.paint_find(cx, &screen_lines);
         let screen_lines = ed.screen_lines.get_untracked();
@@ -1156,6 +1282,7 @@ fn paint(&mut self, cx: &mut PaintCx) {
             &screen_lines,
         );
         let screen_lines = ed.screen_lines.get_untracked();
+        self.paint_inline_blame(cx, &screen_lines, &config);
         self.paint_sticky_headers(cx, viewport, &screen_lines);
         self.paint_scroll_bar(cx, viewport, is_local, config);
     }
@@ -1308,6 +1435,7 @@ pub fn editor_container_view(
     stack((
         editor_breadcrumbs(workspace, editor.get_untracked(), config),
         stack((
+            editor_gutter_blame(editor),
             editor_gutter(window_tab_data.clone(), editor),
             editor_gutter_folding_range(
                 window_tab_data.clone(),
@@ -1378,14 +1506,25 @@ pub fn editor_container_view(
 fn editor_gutter_breakpoint_view(
     i: usize,
     doc: DocSignal,
-    daps: RwSignal<im::HashMap<DapId, DapData>>,
-    breakpoints: RwSignal<BTreeMap<PathBuf, BTreeMap<usize, LapceBreakpoint>>>,
+    debug: RunDebugData,
     screen_lines: RwSignal<ScreenLines>,
     common: Rc<CommonData>,
     icon_padding: f32,
 ) -> impl View {
     let hovered = create_rw_signal(false);
     let config = common.config;
+    let line_of = move || {
+        let screen_lines = screen_lines.get_untracked();
+        let line = screen_lines.lines.get(i).map(|r| r.line).unwrap_or(0);
+        let doc = doc.get_untracked();
+        let offset = doc.buffer.with_untracked(|b| b.offset_of_line(line));
+        doc.content
+            .get_untracked()
+            .path()
+            .map(|path| (path.to_path_buf(), line, offset))
+    };
+    let click_debug = debug.clone();
+    let click_common = common.clone();
     container(
         svg(move || config.get().ui_svg(LapceIcons::DEBUG_BREAKPOINT)).style(
             move |s| {
@@ -1398,70 +1537,36 @@ fn editor_gutter_breakpoint_view(
         ),
     )
     .on_click_stop(move |_| {
-        let screen_lines = screen_lines.get_untracked();
-        let line = screen_lines.lines.get(i).map(|r| r.line).unwrap_or(0);
         // let line = (viewport.get_untracked().y0
         //     / config.get_untracked().editor.line_height() as f64)
         //     .floor() as usize
         //     + i;
-        let doc = doc.get_untracked();
-        let offset = doc.buffer.with_untracked(|b| b.offset_of_line(line));
-        if let Some(path) = doc.content.get_untracked().path() {
-            let path_breakpoints = breakpoints
-                .try_update(|breakpoints| {
-                    let breakpoints = breakpoints.entry(path.clone()).or_default();
-                    if let std::collections::btree_map::Entry::Vacant(e) =
-                        breakpoints.entry(line)
-                    {
-                        e.insert(LapceBreakpoint {
-                            id: None,
-                            verified: false,
-                            message: None,
-                            line,
-                            offset,
-                            dap_line: None,
-                            active: true,
-                        });
-                    } else {
-                        let mut toggle_active = false;
-                        if let Some(breakpint) = breakpoints.get_mut(&line) {
-                            if !breakpint.active {
-                                breakpint.active = true;
-                                toggle_active = true;
-                            }
-                        }
-                        if !toggle_active {
-                            breakpoints.remove(&line);
+        if let Some((path, line, offset)) = line_of() {
+            click_debug.breakpoints.update(|breakpoints| {
+                let breakpoints = breakpoints.entry(path.clone()).or_default();
+                if let std::collections::btree_map::Entry::Vacant(e) =
+                    breakpoints.entry(line)
+                {
+                    e.insert(LapceBreakpoint::new(line, offset));
+                } else {
+                    let mut toggle_active = false;
+                    if let Some(breakpint) = breakpoints.get_mut(&line) {
+                        if !breakpint.active {
+                            breakpint.active = true;
+                            toggle_active = true;
                         }
                     }
-                    breakpoints.clone()
-                })
-                .unwrap();
-            let source_breakpoints: Vec<SourceBreakpoint> = path_breakpoints
-                .iter()
-                .filter_map(|(_, b)| {
-                    if b.active {
-                        Some(SourceBreakpoint {
-                            line: b.line + 1,
-                            column: None,
-                            condition: None,
-                            hit_condition: None,
-                            log_message: None,
-                        })
-                    } else {
-                        None
+                    if !toggle_active {
+                        breakpoints.remove(&line);
                     }
-                })
-                .collect();
-            let daps: Vec<DapId> =
-                daps.with_untracked(|daps| daps.keys().cloned().collect());
-            for dap_id in daps {
-                common.proxy.dap_set_breakpoints(
-                    dap_id,
-                    path.to_path_buf(),
-                    source_breakpoints.clone(),
-                );
-            }
+                }
+            });
+            click_debug.send_breakpoints(&click_common.proxy, &path);
+        }
+    })
+    .on_secondary_click_stop(move |_| {
+        if let Some((path, line, offset)) = line_of() {
+            breakpoint_context_menu(&debug, &common, path, line, offset);
         }
     })
     .on_event_stop(EventListener::PointerEnter, move |_| {
@@ -1480,13 +1585,55 @@ fn editor_gutter_breakpoint_view(
     })
 }
 
+fn breakpoint_context_menu(
+    debug: &RunDebugData,
+    common: &Rc<CommonData>,
+    path: PathBuf,
+    line: usize,
+    offset: usize,
+) {
+    let existing = debug.breakpoints.with_untracked(|breakpoints| {
+        breakpoints
+            .get(&path)
+            .map(|breakpoints| breakpoints.contains_key(&line))
+            .unwrap_or(false)
+    });
+    let internal_command = common.internal_command;
+    let edit = move |title: &str, kind: BreakpointEditKind| {
+        let path = path.clone();
+        MenuItem::new(title).action(move || {
+            internal_command.send(InternalCommand::EditBreakpoint {
+                path: path.clone(),
+                line,
+                offset,
+                kind,
+            });
+        })
+    };
+    let mut menu = Menu::new("");
+    if existing {
+        menu = menu
+            .entry(edit("Edit Condition", BreakpointEditKind::Condition))
+            .entry(edit("Edit Hit Count", BreakpointEditKind::HitCount))
+            .entry(edit("Edit Log Message", BreakpointEditKind::LogMessage));
+    } else {
+        menu = menu
+            .entry(edit(
+                "Add Conditional Breakpoint",
+                BreakpointEditKind::Condition,
+            ))
+            .entry(edit("Add Logpoint", BreakpointEditKind::LogMessage));
+    }
+    show_context_menu(menu, None);
+}
+
 fn editor_gutter_breakpoints(
     window_tab_data: Rc<WindowTabData>,
     e_data: RwSignal<EditorData>,
     icon_padding: f32,
 ) -> impl View {
-    let breakpoints = window_tab_data.terminal.debug.breakpoints;
-    let daps = window_tab_data.terminal.debug.daps;
+    let debug = window_tab_data.terminal.debug.clone();
+    let breakpoints = debug.breakpoints;
     let common = window_tab_data.common.clone();
 
     let (ed, doc, config) = e_data
@@ -1514,8 +1661,7 @@ fn editor_gutter_breakpoints(
                     editor_gutter_breakpoint_view(
                         i,
                         doc,
-                        daps,
-                        breakpoints,
+                        debug.clone(),
                         screen_lines,
                         common.clone(),
                         icon_padding,
@@ -1543,14 +1689,12 @@ fn editor_gutter_breakpoints(
                     };
                     breakpoints.into_iter()
                 },
-                move |(line, b)| (*line, b.active),
+                move |(line, b)| (*line, b.active, b.icon()),
                 move |(line, breakpoint)| {
                     let active = breakpoint.active;
-                    container(
-                        svg(move || {
-                            config.get().ui_svg(LapceIcons::DEBUG_BREAKPOINT)
-                        })
-                        .style(move |s| {
+                    let icon = breakpoint.icon();
+                    container(svg(move || config.get().ui_svg(icon)).style(
+                        move |s| {
                             let config = config.get();
                             let size = config.ui.icon_size() as f32 + 2.0;
                             let color = if active {
@@ -1560,8 +1704,8 @@ fn editor_gutter_breakpoints(
                             };
                             let color = config.color(color);
                             s.size(size, size).color(color)
-                        }),
-                    )
+                        },
+                    ))
                     .style(move |s| {
                         let config = config.get();
                         let line_y = screen_lines
@@ -1732,6 +1876,110 @@ fn editor_gutter_code_lens(
     .debug_name("CodeLens Stack")
 }
 
+/// The gutter icons at the start of the conflict blocks, which open a menu to
+/// resolve the conflict
+fn editor_gutter_conflicts(
+    window_tab_data: Rc<WindowTabData>,
+    doc: DocSignal,
+    screen_lines: RwSignal<ScreenLines>,
+    viewport: RwSignal<Rect>,
+    icon_padding: f32,
+) -> impl View {
+    let config = window_tab_data.common.config;
+    let internal_command = window_tab_data.common.internal_command;
+
+    dyn_stack(
+        move || doc.get().conflicts.get(),
+        |conflict| *conflict,
+        move |conflict: ConflictBlock| {
+            let line = conflict.start;
+            let view = container(
+                svg(move || config.get().ui_svg(LapceIcons::SCM_MERGE)).style(
+                    move |s| {
+                        let config = config.get();
+                        let size = config.ui.icon_size() as f32;
+                        s.size(size, size).color(
+                            config.color(LapceColor::SOURCE_CONTROL_CONFLICTED),
+                        )
+                    },
+                ),
+            )
+            .style(move |s| {
+                let config = config.get();
+                s.padding(4.0)
+                    .border_radius(6.0)
+                    .hover(|s| {
+                        s.cursor(CursorStyle::Pointer).background(
+                            config.color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                        )
+                    })
+                    .active(|s| {
+                        s.background(
+                            config
+                                .color(LapceColor::PANEL_HOVERED_ACTIVE_BACKGROUND),
+                        )
+                    })
+            })
+            .on_click_stop(move |_| {
+                let doc = doc.get_untracked();
+                let resolve = |resolution| {
+                    let doc = doc.clone();
+                    move || doc.resolve_conflict(conflict, resolution)
+                };
+                let mut menu = Menu::new("")
+                    .entry(
+                        MenuItem::new("Accept Current Change")
+                            .action(resolve(ConflictResolution::Current)),
+                    )
+                    .entry(
+                        MenuItem::new("Accept Incoming Change")
+                            .action(resolve(ConflictResolution::Incoming)),
+                    )
+                    .entry(
+                        MenuItem::new("Accept Both Changes")
+                            .action(resolve(ConflictResolution::Both)),
+                    );
+                if let DocContent::File { path, .. } = doc.content.get_untracked() {
+                    menu = menu.separator().entry(
+                        MenuItem::new("Open in Merge Editor").action(move || {
+                            internal_command.send(
+                                InternalCommand::OpenMergeEditor {
+                                    path: path.clone(),
+                                },
+                            );
+                        }),
+                    );
+                }
+                show_context_menu(menu, None);
+            });
+            container(view).style(move |s| {
+                let line_y = screen_lines
+                    .with(|s| s.info_for_line(line))
+                    .map(|l| l.y)
+                    .unwrap_or(-100.0);
+                let rect = viewport.get();
+                let config = config.get();
+                let width = config.ui.icon_size() as f32 + icon_padding * 2.0;
+                s.absolute()
+                    .width(width)
+                    .height(config.editor.line_height() as f32)
+                    .justify_center()
+                    .items_center()
+                    .margin_top(line_y as f32 - rect.y0 as f32)
+            })
+        },
+    )
+    .style(move |s| {
+        let config = config.get();
+        let width = config.ui.icon_size() as f32 + icon_padding * 2.0;
+        s.absolute()
+            .width(width)
+            .height_full()
+            .margin_left(width - 8.0)
+    })
+    .debug_name("Conflict Stack")
+}
+
 fn editor_gutter_folding_range(
     window_tab_data: Rc<WindowTabData>,
     doc: DocSignal,
@@ -1783,11 +2031,7 @@ fn editor_gutter_folding_range(
     .style(move |s| {
         let config = config.get();
         let width = config.ui.icon_size() as f32;
-        // hide for now
-        s.width(width)
-            .height_full()
-            .margin_left(-width / 2.0)
-            .hide()
+        s.width(width).height_full().margin_left(-width / 2.0)
     })
     .debug_name("Folding Range Stack")
 }
@@ -1872,11 +2116,32 @@ fn editor_gutter_code_actions(
     .debug_name("Code Action LightBulb")
 }
 
+/// The menu for staging the change around `line` of a file, or unstaging it
+/// from the index version of the file
+fn gutter_hunk_context_menu(proxy: &ProxyRpcHandler, doc: Rc<Doc>, line: usize) {
+    let proxy = proxy.clone();
+    let menu = match doc.content.get_untracked() {
+        DocContent::File { path, .. } => Menu::new("").entry(
+            MenuItem::new("Stage Hunk")
+                .action(move || proxy.git_stage_hunk(path.clone(), line)),
+        ),
+        DocContent::History(history) if history.version == "index" => Menu::new("")
+            .entry(
+                MenuItem::new("Unstage Hunk").action(move || {
+                    proxy.git_unstage_hunk(history.path.clone(), line)
+                }),
+            ),
+        _ => return,
+    };
+    show_context_menu(menu, None);
+}
+
 fn editor_gutter(
     window_tab_data: Rc<WindowTabData>,
     e_data: RwSignal<EditorData>,
 ) -> impl View {
     let icon_padding = 6.0;
+    let proxy = window_tab_data.common.proxy.clone();
 
     let (ed, doc, config) = e_data
         .with_untracked(|e| (e.editor.clone(), e.doc_signal(), e.common.config));
@@ -1915,10 +2180,45 @@ fn editor_gutter(
                     viewport,
                     icon_padding,
                 ),
+                editor_gutter_conflicts(
+                    window_tab_data.clone(),
+                    doc,
+                    screen_lines,
+                    viewport,
+                    icon_padding,
+                ),
                 editor_gutter_view(e_data.get_untracked(), gutter_padding_right)
                     .on_resize(move |rect| {
                         gutter_rect.set(rect);
                     })
+                    .on_event_cont(EventListener::PointerDown, move |event| {
+                        let Event::PointerDown(pointer_event) = event else {
+                            return;
+                        };
+                        if !pointer_event.button.is_secondary() {
+                            return;
+                        }
+                        let line_height =
+                            config.get_untracked().editor.line_height() as f64;
+                        let y0 = viewport.get_untracked().y0;
+                        let line = screen_lines.with_untracked(|screen_lines| {
+                            screen_lines
+                                .iter_lines_y()
+                                .find(|(_, y)| {
+                                    let y = y - y0;
+                                    y <= pointer_event.pos.y
+                                        && pointer_event.pos.y < y + line_height
+                                })
+                                .map(|(line, _)| line)
+                        });
+                        if let Some(line) = line {
+                            gutter_hunk_context_menu(
+                                &proxy,
+                                doc.get_untracked(),
+                                line,
+                            );
+                        }
+                    })
                     .on_event_stop(EventListener::PointerWheel, move |event| {
                         if let Event::PointerWheel(pointer_event) = event {
                             scroll_delta.set(pointer_event.delta);
@@ -1935,6 +2235,83 @@ fn editor_gutter(
     .debug_name("Editor Gutter")
 }
 
+/// The blame column, which opens the changes of a line's commit on click and
+/// shows its details on hover
+fn editor_gutter_blame(e_data: RwSignal<EditorData>) -> impl View {
+    let (editor, config) = e_data.with_untracked(|e| (e.clone(), e.common.config));
+    let internal_command = editor.common.internal_command;
+    let kind = editor.kind;
+    let viewport = editor.editor.viewport;
+    let screen_lines = editor.editor.screen_lines;
+
+    let line_of_y = move |y: f64| {
+        let line_height = config.get_untracked().editor.line_height() as f64;
+        let y0 = viewport.get_untracked().y0;
+        screen_lines.with_untracked(|screen_lines| {
+            screen_lines
+                .iter_lines_y()
+                .find(|(_, line_y)| {
+                    let line_y = line_y - y0;
+                    line_y <= y && y < line_y + line_height
+                })
+                .map(|(line, _)| line)
+        })
+    };
+
+    let hover_editor = editor.clone();
+    let leave_editor = editor.clone();
+    stack((
+        // Sizes the column for a date and a shortened author name
+        label(|| "0000-00-00 wwwwwwwwwwww".to_string())
+            .style(|s| s.padding_horiz(10.0).color(Color::TRANSPARENT)),
+        clip(
+            blame_gutter_view(editor.clone())
+                .on_event_cont(EventListener::PointerDown, move |event| {
+                    let Event::PointerDown(pointer_event) = event else {
+                        return;
+                    };
+                    if !pointer_event.button.is_primary() {
+                        return;
+                    }
+                    let doc = editor.doc();
+                    let path = doc.content.with_untracked(|c| c.path().cloned());
+                    let commit = line_of_y(pointer_event.pos.y)
+                        .and_then(|line| doc.blame_of_line(line))
+                        .and_then(|hunk| hunk.commit);
+                    if let (Some(path), Some(commit)) = (path, commit) {
+                        internal_command.send(
+                            InternalCommand::OpenCommitFileChanges { path, commit },
+                        );
+                    }
+                })
+                .on_event_cont(EventListener::PointerMove, move |event| {
+                    let Event::PointerMove(pointer_event) = event else {
+                        return;
+                    };
+                    if let Some(line) = line_of_y(pointer_event.pos.y) {
+                        let offset = hover_editor
+                            .doc()
+                            .buffer
+                            .with_untracked(|b| b.offset_of_line(line));
+                        hover_editor.schedule_blame_hover(line, offset);
+                    }
+                })
+                .on_event_cont(EventListener::PointerLeave, move |_| {
+                    leave_editor.pointer_leave();
+                })
+                .style(|s| s.size_pct(100.0, 100.0).cursor(CursorStyle::Pointer)),
+        )
+        .style(|s| s.absolute().size_pct(100.0, 100.0)),
+    ))
+    .style(move |s| {
+        s.height_pct(100.0).apply_if(
+            !config.get().editor.blame_gutter || !kind.get().is_normal(),
+            |s| s.hide(),
+        )
+    })
+    .debug_name("Blame Gutter")
+}
+
 fn editor_breadcrumbs(
     workspace: Arc<LapceWorkspace>,
     e_data: EditorData,
@@ -266,6 +266,33 @@ pub fn view_info(
                     .with(|diff_editors| diff_editors.get(&diff_editor_id).cloned());
                 let confirmed = diff_editor_data.as_ref().map(|d| d.confirmed);
 
+                // A merge editor is named after the file being merged
+                let merge_info = diff_editor_data
+                    .as_ref()
+                    .and_then(|data| data.result.as_ref())
+                    .and_then(|result| {
+                        result.doc_signal().with(|doc| match doc.content.get() {
+                            DocContent::File { path, .. } => {
+                                Some((path, doc.buffer.with(|b| b.is_pristine())))
+                            }
+                            _ => None,
+                        })
+                    });
+                if let Some((path, is_pristine)) = merge_info {
+                    let (icon, color) = config.file_svg(&path);
+                    return EditorTabChildViewInfo {
+                        icon,
+                        color,
+                        name: format!(
+                            "{} (Merge)",
+                            path.file_name().unwrap_or_default().to_string_lossy()
+                        ),
+                        path: None,
+                        confirmed,
+                        is_pristine,
+                    };
+                }
+
                 let info = diff_editor_data
                     .map(|diff_editor_data| {
                         [diff_editor_data.left, diff_editor_data.right].map(|data| {
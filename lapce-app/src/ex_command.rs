@@ -0,0 +1,606 @@
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, bail, Result};
+
+/// A line address of an ex command range
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExLine {
+    /// A 1-based line number
+    Number(usize),
+    /// `.`, the line of the cursor
+    Current,
+    /// `$`, the last line
+    Last,
+}
+
+impl ExLine {
+    /// The 0-based line, clamped to the last line
+    fn resolve(self, current: usize, last: usize) -> usize {
+        match self {
+            ExLine::Number(n) => n.saturating_sub(1).min(last),
+            ExLine::Current => current,
+            ExLine::Last => last,
+        }
+    }
+}
+
+/// The lines an ex command applies to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExRange {
+    /// `%`, the whole file
+    All,
+    /// `'<,'>`, the lines of the selection
+    Selection,
+    /// `n` or `n,m`
+    Lines(ExLine, ExLine),
+}
+
+impl ExRange {
+    /// The 0-based first and last lines of the range, `selection` being the lines
+    /// of the selection if there is one
+    pub fn resolve(
+        self,
+        current: usize,
+        last: usize,
+        selection: Option<(usize, usize)>,
+    ) -> Result<(usize, usize)> {
+        match self {
+            ExRange::All => Ok((0, last)),
+            ExRange::Selection => selection.ok_or_else(|| anyhow!("no selection")),
+            ExRange::Lines(start, end) => {
+                let start = start.resolve(current, last);
+                let end = end.resolve(current, last);
+                Ok((start.min(end), start.max(end)))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Substitute {
+    /// The pattern to replace in vim's syntax, see [`regex_pattern`], or empty
+    /// to reuse the current search
+    pub pattern: String,
+    /// The replacement in vim's syntax, see [`regex_replacement`]
+    pub replacement: String,
+    /// `g`, replace every match of a line instead of the first one
+    pub global: bool,
+    /// `i`, match regardless of case
+    pub ignore_case: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SortOptions {
+    /// `:sort!`
+    pub reverse: bool,
+    /// `i`
+    pub ignore_case: bool,
+    /// `n`, sort by the first number of the lines
+    pub numeric: bool,
+    /// `u`, keep only the first of equal lines
+    pub unique: bool,
+}
+
+impl SortOptions {
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        if self.numeric {
+            first_number(a).cmp(&first_number(b))
+        } else if self.ignore_case {
+            a.to_lowercase().cmp(&b.to_lowercase())
+        } else {
+            a.cmp(b)
+        }
+    }
+
+    /// Sort `lines` the way `:sort` does
+    pub fn sort(&self, lines: &mut Vec<String>) {
+        lines.sort_by(|a, b| self.compare(a, b));
+        if self.unique {
+            lines.dedup_by(|a, b| self.compare(a, b) == Ordering::Equal);
+        }
+        if self.reverse {
+            lines.reverse();
+        }
+    }
+}
+
+/// The first decimal number of `line`, lines without one sorting first
+fn first_number(line: &str) -> Option<i64> {
+    let start = line.find(|c: char| c.is_ascii_digit())?;
+    let end = line[start..]
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or(line.len(), |end| start + end);
+    let n = line[start..end].parse::<i64>().unwrap_or(i64::MAX);
+    if line[..start].ends_with('-') {
+        Some(-n)
+    } else {
+        Some(n)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExCommandKind {
+    /// A range without a command, which moves to its last line
+    GoToLine,
+    /// `:w`
+    Write,
+    /// `:q`, or `:q!` to close without saving
+    Quit { force: bool },
+    /// `:wq` and `:x`
+    WriteQuit,
+    /// `:e <file>`
+    Edit { path: String },
+    /// `:d`
+    Delete,
+    /// `:s/pattern/replacement/flags`
+    Substitute(Substitute),
+    /// `:g/pattern/command`, or `:g!` and `:v` for the lines that don't match,
+    /// with the pattern in vim's syntax
+    Global {
+        pattern: String,
+        invert: bool,
+        command: Box<ExCommandKind>,
+    },
+    /// `:sort`
+    Sort(SortOptions),
+    /// `:noh`
+    NoHighlight,
+}
+
+impl ExCommandKind {
+    /// Whether the command edits the lines of its range, defaulting to the line
+    /// of the cursor, or to the whole file for `:g` and `:sort`
+    pub fn is_edit(&self) -> bool {
+        matches!(
+            self,
+            ExCommandKind::Delete
+                | ExCommandKind::Substitute(_)
+                | ExCommandKind::Global { .. }
+                | ExCommandKind::Sort(_)
+        )
+    }
+}
+
+/// A vim ex command typed in the command line of modal editing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExCommand {
+    pub range: Option<ExRange>,
+    pub kind: ExCommandKind,
+}
+
+impl ExCommand {
+    pub fn parse(input: &str) -> Result<ExCommand> {
+        let input = input.trim();
+        let input = input.strip_prefix(':').unwrap_or(input).trim_start();
+        let (range, rest) = parse_range(input)?;
+        let rest = rest.trim_start();
+        if rest.is_empty() {
+            return match range {
+                Some(range) => Ok(ExCommand {
+                    range: Some(range),
+                    kind: ExCommandKind::GoToLine,
+                }),
+                None => bail!("no command"),
+            };
+        }
+        let kind = parse_kind(rest)?;
+        if range.is_some() && !kind.is_edit() {
+            bail!("no range allowed");
+        }
+        Ok(ExCommand { range, kind })
+    }
+
+    /// The range, or the default range of the command when none was given
+    pub fn range(&self) -> ExRange {
+        match (&self.range, &self.kind) {
+            (Some(range), _) => *range,
+            (None, ExCommandKind::Global { .. } | ExCommandKind::Sort(_)) => {
+                ExRange::All
+            }
+            (None, _) => ExRange::Lines(ExLine::Current, ExLine::Current),
+        }
+    }
+}
+
+fn parse_range(input: &str) -> Result<(Option<ExRange>, &str)> {
+    if let Some(rest) = input.strip_prefix('%') {
+        return Ok((Some(ExRange::All), rest));
+    }
+    if let Some(rest) = input.strip_prefix("'<,'>") {
+        return Ok((Some(ExRange::Selection), rest));
+    }
+    let Some((start, rest)) = parse_line(input) else {
+        return Ok((None, input));
+    };
+    if let Some(rest) = rest.strip_prefix(',') {
+        let (end, rest) =
+            parse_line(rest).ok_or_else(|| anyhow!("invalid range"))?;
+        return Ok((Some(ExRange::Lines(start, end)), rest));
+    }
+    Ok((Some(ExRange::Lines(start, start)), rest))
+}
+
+fn parse_line(input: &str) -> Option<(ExLine, &str)> {
+    if let Some(rest) = input.strip_prefix('.') {
+        return Some((ExLine::Current, rest));
+    }
+    if let Some(rest) = input.strip_prefix('$') {
+        return Some((ExLine::Last, rest));
+    }
+    let end = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let n = input[..end].parse().ok()?;
+    Some((ExLine::Number(n), &input[end..]))
+}
+
+/// Whether `name` is `full` or an abbreviation of it at least `min` long
+fn is_abbreviation(name: &str, full: &str, min: usize) -> bool {
+    name.len() >= min && full.starts_with(name)
+}
+
+fn parse_kind(input: &str) -> Result<ExCommandKind> {
+    let name_end = input
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(input.len());
+    let (name, rest) = input.split_at(name_end);
+    let (bang, rest) = match rest.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let args = rest.trim();
+
+    let kind = if name == "wq" || is_abbreviation(name, "xit", 1) {
+        ExCommandKind::WriteQuit
+    } else if is_abbreviation(name, "write", 1) {
+        if !args.is_empty() {
+            bail!("writing to another file is not supported");
+        }
+        ExCommandKind::Write
+    } else if is_abbreviation(name, "quit", 1) {
+        ExCommandKind::Quit { force: bang }
+    } else if is_abbreviation(name, "edit", 1) {
+        if args.is_empty() {
+            bail!("no file name");
+        }
+        ExCommandKind::Edit {
+            path: args.to_string(),
+        }
+    } else if is_abbreviation(name, "delete", 1) {
+        ExCommandKind::Delete
+    } else if is_abbreviation(name, "sort", 3) {
+        ExCommandKind::Sort(parse_sort(args, bang)?)
+    } else if is_abbreviation(name, "substitute", 1) {
+        ExCommandKind::Substitute(parse_substitute(rest)?)
+    } else if is_abbreviation(name, "global", 1) {
+        parse_global(rest, bang)?
+    } else if is_abbreviation(name, "vglobal", 1) {
+        parse_global(rest, true)?
+    } else if is_abbreviation(name, "nohlsearch", 3) {
+        ExCommandKind::NoHighlight
+    } else {
+        bail!("not an editor command: {input}");
+    };
+    Ok(kind)
+}
+
+/// Split off the delimiter that starts the pattern of `:s` and `:g`
+fn parse_delimiter(input: &str) -> Result<(char, &str)> {
+    let delimiter = input
+        .chars()
+        .next()
+        .ok_or_else(|| anyhow!("missing pattern"))?;
+    if delimiter.is_alphanumeric()
+        || delimiter.is_whitespace()
+        || matches!(delimiter, '\\' | '"' | '|')
+    {
+        bail!("invalid delimiter `{delimiter}`");
+    }
+    Ok((delimiter, &input[delimiter.len_utf8()..]))
+}
+
+/// Split `input` at the first `delimiter` that isn't escaped with a backslash,
+/// dropping the backslash of escaped delimiters
+fn split_delimited(input: &str, delimiter: char) -> (String, Option<&str>) {
+    let mut part = String::new();
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some((_, next)) if next == delimiter => part.push(next),
+                Some((_, next)) => {
+                    part.push('\\');
+                    part.push(next);
+                }
+                None => part.push('\\'),
+            }
+        } else if c == delimiter {
+            return (part, Some(&input[i + c.len_utf8()..]));
+        } else {
+            part.push(c);
+        }
+    }
+    (part, None)
+}
+
+fn parse_substitute(input: &str) -> Result<Substitute> {
+    let (delimiter, rest) = parse_delimiter(input)?;
+    let (pattern, rest) = split_delimited(rest, delimiter);
+    let (replacement, rest) = match rest {
+        Some(rest) => split_delimited(rest, delimiter),
+        None => (String::new(), None),
+    };
+    let mut substitute = Substitute {
+        pattern,
+        replacement,
+        global: false,
+        ignore_case: false,
+    };
+    for c in rest.unwrap_or("").trim().chars() {
+        match c {
+            'g' => substitute.global = true,
+            'i' => substitute.ignore_case = true,
+            'I' => substitute.ignore_case = false,
+            _ => bail!("invalid flag `{c}`"),
+        }
+    }
+    Ok(substitute)
+}
+
+fn parse_global(input: &str, invert: bool) -> Result<ExCommandKind> {
+    let (delimiter, rest) = parse_delimiter(input)?;
+    let (pattern, command) = split_delimited(rest, delimiter);
+    if pattern.is_empty() {
+        bail!("missing pattern");
+    }
+    let command = command.unwrap_or("").trim();
+    if command.is_empty() {
+        bail!("missing command");
+    }
+    let command = parse_kind(command)?;
+    if !matches!(
+        command,
+        ExCommandKind::Delete | ExCommandKind::Substitute(_)
+    ) {
+        bail!("only `d` and `s` can be run on the matching lines");
+    }
+    Ok(ExCommandKind::Global {
+        pattern,
+        invert,
+        command: Box::new(command),
+    })
+}
+
+fn parse_sort(args: &str, reverse: bool) -> Result<SortOptions> {
+    let mut options = SortOptions {
+        reverse,
+        ..Default::default()
+    };
+    for c in args.chars().filter(|c| !c.is_whitespace()) {
+        match c {
+            'i' => options.ignore_case = true,
+            'n' => options.numeric = true,
+            'u' => options.unique = true,
+            _ => bail!("invalid sort flag `{c}`"),
+        }
+    }
+    Ok(options)
+}
+
+/// Convert a pattern in vim's default "magic" syntax to the syntax of the `regex`
+/// crate. The groups `\(\)`, alternation `\|`, counts `\{n,m}` and the `\+`,
+/// `\?` or `\=` quantifiers lose their backslash, `\<` and `\>` match the start
+/// and the end of a word, and the bare `(`, `)`, `|`, `{`, `+` and `?` are matched literally.
+pub fn regex_pattern(pattern: &str) -> String {
+    let mut result = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | ')' | '|' | '{' | '+' | '?' => {
+                result.push('\\');
+                result.push(c);
+            }
+            '\\' => match chars.next() {
+                Some(c @ ('(' | ')' | '|' | '{' | '}' | '+' | '?')) => {
+                    result.push(c)
+                }
+                Some('=') => result.push('?'),
+                Some('<') => result.push_str("\\b{start}"),
+                Some('>') => result.push_str("\\b{end}"),
+                Some(c) => {
+                    result.push('\\');
+                    result.push(c);
+                }
+                None => result.push_str("\\\\"),
+            },
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Convert a replacement in vim's syntax, where `&` and `\0` are the whole match,
+/// `\1` to `\9` are groups and `\r` or `\n` is a line break, to the syntax of
+/// [`regex::Regex::replace`]
+pub fn regex_replacement(replacement: &str) -> String {
+    let mut result = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => result.push_str("${0}"),
+            '$' => result.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(group @ '0'..='9') => {
+                    result.push_str("${");
+                    result.push(group);
+                    result.push('}');
+                }
+                Some('r' | 'n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some('$') => result.push_str("$$"),
+                Some(c) => result.push(c),
+                None => result.push('\\'),
+            },
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let parse = |s: &str| ExCommand::parse(s).unwrap();
+
+        assert_eq!(parse("w").kind, ExCommandKind::Write);
+        assert_eq!(parse(":q!").kind, ExCommandKind::Quit { force: true });
+        assert_eq!(parse("wq").kind, ExCommandKind::WriteQuit);
+        assert_eq!(
+            parse("e src/main.rs").kind,
+            ExCommandKind::Edit {
+                path: "src/main.rs".to_string()
+            }
+        );
+        assert_eq!(parse("noh").kind, ExCommandKind::NoHighlight);
+        assert_eq!(
+            parse("42"),
+            ExCommand {
+                range: Some(ExRange::Lines(ExLine::Number(42), ExLine::Number(42))),
+                kind: ExCommandKind::GoToLine,
+            }
+        );
+        assert_eq!(
+            parse("%s/a\\/b/c/gi"),
+            ExCommand {
+                range: Some(ExRange::All),
+                kind: ExCommandKind::Substitute(Substitute {
+                    pattern: "a/b".to_string(),
+                    replacement: "c".to_string(),
+                    global: true,
+                    ignore_case: true,
+                }),
+            }
+        );
+        assert_eq!(parse("'<,'>s#x#y").range, Some(ExRange::Selection));
+        assert_eq!(
+            parse("3,$d").range,
+            Some(ExRange::Lines(ExLine::Number(3), ExLine::Last))
+        );
+        assert_eq!(
+            parse("g/TODO/d"),
+            ExCommand {
+                range: None,
+                kind: ExCommandKind::Global {
+                    pattern: "TODO".to_string(),
+                    invert: false,
+                    command: Box::new(ExCommandKind::Delete),
+                },
+            }
+        );
+        assert_eq!(
+            parse("v/x/s/a/b/").kind,
+            ExCommandKind::Global {
+                pattern: "x".to_string(),
+                invert: true,
+                command: Box::new(ExCommandKind::Substitute(Substitute {
+                    pattern: "a".to_string(),
+                    replacement: "b".to_string(),
+                    global: false,
+                    ignore_case: false,
+                })),
+            }
+        );
+        assert_eq!(
+            parse("sort! nu").kind,
+            ExCommandKind::Sort(SortOptions {
+                reverse: true,
+                ignore_case: false,
+                numeric: true,
+                unique: true,
+            })
+        );
+
+        assert!(ExCommand::parse("").is_err());
+        assert!(ExCommand::parse("foo").is_err());
+        assert!(ExCommand::parse("2,w").is_err());
+        assert!(ExCommand::parse("%w").is_err());
+        assert!(ExCommand::parse("g/x/w").is_err());
+        assert!(ExCommand::parse("s/a/b/z").is_err());
+    }
+
+    #[test]
+    fn test_range() {
+        let range = |s: &str| ExCommand::parse(s).unwrap().range();
+        assert_eq!(range("%d").resolve(4, 9, None).unwrap(), (0, 9));
+        assert_eq!(range("d").resolve(4, 9, None).unwrap(), (4, 4));
+        assert_eq!(range("sort").resolve(4, 9, None).unwrap(), (0, 9));
+        assert_eq!(range("7,2d").resolve(4, 9, None).unwrap(), (1, 6));
+        assert_eq!(range(".,$d").resolve(4, 9, None).unwrap(), (4, 9));
+        assert_eq!(range("100").resolve(4, 9, None).unwrap(), (9, 9));
+        assert_eq!(range("'<,'>d").resolve(4, 9, Some((2, 3))).unwrap(), (2, 3));
+        assert!(range("'<,'>d").resolve(4, 9, None).is_err());
+    }
+
+    #[test]
+    fn test_regex_replacement() {
+        assert_eq!(regex_replacement("a&b"), "a${0}b");
+        assert_eq!(regex_replacement("\\1-\\2"), "${1}-${2}");
+        assert_eq!(regex_replacement("\\&$5"), "&$$5");
+        assert_eq!(regex_replacement("a\\rb"), "a\nb");
+
+        let regex = regex::Regex::new("(\\w+) (\\w+)").unwrap();
+        assert_eq!(
+            regex.replace("hello world", regex_replacement("\\2 \\1 &").as_str()),
+            "world hello hello world"
+        );
+    }
+
+    #[test]
+    fn test_regex_pattern() {
+        assert_eq!(regex_pattern("\\(a\\|b\\)\\+"), "(a|b)+");
+        assert_eq!(regex_pattern("\\<foo\\>"), "\\b{start}foo\\b{end}");
+        assert_eq!(regex_pattern("f(x) + 1?"), "f\\(x\\) \\+ 1\\?");
+        assert_eq!(regex_pattern("a\\{2,3}\\s\\."), "a{2,3}\\s\\.");
+
+        let regex = regex::Regex::new(&regex_pattern("\\(\\w\\+\\)(\\d)")).unwrap();
+        assert_eq!(regex.replace("call(1)", "$1"), "call");
+    }
+
+    #[test]
+    fn test_sort() {
+        let sort = |options: SortOptions, lines: &[&str]| {
+            let mut lines = lines.iter().map(|l| l.to_string()).collect();
+            options.sort(&mut lines);
+            lines
+        };
+        assert_eq!(
+            sort(SortOptions::default(), &["b", "a", "C"]),
+            vec!["C", "a", "b"]
+        );
+        assert_eq!(
+            sort(
+                SortOptions {
+                    ignore_case: true,
+                    unique: true,
+                    ..Default::default()
+                },
+                &["b", "a", "B", "C"]
+            ),
+            vec!["a", "b", "C"]
+        );
+        assert_eq!(
+            sort(
+                SortOptions {
+                    numeric: true,
+                    reverse: true,
+                    ..Default::default()
+                },
+                &["x10", "x-3", "none", "x2"]
+            ),
+            vec!["x10", "x2", "x-3", "none"]
+        );
+    }
+}
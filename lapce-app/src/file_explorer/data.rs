@@ -120,13 +120,18 @@ fn receive_char(&self, c: &str) {
 impl FileExplorerData {
     pub fn new(cx: Scope, editors: Editors, common: Rc<CommonData>) -> Self {
         let path = common.workspace.path.clone().unwrap_or_default();
-        let root = cx.create_rw_signal(FileNodeItem {
-            path: path.clone(),
-            is_dir: true,
-            read: false,
-            open: false,
-            children: HashMap::new(),
-            children_open_count: 0,
+        let roots = common.workspace.roots();
+        let root = cx.create_rw_signal(if common.workspace.is_multi_root() {
+            FileNodeItem::workspace_roots(&roots)
+        } else {
+            FileNodeItem {
+                path: path.clone(),
+                is_dir: true,
+                read: false,
+                open: false,
+                children: HashMap::new(),
+                children_open_count: 0,
+            }
         });
         let naming = cx.create_rw_signal(Naming::None);
         let naming_editor_data = editors.make_local(cx, common.clone());
@@ -139,9 +144,9 @@ pub fn new(cx: Scope, editors: Editors, common: Rc<CommonData>) -> Self {
             left_diff_path: cx.create_rw_signal(None),
             select: cx.create_rw_signal(None),
         };
-        if data.common.workspace.path.is_some() {
-            // only fill in the child files if there is open folder
-            data.toggle_expand(&path);
+        // only fill in the child files if there is open folder
+        for root in roots {
+            data.toggle_expand(&root);
         }
         data
     }
@@ -149,8 +154,20 @@ pub fn new(cx: Scope, editors: Editors, common: Rc<CommonData>) -> Self {
     /// Reload the file explorer data via reading the root directory.  
     /// Note that this will not update immediately.
     pub fn reload(&self) {
-        let path = self.root.with_untracked(|root| root.path.clone());
-        self.read_dir(&path);
+        let paths = self.root.with_untracked(|root| {
+            if root.is_workspace_roots() {
+                root.children
+                    .values()
+                    .filter(|root| root.read)
+                    .map(|root| root.path.clone())
+                    .collect()
+            } else {
+                vec![root.path.clone()]
+            }
+        });
+        for path in paths {
+            self.read_dir(&path);
+        }
     }
 
     /// Toggle whether the directory is expanded or not.  
@@ -452,8 +469,12 @@ pub fn reveal_in_file_tree(&self, path: PathBuf) {
             })
             .unwrap_or(false);
         if done {
-            let (found, line) =
+            let (found, mut line) =
                 self.root.with_untracked(|x| x.find_file_at_line(&path));
+            if self.root.with_untracked(FileNodeItem::is_workspace_roots) {
+                // The parent of multi-root workspace roots takes no line
+                line -= 1.0;
+            }
             if found {
                 self.scroll_to_line.set(Some(line));
                 self.select.set(Some(FileNodeViewKind::Path(path)));
@@ -490,10 +511,11 @@ pub fn secondary_click(&self, path: &Path) {
         // TODO: should we just pass is_dir into secondary click?
         let is_dir = self.is_dir(path);
 
-        let Some(workspace_path) = self.common.workspace.path.as_ref() else {
+        let Some(workspace_path) = self.common.workspace.root_of(path) else {
             // There is no context menu if we are not in a workspace
             return;
         };
+        let workspace_path = &workspace_path;
 
         let is_workspace = path == workspace_path;
 
@@ -626,11 +648,14 @@ pub fn secondary_click(&self, path: &Path) {
         let path = path_a.clone();
         let workspace = common.workspace.clone();
         menu = menu.entry(MenuItem::new("Copy Relative Path").action(move || {
-            let relative_path = if let Some(workspace_path) = &workspace.path {
-                path.strip_prefix(workspace_path).unwrap_or(&path)
-            } else {
-                path.as_ref()
-            };
+            let relative_path =
+                if let Some(workspace_path) = workspace.root_of(&path) {
+                    path.strip_prefix(workspace_path)
+                        .unwrap_or(&path)
+                        .to_path_buf()
+                } else {
+                    path.clone()
+                };
 
             let mut clipboard = SystemClipboard::new();
             clipboard.put_string(relative_path.to_string_lossy());
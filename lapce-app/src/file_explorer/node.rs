@@ -17,6 +17,10 @@ pub fn new(file_node_item: FileNodeItem, naming: Naming) -> Self {
 
 impl VirtualVector<FileNodeViewData> for FileNodeVirtualList {
     fn total_len(&self) -> usize {
+        if self.file_node_item.is_workspace_roots() {
+            // The roots of a multi-root workspace are shown without their parent
+            return self.file_node_item.children_open_count;
+        }
         if !self.file_node_item.path.exists() {
             return 0;
         }
@@ -30,13 +34,27 @@ fn slice(
         let naming = &self.naming;
         let root = &self.file_node_item;
 
-        if !root.path.exists() {
-            return Vec::new().into_iter();
-        }
         let min = range.start;
         let max = range.end;
         let mut view_items = Vec::new();
 
+        if root.is_workspace_roots() {
+            // Skip the row the hidden parent would take, so that the roots
+            // end up at the first level
+            root.append_children_view_slice(
+                &mut view_items,
+                naming,
+                min + 1,
+                max + 1,
+                0,
+                0,
+            );
+            return view_items.into_iter();
+        }
+        if !root.path.exists() {
+            return Vec::new().into_iter();
+        }
+
         root.append_view_slice(&mut view_items, naming, min, max, 0, 1);
 
         view_items.into_iter()
@@ -1,4 +1,8 @@
-use std::{path::Path, rc::Rc, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+};
 
 use floem::{
     event::{Event, EventListener},
@@ -15,10 +19,11 @@
     },
     View,
 };
+use indexmap::IndexMap;
 use lapce_core::selection::Selection;
 use lapce_rpc::{
     file::{FileNodeViewData, FileNodeViewKind, Naming},
-    source_control::FileDiffKind,
+    source_control::{FileDiff, FileDiffKind},
 };
 use lapce_xi_rope::Rope;
 
@@ -128,7 +133,7 @@ fn file_node_text_color(
     node: FileNodeViewData,
     source_control: SourceControlData,
 ) -> Color {
-    let diff = source_control.file_diffs.with(|file_diffs| {
+    let diff_of = |file_diffs: &IndexMap<PathBuf, FileDiff>| {
         let FileNodeViewKind::Path(path) = &node.kind else {
             return None;
         };
@@ -139,9 +144,13 @@ fn file_node_text_color(
                 .find(|p| p.as_path().starts_with(path))
                 .map(|_| FileDiffKind::Modified)
         } else {
-            file_diffs.get(path).map(|(diff, _)| diff.kind())
+            file_diffs.get(path).map(|diff| diff.kind())
         }
-    });
+    };
+    let diff = source_control
+        .file_diffs
+        .with(diff_of)
+        .or_else(|| source_control.staged_diffs.with(diff_of));
 
     let color = match diff {
         Some(FileDiffKind::Modified | FileDiffKind::Renamed) => {
@@ -149,6 +158,7 @@ fn file_node_text_color(
         }
         Some(FileDiffKind::Added) => LapceColor::SOURCE_CONTROL_ADDED,
         Some(FileDiffKind::Deleted) => LapceColor::SOURCE_CONTROL_REMOVED,
+        Some(FileDiffKind::Conflicted) => LapceColor::SOURCE_CONTROL_CONFLICTED,
         None => LapceColor::PANEL_FOREGROUND,
     };
 
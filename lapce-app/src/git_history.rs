@@ -0,0 +1,202 @@
+use std::{path::PathBuf, rc::Rc};
+
+use floem::{
+    ext_event::create_ext_action,
+    keyboard::Modifiers,
+    prelude::SignalTrack,
+    reactive::{RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
+};
+use lapce_core::mode::Mode;
+use lapce_rpc::{
+    proxy::ProxyResponse,
+    source_control::{FileDiff, GitCommit},
+};
+
+use crate::{
+    command::{CommandExecuted, CommandKind},
+    editor::EditorData,
+    keypress::{condition::Condition, KeyPressFocus},
+    main_split::MainSplitData,
+    window_tab::CommonData,
+};
+
+/// How many commits are listed at most
+const HISTORY_LIMIT: usize = 500;
+
+#[derive(Clone)]
+pub struct GitHistoryData {
+    /// The file whose history is listed, or `None` for the whole repository
+    pub path: RwSignal<Option<PathBuf>>,
+    /// Filters the commits by message or author
+    pub editor: EditorData,
+    pub commits: RwSignal<im::Vector<GitCommit>>,
+    /// The commit whose changed files are listed
+    pub selected: RwSignal<Option<GitCommit>>,
+    pub files: RwSignal<im::Vector<FileDiff>>,
+    /// The id of the last request, so that older responses are ignored
+    request_id: RwSignal<u64>,
+    pub main_split: MainSplitData,
+    pub common: Rc<CommonData>,
+}
+
+impl KeyPressFocus for GitHistoryData {
+    fn get_mode(&self) -> Mode {
+        Mode::Insert
+    }
+
+    fn check_condition(&self, condition: Condition) -> bool {
+        matches!(condition, Condition::PanelFocus)
+    }
+
+    fn run_command(
+        &self,
+        command: &crate::command::LapceCommand,
+        count: Option<usize>,
+        mods: Modifiers,
+    ) -> CommandExecuted {
+        match &command.kind {
+            CommandKind::Edit(_)
+            | CommandKind::Move(_)
+            | CommandKind::MultiSelection(_) => {
+                self.editor.run_command(command, count, mods)
+            }
+            CommandKind::Workbench(_)
+            | CommandKind::Scroll(_)
+            | CommandKind::Focus(_)
+            | CommandKind::MotionMode(_) => CommandExecuted::No,
+        }
+    }
+
+    fn receive_char(&self, c: &str) {
+        self.editor.receive_char(c);
+    }
+}
+
+impl GitHistoryData {
+    pub fn new(
+        cx: Scope,
+        main_split: MainSplitData,
+        branch: RwSignal<String>,
+    ) -> Self {
+        let common = main_split.common.clone();
+        let git_history = Self {
+            path: cx.create_rw_signal(None),
+            editor: main_split.editors.make_local(cx, common.clone()),
+            commits: cx.create_rw_signal(im::Vector::new()),
+            selected: cx.create_rw_signal(None),
+            files: cx.create_rw_signal(im::Vector::new()),
+            request_id: cx.create_rw_signal(0),
+            main_split,
+            common,
+        };
+
+        {
+            let git_history = git_history.clone();
+            let buffer = git_history.editor.doc().buffer;
+            cx.create_effect(move |_| {
+                buffer.track();
+                git_history.path.track();
+                // A checkout or a pull changes the history
+                branch.track();
+                git_history.load();
+            });
+        }
+
+        git_history
+    }
+
+    /// List the commits of the whole repository
+    pub fn show_repository(&self) {
+        if self.path.get_untracked().is_some() {
+            self.path.set(None);
+        } else {
+            self.load();
+        }
+    }
+
+    /// List the commits changing the file at `path`
+    pub fn show_file(&self, path: PathBuf) {
+        if self.path.get_untracked().as_ref() != Some(&path) {
+            self.path.set(Some(path));
+        } else {
+            self.load();
+        }
+    }
+
+    pub fn load(&self) {
+        let path = self.path.get_untracked();
+        let query = self
+            .editor
+            .doc()
+            .buffer
+            .with_untracked(|buffer| buffer.to_string());
+        let request_id = self.request_id.get_untracked() + 1;
+        self.request_id.set(request_id);
+        self.selected.set(None);
+        self.files.set(im::Vector::new());
+
+        let commits = self.commits;
+        let current_id = self.request_id;
+        let send = create_ext_action(self.common.scope, move |result| {
+            if current_id.get_untracked() != request_id {
+                return;
+            }
+            match result {
+                Ok(ProxyResponse::GitLogResponse { commits: new }) => {
+                    commits.set(new.into());
+                }
+                _ => commits.set(im::Vector::new()),
+            }
+        });
+        self.common.proxy.git_log(
+            path,
+            query.trim().to_string(),
+            HISTORY_LIMIT,
+            move |result| {
+                send(result);
+            },
+        );
+    }
+
+    /// Select `commit` and list the files it changed
+    pub fn select(&self, commit: GitCommit) {
+        let files = self.files;
+        let selected = self.selected;
+        let id = commit.id.clone();
+        let send = create_ext_action(self.common.scope, move |result| {
+            // Another commit may have been selected since
+            if selected.with_untracked(|c| c.as_ref().map(|c| &c.id) != Some(&id)) {
+                return;
+            }
+            if let Ok(ProxyResponse::GitCommitFilesResponse { files: new }) = result
+            {
+                files.set(new.into());
+            }
+        });
+        self.files.set(im::Vector::new());
+        self.common.proxy.git_commit_files(
+            commit.id.clone(),
+            self.path.get_untracked(),
+            move |result| {
+                send(result);
+            },
+        );
+        self.selected.set(Some(commit));
+    }
+
+    /// Open a diff of the changes the selected commit made to `file`
+    pub fn open_file(&self, file: &FileDiff) {
+        let Some(commit) = self.selected.get_untracked() else {
+            return;
+        };
+        let (old_path, path) = match file {
+            FileDiff::Renamed(new, old) => (old.clone(), new.clone()),
+            FileDiff::Modified(path)
+            | FileDiff::Added(path)
+            | FileDiff::Deleted(path)
+            | FileDiff::Conflicted(path) => (path.clone(), path.clone()),
+        };
+        self.main_split
+            .open_commit_file_changes(old_path, path, commit.id);
+    }
+}
@@ -1,4 +1,8 @@
-use std::{ops::Range, path::PathBuf, rc::Rc};
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use floem::{
     ext_event::create_ext_action,
@@ -7,13 +11,22 @@
     views::VirtualVector,
 };
 use indexmap::IndexMap;
-use lapce_core::{mode::Mode, selection::Selection};
+use lapce_core::{
+    buffer::Buffer,
+    mode::Mode,
+    search::{build_search_matcher, replace_in_line},
+    selection::Selection,
+};
 use lapce_rpc::proxy::{ProxyResponse, SearchMatch};
 use lapce_xi_rope::Rope;
+use lsp_types::TextEdit;
 
 use crate::{
-    command::{CommandExecuted, CommandKind},
-    editor::EditorData,
+    command::{CommandExecuted, CommandKind, InternalCommand},
+    editor::{
+        location::{EditorLocation, EditorPosition},
+        EditorData,
+    },
     keypress::{condition::Condition, KeyPressFocus},
     main_split::MainSplitData,
     window_tab::CommonData,
@@ -38,9 +51,36 @@ pub fn height(&self) -> f64 {
     }
 }
 
+/// The inputs of the global search panel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchInput {
+    Search,
+    Replace,
+    Include,
+    Exclude,
+}
+
 #[derive(Clone, Debug)]
 pub struct GlobalSearchData {
     pub editor: EditorData,
+    pub replace_editor: EditorData,
+    /// Comma separated globs of the files to search in
+    pub include_editor: EditorData,
+    /// Comma separated globs of the files to leave out
+    pub exclude_editor: EditorData,
+    /// Whether the replace input is shown
+    pub replace_active: RwSignal<bool>,
+    /// The input that has the focus when the panel is focused
+    pub input_focus: RwSignal<SearchInput>,
+    /// Whether files ignored by `.gitignore` and `.ignore` are searched too
+    pub search_ignored: RwSignal<bool>,
+    /// Whether the last search stopped at `max-search-results`
+    pub limit_reached: RwSignal<bool>,
+    /// The id of the last search that was sent, so that matches from older
+    /// searches are ignored
+    search_id: RwSignal<u64>,
+    /// The match that was last jumped to, as its path, line and start column
+    pub active_match: RwSignal<Option<(PathBuf, usize, usize)>>,
     pub search_result: RwSignal<IndexMap<PathBuf, SearchMatchData>>,
     pub main_split: MainSplitData,
     pub common: Rc<CommonData>,
@@ -68,7 +108,7 @@ fn run_command(
             CommandKind::Edit(_)
             | CommandKind::Move(_)
             | CommandKind::MultiSelection(_) => {
-                return self.editor.run_command(command, count, mods);
+                return self.active_editor().run_command(command, count, mods);
             }
             CommandKind::MotionMode(_) => {}
         }
@@ -76,7 +116,7 @@ fn run_command(
     }
 
     fn receive_char(&self, c: &str) {
-        self.editor.receive_char(c);
+        self.active_editor().receive_char(c);
     }
 }
 
@@ -108,10 +148,22 @@ impl GlobalSearchData {
     pub fn new(cx: Scope, main_split: MainSplitData) -> Self {
         let common = main_split.common.clone();
         let editor = main_split.editors.make_local(cx, common.clone());
+        let replace_editor = main_split.editors.make_local(cx, common.clone());
+        let include_editor = main_split.editors.make_local(cx, common.clone());
+        let exclude_editor = main_split.editors.make_local(cx, common.clone());
         let search_result = cx.create_rw_signal(IndexMap::new());
 
         let global_search = Self {
             editor,
+            replace_editor,
+            include_editor,
+            exclude_editor,
+            replace_active: cx.create_rw_signal(false),
+            input_focus: cx.create_rw_signal(SearchInput::Search),
+            search_ignored: cx.create_rw_signal(false),
+            limit_reached: cx.create_rw_signal(false),
+            search_id: cx.create_rw_signal(0),
+            active_match: cx.create_rw_signal(None),
             search_result,
             main_split,
             common,
@@ -120,22 +172,45 @@ pub fn new(cx: Scope, main_split: MainSplitData) -> Self {
         {
             let global_search = global_search.clone();
             let buffer = global_search.editor.doc().buffer;
+            let include_buffer = global_search.include_editor.doc().buffer;
+            let exclude_buffer = global_search.exclude_editor.doc().buffer;
+            // Only a change of the cap itself searches again, not of the rest of
+            // the config
+            let config = global_search.common.config;
+            let max_results = cx.create_memo(move |_| {
+                config.with(|config| config.ui.max_search_results)
+            });
             cx.create_effect(move |_| {
                 let pattern = buffer.with(|buffer| buffer.to_string());
-                if pattern.is_empty() {
-                    global_search.search_result.update(|r| r.clear());
-                    return;
-                }
+                let include = include_buffer.with(split_globs);
+                let exclude = exclude_buffer.with(split_globs);
+                let search_ignored = global_search.search_ignored.get();
                 let case_sensitive = global_search.common.find.case_sensitive(true);
                 let whole_word = global_search.common.find.whole_words.get();
                 let is_regex = global_search.common.find.is_regex.get();
+                let max_results = max_results.get();
+
+                let search_id = global_search.search_id.get_untracked() + 1;
+                global_search.search_id.set(search_id);
+                global_search.limit_reached.set(false);
+                global_search.search_result.update(|r| r.clear());
+                if pattern.is_empty() {
+                    return;
+                }
+
                 let send = {
                     let global_search = global_search.clone();
                     create_ext_action(cx, move |result| {
-                        if let Ok(ProxyResponse::GlobalSearchResponse { matches }) =
-                            result
+                        if global_search.search_id.get_untracked() != search_id {
+                            return;
+                        }
+                        if let Ok(ProxyResponse::GlobalSearchResponse {
+                            matches,
+                            limit_reached,
+                        }) = result
                         {
-                            global_search.update_matches(matches);
+                            global_search.add_matches(matches);
+                            global_search.limit_reached.set(limit_reached);
                         }
                     })
                 };
@@ -144,6 +219,11 @@ pub fn new(cx: Scope, main_split: MainSplitData) -> Self {
                     case_sensitive,
                     whole_word,
                     is_regex,
+                    include,
+                    exclude,
+                    search_ignored,
+                    (max_results > 0).then_some(max_results),
+                    search_id,
                     move |result| {
                         send(result);
                     },
@@ -163,33 +243,263 @@ pub fn new(cx: Scope, main_split: MainSplitData) -> Self {
         global_search
     }
 
-    fn update_matches(&self, matches: IndexMap<PathBuf, Vec<SearchMatch>>) {
-        let current = self.search_result.get_untracked();
-
-        self.search_result.set(
-            matches
-                .into_iter()
-                .map(|(path, matches)| {
-                    let match_data =
-                        current.get(&path).cloned().unwrap_or_else(|| {
-                            SearchMatchData {
-                                expanded: self.common.scope.create_rw_signal(true),
-                                matches: self
-                                    .common
-                                    .scope
-                                    .create_rw_signal(im::Vector::new()),
-                                line_height: self.common.ui_line_height,
-                            }
-                        });
-
-                    match_data.matches.set(matches.into());
-
-                    (path, match_data)
-                })
-                .collect(),
+    /// Matches sent by the proxy while the search with `search_id` is still
+    /// running
+    pub fn partial_matches(
+        &self,
+        search_id: u64,
+        matches: IndexMap<PathBuf, Vec<SearchMatch>>,
+    ) {
+        if self.search_id.get_untracked() == search_id {
+            self.add_matches(matches);
+        }
+    }
+
+    fn add_matches(&self, matches: IndexMap<PathBuf, Vec<SearchMatch>>) {
+        if matches.is_empty() {
+            return;
+        }
+        self.search_result.update(|result| {
+            for (path, matches) in matches {
+                let match_data =
+                    result.entry(path).or_insert_with(|| SearchMatchData {
+                        expanded: self.common.scope.create_rw_signal(true),
+                        matches: self
+                            .common
+                            .scope
+                            .create_rw_signal(im::Vector::new()),
+                        line_height: self.common.ui_line_height,
+                    });
+                match_data.matches.update(|m| m.extend(matches));
+            }
+        });
+    }
+
+    /// The input that currently receives key presses
+    fn active_editor(&self) -> &EditorData {
+        match self.input_focus.get_untracked() {
+            SearchInput::Search => &self.editor,
+            SearchInput::Replace if self.replace_active.get_untracked() => {
+                &self.replace_editor
+            }
+            SearchInput::Replace => &self.editor,
+            SearchInput::Include => &self.include_editor,
+            SearchInput::Exclude => &self.exclude_editor,
+        }
+    }
+
+    /// Whether the match is the one that was last jumped to
+    pub fn is_active_match(&self, path: &Path, search_match: &SearchMatch) -> bool {
+        self.active_match.with(|active| {
+            active.as_ref().is_some_and(|(p, line, start)| {
+                p == path
+                    && *line == search_match.line
+                    && *start == search_match.start
+            })
+        })
+    }
+
+    pub fn jump_to_match(&self, path: &Path, search_match: &SearchMatch) {
+        self.active_match.set(Some((
+            path.to_path_buf(),
+            search_match.line,
+            search_match.start,
+        )));
+        self.common
+            .internal_command
+            .send(InternalCommand::JumpToLocation {
+                location: EditorLocation {
+                    path: path.to_path_buf(),
+                    position: Some(EditorPosition::LineColumn(
+                        search_match.line.saturating_sub(1),
+                        search_match.start,
+                    )),
+                    scroll_offset: None,
+                    ignore_unconfirmed: false,
+                    same_editor_tab: false,
+                },
+            });
+    }
+
+    /// Jump to the next or previous match in the results, wrapping around at
+    /// either end
+    pub fn step_result(&self, forward: bool) {
+        let matches: Vec<(PathBuf, SearchMatch)> =
+            self.search_result.with_untracked(|result| {
+                result
+                    .iter()
+                    .flat_map(|(path, data)| {
+                        data.matches
+                            .get_untracked()
+                            .into_iter()
+                            .map(move |m| (path.clone(), m))
+                    })
+                    .collect()
+            });
+        if matches.is_empty() {
+            return;
+        }
+
+        let current =
+            self.active_match
+                .get_untracked()
+                .and_then(|(path, line, start)| {
+                    matches.iter().position(|(p, m)| {
+                        p == &path && m.line == line && m.start == start
+                    })
+                });
+        let index = match current {
+            Some(i) if forward => (i + 1) % matches.len(),
+            Some(i) => (i + matches.len() - 1) % matches.len(),
+            None if forward => 0,
+            None => matches.len() - 1,
+        };
+        let (path, search_match) = &matches[index];
+        self.jump_to_match(path, search_match);
+    }
+
+    /// The text a match would be replaced with, if the replace input is shown
+    pub fn replacement_preview(&self, search_match: &SearchMatch) -> Option<String> {
+        if !self.replace_active.get() {
+            return None;
+        }
+        let replacement = self.replace_editor.doc().buffer.with(|b| b.to_string());
+        if !self.common.find.is_regex.get() {
+            return Some(replacement);
+        }
+
+        // Expand the captures the same way the replace will, on the whole line
+        // since the displayed content may have been shortened.
+        let pattern = self.editor.doc().buffer.with(|b| b.to_string());
+        let matcher = build_search_matcher(
+            &pattern,
+            self.common.find.case_sensitive(true),
+            self.common.find.whole_words.get(),
+            true,
+        )
+        .ok()?;
+        let edits = replace_in_line(
+            &matcher,
+            search_match.line(),
+            &replacement,
+            true,
+            &[search_match],
+        )
+        .ok()?;
+        edits.into_iter().next().map(|(_, preview)| preview)
+    }
+
+    /// Remove a single match from the results, so it won't be replaced
+    pub fn dismiss_match(&self, path: &Path, search_match: &SearchMatch) {
+        let Some(match_data) =
+            self.search_result.with_untracked(|r| r.get(path).cloned())
+        else {
+            return;
+        };
+        match_data.matches.update(|matches| {
+            matches.retain(|m| {
+                (m.line, m.start, m.end)
+                    != (search_match.line, search_match.start, search_match.end)
+            })
+        });
+        if match_data.matches.with_untracked(|m| m.is_empty()) {
+            self.dismiss_file(path);
+        }
+    }
+
+    /// Remove all the matches of a file from the results
+    pub fn dismiss_file(&self, path: &Path) {
+        self.search_result.update(|r| {
+            r.shift_remove(path);
+        });
+    }
+
+    /// Replace every match that is still in the results with the content of the
+    /// replace input.
+    pub fn replace_all(&self) {
+        let pattern = self.editor.doc().buffer.with_untracked(|b| b.to_string());
+        if pattern.is_empty() {
+            return;
+        }
+        let replacement = self
+            .replace_editor
+            .doc()
+            .buffer
+            .with_untracked(|b| b.to_string());
+        let matches: IndexMap<PathBuf, Vec<SearchMatch>> =
+            self.search_result.with_untracked(|result| {
+                result
+                    .iter()
+                    .map(|(path, data)| {
+                        (
+                            path.clone(),
+                            data.matches.get_untracked().into_iter().collect(),
+                        )
+                    })
+                    .filter(|(_, matches): &(PathBuf, Vec<SearchMatch>)| {
+                        !matches.is_empty()
+                    })
+                    .collect()
+            });
+        if matches.is_empty() {
+            return;
+        }
+
+        let case_sensitive = self.common.find.case_sensitive(false);
+        let whole_word = self.common.find.whole_words.get_untracked();
+        let is_regex = self.common.find.is_regex.get_untracked();
+        let global_search = self.clone();
+        let send = create_ext_action(self.common.scope, move |result| {
+            if let Ok(ProxyResponse::GlobalReplaceResponse { edits, written }) =
+                result
+            {
+                global_search.apply_replace(edits, written);
+            }
+        });
+        self.common.proxy.global_replace(
+            pattern,
+            replacement,
+            case_sensitive,
+            whole_word,
+            is_regex,
+            matches,
+            move |result| {
+                send(result);
+            },
         );
     }
 
+    fn apply_replace(
+        &self,
+        edits: IndexMap<PathBuf, Vec<TextEdit>>,
+        written: Vec<PathBuf>,
+    ) {
+        for (path, edits) in edits {
+            let doc = self
+                .main_split
+                .docs
+                .with_untracked(|docs| docs.get(&path).cloned());
+            if let Some(doc) = doc {
+                doc.do_text_edit(&edits);
+            } else {
+                self.main_split.jump_to_location(
+                    EditorLocation {
+                        path: path.clone(),
+                        position: None,
+                        scroll_offset: None,
+                        ignore_unconfirmed: true,
+                        same_editor_tab: false,
+                    },
+                    Some(edits),
+                );
+            }
+            self.dismiss_file(&path);
+        }
+        for path in written {
+            self.dismiss_file(&path);
+        }
+    }
+
     pub fn set_pattern(&self, pattern: String) {
         let pattern_len = pattern.len();
         self.editor.doc().reload(Rope::from(pattern), true);
@@ -198,3 +508,14 @@ pub fn set_pattern(&self, pattern: String) {
             .update(|cursor| cursor.set_insert(Selection::region(0, pattern_len)));
     }
 }
+
+/// Split the content of a glob input into its comma separated globs
+fn split_globs(buffer: &Buffer) -> Vec<String> {
+    buffer
+        .to_string()
+        .split(',')
+        .map(|glob| glob.trim())
+        .filter(|glob| !glob.is_empty())
+        .map(|glob| glob.to_string())
+        .collect()
+}
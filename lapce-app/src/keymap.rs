@@ -257,6 +257,8 @@ pub fn keymap_view(editors: Editors, common: Rc<CommonData>) -> impl View {
                         key: Vec::new(),
                         modes: Modes::empty(),
                         when: None,
+                        when_expr: None,
+                        args: None,
                     }
                 };
                 picker.keymap.set(Some(keymap));
@@ -4,7 +4,7 @@
 mod loader;
 mod press;
 
-use std::{path::PathBuf, rc::Rc, str::FromStr, time::SystemTime};
+use std::{collections::HashMap, path::PathBuf, rc::Rc, time::SystemTime};
 
 use anyhow::Result;
 use floem::{
@@ -15,6 +15,7 @@
 use indexmap::IndexMap;
 use itertools::Itertools;
 use lapce_core::mode::{Mode, Modes};
+use serde_json::Value;
 
 pub use self::press::KeyPress;
 use self::{
@@ -26,7 +27,7 @@
     command::{lapce_internal_commands, CommandExecuted, CommandKind, LapceCommand},
     config::LapceConfig,
     keypress::{
-        condition::{CheckCondition, Condition},
+        condition::{Condition, WhenExpr},
         keymap::KeymapMatch,
     },
     tracing::*,
@@ -44,6 +45,12 @@ pub trait KeyPressFocus: std::fmt::Debug {
 
     fn check_condition(&self, condition: Condition) -> bool;
 
+    /// The value of a context key that depends on the focus, such as the
+    /// language of the focused editor
+    fn context_value(&self, _key: &str) -> Option<String> {
+        None
+    }
+
     fn run_command(
         &self,
         command: &LapceCommand,
@@ -98,6 +105,10 @@ fn check_condition(&self, condition: Condition) -> bool {
         (**self).check_condition(condition)
     }
 
+    fn context_value(&self, key: &str) -> Option<String> {
+        (**self).context_value(key)
+    }
+
     fn run_command(
         &self,
         command: &LapceCommand,
@@ -153,6 +164,9 @@ pub struct KeyPressData {
     pub command_keymaps: Rc<IndexMap<String, Vec<KeyMap>>>,
     pub commands_with_keymap: Rc<Vec<KeyMap>>,
     pub commands_without_keymap: Rc<Vec<LapceCommand>>,
+    /// The context keys that don't depend on the focus, such as the debug
+    /// state or the keys set by volts
+    pub context: RwSignal<HashMap<String, String>>,
 }
 
 impl KeyPressData {
@@ -167,6 +181,7 @@ pub fn new(cx: Scope, config: &LapceConfig) -> Self {
             commands: Rc::new(lapce_internal_commands()),
             commands_with_keymap: Rc::new(Vec::new()),
             commands_without_keymap: Rc::new(Vec::new()),
+            context: cx.create_rw_signal(HashMap::new()),
         };
         keypress.load_commands();
         keypress
@@ -180,6 +195,25 @@ pub fn update_keymaps(&mut self, config: &LapceConfig) {
         }
     }
 
+    /// Set the context key `key` for `when` expressions, or unset it if
+    /// `value` is `None`
+    pub fn set_context(&self, key: &str, value: Option<String>) {
+        let changed = self
+            .context
+            .with_untracked(|context| context.get(key) != value.as_ref());
+        if !changed {
+            return;
+        }
+        self.context.update(|context| match value {
+            Some(value) => {
+                context.insert(key.to_string(), value);
+            }
+            None => {
+                context.remove(key);
+            }
+        });
+    }
+
     fn load_commands(&mut self) {
         let mut commands_with_keymap = Vec::new();
         let mut commands_without_keymap = Vec::new();
@@ -242,13 +276,22 @@ fn handle_count<T: KeyPressFocus + ?Sized>(
 
     fn run_command<T: KeyPressFocus + ?Sized>(
         &self,
-        command: &str,
+        keymap: &KeyMap,
         count: Option<usize>,
         mods: Modifiers,
         focus: &T,
     ) -> CommandExecuted {
-        if let Some(cmd) = self.commands.get(command) {
-            focus.run_command(cmd, count, mods)
+        if let Some(cmd) = self.commands.get(&keymap.command) {
+            match &keymap.args {
+                Some(args) => {
+                    let cmd = LapceCommand {
+                        kind: cmd.kind.clone(),
+                        data: Some(args.clone()),
+                    };
+                    focus.run_command(&cmd, count, mods)
+                }
+                None => focus.run_command(cmd, count, mods),
+            }
         } else {
             CommandExecuted::No
         }
@@ -336,14 +379,14 @@ pub fn handle_keymatch<T: KeyPressFocus + ?Sized>(
     ) -> KeyPressHandle {
         let mods = keypress.mods;
         match &keymatch {
-            KeymapMatch::Full(command) => {
+            KeymapMatch::Full(keymap) => {
                 self.pending_keypress
                     .update(|(pending_keypress, last_time)| {
                         last_time.take();
                         pending_keypress.clear();
                     });
                 let count = self.count.try_update(|count| count.take()).unwrap();
-                let handled = self.run_command(command, count, mods, focus)
+                let handled = self.run_command(keymap, count, mods, focus)
                     == CommandExecuted::Yes;
                 return KeyPressHandle {
                     handled,
@@ -351,15 +394,15 @@ pub fn handle_keymatch<T: KeyPressFocus + ?Sized>(
                     keypress,
                 };
             }
-            KeymapMatch::Multiple(commands) => {
+            KeymapMatch::Multiple(keymaps) => {
                 self.pending_keypress
                     .update(|(pending_keypress, last_time)| {
                         last_time.take();
                         pending_keypress.clear();
                     });
                 let count = self.count.try_update(|count| count.take()).unwrap();
-                for command in commands {
-                    let handled = self.run_command(command, count, mods, focus)
+                for keymap in keymaps {
+                    let handled = self.run_command(keymap, count, mods, focus)
                         == CommandExecuted::Yes;
                     if handled {
                         return KeyPressHandle {
@@ -395,10 +438,10 @@ pub fn handle_keymatch<T: KeyPressFocus + ?Sized>(
                     let old_keypress = keypress.clone();
                     let mut keypress = keypress.clone();
                     keypress.mods.set(Modifiers::SHIFT, false);
-                    if let KeymapMatch::Full(command) =
+                    if let KeymapMatch::Full(keymap) =
                         self.match_keymap(&[keypress], focus)
                     {
-                        if let Some(cmd) = self.commands.get(&command) {
+                        if let Some(cmd) = self.commands.get(&keymap.command) {
                             if let CommandKind::Move(_) = cmd.kind {
                                 let handled = focus.run_command(cmd, None, mods)
                                     == CommandExecuted::Yes;
@@ -477,6 +520,7 @@ fn match_keymap<T: KeyPressFocus + ?Sized>(
     ) -> KeymapMatch {
         let keypresses: Vec<KeyMapPress> =
             keypresses.iter().filter_map(|k| k.keymap_press()).collect();
+        let context = self.context.get_untracked();
         let matches: Vec<_> = self
             .keymaps
             .get(&keypresses)
@@ -495,8 +539,8 @@ fn match_keymap<T: KeyPressFocus + ?Sized>(
                         {
                             return false;
                         }
-                        if let Some(condition) = &keymap.when {
-                            if !Self::check_condition(condition, check) {
+                        if let Some(condition) = &keymap.when_expr {
+                            if !Self::check_condition(condition, check, &context) {
                                 return false;
                             }
                         }
@@ -509,57 +553,29 @@ fn match_keymap<T: KeyPressFocus + ?Sized>(
         if matches.is_empty() {
             KeymapMatch::None
         } else if matches.len() == 1 && matches[0].key == keypresses {
-            KeymapMatch::Full(matches[0].command.clone())
+            KeymapMatch::Full(matches[0].clone())
         } else if matches.len() > 1
             && matches.iter().filter(|m| m.key != keypresses).count() == 0
         {
-            KeymapMatch::Multiple(
-                matches.iter().rev().map(|m| m.command.clone()).collect(),
-            )
+            KeymapMatch::Multiple(matches.into_iter().rev().cloned().collect())
         } else {
             KeymapMatch::Prefix
         }
     }
 
     fn check_condition<T: KeyPressFocus + ?Sized>(
-        condition: &str,
+        condition: &WhenExpr,
         check: &T,
+        context: &HashMap<String, String>,
     ) -> bool {
-        fn check_one_condition<T: KeyPressFocus + ?Sized>(
-            condition: &str,
-            check: &T,
-        ) -> bool {
-            let trimmed = condition.trim();
-            if let Some(stripped) = trimmed.strip_prefix('!') {
-                if let Ok(condition) = Condition::from_str(stripped) {
-                    !check.check_condition(condition)
-                } else {
-                    true
-                }
-            } else if let Ok(condition) = Condition::from_str(trimmed) {
-                check.check_condition(condition)
-            } else {
-                false
-            }
-        }
-
-        match CheckCondition::parse_first(condition) {
-            CheckCondition::Single(condition) => {
-                check_one_condition(condition, check)
-            }
-            CheckCondition::Or(left, right) => {
-                let left = check_one_condition(left, check);
-                let right = Self::check_condition(right, check);
-
-                left || right
-            }
-            CheckCondition::And(left, right) => {
-                let left = check_one_condition(left, check);
-                let right = Self::check_condition(right, check);
-
-                left && right
+        condition.eval(&|condition| check.check_condition(condition), &|key| {
+            if key == "mode" {
+                return Some(mode_name(check.get_mode()).to_string());
             }
-        }
+            check
+                .context_value(key)
+                .or_else(|| context.get(key).cloned())
+        })
     }
 
     #[allow(clippy::type_complexity)]
@@ -657,6 +673,9 @@ pub fn update_file(keymap: &KeyMap, keys: &[KeyMapPress]) -> Option<()> {
                     toml_edit::value(toml_edit::Value::from(when.to_string())),
                 );
             }
+            if let Some(args) = keymap.args.as_ref().and_then(json_to_toml) {
+                table.insert("args", toml_edit::value(args));
+            }
 
             if !keys.is_empty() {
                 table.insert(
@@ -692,6 +711,39 @@ pub fn update_file(keymap: &KeyMap, keys: &[KeyMapPress]) -> Option<()> {
     }
 }
 
+/// Convert the args of a keymap back to TOML, leaving out `null` values
+fn json_to_toml(value: &Value) -> Option<toml_edit::Value> {
+    let value = match value {
+        Value::Null => return None,
+        Value::Bool(b) => toml_edit::Value::from(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(n) => toml_edit::Value::from(n),
+            None => toml_edit::Value::from(n.as_f64()?),
+        },
+        Value::String(s) => toml_edit::Value::from(s.as_str()),
+        Value::Array(values) => {
+            toml_edit::Value::Array(values.iter().filter_map(json_to_toml).collect())
+        }
+        Value::Object(map) => toml_edit::Value::InlineTable(
+            map.iter()
+                .filter_map(|(key, value)| {
+                    Some((key.as_str(), json_to_toml(value)?))
+                })
+                .collect(),
+        ),
+    };
+    Some(value)
+}
+
+fn mode_name(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Normal => "normal",
+        Mode::Insert => "insert",
+        Mode::Visual(_) => "visual",
+        Mode::Terminal => "terminal",
+    }
+}
+
 fn get_modes(toml_keymap: &toml_edit::Table) -> Modes {
     toml_keymap
         .get("mode")
@@ -1,38 +1,190 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
 use strum_macros::EnumString;
 
-#[derive(Debug, PartialEq, Eq)]
-pub(super) enum CheckCondition<'a> {
-    Single(&'a str),
-    Or(&'a str, &'a str),
-    And(&'a str, &'a str),
+/// A `when` expression of a keymap, such as
+/// `editor_focus && (language_id == rust || !modal)`
+///
+/// `&&` binds tighter than `||`, and a bare name is either a focus
+/// [`Condition`] or a context key that is set to anything but `false`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WhenExpr {
+    Key(String),
+    Equals(String, String),
+    NotEquals(String, String),
+    Not(Box<WhenExpr>),
+    And(Box<WhenExpr>, Box<WhenExpr>),
+    Or(Box<WhenExpr>, Box<WhenExpr>),
+}
+
+impl WhenExpr {
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut parser = WhenParser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+        let expr = parser.or()?;
+        if let Some(token) = parser.next() {
+            bail!("unexpected {token:?}");
+        }
+        Ok(expr)
+    }
+
+    /// Evaluate the expression, with `condition` checking the focus conditions
+    /// and `value` giving the value of the other context keys
+    pub fn eval(
+        &self,
+        condition: &dyn Fn(Condition) -> bool,
+        value: &dyn Fn(&str) -> Option<String>,
+    ) -> bool {
+        match self {
+            WhenExpr::Key(key) => match Condition::from_str(key) {
+                Ok(c) => condition(c),
+                Err(_) => value(key).is_some_and(|v| !v.is_empty() && v != "false"),
+            },
+            WhenExpr::Equals(key, expected) => {
+                value(key).as_deref() == Some(expected.as_str())
+            }
+            WhenExpr::NotEquals(key, expected) => {
+                value(key).as_deref() != Some(expected.as_str())
+            }
+            WhenExpr::Not(expr) => !expr.eval(condition, value),
+            WhenExpr::And(left, right) => {
+                left.eval(condition, value) && right.eval(condition, value)
+            }
+            WhenExpr::Or(left, right) => {
+                left.eval(condition, value) || right.eval(condition, value)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    LeftParen,
+    RightParen,
+    Not,
+    And,
+    Or,
+    Equals,
+    NotEquals,
+    Word(String),
 }
 
-impl<'a> CheckCondition<'a> {
-    pub(super) fn parse_first(condition: &'a str) -> Self {
-        let or = condition.match_indices("||").next();
-        let and = condition.match_indices("&&").next();
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !"()!&|='\"".contains(c)
+}
 
-        match (or, and) {
-            (None, None) => CheckCondition::Single(condition),
-            (Some((pos, _)), None) => {
-                CheckCondition::Or(&condition[..pos], &condition[pos + 2..])
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            '!' if chars.next_if_eq(&'=').is_some() => Token::NotEquals,
+            '!' => Token::Not,
+            '&' if chars.next_if_eq(&'&').is_some() => Token::And,
+            '|' if chars.next_if_eq(&'|').is_some() => Token::Or,
+            '=' if chars.next_if_eq(&'=').is_some() => Token::Equals,
+            '\'' | '"' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some(ch) if ch == c => break,
+                        Some(ch) => word.push(ch),
+                        None => bail!("unterminated string"),
+                    }
+                }
+                Token::Word(word)
             }
-            (None, Some((pos, _))) => {
-                CheckCondition::And(&condition[..pos], &condition[pos + 2..])
+            c if is_word_char(c) => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| is_word_char(*c)) {
+                    word.push(c);
+                }
+                Token::Word(word)
             }
-            (Some((or_pos, _)), Some((and_pos, _))) => {
-                if or_pos < and_pos {
-                    CheckCondition::Or(
-                        &condition[..or_pos],
-                        &condition[or_pos + 2..],
-                    )
+            c => bail!("unexpected `{c}`"),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+struct WhenParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl WhenParser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.tokens.get(self.pos) == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<WhenExpr> {
+        let mut expr = self.and()?;
+        while self.eat(&Token::Or) {
+            expr = WhenExpr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<WhenExpr> {
+        let mut expr = self.not()?;
+        while self.eat(&Token::And) {
+            expr = WhenExpr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<WhenExpr> {
+        if self.eat(&Token::Not) {
+            return Ok(WhenExpr::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<WhenExpr> {
+        match self.next() {
+            Some(Token::LeftParen) => {
+                let expr = self.or()?;
+                if !self.eat(&Token::RightParen) {
+                    bail!("missing `)`");
+                }
+                Ok(expr)
+            }
+            Some(Token::Word(key)) => {
+                if self.eat(&Token::Equals) {
+                    Ok(WhenExpr::Equals(key, self.value()?))
+                } else if self.eat(&Token::NotEquals) {
+                    Ok(WhenExpr::NotEquals(key, self.value()?))
                 } else {
-                    CheckCondition::And(
-                        &condition[..and_pos],
-                        &condition[and_pos + 2..],
-                    )
+                    Ok(WhenExpr::Key(key))
                 }
             }
+            Some(token) => bail!("unexpected {token:?}"),
+            None => bail!("unexpected end of expression"),
+        }
+    }
+
+    fn value(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Word(value)) => Ok(value),
+            _ => Err(anyhow!("expected a value to compare with")),
         }
     }
 }
@@ -75,11 +227,13 @@ pub enum Condition {
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashMap;
+
     use floem::keyboard::Modifiers;
     use lapce_core::mode::Mode;
 
-    use super::Condition;
-    use crate::keypress::{condition::CheckCondition, KeyPressData, KeyPressFocus};
+    use super::{Condition, WhenExpr};
+    use crate::keypress::{KeyPressData, KeyPressFocus};
 
     #[derive(Clone, Copy, Debug)]
     struct MockFocus {
@@ -92,7 +246,7 @@ fn check_condition(&self, condition: Condition) -> bool {
         }
 
         fn get_mode(&self) -> Mode {
-            unimplemented!()
+            Mode::Normal
         }
 
         fn run_command(
@@ -111,22 +265,38 @@ fn receive_char(&self, _c: &str) {
 
     #[test]
     fn test_parse() {
+        use WhenExpr::*;
+        let key = |k: &str| Box::new(Key(k.to_string()));
+
         assert_eq!(
-            CheckCondition::Or("foo", "bar"),
-            CheckCondition::parse_first("foo||bar")
+            WhenExpr::parse("foo||bar").unwrap(),
+            Or(key("foo"), key("bar"))
         );
         assert_eq!(
-            CheckCondition::And("foo", "bar"),
-            CheckCondition::parse_first("foo&&bar")
+            WhenExpr::parse("foo && bar || baz").unwrap(),
+            Or(Box::new(And(key("foo"), key("bar"))), key("baz"))
         );
         assert_eq!(
-            CheckCondition::And("foo", "bar||baz"),
-            CheckCondition::parse_first("foo&&bar||baz")
+            WhenExpr::parse("foo && !(bar || baz)").unwrap(),
+            And(
+                key("foo"),
+                Box::new(Not(Box::new(Or(key("bar"), key("baz")))))
+            )
         );
         assert_eq!(
-            CheckCondition::And("foo ", " bar || baz"),
-            CheckCondition::parse_first("foo && bar || baz")
+            WhenExpr::parse("language_id == rust && file_extension != 'md'")
+                .unwrap(),
+            And(
+                Box::new(Equals("language_id".to_string(), "rust".to_string())),
+                Box::new(NotEquals("file_extension".to_string(), "md".to_string()))
+            )
         );
+
+        assert!(WhenExpr::parse("foo &&").is_err());
+        assert!(WhenExpr::parse("(foo").is_err());
+        assert!(WhenExpr::parse("foo ==").is_err());
+        assert!(WhenExpr::parse("foo & bar").is_err());
+        assert!(WhenExpr::parse("foo bar").is_err());
     }
 
     #[test]
@@ -134,6 +304,12 @@ fn test_check_condition() {
         let focus = MockFocus {
             accepted_conditions: &[Condition::EditorFocus, Condition::ListFocus],
         };
+        let context = HashMap::from([
+            ("language_id".to_string(), "rust".to_string()),
+            ("debug_state".to_string(), "stopped".to_string()),
+            ("git_repository".to_string(), "true".to_string()),
+            ("modal".to_string(), "false".to_string()),
+        ]);
 
         let test_cases = [
             ("editor_focus", true),
@@ -149,12 +325,20 @@ fn test_check_condition() {
             ("editor_focus && list_focus || baz", true),
             ("editor_focus && list_focus && baz", false),
             ("editor_focus && list_focus && !baz", true),
+            ("editor_focus && (baz || list_focus)", true),
+            ("!(editor_focus || baz)", false),
+            ("language_id == rust", true),
+            ("language_id != rust", false),
+            ("language_id == go || debug_state == stopped", true),
+            ("git_repository && mode == normal", true),
+            ("modal", false),
+            ("editor_focus &&", false),
         ];
 
         for (condition, should_accept) in test_cases.into_iter() {
             assert_eq!(
                 should_accept,
-                KeyPressData::check_condition(condition, &focus),
+                KeyPressData::check_condition(condition, &focus, &context),
                 "Condition check failed. Condition: {condition}. Expected result: {should_accept}",
             );
         }
@@ -5,21 +5,40 @@
     pointer::{MouseButton, PointerButton},
 };
 use lapce_core::mode::Modes;
+use serde_json::Value;
+
+use super::condition::WhenExpr;
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum KeymapMatch {
-    Full(String),
-    Multiple(Vec<String>),
+    Full(KeyMap),
+    Multiple(Vec<KeyMap>),
     Prefix,
     None,
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+#[derive(PartialEq, Eq, Clone, Debug)]
 pub struct KeyMap {
     pub key: Vec<KeyMapPress>,
     pub modes: Modes,
+    /// The `when` expression as written
     pub when: Option<String>,
+    /// The parsed `when`, which is evaluated on every key press
+    pub when_expr: Option<WhenExpr>,
     pub command: String,
+    /// The `args` table of the keymap, which the command is run with
+    pub args: Option<Value>,
+}
+
+impl std::hash::Hash for KeyMap {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        // The args are left out as a JSON value can't be hashed, and the parsed
+        // when as it follows from the written one
+        self.key.hash(state);
+        self.modes.hash(state);
+        self.when.hash(state);
+        self.command.hash(state);
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Debug)]
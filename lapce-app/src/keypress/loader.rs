@@ -1,9 +1,13 @@
 use anyhow::{anyhow, Result};
 use indexmap::IndexMap;
 use lapce_core::mode::Modes;
+use serde_json::Value;
 use tracing::{debug, error};
 
-use super::keymap::{KeyMap, KeyMapPress};
+use super::{
+    condition::WhenExpr,
+    keymap::{KeyMap, KeyMapPress},
+};
 
 pub struct KeyMapLoader {
     keymaps: IndexMap<Vec<KeyMapPress>, Vec<KeyMap>>,
@@ -111,18 +115,29 @@ fn get_keymap(
             return Ok(None);
         }
 
+        let when = toml_keymap
+            .get("when")
+            .and_then(|w| w.as_str())
+            .map(|w| w.to_string());
+        let when_expr = when
+            .as_deref()
+            .map(|when| {
+                WhenExpr::parse(when)
+                    .map_err(|err| anyhow!("invalid when `{when}`: {err}"))
+            })
+            .transpose()?;
+
         Ok(Some(KeyMap {
             key: KeyMapPress::parse(key),
             modes,
-            when: toml_keymap
-                .get("when")
-                .and_then(|w| w.as_str())
-                .map(|w| w.to_string()),
+            when,
+            when_expr,
             command: toml_keymap
                 .get("command")
                 .and_then(|c| c.as_str())
                 .map(|w| w.trim().to_string())
                 .unwrap_or_default(),
+            args: toml_keymap.get("args").and_then(item_to_json),
         }))
     }
 }
@@ -135,6 +150,50 @@ fn get_modes(toml_keymap: &toml_edit::Table) -> Modes {
         .unwrap_or_else(Modes::empty)
 }
 
+/// Convert the `args` of a keymap, which may be written as an inline table or as
+/// a `[keymaps.args]` table, to the JSON value the command is run with
+fn item_to_json(item: &toml_edit::Item) -> Option<Value> {
+    match item {
+        toml_edit::Item::None => None,
+        toml_edit::Item::Value(value) => Some(toml_to_json(value)),
+        toml_edit::Item::Table(table) => Some(Value::Object(
+            table
+                .iter()
+                .filter_map(|(key, item)| {
+                    Some((key.to_string(), item_to_json(item)?))
+                })
+                .collect(),
+        )),
+        toml_edit::Item::ArrayOfTables(tables) => Some(Value::Array(
+            tables
+                .iter()
+                .filter_map(|table| {
+                    item_to_json(&toml_edit::Item::Table(table.clone()))
+                })
+                .collect(),
+        )),
+    }
+}
+
+fn toml_to_json(value: &toml_edit::Value) -> Value {
+    match value {
+        toml_edit::Value::String(s) => Value::from(s.value().as_str()),
+        toml_edit::Value::Integer(i) => Value::from(*i.value()),
+        toml_edit::Value::Float(f) => Value::from(*f.value()),
+        toml_edit::Value::Boolean(b) => Value::from(*b.value()),
+        toml_edit::Value::Datetime(d) => Value::from(d.value().to_string()),
+        toml_edit::Value::Array(values) => {
+            Value::Array(values.iter().map(toml_to_json).collect())
+        }
+        toml_edit::Value::InlineTable(table) => Value::Object(
+            table
+                .iter()
+                .map(|(key, value)| (key.to_string(), toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use floem::keyboard::Key;
@@ -250,4 +309,53 @@ fn test_keymap() {
             KeyMapKey::Logical(Key::Character("+".into()))
         );
     }
+
+    #[test]
+    fn test_keymap_args() {
+        let keymaps = r#"
+[[keymaps]]
+key = "ctrl+k t"
+command = "insert_text"
+args = { text = "TODO: " }
+
+[[keymaps]]
+key = "ctrl+k g"
+command = "go_to_line"
+[keymaps.args]
+line = 1
+
+[[keymaps]]
+key = "ctrl+k s"
+command = "run_commands"
+args = { commands = ["select_all", { command = "insert_text", args = { text = "" } }] }
+
+[[keymaps]]
+key = "ctrl+k n"
+command = "new_file"
+        "#;
+        let mut loader = KeyMapLoader::new();
+        loader.load_from_str(keymaps, true).unwrap();
+        let (keymaps, _) = loader.finalize();
+
+        let args = |key: &str| {
+            keymaps.get(&KeyMapPress::parse(key)).unwrap()[0]
+                .args
+                .clone()
+        };
+        assert_eq!(
+            args("ctrl+k t"),
+            Some(serde_json::json!({ "text": "TODO: " }))
+        );
+        assert_eq!(args("ctrl+k g"), Some(serde_json::json!({ "line": 1 })));
+        assert_eq!(
+            args("ctrl+k s"),
+            Some(serde_json::json!({
+                "commands": [
+                    "select_all",
+                    { "command": "insert_text", "args": { "text": "" } }
+                ]
+            }))
+        );
+        assert_eq!(args("ctrl+k n"), None);
+    }
 }
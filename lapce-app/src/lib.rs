@@ -1,6 +1,7 @@
 pub mod about;
 pub mod alert;
 pub mod app;
+pub mod breakpoint;
 pub mod code_action;
 pub mod code_lens;
 pub mod command;
@@ -11,9 +12,11 @@
 pub mod doc;
 pub mod editor;
 pub mod editor_tab;
+pub mod ex_command;
 pub mod file_explorer;
 pub mod find;
 pub mod focus_text;
+pub mod git_history;
 pub mod global_search;
 pub mod history;
 pub mod hover;
@@ -2,6 +2,7 @@
     collections::HashMap,
     path::{Path, PathBuf},
     rc::Rc,
+    sync::Arc,
 };
 
 use floem::{
@@ -10,13 +11,16 @@
     file::{FileDialogOptions, FileInfo},
     keyboard::Modifiers,
     peniko::kurbo::{Point, Rect, Vec2},
-    reactive::{Memo, RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
+    reactive::{
+        use_context, Memo, RwSignal, Scope, SignalGet, SignalUpdate, SignalWith,
+    },
     views::editor::id::EditorId,
 };
 use itertools::Itertools;
 use lapce_core::{
     buffer::rope_text::RopeText, command::FocusCommand, cursor::Cursor,
-    rope_text_pos::RopeTextPosition, selection::Selection, syntax::Syntax,
+    language::LapceLanguage, rope_text_pos::RopeTextPosition, selection::Selection,
+    syntax::Syntax,
 };
 use lapce_rpc::{
     buffer::BufferId,
@@ -37,6 +41,7 @@
     alert::AlertButton,
     code_lens::CodeLensData,
     command::InternalCommand,
+    db::LapceDb,
     doc::{DiagnosticData, Doc, DocContent, DocHistory, EditorDiagnostic},
     editor::{
         diff::DiffEditorData,
@@ -400,9 +405,14 @@ pub struct MainSplitData {
     pub docs: RwSignal<im::HashMap<PathBuf, Rc<Doc>>>,
     pub scratch_docs: RwSignal<im::HashMap<String, Rc<Doc>>>,
     pub diagnostics: RwSignal<im::HashMap<PathBuf, DiagnosticData>>,
+    /// The files git reports unresolved conflicts in
+    pub conflicted: RwSignal<im::HashSet<PathBuf>>,
     pub references: RwSignal<ReferencesRoot>,
     pub implementations: RwSignal<crate::panel::implementation_view::ReferencesRoot>,
     pub active_editor: Memo<Option<EditorData>>,
+    /// The language of the most recently active editor, which stays set while
+    /// a non-editor tab such as the settings is active
+    pub last_language: RwSignal<Option<LapceLanguage>>,
     pub find_editor: EditorData,
     pub replace_editor: EditorData,
     pub locations: RwSignal<im::Vector<EditorLocation>>,
@@ -457,10 +467,10 @@ pub fn new(cx: Scope, common: Rc<CommonData>) -> Self {
                     let diff_editor = diff_editors.with(|diff_editors| {
                         diff_editors.get(&diff_editor_id).cloned()
                     })?;
-                    if diff_editor.focus_right.get() {
-                        diff_editor.right
-                    } else {
-                        diff_editor.left
+                    match diff_editor.result {
+                        Some(result) if diff_editor.focus_result.get() => result,
+                        _ if diff_editor.focus_right.get() => diff_editor.right,
+                        _ => diff_editor.left,
                     }
                 }
                 _ => return None,
@@ -469,6 +479,16 @@ pub fn new(cx: Scope, common: Rc<CommonData>) -> Self {
             Some(editor)
         });
 
+        let last_language = cx.create_rw_signal(None);
+        cx.create_effect(move |_| {
+            if let Some(editor) = active_editor.get() {
+                let language = editor.doc().language();
+                if last_language.get_untracked() != Some(language) {
+                    last_language.set(Some(language));
+                }
+            }
+        });
+
         {
             let buffer = find_editor.doc().buffer;
             let find = common.find.clone();
@@ -489,9 +509,11 @@ pub fn new(cx: Scope, common: Rc<CommonData>) -> Self {
             docs,
             scratch_docs,
             active_editor,
+            last_language,
             find_editor,
             replace_editor,
             diagnostics,
+            conflicted: cx.create_rw_signal(im::HashSet::new()),
             locations,
             current_location,
             width: cx.create_rw_signal(0.0),
@@ -526,10 +548,14 @@ pub fn key_down<'a>(
                     self.diff_editors.with_untracked(|diff_editors| {
                         diff_editors.get(&diff_editor_id).cloned()
                     })?;
-                let editor = if diff_editor.focus_right.get_untracked() {
-                    &diff_editor.right
-                } else {
-                    &diff_editor.left
+                let editor = match &diff_editor.result {
+                    Some(result) if diff_editor.focus_result.get_untracked() => {
+                        result
+                    }
+                    _ if diff_editor.focus_right.get_untracked() => {
+                        &diff_editor.right
+                    }
+                    _ => &diff_editor.left,
                 };
                 let handle = keypress.key_down(event, editor);
                 editor.get_code_actions();
@@ -637,6 +663,15 @@ pub fn get_doc(
             self.docs.update(|docs| {
                 docs.insert(path.clone(), doc.clone());
             });
+            doc.set_conflicted(
+                self.conflicted
+                    .with_untracked(|conflicted| conflicted.contains(&path)),
+            );
+
+            let db: Arc<LapceDb> = use_context().unwrap();
+            if let Ok(info) = db.get_doc_info(&self.common.workspace, &path) {
+                doc.folds_to_restore.set(info.folded_lines);
+            }
 
             {
                 let doc = doc.clone();
@@ -645,8 +680,12 @@ pub fn get_doc(
                     if let Ok(ProxyResponse::NewBufferResponse {
                         content,
                         read_only,
+                        encoding,
+                        editorconfig,
                     }) = result
                     {
+                        local_doc.encoding.set(encoding);
+                        local_doc.editorconfig.set(editorconfig);
                         local_doc.init_content(Rope::from(content));
                         if read_only {
                             local_doc.content.update(|content| {
@@ -696,37 +735,142 @@ pub fn go_to_location(
         }
     }
 
-    pub fn open_file_changes(&self, path: PathBuf) {
-        let (right, _) = self.get_doc(path.clone(), None);
-        let left = Doc::new_history(
+    /// Open the changes of a file in a diff editor, the staged changes against
+    /// the last commit or else the working file against the index
+    pub fn open_file_changes(&self, path: PathBuf, staged: bool) {
+        let (left, right) = if staged {
+            (
+                self.history_doc(path.clone(), "head"),
+                self.history_doc(path, "index"),
+            )
+        } else {
+            let (right, _) = self.get_doc(path.clone(), None);
+            (self.history_doc(path, "index"), right)
+        };
+
+        self.get_editor_tab_child(
+            EditorTabChildSource::DiffEditor {
+                left,
+                right,
+                result: None,
+            },
+            false,
+            false,
+        );
+    }
+
+    /// Open a diff of the changes `commit` made to the file, which was at
+    /// `old_path` before the commit
+    pub fn open_commit_file_changes(
+        &self,
+        old_path: PathBuf,
+        path: PathBuf,
+        commit: String,
+    ) {
+        let left = self.history_doc(old_path, &format!("{commit}^"));
+        let right = self.history_doc(path, &commit);
+
+        self.get_editor_tab_child(
+            EditorTabChildSource::DiffEditor {
+                left,
+                right,
+                result: None,
+            },
+            false,
+            false,
+        );
+    }
+
+    /// Open a merge editor for a conflicted file, comparing the current and
+    /// incoming versions above the file itself, where the conflicts are
+    /// resolved
+    pub fn open_merge_editor(&self, path: PathBuf) {
+        if self.common.focus.get_untracked() != Focus::Workbench {
+            self.common.focus.set(Focus::Workbench);
+        }
+        let left = self.history_doc(path.clone(), ":2");
+        let right = self.history_doc(path.clone(), ":3");
+        let (result, _) = self.get_doc(path, None);
+
+        self.get_editor_tab_child(
+            EditorTabChildSource::DiffEditor {
+                left,
+                right,
+                result: Some(result),
+            },
+            false,
+            false,
+        );
+    }
+
+    /// A read only document of the file's `version` in source control, which is
+    /// either `"head"`, `"index"` or a git revision
+    fn history_doc(&self, path: PathBuf, version: &str) -> Rc<Doc> {
+        let doc = Doc::new_history(
             self.scope,
             DocContent::History(DocHistory {
-                path: path.clone(),
-                version: "head".to_string(),
+                path,
+                version: version.to_string(),
             }),
             self.editors,
             self.common.clone(),
         );
-        let left = Rc::new(left);
+        let doc = Rc::new(doc);
+        self.load_history_doc(&doc);
+        doc
+    }
+
+    /// Load the content of a history document from source control, or load it
+    /// again
+    pub fn load_history_doc(&self, doc: &Rc<Doc>) {
+        let DocContent::History(history) = doc.content.get_untracked() else {
+            return;
+        };
 
         let send = {
-            let left = left.clone();
+            let doc = doc.clone();
             create_ext_action(self.scope, move |result| {
                 if let Ok(ProxyResponse::BufferHeadResponse { content, .. }) = result
                 {
-                    left.init_content(Rope::from(content));
+                    if doc.loaded() {
+                        if doc.buffer.with_untracked(|b| b.to_string() != content) {
+                            doc.reload(Rope::from(content), true);
+                        }
+                    } else {
+                        doc.init_content(Rope::from(content));
+                    }
                 }
             })
         };
-        self.common.proxy.get_buffer_head(path, move |result| {
+        let f = move |result| {
             send(result);
-        });
+        };
+        match history.version.as_str() {
+            "index" => self.common.proxy.get_buffer_index(history.path, f),
+            "head" => self.common.proxy.get_buffer_head(history.path, f),
+            revision => self.common.proxy.get_buffer_revision(
+                history.path,
+                revision.to_string(),
+                f,
+            ),
+        }
+    }
 
-        self.get_editor_tab_child(
-            EditorTabChildSource::DiffEditor { left, right },
-            false,
-            false,
-        );
+    /// Reload the source control versions shown in the diff editors, for when
+    /// the repository changed
+    pub fn refresh_file_changes(&self) {
+        let diff_editors = self.diff_editors.get_untracked();
+        for (_, diff_editor) in diff_editors {
+            for editor in [&diff_editor.left, &diff_editor.right] {
+                let doc = editor.doc();
+                if doc
+                    .content
+                    .with_untracked(|c| matches!(c, DocContent::History(_)))
+                {
+                    self.load_history_doc(&doc);
+                }
+            }
+        }
     }
 
     pub fn open_diff_files(&self, left_path: PathBuf, right_path: PathBuf) {
@@ -734,7 +878,11 @@ pub fn open_diff_files(&self, left_path: PathBuf, right_path: PathBuf) {
             [left_path, right_path].map(|path| self.get_doc(path, None).0);
 
         self.get_editor_tab_child(
-            EditorTabChildSource::DiffEditor { left, right },
+            EditorTabChildSource::DiffEditor {
+                left,
+                right,
+                result: None,
+            },
             false,
             false,
         );
@@ -806,7 +954,10 @@ fn get_editor_tab_child(
         };
 
         let is_same_diff_editor =
-            |diff_editor_id: &DiffEditorId, left: &Rc<Doc>, right: &Rc<Doc>| {
+            |diff_editor_id: &DiffEditorId,
+             left: &Rc<Doc>,
+             right: &Rc<Doc>,
+             result: &Option<Rc<Doc>>| {
                 diff_editors
                     .get(diff_editor_id)
                     .map(|diff_editor| {
@@ -814,6 +965,10 @@ fn get_editor_tab_child(
                             == diff_editor.left.doc().content.get_untracked()
                             && right.content.get_untracked()
                                 == diff_editor.right.doc().content.get_untracked()
+                            && result.as_ref().map(|doc| doc.content.get_untracked())
+                                == diff_editor.result.as_ref().map(|result| {
+                                    result.doc().content.get_untracked()
+                                })
                     })
                     .unwrap_or(false)
             };
@@ -850,20 +1005,24 @@ fn get_editor_tab_child(
                             }
                         }
                         EditorTabChild::DiffEditor(diff_editor_id) => {
-                            if let EditorTabChildSource::DiffEditor { left, right } =
-                                &source
+                            if let EditorTabChildSource::DiffEditor {
+                                left,
+                                right,
+                                result,
+                            } = &source
                             {
-                                is_same_diff_editor(diff_editor_id, left, right)
-                                    || diff_editors
-                                        .get(diff_editor_id)
-                                        .map(|diff_editor| {
-                                            diff_editor.left.doc().is_pristine()
-                                                && diff_editor
-                                                    .right
-                                                    .doc()
-                                                    .is_pristine()
-                                        })
-                                        .unwrap_or(false)
+                                is_same_diff_editor(
+                                    diff_editor_id,
+                                    left,
+                                    right,
+                                    result,
+                                ) || diff_editors
+                                    .get(diff_editor_id)
+                                    .map(|diff_editor| {
+                                        diff_editor.left.doc().is_pristine()
+                                            && diff_editor.right.doc().is_pristine()
+                                    })
+                                    .unwrap_or(false)
                             } else {
                                 false
                             }
@@ -900,14 +1059,23 @@ fn get_editor_tab_child(
                                 }
                             })
                     }),
-                EditorTabChildSource::DiffEditor { left, right } => {
+                EditorTabChildSource::DiffEditor {
+                    left,
+                    right,
+                    result,
+                } => {
                     if let Some(index) =
                         active_editor_tab.with_untracked(|editor_tab| {
                             editor_tab.children.iter().position(|(_, _, child)| {
                                 if let EditorTabChild::DiffEditor(diff_editor_id) =
                                     child
                                 {
-                                    is_same_diff_editor(diff_editor_id, left, right)
+                                    is_same_diff_editor(
+                                        diff_editor_id,
+                                        left,
+                                        right,
+                                        result,
+                                    )
                                 } else {
                                     false
                                 }
@@ -1094,7 +1262,11 @@ fn get_editor_tab_child(
                 EditorTabChildSource::Volt(id) => {
                     EditorTabChild::Volt(VoltViewId::next(), id.to_owned())
                 }
-                EditorTabChildSource::DiffEditor { left, right } => {
+                EditorTabChildSource::DiffEditor {
+                    left,
+                    right,
+                    result,
+                } => {
                     let diff_editor_id = DiffEditorId::next();
                     let diff_editor = DiffEditorData::new(
                         self.scope,
@@ -1102,6 +1274,7 @@ fn get_editor_tab_child(
                         editor_tab_id,
                         left.clone(),
                         right.clone(),
+                        result.clone(),
                         editors,
                         self.common.clone(),
                     );
@@ -1157,16 +1330,33 @@ fn get_editor_tab_child(
                 }
                 (
                     EditorTabChild::DiffEditor(diff_editor_id),
-                    EditorTabChildSource::DiffEditor { left, right },
-                ) => {
-                    if !is_same_diff_editor(diff_editor_id, left, right) {
-                        if let Some(diff_editor) = diff_editors.get(diff_editor_id) {
+                    EditorTabChildSource::DiffEditor {
+                        left,
+                        right,
+                        result,
+                    },
+                ) => match diff_editors.get(diff_editor_id) {
+                    // Only a merge editor has a result pane to load a doc into
+                    Some(diff_editor)
+                        if diff_editor.result.is_some() != result.is_some() =>
+                    {
+                        false
+                    }
+                    Some(diff_editor) => {
+                        if !is_same_diff_editor(diff_editor_id, left, right, result)
+                        {
                             diff_editor.left.update_doc(left.clone());
                             diff_editor.right.update_doc(right.clone());
+                            if let (Some(editor), Some(doc)) =
+                                (&diff_editor.result, result)
+                            {
+                                editor.update_doc(doc.clone());
+                            }
                         }
+                        true
                     }
-                    true
-                }
+                    None => true,
+                },
                 (EditorTabChild::Settings(_), EditorTabChildSource::Settings) => {
                     true
                 }
@@ -1220,24 +1410,27 @@ fn get_editor_tab_child(
                             EditorTabChildSource::Editor { path, .. } => editor_tab
                                 .get_editor(editors, path)
                                 .map(|(index, _)| index),
-                            EditorTabChildSource::DiffEditor { left, right } => {
-                                editor_tab.children.iter().position(
-                                    |(_, _, child)| {
-                                        if let EditorTabChild::DiffEditor(
+                            EditorTabChildSource::DiffEditor {
+                                left,
+                                right,
+                                result,
+                            } => editor_tab.children.iter().position(
+                                |(_, _, child)| {
+                                    if let EditorTabChild::DiffEditor(
+                                        diff_editor_id,
+                                    ) = child
+                                    {
+                                        is_same_diff_editor(
                                             diff_editor_id,
-                                        ) = child
-                                        {
-                                            is_same_diff_editor(
-                                                diff_editor_id,
-                                                left,
-                                                right,
-                                            )
-                                        } else {
-                                            false
-                                        }
-                                    },
-                                )
-                            }
+                                            left,
+                                            right,
+                                            result,
+                                        )
+                                    } else {
+                                        false
+                                    }
+                                },
+                            ),
                             EditorTabChildSource::Settings => editor_tab
                                 .children
                                 .iter()
@@ -2149,6 +2342,10 @@ pub fn editor_tab_child_close(
                     diff_editor.right.save_doc_position();
                     self.editors.remove(diff_editor.right.editor.id());
                     self.editors.remove(diff_editor.left.editor.id());
+                    if let Some(result) = diff_editor.result {
+                        result.save_doc_position();
+                        self.editors.remove(result.editor.id());
+                    }
                 }
             }
             EditorTabChild::Settings(_) => {}
@@ -2193,7 +2390,9 @@ pub fn run_code_action(&self, plugin_id: PluginId, action: CodeActionOrCommand)
             }
             CodeActionOrCommand::CodeAction(action) => {
                 if let Some(edit) = action.edit.as_ref() {
-                    self.apply_workspace_edit(edit);
+                    if let Err(e) = self.apply_workspace_edit(edit) {
+                        tracing::error!("failed to apply code action: {e}");
+                    }
                 } else {
                     self.resolve_code_action(plugin_id, action);
                 }
@@ -2209,7 +2408,9 @@ pub fn run_code_lens(&self, command: &str, args: Vec<Value>) {
     fn resolve_code_action(&self, plugin_id: PluginId, action: CodeAction) {
         let main_split = self.clone();
         let send = create_ext_action(self.scope, move |edit| {
-            main_split.apply_workspace_edit(&edit);
+            if let Err(e) = main_split.apply_workspace_edit(&edit) {
+                tracing::error!("failed to apply code action: {e}");
+            }
         });
         self.common
             .proxy
@@ -2224,40 +2425,55 @@ fn resolve_code_action(&self, plugin_id: PluginId, action: CodeAction) {
     }
 
     /// Perform a workspace edit, which are from the LSP (such as code actions, or symbol renaming)
-    pub fn apply_workspace_edit(&self, edit: &WorkspaceEdit) {
-        if let Some(DocumentChanges::Operations(_op)) =
+    /// Nothing is applied, and the reason is returned, when it can't be applied
+    /// as a whole.
+    pub fn apply_workspace_edit(&self, edit: &WorkspaceEdit) -> Result<(), String> {
+        if let Some(DocumentChanges::Operations(ops)) =
             edit.document_changes.as_ref()
         {
-            // TODO
+            if ops
+                .iter()
+                .any(|op| matches!(op, DocumentChangeOperation::Op(_)))
+            {
+                return Err("creating, renaming and deleting files isn't supported"
+                    .to_string());
+            }
         }
 
-        if let Some(edits) = workspace_edits(edit) {
-            for (url, edits) in edits {
-                if let Ok(path) = url.to_file_path() {
-                    let active_path = self
-                        .active_editor
-                        .get_untracked()
-                        .map(|editor| editor.doc())
-                        .map(|doc| doc.content.get_untracked())
-                        .and_then(|content| content.path().cloned());
-                    let position = if active_path.as_ref() == Some(&path) {
-                        None
-                    } else {
-                        edits
-                            .first()
-                            .map(|edit| EditorPosition::Position(edit.range.start))
-                    };
-                    let location = EditorLocation {
-                        path,
-                        position,
-                        scroll_offset: None,
-                        ignore_unconfirmed: true,
-                        same_editor_tab: false,
-                    };
-                    self.jump_to_location(location, Some(edits));
-                }
-            }
+        let Some(edits) = workspace_edits(edit) else {
+            return Ok(());
+        };
+        let edits = edits
+            .into_iter()
+            .map(|(url, edits)| match url.to_file_path() {
+                Ok(path) => Ok((path, edits)),
+                Err(_) => Err(format!("{url} isn't a file path")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        for (path, edits) in edits {
+            let active_path = self
+                .active_editor
+                .get_untracked()
+                .map(|editor| editor.doc())
+                .map(|doc| doc.content.get_untracked())
+                .and_then(|content| content.path().cloned());
+            let position = if active_path.as_ref() == Some(&path) {
+                None
+            } else {
+                edits
+                    .first()
+                    .map(|edit| EditorPosition::Position(edit.range.start))
+            };
+            let location = EditorLocation {
+                path,
+                position,
+                scroll_offset: None,
+                ignore_unconfirmed: true,
+                same_editor_tab: false,
+            };
+            self.jump_to_location(location, Some(edits));
         }
+        Ok(())
     }
 
     pub fn next_error(&self) {
@@ -2737,6 +2953,11 @@ fn editor_tab_child_set_parent(
                     .right
                     .diff_editor_id
                     .set(Some((editor_tab_id, *diff_editor_id)));
+                if let Some(result) = &diff_editor.result {
+                    result
+                        .diff_editor_id
+                        .set(Some((editor_tab_id, *diff_editor_id)));
+                }
             }
             EditorTabChild::Settings(_) => {}
             EditorTabChild::ThemeColorSettings(_) => {}
@@ -27,7 +27,11 @@
     line_ending::LineEnding, mode::Mode, movement::Movement, selection::Selection,
     syntax::Syntax,
 };
-use lapce_rpc::proxy::ProxyResponse;
+use lapce_rpc::{
+    buffer::ENCODINGS,
+    dap_types::{DebugRequest, RunDebugConfig},
+    proxy::ProxyResponse,
+};
 use lapce_xi_rope::Rope;
 use lsp_types::{DocumentSymbol, DocumentSymbolResponse};
 use nucleo::Utf32Str;
@@ -51,7 +55,7 @@
     keypress::{condition::Condition, KeyPressData, KeyPressFocus},
     lsp::path_from_url,
     main_split::MainSplitData,
-    source_control::SourceControlData,
+    source_control::{ScmAction, SourceControlData},
     window_tab::{CommonData, Focus},
     workspace::{LapceWorkspace, LapceWorkspaceType, SshHost},
 };
@@ -104,6 +108,11 @@ pub struct PaletteData {
     pub executed_run_configs: Rc<RefCell<HashMap<(RunDebugMode, String), Instant>>>,
     pub main_split: MainSplitData,
     pub references: RwSignal<Vec<EditorLocation>>,
+    /// The attach run config waiting for a process to be picked
+    pub attach_config: RwSignal<Option<RunDebugConfig>>,
+    /// What is done with the reference, stash or name picked in the source
+    /// control palettes
+    pub scm_action: RwSignal<ScmAction>,
     pub source_control: SourceControlData,
     pub common: Rc<CommonData>,
     left_diff_path: RwSignal<Option<PathBuf>>,
@@ -214,6 +223,8 @@ pub fn new(
 
         let clicked_index = cx.create_rw_signal(Option::<usize>::None);
         let left_diff_path = cx.create_rw_signal(None);
+        let attach_config = cx.create_rw_signal(None);
+        let scm_action = cx.create_rw_signal(ScmAction::default());
 
         let palette = Self {
             run_id_counter,
@@ -235,6 +246,8 @@ pub fn new(
             executed_commands: Rc::new(RefCell::new(HashMap::new())),
             executed_run_configs: Rc::new(RefCell::new(HashMap::new())),
             references,
+            attach_config,
+            scm_action,
             source_control,
             common,
             left_diff_path,
@@ -341,6 +354,26 @@ pub fn run(&self, kind: PaletteKind) {
             .update(|cursor| cursor.set_insert(Selection::caret(symbol.len())));
     }
 
+    /// Start the ex command line, which starts with the range of the selection
+    /// when `:` is pressed in visual mode, as in vim
+    pub fn run_ex_command(&self, visual: bool) {
+        self.run(PaletteKind::ExCommand);
+        if visual {
+            let range = "'<,'>";
+            self.input_editor.doc().reload(Rope::from(range), true);
+            self.input_editor
+                .cursor()
+                .update(|cursor| cursor.set_insert(Selection::caret(range.len())));
+        }
+    }
+
+    /// Start the source control palette that picks what `action` is done with
+    pub fn run_scm(&self, action: ScmAction) {
+        let kind = action.palette_kind();
+        self.scm_action.set(action);
+        self.run(kind);
+    }
+
     /// Get the placeholder text to use in the palette input field.
     pub fn placeholder_text(&self) -> &'static str {
         match self.kind.get() {
@@ -354,6 +387,14 @@ pub fn placeholder_text(&self) -> &'static str {
                     "Seleft left file"
                 }
             }
+            PaletteKind::SCMReferences
+            | PaletteKind::SCMInput
+            | PaletteKind::SCMStashes => {
+                self.scm_action.with(ScmAction::placeholder)
+            }
+            PaletteKind::ExCommand => {
+                "Ex command, such as w, 42, %s/foo/bar/g or g/TODO/d"
+            }
             _ => "",
         }
     }
@@ -400,6 +441,9 @@ fn run_inner(&self, kind: PaletteKind) {
             PaletteKind::RunAndDebug => {
                 self.get_run_configs();
             }
+            PaletteKind::DebugProcess => {
+                self.get_debug_processes();
+            }
             PaletteKind::ColorTheme => {
                 self.get_color_themes();
             }
@@ -412,9 +456,24 @@ fn run_inner(&self, kind: PaletteKind) {
             PaletteKind::LineEnding => {
                 self.get_line_endings();
             }
+            PaletteKind::ReopenWithEncoding => {
+                self.get_encodings(false);
+            }
+            PaletteKind::SaveWithEncoding => {
+                self.get_encodings(true);
+            }
+            PaletteKind::Snippet => {
+                self.get_snippets();
+            }
             PaletteKind::SCMReferences => {
                 self.get_scm_references();
             }
+            PaletteKind::SCMInput | PaletteKind::ExCommand => {
+                self.items.update(|items| items.clear());
+            }
+            PaletteKind::SCMStashes => {
+                self.get_scm_stashes();
+            }
             PaletteKind::TerminalProfile => self.get_terminal_profiles(),
         }
     }
@@ -473,15 +532,7 @@ fn get_files_and_prepend(&self, prepend: Option<im::Vector<PaletteItem>>) {
                     .into_iter()
                     .map(|full_path| {
                         // Strip the workspace prefix off the path, to avoid clutter
-                        let path =
-                            if let Some(workspace_path) = workspace.path.as_ref() {
-                                full_path
-                                    .strip_prefix(workspace_path)
-                                    .unwrap_or(&full_path)
-                                    .to_path_buf()
-                            } else {
-                                full_path.clone()
-                            };
+                        let path = workspace.relative_path(&full_path);
                         let filter_text = path.to_string_lossy().into_owned();
                         PaletteItem {
                             content: PaletteItemContent::File { path, full_path },
@@ -606,7 +657,8 @@ fn get_workspaces(&self) {
         let items = workspaces
             .into_iter()
             .filter_map(|w| {
-                let text = w.path.as_ref()?.to_str()?.to_string();
+                let path = w.file.as_ref().or(w.path.as_ref())?;
+                let text = path.to_str()?.to_string();
                 let filter_text = match &w.kind {
                     LapceWorkspaceType::Local => text,
                     LapceWorkspaceType::RemoteSSH(remote) => {
@@ -636,14 +688,7 @@ fn get_references(&self) {
             .get_untracked()
             .into_iter()
             .map(|l| {
-                let full_path = l.path.clone();
-                let mut path = l.path.clone();
-                if let Some(workspace_path) = self.workspace.path.as_ref() {
-                    path = path
-                        .strip_prefix(workspace_path)
-                        .unwrap_or(&full_path)
-                        .to_path_buf();
-                }
+                let path = self.workspace.relative_path(&l.path);
                 let filter_text = path.to_str().unwrap_or("").to_string();
                 PaletteItem {
                     content: PaletteItemContent::Reference { path, location: l },
@@ -894,24 +939,27 @@ fn set_run_configs(&self, content: String) {
         let mut items = Vec::new();
         if let Some(configs) = configs.as_ref() {
             for config in &configs.configs {
-                items.push((
-                    executed_run_configs
-                        .get(&(RunDebugMode::Run, config.name.clone())),
-                    PaletteItem {
-                        content: PaletteItemContent::RunAndDebug {
-                            mode: RunDebugMode::Run,
-                            config: config.clone(),
+                // there's nothing to run for configs attaching to a process
+                if config.request == DebugRequest::Launch {
+                    items.push((
+                        executed_run_configs
+                            .get(&(RunDebugMode::Run, config.name.clone())),
+                        PaletteItem {
+                            content: PaletteItemContent::RunAndDebug {
+                                mode: RunDebugMode::Run,
+                                config: config.clone(),
+                            },
+                            filter_text: format!(
+                                "Run {} {} {}",
+                                config.name,
+                                config.program,
+                                config.args.clone().unwrap_or_default().join(" ")
+                            ),
+                            score: 0,
+                            indices: vec![],
                         },
-                        filter_text: format!(
-                            "Run {} {} {}",
-                            config.name,
-                            config.program,
-                            config.args.clone().unwrap_or_default().join(" ")
-                        ),
-                        score: 0,
-                        indices: vec![],
-                    },
-                ));
+                    ));
+                }
                 if config.ty.is_some() {
                     items.push((
                         executed_run_configs
@@ -1047,9 +1095,62 @@ fn get_line_endings(&self) {
         self.items.set(items);
     }
 
+    fn get_encodings(&self, save: bool) {
+        let items = ENCODINGS
+            .iter()
+            .map(|&(name, description)| PaletteItem {
+                content: PaletteItemContent::Encoding { name, save },
+                filter_text: description.to_string(),
+                score: 0,
+                indices: Vec::new(),
+            })
+            .collect();
+        if let Some(editor) = self.main_split.active_editor.get_untracked() {
+            let encoding = editor.doc().encoding.get_untracked();
+            if let Some((_, description)) =
+                ENCODINGS.iter().find(|(name, _)| *name == encoding)
+            {
+                self.preselect_matching(&items, description);
+            }
+        }
+        self.items.set(items);
+    }
+
+    /// The snippets of the active editor's language
+    fn get_snippets(&self) {
+        let Some(editor) = self.main_split.active_editor.get_untracked() else {
+            self.items.update(|items| items.clear());
+            return;
+        };
+        let language = editor.doc().language();
+        let config = self.common.config.get_untracked();
+        let items = config
+            .snippets_for(language)
+            .map(|snippet| {
+                let filter_text = match snippet.prefixes.first() {
+                    Some(prefix) => format!("{} ({prefix})", snippet.name),
+                    None => snippet.name.clone(),
+                };
+                PaletteItem {
+                    content: PaletteItemContent::Snippet {
+                        body: snippet.body.clone(),
+                    },
+                    filter_text,
+                    score: 0,
+                    indices: Vec::new(),
+                }
+            })
+            .collect();
+        self.items.set(items);
+    }
+
     fn get_scm_references(&self) {
         let branches = self.source_control.branches.get_untracked();
-        let tags = self.source_control.tags.get_untracked();
+        let tags = if self.scm_action.with_untracked(ScmAction::branches_only) {
+            im::Vector::new()
+        } else {
+            self.source_control.tags.get_untracked()
+        };
         let mut items: im::Vector<PaletteItem> = im::Vector::new();
         for refs in branches.into_iter() {
             items.push_back(PaletteItem {
@@ -1074,6 +1175,55 @@ fn get_scm_references(&self) {
         self.items.set(items);
     }
 
+    fn get_scm_stashes(&self) {
+        let items = self
+            .source_control
+            .stashes
+            .get_untracked()
+            .into_iter()
+            .map(|stash| PaletteItem {
+                filter_text: format!("stash@{{{}}}: {}", stash.index, stash.message),
+                content: PaletteItemContent::SCMStash { stash },
+                score: 0,
+                indices: Vec::new(),
+            })
+            .collect();
+        self.items.set(items);
+    }
+
+    /// Initialize the palette with the running processes a debugger can attach to.
+    fn get_debug_processes(&self) {
+        let set_items = self.items.write_only();
+        let send = create_ext_action(self.common.scope, move |result| {
+            if let Ok(ProxyResponse::DapListProcessesResponse { processes }) = result
+            {
+                let items = processes
+                    .into_iter()
+                    .map(|p| {
+                        let filter_text = if p.command == p.name {
+                            format!("{} ({})", p.name, p.pid)
+                        } else {
+                            format!("{} ({})  {}", p.name, p.pid, p.command)
+                        };
+                        PaletteItem {
+                            content: PaletteItemContent::DebugProcess { pid: p.pid },
+                            filter_text,
+                            score: 0,
+                            indices: Vec::new(),
+                        }
+                    })
+                    .collect();
+                set_items.set(items);
+            } else {
+                set_items.update(|items| items.clear());
+            }
+        });
+
+        self.common.proxy.dap_list_processes(move |result| {
+            send(result);
+        });
+    }
+
     fn get_terminal_profiles(&self) {
         let profiles = self.common.config.get().terminal.profiles.clone();
         let mut items: im::Vector<PaletteItem> = im::Vector::new();
@@ -1124,6 +1274,10 @@ fn preselect_matching(&self, items: &im::Vector<PaletteItem>, matching: &str) {
     fn select(&self) {
         let index = self.index.get_untracked();
         let items = self.filtered_items.get_untracked();
+        let scm_action = self
+            .scm_action
+            .try_update(std::mem::take)
+            .unwrap_or_default();
         self.close();
         if let Some(item) = items.get(index) {
             match &item.content {
@@ -1208,6 +1362,8 @@ fn select(&self) {
                             workspace: LapceWorkspace {
                                 kind: LapceWorkspaceType::RemoteSSH(host.clone()),
                                 path: None,
+                                file: None,
+                                folders: Vec::new(),
                                 last_open: 0,
                             },
                         },
@@ -1220,6 +1376,8 @@ fn select(&self) {
                             workspace: LapceWorkspace {
                                 kind: LapceWorkspaceType::RemoteWSL(host.clone()),
                                 path: None,
+                                file: None,
+                                folders: Vec::new(),
                                 last_open: 0,
                             },
                         },
@@ -1269,6 +1427,19 @@ fn select(&self) {
                         },
                     );
                 }
+                PaletteItemContent::DebugProcess { pid } => {
+                    if let Some(mut config) =
+                        self.attach_config.try_update(Option::take).flatten()
+                    {
+                        config.process_id = Some(*pid);
+                        self.common.internal_command.send(
+                            InternalCommand::RunAndDebug {
+                                mode: RunDebugMode::Debug,
+                                config,
+                            },
+                        );
+                    }
+                }
                 PaletteItemContent::ColorTheme { name } => self
                     .common
                     .internal_command
@@ -1313,16 +1484,81 @@ fn select(&self) {
                         buffer.set_line_ending(*kind);
                     });
                 }
-                PaletteItemContent::SCMReference { name } => {
-                    self.common
-                        .lapce_command
-                        .send(crate::command::LapceCommand {
-                        kind: CommandKind::Workbench(
-                            crate::command::LapceWorkbenchCommand::CheckoutReference,
-                        ),
-                        data: Some(serde_json::json!(name.to_owned())),
-                    });
+                PaletteItemContent::Encoding { name, save } => {
+                    let Some(editor) = self.main_split.active_editor.get_untracked()
+                    else {
+                        return;
+                    };
+                    let doc = editor.doc();
+                    if *save {
+                        doc.save_with_encoding(name);
+                    } else {
+                        doc.reopen_with_encoding(name);
+                    }
+                }
+                PaletteItemContent::Snippet { body } => {
+                    let Some(editor) = self.main_split.active_editor.get_untracked()
+                    else {
+                        return;
+                    };
+                    if let Err(err) = editor.insert_snippet(body) {
+                        tracing::error!("{:?}", err);
+                    }
                 }
+                PaletteItemContent::SCMReference { name } => match scm_action {
+                    ScmAction::CreateBranch => {
+                        self.run_scm(ScmAction::NameBranch { from: name.clone() });
+                    }
+                    ScmAction::RenameBranch => {
+                        self.run_scm(ScmAction::NameRenamedBranch {
+                            old: name.clone(),
+                        });
+                        // Start from the old name to edit it
+                        self.input_editor.doc().reload(Rope::from(name), true);
+                        self.input_editor.cursor().update(|cursor| {
+                            cursor.set_insert(Selection::region(0, name.len()))
+                        });
+                    }
+                    ScmAction::DeleteBranch { force } => {
+                        self.common.proxy.git_delete_branch(
+                            self.source_control.root.get_untracked(),
+                            name.clone(),
+                            force,
+                        );
+                    }
+                    ScmAction::Merge => {
+                        self.common.proxy.git_merge(
+                            self.source_control.root.get_untracked(),
+                            name.clone(),
+                        );
+                    }
+                    _ => {
+                        self.common
+                            .lapce_command
+                            .send(crate::command::LapceCommand {
+                            kind: CommandKind::Workbench(
+                                crate::command::LapceWorkbenchCommand::CheckoutReference,
+                            ),
+                            data: Some(serde_json::json!(name.to_owned())),
+                        });
+                    }
+                },
+                PaletteItemContent::SCMStash { stash } => match scm_action {
+                    ScmAction::DropStash => {
+                        self.common.proxy.git_stash_drop(
+                            self.source_control.root.get_untracked(),
+                            stash.index,
+                        );
+                    }
+                    ScmAction::ApplyStash { pop } => {
+                        self.common.proxy.git_stash_apply(
+                            self.source_control.root.get_untracked(),
+                            stash.index,
+                            pop,
+                        );
+                    }
+                    _ => {}
+                },
                 PaletteItemContent::TerminalProfile { name: _, profile } => self
                     .common
                     .internal_command
@@ -1330,6 +1566,34 @@ fn select(&self) {
                         profile: Some(profile.to_owned()),
                     }),
             }
+        } else if self.kind.get_untracked() == PaletteKind::SCMInput {
+            let input = self.input.with_untracked(|input| input.input.clone());
+            let name = input.trim().to_string();
+            let root = self.source_control.root.get_untracked();
+            match scm_action {
+                ScmAction::NameBranch { from } if !name.is_empty() => {
+                    self.common.proxy.git_create_branch(root, name, from);
+                }
+                ScmAction::NameRenamedBranch { old }
+                    if !name.is_empty() && name != old =>
+                {
+                    self.common.proxy.git_rename_branch(root, old, name);
+                }
+                ScmAction::Stash => {
+                    self.common.proxy.git_stash_save(root, name);
+                }
+                _ => {}
+            }
+        } else if self.kind.get_untracked() == PaletteKind::ExCommand {
+            let input = self.input.with_untracked(|input| input.input.clone());
+            if !input.trim().is_empty() {
+                self.common.lapce_command.send(LapceCommand {
+                    kind: CommandKind::Workbench(
+                        crate::command::LapceWorkbenchCommand::RunExCommand,
+                    ),
+                    data: Some(serde_json::json!(input)),
+                });
+            }
         } else if self.kind.get_untracked() == PaletteKind::SshHost {
             let input = self.input.with_untracked(|input| input.input.clone());
             let ssh = SshHost::from_string(&input);
@@ -1338,6 +1602,8 @@ fn select(&self) {
                     workspace: LapceWorkspace {
                         kind: LapceWorkspaceType::RemoteSSH(ssh),
                         path: None,
+                        file: None,
+                        folders: Vec::new(),
                         last_open: 0,
                     },
                 },
@@ -1389,11 +1655,14 @@ fn preview(&self) {
                 PaletteItemContent::Command { .. } => {}
                 PaletteItemContent::Workspace { .. } => {}
                 PaletteItemContent::RunAndDebug { .. } => {}
+                PaletteItemContent::DebugProcess { .. } => {}
                 PaletteItemContent::SshHost { .. } => {}
                 #[cfg(windows)]
                 PaletteItemContent::WslHost { .. } => {}
                 PaletteItemContent::Language { .. } => {}
                 PaletteItemContent::LineEnding { .. } => {}
+                PaletteItemContent::Encoding { .. } => {}
+                PaletteItemContent::Snippet { .. } => {}
                 PaletteItemContent::Reference { location, .. } => {
                     self.has_preview.set(true);
                     let (doc, new_doc) =
@@ -1460,6 +1729,7 @@ fn preview(&self) {
                         save: false,
                     }),
                 PaletteItemContent::SCMReference { .. } => {}
+                PaletteItemContent::SCMStash { .. } => {}
                 PaletteItemContent::TerminalProfile { .. } => {}
             }
         }
@@ -1477,6 +1747,8 @@ fn cancel(&self) {
         }
 
         self.left_diff_path.set(None);
+        self.attach_config.set(None);
+        self.scm_action.set(ScmAction::default());
         self.close();
     }
 
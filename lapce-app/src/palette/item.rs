@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 
 use lapce_core::line_ending::LineEnding;
-use lapce_rpc::dap_types::RunDebugConfig;
+use lapce_rpc::{dap_types::RunDebugConfig, source_control::GitStash};
 use lsp_types::{Range, SymbolKind};
 
 use crate::{
@@ -65,6 +65,9 @@ pub enum PaletteItemContent {
         mode: RunDebugMode,
         config: RunDebugConfig,
     },
+    DebugProcess {
+        pid: u32,
+    },
     ColorTheme {
         name: String,
     },
@@ -77,9 +80,20 @@ pub enum PaletteItemContent {
     LineEnding {
         kind: LineEnding,
     },
+    Encoding {
+        name: &'static str,
+        /// Whether to save with the encoding rather than reopen with it
+        save: bool,
+    },
+    Snippet {
+        body: String,
+    },
     SCMReference {
         name: String,
     },
+    SCMStash {
+        stash: GitStash,
+    },
     TerminalProfile {
         name: String,
         profile: lapce_rpc::terminal::TerminalProfile,
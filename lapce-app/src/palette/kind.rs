@@ -16,11 +16,20 @@ pub enum PaletteKind {
     #[cfg(windows)]
     WslHost,
     RunAndDebug,
+    DebugProcess,
     ColorTheme,
     IconTheme,
     Language,
     LineEnding,
+    ReopenWithEncoding,
+    SaveWithEncoding,
+    Snippet,
     SCMReferences,
+    /// A name typed for a source control action, such as a new branch name
+    SCMInput,
+    SCMStashes,
+    /// A vim ex command typed after `:` in modal editing
+    ExCommand,
     TerminalProfile,
     DiffFiles,
     HelpAndFile,
@@ -42,11 +51,18 @@ pub fn symbol(&self) -> &'static str {
             | PaletteKind::Reference
             | PaletteKind::SshHost
             | PaletteKind::RunAndDebug
+            | PaletteKind::DebugProcess
             | PaletteKind::ColorTheme
             | PaletteKind::IconTheme
             | PaletteKind::Language
             | PaletteKind::LineEnding
+            | PaletteKind::ReopenWithEncoding
+            | PaletteKind::SaveWithEncoding
+            | PaletteKind::Snippet
             | PaletteKind::SCMReferences
+            | PaletteKind::SCMInput
+            | PaletteKind::SCMStashes
+            | PaletteKind::ExCommand
             | PaletteKind::HelpAndFile
             | PaletteKind::DiffFiles => "",
             #[cfg(windows)]
@@ -92,15 +108,26 @@ pub fn command(self) -> Option<LapceWorkbenchCommand> {
             PaletteKind::RunAndDebug => {
                 Some(LapceWorkbenchCommand::PaletteRunAndDebug)
             }
+            PaletteKind::DebugProcess => None, // attach run configs
             PaletteKind::ColorTheme => Some(LapceWorkbenchCommand::ChangeColorTheme),
             PaletteKind::IconTheme => Some(LapceWorkbenchCommand::ChangeIconTheme),
             PaletteKind::Language => Some(LapceWorkbenchCommand::ChangeFileLanguage),
             PaletteKind::LineEnding => {
                 Some(LapceWorkbenchCommand::ChangeFileLineEnding)
             }
+            PaletteKind::ReopenWithEncoding => {
+                Some(LapceWorkbenchCommand::ReopenWithEncoding)
+            }
+            PaletteKind::SaveWithEncoding => {
+                Some(LapceWorkbenchCommand::SaveWithEncoding)
+            }
+            PaletteKind::Snippet => Some(LapceWorkbenchCommand::InsertSnippet),
             PaletteKind::SCMReferences => {
                 Some(LapceWorkbenchCommand::PaletteSCMReferences)
             }
+            PaletteKind::SCMInput => None, // ScmAction
+            PaletteKind::SCMStashes => None, // ScmAction
+            PaletteKind::ExCommand => Some(LapceWorkbenchCommand::ExCommandLine),
             PaletteKind::TerminalProfile => None, // InternalCommand::NewTerminal
             PaletteKind::DiffFiles => Some(LapceWorkbenchCommand::DiffFiles),
         }
@@ -125,12 +152,17 @@ pub fn get_input<'a>(&self, input: &'a str) -> &'a str {
             | PaletteKind::Reference
             | PaletteKind::SshHost
             | PaletteKind::RunAndDebug
+            | PaletteKind::DebugProcess
             | PaletteKind::ColorTheme
             | PaletteKind::IconTheme
             | PaletteKind::Language
             | PaletteKind::LineEnding
-            | PaletteKind::SCMReferences | PaletteKind::HelpAndFile
-            | PaletteKind::DiffFiles => input,
+            | PaletteKind::ReopenWithEncoding
+            | PaletteKind::SaveWithEncoding
+            | PaletteKind::Snippet
+            | PaletteKind::SCMReferences | PaletteKind::SCMInput
+            | PaletteKind::SCMStashes | PaletteKind::HelpAndFile
+            | PaletteKind::ExCommand | PaletteKind::DiffFiles => input,
             PaletteKind::PaletteHelp
             | PaletteKind::Command
             | PaletteKind::Workspace
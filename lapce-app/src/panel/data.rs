@@ -28,6 +28,7 @@ pub fn default_panel_order() -> PanelOrder {
             PanelKind::FileExplorer,
             PanelKind::Plugin,
             PanelKind::SourceControl,
+            PanelKind::GitHistory,
             PanelKind::Debug,
         ],
     );
@@ -57,12 +58,18 @@ pub enum PanelSection {
     Error,
     Warn,
     Changes,
+    StagedChanges,
     Installed,
     Available,
     Process,
     Variable,
     StackFrame,
     Breakpoint,
+    Watch,
+    DebugConsole,
+    Commits,
+    CommitFiles,
+    Stashes,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
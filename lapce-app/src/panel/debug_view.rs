@@ -2,7 +2,9 @@
 
 use floem::{
     event::EventListener,
+    kurbo::Point,
     peniko::Color,
+    prelude::SignalTrack,
     reactive::{
         create_rw_signal, ReadSignal, RwSignal, SignalGet, SignalUpdate, SignalWith,
     },
@@ -19,17 +21,23 @@
     terminal::TermId,
 };
 
-use super::{data::PanelSection, position::PanelPosition, view::PanelBuilder};
+use super::{
+    data::PanelSection, kind::PanelKind, position::PanelPosition, view::PanelBuilder,
+};
 use crate::{
     app::clickable_icon,
+    breakpoint::BreakpointEditKind,
     command::InternalCommand,
     config::{color::LapceColor, icon::LapceIcons, LapceConfig},
-    debug::{DapVariable, RunDebugMode, StackTraceData},
+    debug::{
+        DapVariable, DebugConsoleKind, DebugInput, RunDebugMode, StackTraceData,
+    },
     editor::location::{EditorLocation, EditorPosition},
     listener::Listener,
     settings::checkbox,
     terminal::panel::TerminalPanelData,
-    window_tab::WindowTabData,
+    text_input::TextInputBuilder,
+    window_tab::{Focus, WindowTabData},
 };
 
 pub fn debug_panel(
@@ -52,6 +60,12 @@ pub fn debug_panel(
             variables_view(window_tab_data.clone()),
             window_tab_data.panel.section_open(PanelSection::Variable),
         )
+        .add_height(
+            "Watch",
+            120.0,
+            watch_view(window_tab_data.clone()),
+            window_tab_data.panel.section_open(PanelSection::Watch),
+        )
         .add(
             "Stack Frames",
             debug_stack_traces(terminal.clone(), internal_command, config),
@@ -63,6 +77,13 @@ pub fn debug_panel(
             breakpoints_view(window_tab_data.clone()),
             window_tab_data.panel.section_open(PanelSection::Breakpoint),
         )
+        .add(
+            "Debug Console",
+            debug_console_view(window_tab_data.clone()),
+            window_tab_data
+                .panel
+                .section_open(PanelSection::DebugConsole),
+        )
         .build()
         .debug_name("Debug Panel")
 }
@@ -623,7 +644,9 @@ fn debug_stack_traces(
 }
 
 fn breakpoints_view(window_tab_data: Rc<WindowTabData>) -> impl View {
-    let breakpoints = window_tab_data.terminal.debug.breakpoints;
+    let debug = window_tab_data.terminal.debug.clone();
+    let breakpoints = debug.breakpoints;
+    let proxy = window_tab_data.common.proxy.clone();
     let config = window_tab_data.common.config;
     let workspace = window_tab_data.common.workspace.clone();
     let available_width = create_rw_signal(0.0);
@@ -640,13 +663,26 @@ fn breakpoints_view(window_tab_data: Rc<WindowTabData>) -> impl View {
                         })
                 },
                 move |(path, breakpoint)| {
-                    (path.clone(), breakpoint.line, breakpoint.active)
+                    (
+                        path.clone(),
+                        breakpoint.line,
+                        breakpoint.active,
+                        breakpoint.detail(),
+                    )
                 },
                 move |(path, breakpoint)| {
                     let line = breakpoint.line;
+                    let offset = breakpoint.offset;
                     let full_path = path.clone();
                     let full_path_for_jump = path.clone();
                     let full_path_for_close = path.clone();
+                    let full_path_for_edit = path.clone();
+                    let detail = breakpoint.detail();
+                    let detail_empty = detail.is_empty();
+                    let close_debug = debug.clone();
+                    let close_proxy = proxy.clone();
+                    let toggle_debug = debug.clone();
+                    let toggle_proxy = proxy.clone();
                     let path = if let Some(workspace_path) = workspace.path.as_ref()
                     {
                         path.strip_prefix(workspace_path)
@@ -673,6 +709,10 @@ fn breakpoints_view(window_tab_data: Rc<WindowTabData>) -> impl View {
                                         breakpoints.remove(&line);
                                     }
                                 });
+                                close_debug.send_breakpoints(
+                                    &close_proxy,
+                                    &full_path_for_close,
+                                );
                             },
                             || false,
                             || false,
@@ -680,6 +720,24 @@ fn breakpoints_view(window_tab_data: Rc<WindowTabData>) -> impl View {
                             config,
                         )
                         .on_event_stop(EventListener::PointerDown, |_| {}),
+                        clickable_icon(
+                            move || LapceIcons::DEBUG_BREAKPOINT_EDIT,
+                            move || {
+                                internal_command.send(
+                                    InternalCommand::EditBreakpoint {
+                                        path: full_path_for_edit.clone(),
+                                        line,
+                                        offset,
+                                        kind: BreakpointEditKind::Condition,
+                                    },
+                                );
+                            },
+                            || false,
+                            || false,
+                            || "Edit Breakpoint",
+                            config,
+                        )
+                        .on_event_stop(EventListener::PointerDown, |_| {}),
                         checkbox(move || breakpoint.active, config)
                             .style(|s| {
                                 s.margin_right(6.0).cursor(CursorStyle::Pointer)
@@ -696,6 +754,8 @@ fn breakpoints_view(window_tab_data: Rc<WindowTabData>) -> impl View {
                                         }
                                     }
                                 });
+                                toggle_debug
+                                    .send_breakpoints(&toggle_proxy, &full_path);
                             }),
                         text(format!("{file_name}:{}", breakpoint.line + 1)).style(
                             move |s| {
@@ -706,10 +766,20 @@ fn breakpoints_view(window_tab_data: Rc<WindowTabData>) -> impl View {
                                         - size
                                         - 6.0
                                         - size
+                                        - 8.0
+                                        - size
                                         - 8.0,
                                 )
                             },
                         ),
+                        text(detail).style(move |s| {
+                            s.text_ellipsis()
+                                .flex_shrink(1.0)
+                                .min_width(0.0)
+                                .margin_left(6.0)
+                                .font_style(FontStyle::Italic)
+                                .apply_if(detail_empty, |s| s.hide())
+                        }),
                         text(folder).style(move |s| {
                             s.text_ellipsis()
                                 .flex_grow(1.0)
@@ -756,3 +826,156 @@ fn breakpoints_view(window_tab_data: Rc<WindowTabData>) -> impl View {
     )
     .style(|s| s.size_pct(100.0, 100.0))
 }
+
+/// An input of the debug panel which takes the keyboard focus when clicked
+fn debug_input(
+    window_tab_data: Rc<WindowTabData>,
+    input: DebugInput,
+    placeholder: &'static str,
+) -> impl View {
+    let debug = window_tab_data.terminal.debug.clone();
+    let focus = window_tab_data.common.focus;
+    let config = window_tab_data.common.config;
+    let input_focus = debug.input_focus;
+    let editor = match input {
+        DebugInput::Console => debug.console_editor.clone(),
+        DebugInput::Watch => debug.watch_editor.clone(),
+    };
+
+    container(
+        TextInputBuilder::new()
+            .is_focused(move || {
+                focus.get() == Focus::Panel(PanelKind::Debug)
+                    && input_focus.get() == input
+            })
+            .build_editor(editor)
+            .placeholder(move || placeholder.to_string())
+            .style(|s| s.width_pct(100.0)),
+    )
+    .on_event_cont(EventListener::PointerDown, move |_| {
+        focus.set(Focus::Panel(PanelKind::Debug));
+        input_focus.set(input);
+    })
+    .style(move |s| {
+        let config = config.get();
+        s.width_pct(100.0)
+            .margin_horiz(10.0)
+            .margin_vert(4.0)
+            .border(1.0)
+            .border_radius(6.0)
+            .border_color(config.color(LapceColor::LAPCE_BORDER))
+            .background(config.color(LapceColor::EDITOR_BACKGROUND))
+    })
+}
+
+fn watch_view(window_tab_data: Rc<WindowTabData>) -> impl View {
+    let debug = window_tab_data.terminal.debug.clone();
+    let watches = debug.watches;
+    let config = window_tab_data.common.config;
+    stack((
+        container(
+            scroll(
+                dyn_stack(
+                    move || watches.get().into_iter().enumerate(),
+                    |(i, watch)| (*i, watch.clone()),
+                    move |(i, watch)| {
+                        let debug = debug.clone();
+                        let (value, is_err) = match watch.value {
+                            Some(Ok(value)) => (value, false),
+                            Some(Err(err)) => (err, true),
+                            None => ("not available".to_string(), true),
+                        };
+                        stack((
+                            clickable_icon(
+                                move || LapceIcons::CLOSE,
+                                move || {
+                                    debug.remove_watch(i);
+                                },
+                                || false,
+                                || false,
+                                || "Remove",
+                                config,
+                            )
+                            .style(|s| s.margin_right(4.0))
+                            .on_event_stop(EventListener::PointerDown, |_| {}),
+                            text(format!("{}: ", watch.expression)),
+                            text(value).style(move |s| {
+                                let config = config.get();
+                                s.text_ellipsis().min_width(0.0).color(config.color(
+                                    if is_err {
+                                        LapceColor::EDITOR_DIM
+                                    } else {
+                                        LapceColor::EDITOR_FOREGROUND
+                                    },
+                                ))
+                            }),
+                        ))
+                        .style(move |s| {
+                            s.items_center().padding_horiz(10.0).width_pct(100.0)
+                        })
+                    },
+                )
+                .style(|s| s.flex_col().line_height(1.6).width_pct(100.0)),
+            )
+            .style(|s| s.absolute().size_pct(100.0, 100.0)),
+        )
+        .style(|s| s.width_pct(100.0).flex_grow(1.0).flex_basis(0.0)),
+        debug_input(window_tab_data, DebugInput::Watch, "Add Expression"),
+    ))
+    .style(|s| s.flex_col().size_pct(100.0, 100.0))
+}
+
+fn debug_console_view(window_tab_data: Rc<WindowTabData>) -> impl View {
+    let debug = window_tab_data.terminal.debug.clone();
+    let scroll_debug = debug.clone();
+    let config = window_tab_data.common.config;
+    stack((
+        container(
+            scroll(
+                dyn_stack(
+                    move || {
+                        debug
+                            .active_dap(true)
+                            .map(|dap| dap.console.get())
+                            .unwrap_or_default()
+                            .into_iter()
+                            .enumerate()
+                    },
+                    |(i, _)| *i,
+                    move |(_, entry)| {
+                        let text = match entry.kind {
+                            DebugConsoleKind::Input => format!("> {}", entry.text),
+                            _ => entry.text,
+                        };
+                        label(move || text.clone()).style(move |s| {
+                            let config = config.get();
+                            let color = match entry.kind {
+                                DebugConsoleKind::Error => LapceColor::LAPCE_ERROR,
+                                DebugConsoleKind::Input => LapceColor::EDITOR_DIM,
+                                DebugConsoleKind::Output
+                                | DebugConsoleKind::Result => {
+                                    LapceColor::EDITOR_FOREGROUND
+                                }
+                            };
+                            s.padding_horiz(10.0)
+                                .font_family(config.editor.font_family.clone())
+                                .color(config.color(color))
+                        })
+                    },
+                )
+                .style(|s| s.flex_col().line_height(1.6).min_width_pct(100.0)),
+            )
+            .scroll_to(move || {
+                // keep the latest output in view
+                if let Some(dap) = scroll_debug.active_dap(true) {
+                    dap.console.track();
+                }
+                Some(Point::new(0.0, f64::MAX))
+            })
+            .style(|s| s.absolute().size_pct(100.0, 100.0)),
+        )
+        .style(|s| s.width_pct(100.0).flex_grow(1.0).flex_basis(0.0)),
+        debug_input(window_tab_data, DebugInput::Console, "Evaluate Expression"),
+    ))
+    .style(|s| s.flex_col().size_pct(100.0, 100.0))
+}
@@ -0,0 +1,312 @@
+use std::{path::PathBuf, rc::Rc};
+
+use floem::{
+    event::EventListener,
+    reactive::{SignalGet, SignalUpdate, SignalWith},
+    style::{CursorStyle, Style},
+    views::{
+        container, dyn_stack, label, scroll, stack, svg, virtual_stack, Decorators,
+    },
+    View,
+};
+use lapce_rpc::source_control::{FileDiff, GitCommit};
+
+use super::{
+    data::PanelSection, kind::PanelKind, position::PanelPosition, view::PanelBuilder,
+};
+use crate::{
+    app::clickable_icon,
+    config::{color::LapceColor, icon::LapceIcons},
+    git_history::GitHistoryData,
+    source_control::time_ago,
+    text_input::TextInputBuilder,
+    window_tab::{Focus, WindowTabData},
+};
+
+pub fn git_history_panel(
+    window_tab_data: Rc<WindowTabData>,
+    position: PanelPosition,
+) -> impl View {
+    let git_history = window_tab_data.git_history.clone();
+    let config = git_history.common.config;
+    let focus = git_history.common.focus;
+    let workspace = git_history.common.workspace.clone();
+    let path = git_history.path;
+    let is_focused = move || focus.get() == Focus::Panel(PanelKind::GitHistory);
+
+    stack((
+        stack((
+            stack((
+                label(move || match path.get() {
+                    Some(path) => {
+                        format!(
+                            "History of {}",
+                            workspace.relative_path(&path).display()
+                        )
+                    }
+                    None => "Repository History".to_string(),
+                })
+                .style(|s| {
+                    s.text_ellipsis()
+                        .flex_grow(1.0)
+                        .min_width(0.0)
+                        .selectable(false)
+                }),
+                {
+                    let git_history = git_history.clone();
+                    clickable_icon(
+                        || LapceIcons::CLOSE,
+                        move || git_history.show_repository(),
+                        || false,
+                        || false,
+                        || "Show Repository History",
+                        config,
+                    )
+                    .style(move |s| {
+                        s.margin_left(6.0)
+                            .apply_if(path.with(|path| path.is_none()), |s| s.hide())
+                    })
+                },
+                {
+                    let git_history = git_history.clone();
+                    clickable_icon(
+                        || LapceIcons::GIT_HISTORY_REFRESH,
+                        move || git_history.load(),
+                        || false,
+                        || false,
+                        || "Refresh",
+                        config,
+                    )
+                    .style(|s| s.margin_left(6.0))
+                },
+            ))
+            .style(|s| s.items_center().width_pct(100.0)),
+            container(
+                TextInputBuilder::new()
+                    .is_focused(is_focused)
+                    .build_editor(git_history.editor.clone())
+                    .placeholder(|| "Search messages and authors".to_string())
+                    .style(|s| s.width_pct(100.0)),
+            )
+            .style(move |s| {
+                s.width_pct(100.0)
+                    .margin_top(6.0)
+                    .padding_right(6.0)
+                    .items_center()
+                    .border(1.0)
+                    .border_radius(6.0)
+                    .border_color(config.get().color(LapceColor::LAPCE_BORDER))
+            }),
+        ))
+        .style(|s| s.flex_col().width_pct(100.0).padding(10.0)),
+        PanelBuilder::new(config, position)
+            .add(
+                "Commits",
+                commits_view(git_history.clone()),
+                window_tab_data.panel.section_open(PanelSection::Commits),
+            )
+            .add(
+                "Changed Files",
+                commit_files_view(git_history),
+                window_tab_data
+                    .panel
+                    .section_open(PanelSection::CommitFiles),
+            )
+            .build()
+            .style(|s| s.flex_grow(1.0).flex_basis(0.0)),
+    ))
+    .on_event_stop(EventListener::PointerDown, move |_| {
+        if focus.get_untracked() != Focus::Panel(PanelKind::GitHistory) {
+            focus.set(Focus::Panel(PanelKind::GitHistory));
+        }
+    })
+    .style(|s| s.flex_col().size_pct(100.0, 100.0))
+    .debug_name("Git History Panel")
+}
+
+fn commits_view(git_history: GitHistoryData) -> impl View {
+    let config = git_history.common.config;
+    let ui_line_height = git_history.common.ui_line_height;
+    let commits = git_history.commits;
+    let selected = git_history.selected;
+
+    container(
+        scroll(
+            virtual_stack(
+                move || commits.get(),
+                |commit| commit.id.clone(),
+                move |commit: GitCommit| {
+                    let git_history = git_history.clone();
+                    let id = commit.id.clone();
+                    let summary = commit.summary().to_string();
+                    let now = chrono::Utc::now().timestamp();
+                    let details = format!(
+                        "{} {}, {}",
+                        commit.short_id(),
+                        commit.author,
+                        time_ago(commit.time, now)
+                    );
+                    stack((
+                        label(move || summary.clone()).style(|s| {
+                            s.text_ellipsis()
+                                .flex_grow(1.0)
+                                .flex_basis(0.0)
+                                .min_width(0.0)
+                                .selectable(false)
+                        }),
+                        label(move || details.clone()).style(move |s| {
+                            s.margin_left(10.0)
+                                .color(config.get().color(LapceColor::EDITOR_DIM))
+                                .selectable(false)
+                        }),
+                    ))
+                    .on_click_stop(move |_| {
+                        git_history.select(commit.clone());
+                    })
+                    .style(move |s| {
+                        let config = config.get();
+                        let is_selected = selected
+                            .with(|c| c.as_ref().map(|c| &c.id) == Some(&id));
+                        s.padding_horiz(10.0)
+                            .width_pct(100.0)
+                            .height(ui_line_height.get() as f32)
+                            .items_center()
+                            .apply_if(is_selected, |s| {
+                                s.background(
+                                    config
+                                        .color(LapceColor::PANEL_CURRENT_BACKGROUND),
+                                )
+                            })
+                            .hover(|s| {
+                                s.cursor(CursorStyle::Pointer).background(
+                                    config
+                                        .color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                                )
+                            })
+                    })
+                },
+            )
+            .style(|s| s.flex_col().width_pct(100.0)),
+        )
+        .style(|s| s.absolute().size_pct(100.0, 100.0)),
+    )
+    .style(|s| s.size_pct(100.0, 100.0))
+}
+
+/// The files changed by the selected commit, which open a diff of the commit's
+/// changes on click
+fn commit_files_view(git_history: GitHistoryData) -> impl View {
+    let config = git_history.common.config;
+    let workspace = git_history.common.workspace.clone();
+    let files = git_history.files;
+    let selected = git_history.selected;
+
+    let file_view = move |file: FileDiff| {
+        let git_history = git_history.clone();
+        let path = match &file {
+            FileDiff::Renamed(new, _) => new.clone(),
+            FileDiff::Modified(path)
+            | FileDiff::Added(path)
+            | FileDiff::Deleted(path)
+            | FileDiff::Conflicted(path) => path.clone(),
+        };
+        let path = workspace.relative_path(&path);
+        let file_name = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_string();
+        let folder = path
+            .parent()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_string();
+        let style_path: PathBuf = path.clone();
+        let diff_svg = match &file {
+            FileDiff::Modified(_) => LapceIcons::SCM_DIFF_MODIFIED,
+            FileDiff::Added(_) => LapceIcons::SCM_DIFF_ADDED,
+            FileDiff::Deleted(_) => LapceIcons::SCM_DIFF_REMOVED,
+            FileDiff::Renamed(_, _) => LapceIcons::SCM_DIFF_RENAMED,
+            FileDiff::Conflicted(_) => LapceIcons::SCM_DIFF_CONFLICTED,
+        };
+        let diff_color = match &file {
+            FileDiff::Modified(_) | FileDiff::Renamed(_, _) => {
+                LapceColor::SOURCE_CONTROL_MODIFIED
+            }
+            FileDiff::Added(_) => LapceColor::SOURCE_CONTROL_ADDED,
+            FileDiff::Deleted(_) => LapceColor::SOURCE_CONTROL_REMOVED,
+            FileDiff::Conflicted(_) => LapceColor::SOURCE_CONTROL_CONFLICTED,
+        };
+        stack((
+            svg(move || config.get().file_svg(&path).0).style(move |s| {
+                let config = config.get();
+                let size = config.ui.icon_size() as f32;
+                let color = config.file_svg(&style_path).1;
+                s.min_width(size)
+                    .size(size, size)
+                    .margin_right(6.0)
+                    .apply_opt(color, Style::color)
+            }),
+            label(move || file_name.clone())
+                .style(|s| s.text_ellipsis().margin_right(6.0).selectable(false)),
+            label(move || folder.clone()).style(move |s| {
+                s.text_ellipsis()
+                    .flex_grow(1.0)
+                    .flex_basis(0.0)
+                    .min_width(0.0)
+                    .color(config.get().color(LapceColor::EDITOR_DIM))
+                    .selectable(false)
+            }),
+            svg(move || config.get().ui_svg(diff_svg)).style(move |s| {
+                let config = config.get();
+                let size = config.ui.icon_size() as f32;
+                s.min_width(size)
+                    .size(size, size)
+                    .margin_left(6.0)
+                    .color(config.color(diff_color))
+            }),
+        ))
+        .on_click_stop(move |_| {
+            git_history.open_file(&file);
+        })
+        .style(move |s| {
+            let config = config.get();
+            s.padding_horiz(10.0)
+                .width_pct(100.0)
+                .items_center()
+                .hover(|s| {
+                    s.cursor(CursorStyle::Pointer).background(
+                        config.color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                    )
+                })
+        })
+    };
+
+    container(
+        scroll(
+            stack((
+                label(move || {
+                    selected
+                        .with(|commit| {
+                            commit.as_ref().map(|commit| {
+                                format!("{} {}", commit.short_id(), commit.summary())
+                            })
+                        })
+                        .unwrap_or_default()
+                })
+                .style(move |s| {
+                    s.padding_horiz(10.0)
+                        .text_ellipsis()
+                        .color(config.get().color(LapceColor::EDITOR_DIM))
+                        .selectable(false)
+                        .apply_if(selected.with(|c| c.is_none()), |s| s.hide())
+                }),
+                dyn_stack(move || files.get(), |file| file.clone(), file_view)
+                    .style(|s| s.flex_col().width_pct(100.0)),
+            ))
+            .style(|s| s.flex_col().line_height(1.6).width_pct(100.0)),
+        )
+        .style(|s| s.absolute().size_pct(100.0, 100.0)),
+    )
+    .style(|s| s.size_pct(100.0, 100.0))
+}
@@ -1,4 +1,4 @@
-use std::{path::PathBuf, rc::Rc, sync::Arc};
+use std::{ops::Range, path::PathBuf, rc::Rc, sync::Arc};
 
 use floem::{
     event::EventListener,
@@ -7,17 +7,15 @@
     views::{container, label, scroll, stack, svg, virtual_stack, Decorators},
     View,
 };
+use lapce_rpc::proxy::SearchMatch;
 use lapce_xi_rope::find::CaseMatching;
 
 use super::{kind::PanelKind, position::PanelPosition};
 use crate::{
     app::clickable_icon,
-    command::InternalCommand,
     config::{color::LapceColor, icon::LapceIcons, LapceConfig},
-    editor::location::{EditorLocation, EditorPosition},
     focus_text::focus_text,
-    global_search::{GlobalSearchData, SearchMatchData},
-    listener::Listener,
+    global_search::{GlobalSearchData, SearchInput, SearchMatchData},
     text_input::TextInputBuilder,
     window_tab::{Focus, WindowTabData},
     workspace::LapceWorkspace,
@@ -31,77 +29,228 @@ pub fn global_search_panel(
     let editor = global_search.editor.clone();
     let config = global_search.common.config;
     let workspace = global_search.common.workspace.clone();
-    let internal_command = global_search.common.internal_command;
     let case_matching = global_search.common.find.case_matching;
     let whole_word = global_search.common.find.whole_words;
     let is_regex = global_search.common.find.is_regex;
 
     let focus = global_search.common.focus;
     let is_focused = move || focus.get() == Focus::Panel(PanelKind::Search);
+    let replace_editor = global_search.replace_editor.clone();
+    let replace_active = global_search.replace_active;
+    let input_focus = global_search.input_focus;
+    let limit_reached = global_search.limit_reached;
+    let search_ignored = global_search.search_ignored;
+    let include_editor = global_search.include_editor.clone();
+    let exclude_editor = global_search.exclude_editor.clone();
 
     stack((
         container(
             stack((
-                TextInputBuilder::new()
-                    .is_focused(is_focused)
-                    .build_editor(editor.clone())
-                    .style(|s| s.width_pct(100.0)),
                 clickable_icon(
-                    || LapceIcons::SEARCH_CASE_SENSITIVE,
                     move || {
-                        let new = match case_matching.get_untracked() {
-                            CaseMatching::Exact => CaseMatching::CaseInsensitive,
-                            CaseMatching::CaseInsensitive => CaseMatching::Exact,
-                        };
-                        case_matching.set(new);
+                        if replace_active.get() {
+                            LapceIcons::ITEM_OPENED
+                        } else {
+                            LapceIcons::ITEM_CLOSED
+                        }
                     },
-                    move || case_matching.get() == CaseMatching::Exact,
-                    || false,
-                    || "Case Sensitive",
-                    config,
-                )
-                .style(|s| s.padding_vert(4.0)),
-                clickable_icon(
-                    || LapceIcons::SEARCH_WHOLE_WORD,
-                    move || {
-                        whole_word.update(|whole_word| {
-                            *whole_word = !*whole_word;
-                        });
-                    },
-                    move || whole_word.get(),
-                    || false,
-                    || "Whole Word",
-                    config,
-                )
-                .style(|s| s.padding_left(6.0)),
-                clickable_icon(
-                    || LapceIcons::SEARCH_REGEX,
                     move || {
-                        is_regex.update(|is_regex| {
-                            *is_regex = !*is_regex;
-                        });
+                        replace_active.update(|active| *active = !*active);
                     },
-                    move || is_regex.get(),
+                    move || false,
                     || false,
-                    || "Use Regex",
+                    || "Toggle Replace",
                     config,
                 )
-                .style(|s| s.padding_left(6.0)),
+                .style(|s| s.padding_right(6.0)),
+                stack((
+                    stack((
+                        TextInputBuilder::new()
+                            .is_focused(move || {
+                                is_focused()
+                                    && match input_focus.get() {
+                                        SearchInput::Search => true,
+                                        SearchInput::Replace => {
+                                            !replace_active.get()
+                                        }
+                                        _ => false,
+                                    }
+                            })
+                            .build_editor(editor.clone())
+                            .style(|s| s.width_pct(100.0)),
+                        clickable_icon(
+                            || LapceIcons::SEARCH_CASE_SENSITIVE,
+                            move || {
+                                let new = match case_matching.get_untracked() {
+                                    CaseMatching::Exact => {
+                                        CaseMatching::CaseInsensitive
+                                    }
+                                    CaseMatching::CaseInsensitive => {
+                                        CaseMatching::Exact
+                                    }
+                                };
+                                case_matching.set(new);
+                            },
+                            move || case_matching.get() == CaseMatching::Exact,
+                            || false,
+                            || "Case Sensitive",
+                            config,
+                        )
+                        .style(|s| s.padding_vert(4.0)),
+                        clickable_icon(
+                            || LapceIcons::SEARCH_WHOLE_WORD,
+                            move || {
+                                whole_word.update(|whole_word| {
+                                    *whole_word = !*whole_word;
+                                });
+                            },
+                            move || whole_word.get(),
+                            || false,
+                            || "Whole Word",
+                            config,
+                        )
+                        .style(|s| s.padding_left(6.0)),
+                        clickable_icon(
+                            || LapceIcons::SEARCH_REGEX,
+                            move || {
+                                is_regex.update(|is_regex| {
+                                    *is_regex = !*is_regex;
+                                });
+                            },
+                            move || is_regex.get(),
+                            || false,
+                            || "Use Regex",
+                            config,
+                        )
+                        .style(|s| s.padding_left(6.0)),
+                    ))
+                    .on_event_cont(EventListener::PointerDown, move |_| {
+                        focus.set(Focus::Panel(PanelKind::Search));
+                        input_focus.set(SearchInput::Search);
+                    })
+                    .style(move |s| {
+                        s.width_pct(100.0)
+                            .padding_right(6.0)
+                            .items_center()
+                            .border(1.0)
+                            .border_radius(6.0)
+                            .border_color(
+                                config.get().color(LapceColor::LAPCE_BORDER),
+                            )
+                    }),
+                    stack((
+                        TextInputBuilder::new()
+                            .is_focused(move || {
+                                is_focused()
+                                    && replace_active.get()
+                                    && input_focus.get() == SearchInput::Replace
+                            })
+                            .build_editor(replace_editor)
+                            .style(|s| s.width_pct(100.0)),
+                        clickable_icon(
+                            || LapceIcons::SEARCH_REPLACE_ALL,
+                            {
+                                let global_search = global_search.clone();
+                                move || {
+                                    global_search.replace_all();
+                                }
+                            },
+                            move || false,
+                            || false,
+                            || "Replace All",
+                            config,
+                        )
+                        .style(|s| s.padding_vert(4.0)),
+                    ))
+                    .on_event_cont(EventListener::PointerDown, move |_| {
+                        focus.set(Focus::Panel(PanelKind::Search));
+                        input_focus.set(SearchInput::Replace);
+                    })
+                    .style(move |s| {
+                        s.width_pct(100.0)
+                            .margin_top(4.0)
+                            .padding_right(6.0)
+                            .items_center()
+                            .border(1.0)
+                            .border_radius(6.0)
+                            .border_color(
+                                config.get().color(LapceColor::LAPCE_BORDER),
+                            )
+                            .apply_if(!replace_active.get(), |s| s.hide())
+                    }),
+                    stack((
+                        TextInputBuilder::new()
+                            .is_focused(move || {
+                                is_focused()
+                                    && input_focus.get() == SearchInput::Include
+                            })
+                            .build_editor(include_editor)
+                            .placeholder(|| "files to include".to_string())
+                            .style(|s| s.width_pct(100.0)),
+                        clickable_icon(
+                            || LapceIcons::SEARCH_IGNORED,
+                            move || {
+                                search_ignored.update(|ignored| {
+                                    *ignored = !*ignored;
+                                });
+                            },
+                            move || search_ignored.get(),
+                            || false,
+                            || "Search Ignored Files",
+                            config,
+                        )
+                        .style(|s| s.padding_vert(4.0)),
+                    ))
+                    .on_event_cont(EventListener::PointerDown, move |_| {
+                        focus.set(Focus::Panel(PanelKind::Search));
+                        input_focus.set(SearchInput::Include);
+                    })
+                    .style(move |s| {
+                        s.width_pct(100.0)
+                            .margin_top(4.0)
+                            .padding_right(6.0)
+                            .items_center()
+                            .border(1.0)
+                            .border_radius(6.0)
+                            .border_color(
+                                config.get().color(LapceColor::LAPCE_BORDER),
+                            )
+                    }),
+                    TextInputBuilder::new()
+                        .is_focused(move || {
+                            is_focused() && input_focus.get() == SearchInput::Exclude
+                        })
+                        .build_editor(exclude_editor)
+                        .placeholder(|| "files to exclude".to_string())
+                        .on_event_cont(EventListener::PointerDown, move |_| {
+                            focus.set(Focus::Panel(PanelKind::Search));
+                            input_focus.set(SearchInput::Exclude);
+                        })
+                        .style(move |s| {
+                            s.width_pct(100.0)
+                                .margin_top(4.0)
+                                .border(1.0)
+                                .border_radius(6.0)
+                                .border_color(
+                                    config.get().color(LapceColor::LAPCE_BORDER),
+                                )
+                        }),
+                    label(|| {
+                        "The result limit was reached, refine the search to see more"
+                            .to_string()
+                    })
+                    .style(move |s| {
+                        s.margin_top(4.0)
+                            .color(config.get().color(LapceColor::EDITOR_DIM))
+                            .apply_if(!limit_reached.get(), |s| s.hide())
+                    }),
+                ))
+                .style(|s| s.flex_col().flex_grow(1.0).min_width(0.0)),
             ))
-            .on_event_cont(EventListener::PointerDown, move |_| {
-                focus.set(Focus::Panel(PanelKind::Search));
-            })
-            .style(move |s| {
-                s.width_pct(100.0)
-                    .padding_right(6.0)
-                    .items_center()
-                    .border(1.0)
-                    .border_radius(6.0)
-                    .border_color(config.get().color(LapceColor::LAPCE_BORDER))
-            }),
+            .style(|s| s.width_pct(100.0).items_start()),
         )
         .style(|s| s.width_pct(100.0).padding(10.0)),
-        search_result(workspace, global_search, internal_command, config),
+        search_result(workspace, global_search, config),
     ))
     .style(|s| s.absolute().size_pct(100.0, 100.0).flex_col())
     .debug_name("Global Search Panel")
@@ -110,25 +259,20 @@ pub fn global_search_panel(
 fn search_result(
     workspace: Arc<LapceWorkspace>,
     global_search_data: GlobalSearchData,
-    internal_command: Listener<InternalCommand>,
     config: ReadSignal<Arc<LapceConfig>>,
 ) -> impl View {
     let ui_line_height = global_search_data.common.ui_line_height;
+    let replace_active = global_search_data.replace_active;
+    let global_search = global_search_data.clone();
     container({
         scroll({
             virtual_stack(
                 move || global_search_data.clone(),
                 move |(path, _)| path.to_owned(),
                 move |(path, match_data)| {
+                    let global_search = global_search.clone();
                     let full_path = path.clone();
-                    let path = if let Some(workspace_path) = workspace.path.as_ref()
-                    {
-                        path.strip_prefix(workspace_path)
-                            .unwrap_or(&full_path)
-                            .to_path_buf()
-                    } else {
-                        path
-                    };
+                    let path = workspace.relative_path(&path);
                     let style_path = path.clone();
 
                     let file_name = path
@@ -144,6 +288,8 @@ fn search_result(
                         .to_string();
 
                     let expanded = match_data.expanded;
+                    let header_path = full_path.clone();
+                    let header_search = global_search.clone();
 
                     stack((
                         stack((
@@ -190,7 +336,23 @@ fn search_result(
                                     .text_ellipsis()
                                 }),
                             ))
-                            .style(move |s| s.min_width(0.0).items_center()),
+                            .style(move |s| {
+                                s.min_width(0.0).flex_grow(1.0).items_center()
+                            }),
+                            clickable_icon(
+                                || LapceIcons::CLOSE,
+                                move || {
+                                    header_search.dismiss_file(&header_path);
+                                },
+                                || false,
+                                || false,
+                                || "Dismiss",
+                                config,
+                            )
+                            .style(move |s| {
+                                s.padding_horiz(6.0)
+                                    .apply_if(!replace_active.get(), |s| s.hide())
+                            }),
                         ))
                         .on_click_stop(move |_| {
                             expanded.update(|expanded| *expanded = !*expanded);
@@ -218,78 +380,102 @@ fn search_result(
                             |m| (m.line, m.start, m.end),
                             move |m| {
                                 let path = full_path.clone();
-                                let line_number = m.line;
-                                let start = m.start;
-                                let end = m.end;
-                                let line_content = m.line_content.clone();
-
-                                focus_text(
-                                    move || {
-                                        let config = config.get();
-                                        let content = if config
-                                            .ui
-                                            .trim_search_results_whitespace
-                                        {
-                                            m.line_content.trim()
-                                        } else {
-                                            &m.line_content
-                                        };
-                                        format!("{}: {content}", m.line,)
-                                    },
-                                    move || {
-                                        let config = config.get();
-                                        let mut offset = if config
-                                            .ui
-                                            .trim_search_results_whitespace
-                                        {
-                                            line_content.trim_start().len() as i32
-                                                - line_content.len() as i32
-                                        } else {
-                                            0
-                                        };
-                                        offset +=
-                                            line_number.to_string().len() as i32 + 2;
+                                let active_path = full_path.clone();
+                                let active_match = m.clone();
+                                let active_search = global_search.clone();
+                                let text_match = m.clone();
+                                let text_search = global_search.clone();
+                                let indices_match = m.clone();
+                                let indices_search = global_search.clone();
+                                let dismiss_path = full_path.clone();
+                                let dismiss_search = global_search.clone();
 
-                                        ((start as i32 + offset) as usize
-                                            ..(end as i32 + offset) as usize)
+                                stack((
+                                    focus_text(
+                                        move || {
+                                            let trim = config
+                                                .get()
+                                                .ui
+                                                .trim_search_results_whitespace;
+                                            let replacement = text_search
+                                                .replacement_preview(&text_match);
+                                            match_display(
+                                                &text_match,
+                                                replacement,
+                                                trim,
+                                            )
+                                            .0
+                                        },
+                                        move || {
+                                            let trim = config
+                                                .get()
+                                                .ui
+                                                .trim_search_results_whitespace;
+                                            let replacement = indices_search
+                                                .replacement_preview(&indices_match);
+                                            match_display(
+                                                &indices_match,
+                                                replacement,
+                                                trim,
+                                            )
+                                            .1
                                             .collect()
-                                    },
-                                    move || {
-                                        config.get().color(LapceColor::EDITOR_FOCUS)
-                                    },
-                                )
+                                        },
+                                        move || {
+                                            config
+                                                .get()
+                                                .color(LapceColor::EDITOR_FOCUS)
+                                        },
+                                    )
+                                    .style(|s| s.flex_grow(1.0).min_width(0.0))
+                                    .on_click_stop({
+                                        let global_search = global_search.clone();
+                                        let m = m.clone();
+                                        move |_| {
+                                            global_search.jump_to_match(&path, &m);
+                                        }
+                                    }),
+                                    clickable_icon(
+                                        || LapceIcons::CLOSE,
+                                        move || {
+                                            dismiss_search
+                                                .dismiss_match(&dismiss_path, &m);
+                                        },
+                                        || false,
+                                        || false,
+                                        || "Dismiss",
+                                        config,
+                                    )
+                                    .style(
+                                        move |s| {
+                                            s.padding_horiz(6.0).apply_if(
+                                                !replace_active.get(),
+                                                |s| s.hide(),
+                                            )
+                                        },
+                                    ),
+                                ))
                                 .style(move |s| {
                                     let config = config.get();
                                     let icon_size = config.ui.icon_size() as f32;
-                                    s.margin_left(10.0 + icon_size + 6.0).hover(
-                                        |s| {
+                                    let is_active = active_search.is_active_match(
+                                        &active_path,
+                                        &active_match,
+                                    );
+                                    s.margin_left(10.0 + icon_size + 6.0)
+                                        .items_center()
+                                        .apply_if(is_active, |s| {
+                                            s.background(config.color(
+                                                LapceColor::PANEL_CURRENT_BACKGROUND,
+                                            ))
+                                        })
+                                        .hover(|s| {
                                             s.cursor(CursorStyle::Pointer)
                                                 .background(config.color(
                                                 LapceColor::PANEL_HOVERED_BACKGROUND,
                                             ))
-                                        },
-                                    )
+                                        })
                                 })
-                                .on_click_stop(
-                                    move |_| {
-                                        internal_command.send(
-                                            InternalCommand::JumpToLocation {
-                                                location: EditorLocation {
-                                                    path: path.clone(),
-                                                    position: Some(
-                                                        EditorPosition::Line(
-                                                            line_number
-                                                                .saturating_sub(1),
-                                                        ),
-                                                    ),
-                                                    scroll_offset: None,
-                                                    ignore_unconfirmed: false,
-                                                    same_editor_tab: false,
-                                                },
-                                            },
-                                        );
-                                    },
-                                )
                             },
                         )
                         .item_size_fixed(move || ui_line_height.get())
@@ -307,3 +493,38 @@ fn search_result(
     })
     .style(|s| s.size_pct(100.0, 100.0))
 }
+
+/// The text shown for a match along with the range that is highlighted.
+///
+/// When a replacement is given the matched text is swapped for it, so the
+/// highlighted range is what the line will look like after replacing.
+fn match_display(
+    m: &SearchMatch,
+    replacement: Option<String>,
+    trim: bool,
+) -> (String, Range<usize>) {
+    let match_range = m.content_range();
+    let (mut content, mut range) = match replacement {
+        Some(new_text) if m.line_content.get(match_range.clone()).is_some() => {
+            let content = format!(
+                "{}{new_text}{}",
+                &m.line_content[..match_range.start],
+                &m.line_content[match_range.end..]
+            );
+            (
+                content,
+                match_range.start..match_range.start + new_text.len(),
+            )
+        }
+        _ => (m.line_content.clone(), match_range),
+    };
+    if trim {
+        let trimmed_start = content.len() - content.trim_start().len();
+        content = content.trim().to_string();
+        range = range.start.saturating_sub(trimmed_start)
+            ..range.end.saturating_sub(trimmed_start);
+    }
+    let prefix = format!("{}: ", m.line);
+    let range = range.start + prefix.len()..range.end + prefix.len();
+    (format!("{prefix}{content}"), range)
+}
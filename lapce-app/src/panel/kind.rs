@@ -1,12 +1,22 @@
 use serde::{Deserialize, Serialize};
-use strum_macros::EnumIter;
+use strum_macros::{EnumIter, IntoStaticStr};
 
 use super::{data::PanelOrder, position::PanelPosition};
 use crate::config::icon::LapceIcons;
 
 #[derive(
-    Clone, Copy, PartialEq, Serialize, Deserialize, Hash, Eq, Debug, EnumIter,
+    Clone,
+    Copy,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    Hash,
+    Eq,
+    Debug,
+    EnumIter,
+    IntoStaticStr,
 )]
+#[strum(serialize_all = "snake_case")]
 pub enum PanelKind {
     Terminal,
     FileExplorer,
@@ -19,6 +29,7 @@ pub enum PanelKind {
     DocumentSymbol,
     References,
     Implementation,
+    GitHistory,
 }
 
 impl PanelKind {
@@ -35,6 +46,7 @@ pub fn svg_name(&self) -> &'static str {
             PanelKind::DocumentSymbol => LapceIcons::DOCUMENT_SYMBOL,
             PanelKind::References => LapceIcons::REFERENCES,
             PanelKind::Implementation => LapceIcons::IMPLEMENTATION,
+            PanelKind::GitHistory => LapceIcons::GIT_HISTORY,
         }
     }
 
@@ -61,6 +73,7 @@ pub fn default_position(&self) -> PanelPosition {
             PanelKind::DocumentSymbol => PanelPosition::RightTop,
             PanelKind::References => PanelPosition::BottomLeft,
             PanelKind::Implementation => PanelPosition::BottomLeft,
+            PanelKind::GitHistory => PanelPosition::LeftTop,
         }
     }
 }
@@ -2,6 +2,7 @@
 pub mod data;
 pub mod debug_view;
 pub mod document_symbol;
+pub mod git_history_view;
 pub mod global_search_view;
 pub mod implementation_view;
 pub mod kind;
@@ -127,13 +127,7 @@ fn file_view(
     });
 
     let full_path = path.clone();
-    let path = if let Some(workspace_path) = workspace.path.as_ref() {
-        path.strip_prefix(workspace_path)
-            .unwrap_or(&full_path)
-            .to_path_buf()
-    } else {
-        path
-    };
+    let path = workspace.relative_path(&path);
     let style_path = path.clone();
 
     let icon = match severity {
@@ -11,16 +11,15 @@
     views::{
         container, dyn_stack,
         editor::view::{cursor_caret, LineRegion},
-        label, scroll, stack, svg, text, Decorators,
+        label, scroll, stack, svg, Decorators,
     },
     View,
 };
 use lapce_core::buffer::rope_text::RopeText;
-use lapce_rpc::source_control::FileDiff;
+use lapce_rpc::source_control::{FileDiff, GitStash};
 
 use super::{
-    data::PanelSection, kind::PanelKind, position::PanelPosition,
-    view::foldable_panel_section,
+    data::PanelSection, kind::PanelKind, position::PanelPosition, view::PanelBuilder,
 };
 use crate::{
     command::{CommandKind, InternalCommand, LapceCommand, LapceWorkbenchCommand},
@@ -33,7 +32,7 @@
 
 pub fn source_control_panel(
     window_tab_data: Rc<WindowTabData>,
-    _position: PanelPosition,
+    position: PanelPosition,
 ) -> impl View {
     let config = window_tab_data.common.config;
     let source_control = window_tab_data.source_control.clone();
@@ -149,44 +148,83 @@ pub fn source_control_panel(
                     .border_color(config.color(LapceColor::LAPCE_BORDER))
                     .background(config.color(LapceColor::EDITOR_BACKGROUND))
             }),
+            {
+                let amend = source_control.amend;
+                stack((
+                    checkbox(move || amend.get(), config),
+                    label(|| "Amend Last Commit".to_string())
+                        .style(|s| s.margin_left(6.0).selectable(false)),
+                ))
+                .on_click_stop(move |_| {
+                    amend.update(|amend| *amend = !*amend);
+                })
+                .style(|s| {
+                    s.margin_top(10.0)
+                        .items_center()
+                        .cursor(CursorStyle::Pointer)
+                })
+            },
             {
                 let source_control = source_control.clone();
-                label(|| "Commit".to_string())
-                    .on_click_stop(move |_| {
-                        source_control.commit();
-                    })
-                    .style(move |s| {
-                        let config = config.get();
-                        s.margin_top(10.0)
-                            .line_height(1.6)
-                            .width_pct(100.0)
-                            .justify_center()
-                            .border(1.0)
-                            .border_radius(6.0)
-                            .border_color(config.color(LapceColor::LAPCE_BORDER))
-                            .hover(|s| {
-                                s.cursor(CursorStyle::Pointer).background(
-                                    config
-                                        .color(LapceColor::PANEL_HOVERED_BACKGROUND),
-                                )
-                            })
-                            .active(|s| {
-                                s.background(config.color(
+                let amend = source_control.amend;
+                label(move || {
+                    if amend.get() {
+                        "Amend Commit".to_string()
+                    } else {
+                        "Commit".to_string()
+                    }
+                })
+                .on_click_stop(move |_| {
+                    source_control.commit();
+                })
+                .style(move |s| {
+                    let config = config.get();
+                    s.margin_top(10.0)
+                        .line_height(1.6)
+                        .width_pct(100.0)
+                        .justify_center()
+                        .border(1.0)
+                        .border_radius(6.0)
+                        .border_color(config.color(LapceColor::LAPCE_BORDER))
+                        .hover(|s| {
+                            s.cursor(CursorStyle::Pointer).background(
+                                config.color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                            )
+                        })
+                        .active(|s| {
+                            s.background(
+                                config.color(
                                     LapceColor::PANEL_HOVERED_ACTIVE_BACKGROUND,
-                                ))
-                            })
-                            .selectable(false)
-                    })
+                                ),
+                            )
+                        })
+                        .selectable(false)
+                })
             },
+            remote_buttons(&source_control),
+            branch_buttons(&source_control),
         ))
         .style(|s| s.flex_col().width_pct(100.0).padding(10.0)),
-        foldable_panel_section(
-            text("Changes"),
-            file_diffs_view(source_control),
-            window_tab_data.panel.section_open(PanelSection::Changes),
-            config,
-        )
-        .style(|s| s.flex_col().size_pct(100.0, 100.0)),
+        PanelBuilder::new(config, position)
+            .add(
+                "Staged Changes",
+                file_diffs_view(source_control.clone(), true),
+                window_tab_data
+                    .panel
+                    .section_open(PanelSection::StagedChanges),
+            )
+            .add(
+                "Changes",
+                file_diffs_view(source_control.clone(), false),
+                window_tab_data.panel.section_open(PanelSection::Changes),
+            )
+            .add(
+                "Stashes",
+                stashes_view(source_control),
+                window_tab_data.panel.section_open(PanelSection::Stashes),
+            )
+            .build()
+            .style(|s| s.flex_grow(1.0).flex_basis(0.0)),
     ))
     .on_event_stop(EventListener::PointerDown, move |_| {
         if focus.get_untracked() != Focus::Panel(PanelKind::SourceControl) {
@@ -197,8 +235,108 @@ pub fn source_control_panel(
     .debug_name("Source Control Panel")
 }
 
-fn file_diffs_view(source_control: SourceControlData) -> impl View {
-    let file_diffs = source_control.file_diffs;
+/// The fetch, pull and push buttons, with how many commits there are to pull and
+/// to push
+fn remote_buttons(source_control: &SourceControlData) -> impl View {
+    stack((
+        command_button(
+            source_control,
+            "Fetch",
+            LapceWorkbenchCommand::SourceControlFetch,
+            |_| None,
+        ),
+        command_button(
+            source_control,
+            "Pull",
+            LapceWorkbenchCommand::SourceControlPull,
+            |(_, behind)| (behind > 0).then(|| format!("{behind}↓")),
+        ),
+        command_button(
+            source_control,
+            "Push",
+            LapceWorkbenchCommand::SourceControlPush,
+            |(ahead, _)| (ahead > 0).then(|| format!("{ahead}↑")),
+        ),
+    ))
+    .style(|s| s.margin_top(10.0).width_pct(100.0).gap(6.0))
+}
+
+/// The buttons to create a branch, merge one and stash the changes
+fn branch_buttons(source_control: &SourceControlData) -> impl View {
+    stack((
+        command_button(
+            source_control,
+            "Branch",
+            LapceWorkbenchCommand::SourceControlCreateBranch,
+            |_| None,
+        ),
+        command_button(
+            source_control,
+            "Merge",
+            LapceWorkbenchCommand::SourceControlMergeBranch,
+            |_| None,
+        ),
+        command_button(
+            source_control,
+            "Stash",
+            LapceWorkbenchCommand::SourceControlStash,
+            |_| None,
+        ),
+    ))
+    .style(|s| s.margin_top(6.0).width_pct(100.0).gap(6.0))
+}
+
+/// A button running `cmd`, with the count of commits given by `count`
+fn command_button(
+    source_control: &SourceControlData,
+    name: &'static str,
+    cmd: LapceWorkbenchCommand,
+    count: fn((usize, usize)) -> Option<String>,
+) -> impl View {
+    let config = source_control.common.config;
+    let lapce_command = source_control.common.lapce_command;
+    let ahead_behind = source_control.ahead_behind;
+    label(move || match ahead_behind.get().and_then(count) {
+        Some(count) => format!("{name} {count}"),
+        None => name.to_string(),
+    })
+    .on_click_stop(move |_| {
+        lapce_command.send(LapceCommand {
+            kind: CommandKind::Workbench(cmd.clone()),
+            data: None,
+        });
+    })
+    .style(move |s| {
+        let config = config.get();
+        s.flex_grow(1.0)
+            .flex_basis(0.0)
+            .line_height(1.6)
+            .justify_center()
+            .border(1.0)
+            .border_radius(6.0)
+            .border_color(config.color(LapceColor::LAPCE_BORDER))
+            .hover(|s| {
+                s.cursor(CursorStyle::Pointer)
+                    .background(config.color(LapceColor::PANEL_HOVERED_BACKGROUND))
+            })
+            .active(|s| {
+                s.background(
+                    config.color(LapceColor::PANEL_HOVERED_ACTIVE_BACKGROUND),
+                )
+            })
+            .selectable(false)
+    })
+}
+
+/// The files with changes in the working tree, or with `staged` the ones with
+/// changes staged in the index
+fn file_diffs_view(source_control: SourceControlData, staged: bool) -> impl View {
+    let file_diffs = if staged {
+        source_control.staged_diffs
+    } else {
+        source_control.file_diffs
+    };
+    let root_branches = source_control.root_branches;
     let config = source_control.common.config;
     let workspace = source_control.common.workspace.clone();
     let panel_rect = create_rw_signal(Rect::ZERO);
@@ -206,16 +344,20 @@ fn file_diffs_view(source_control: SourceControlData) -> impl View {
     let lapce_command = source_control.common.lapce_command;
     let internal_command = source_control.common.internal_command;
 
-    let view_fn = move |(path, (diff, checked)): (PathBuf, (FileDiff, bool))| {
+    let row_source_control = source_control.clone();
+
+    let view_fn = move |(path, diff): (PathBuf, FileDiff)| {
         let diff_for_style = diff.clone();
         let full_path = path.clone();
         let diff_for_menu = diff.clone();
+        let diff_for_stage = diff.clone();
+        let diff_for_click = diff.clone();
         let path_for_click = full_path.clone();
+        let source_control = row_source_control.clone();
+        let stage_source_control = source_control.clone();
 
-        let path = if let Some(workspace_path) = workspace.path.as_ref() {
-            path.strip_prefix(workspace_path)
-                .unwrap_or(&full_path)
-                .to_path_buf()
+        let path = if let Some(root) = workspace.root_of(&path) {
+            path.strip_prefix(root).unwrap_or(&full_path).to_path_buf()
         } else {
             path
         };
@@ -231,15 +373,28 @@ fn file_diffs_view(source_control: SourceControlData) -> impl View {
             .to_string();
         let style_path = path.clone();
         stack((
-            checkbox(move || checked, config)
-                .style(|s| s.hover(|s| s.cursor(CursorStyle::Pointer)))
-                .on_click_stop(move |_| {
-                    file_diffs.update(|diffs| {
-                        if let Some((_, checked)) = diffs.get_mut(&full_path) {
-                            *checked = !*checked;
-                        }
-                    });
-                }),
+            svg(move || {
+                config.get().ui_svg(if staged {
+                    LapceIcons::SCM_CHANGE_REMOVE
+                } else {
+                    LapceIcons::SCM_CHANGE_ADD
+                })
+            })
+            .on_click_stop(move |_| {
+                if staged {
+                    stage_source_control.unstage(&[diff_for_stage.clone()]);
+                } else {
+                    stage_source_control.stage(&[diff_for_stage.clone()]);
+                }
+            })
+            .style(move |s| {
+                let config = config.get();
+                let size = config.ui.icon_size() as f32;
+                s.min_width(size)
+                    .size(size, size)
+                    .color(config.color(LapceColor::LAPCE_ICON_ACTIVE))
+                    .hover(|s| s.cursor(CursorStyle::Pointer))
+            }),
             svg(move || config.get().file_svg(&path).0).style(move |s| {
                 let config = config.get();
                 let size = config.ui.icon_size() as f32;
@@ -281,6 +436,7 @@ fn file_diffs_view(source_control: SourceControlData) -> impl View {
                         FileDiff::Added(_) => LapceIcons::SCM_DIFF_ADDED,
                         FileDiff::Deleted(_) => LapceIcons::SCM_DIFF_REMOVED,
                         FileDiff::Renamed(_, _) => LapceIcons::SCM_DIFF_RENAMED,
+                        FileDiff::Conflicted(_) => LapceIcons::SCM_DIFF_CONFLICTED,
                     };
                     config.get().ui_svg(svg)
                 })
@@ -294,6 +450,9 @@ fn file_diffs_view(source_control: SourceControlData) -> impl View {
                         FileDiff::Renamed(_, _) => {
                             LapceColor::SOURCE_CONTROL_MODIFIED
                         }
+                        FileDiff::Conflicted(_) => {
+                            LapceColor::SOURCE_CONTROL_CONFLICTED
+                        }
                     };
                     let color = config.color(color);
                     s.min_width(size).size(size, size).color(color)
@@ -308,29 +467,62 @@ fn file_diffs_view(source_control: SourceControlData) -> impl View {
             }),
         ))
         .on_click_stop(move |_| {
-            internal_command.send(InternalCommand::OpenFileChanges {
-                path: path_for_click.clone(),
-            });
+            if let FileDiff::Conflicted(path) = &diff_for_click {
+                internal_command
+                    .send(InternalCommand::OpenMergeEditor { path: path.clone() });
+            } else {
+                internal_command.send(InternalCommand::OpenFileChanges {
+                    path: path_for_click.clone(),
+                    staged,
+                });
+            }
         })
         .on_event_cont(EventListener::PointerDown, move |event| {
-            let diff_for_menu = diff_for_menu.clone();
-
-            let discard = move || {
-                lapce_command.send(LapceCommand {
-                    kind: CommandKind::Workbench(
-                        LapceWorkbenchCommand::SourceControlDiscardTargetFileChanges,
-                    ),
-                    data: Some(serde_json::json!(diff_for_menu.clone())),
-                });
+            let Event::PointerDown(pointer_event) = event else {
+                return;
             };
-
-            if let Event::PointerDown(pointer_event) = event {
-                if pointer_event.button.is_secondary() {
-                    let menu = Menu::new("")
-                        .entry(MenuItem::new("Discard Changes").action(discard));
-                    show_context_menu(menu, None);
-                }
+            if !pointer_event.button.is_secondary() {
+                return;
             }
+
+            let diff = diff_for_menu.clone();
+            let menu = if let FileDiff::Conflicted(path) = &diff {
+                let source_control = source_control.clone();
+                let path = path.clone();
+                Menu::new("")
+                    .entry(MenuItem::new("Open in Merge Editor").action(move || {
+                        internal_command.send(InternalCommand::OpenMergeEditor {
+                            path: path.clone(),
+                        });
+                    }))
+                    .entry(
+                        MenuItem::new("Mark as Resolved")
+                            .action(move || source_control.stage(&[diff.clone()])),
+                    )
+            } else if staged {
+                let source_control = source_control.clone();
+                Menu::new("").entry(
+                    MenuItem::new("Unstage Changes")
+                        .action(move || source_control.unstage(&[diff.clone()])),
+                )
+            } else {
+                let source_control = source_control.clone();
+                let stage_diff = diff.clone();
+                let discard = move || {
+                    lapce_command.send(LapceCommand {
+                        kind: CommandKind::Workbench(
+                            LapceWorkbenchCommand::SourceControlDiscardTargetFileChanges,
+                        ),
+                        data: Some(serde_json::json!(diff.clone())),
+                    });
+                };
+                Menu::new("")
+                    .entry(MenuItem::new("Stage Changes").action(move || {
+                        source_control.stage(&[stage_diff.clone()])
+                    }))
+                    .entry(MenuItem::new("Discard Changes").action(discard))
+            };
+            show_context_menu(menu, None);
         })
         .style(move |s| {
             let config = config.get();
@@ -345,14 +537,50 @@ fn file_diffs_view(source_control: SourceControlData) -> impl View {
         })
     };
 
+    // Changes are grouped by the workspace root folder they belong to, with a
+    // header showing the root and its branch in multi-root workspaces
+    let workspace = source_control.common.workspace.clone();
+    let root_view = move |(root, branch): (PathBuf, String)| {
+        let workspace = workspace.clone();
+        let name = root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| root.to_string_lossy().to_string());
+        stack((
+            label(move || format!("{name} ({branch})")).style(move |s| {
+                s.padding_horiz(10.0)
+                    .color(config.get().color(LapceColor::EDITOR_DIM))
+                    .selectable(false)
+                    .apply_if(root_branches.with(|roots| roots.len() <= 1), |s| {
+                        s.hide()
+                    })
+            }),
+            dyn_stack(
+                move || {
+                    file_diffs.with(|file_diffs| {
+                        file_diffs
+                            .iter()
+                            .filter(|(path, _)| {
+                                workspace.root_of(path).as_ref() == Some(&root)
+                            })
+                            .map(|(path, diff)| (path.clone(), diff.clone()))
+                            .collect::<Vec<_>>()
+                    })
+                },
+                |(path, diff)| (path.to_path_buf(), diff.clone()),
+                view_fn.clone(),
+            )
+            .style(|s| s.flex_col().width_pct(100.0)),
+        ))
+        .style(|s| s.flex_col().width_pct(100.0))
+    };
+
     container({
         scroll({
             dyn_stack(
-                move || file_diffs.get(),
-                |(path, (diff, checked))| {
-                    (path.to_path_buf(), diff.clone(), *checked)
-                },
-                view_fn,
+                move || root_branches.get(),
+                |(root, branch)| (root.clone(), branch.clone()),
+                root_view,
             )
             .style(|s| s.line_height(1.6).flex_col().width_pct(100.0))
         })
@@ -363,3 +591,76 @@ fn file_diffs_view(source_control: SourceControlData) -> impl View {
     })
     .style(|s| s.size_pct(100.0, 100.0))
 }
+
+/// The saved stashes, which can be applied, popped or dropped from their
+/// context menu
+fn stashes_view(source_control: SourceControlData) -> impl View {
+    let config = source_control.common.config;
+    let proxy = source_control.common.proxy.clone();
+    let stashes = source_control.stashes;
+    let root = source_control.root;
+
+    let stash_view = move |stash: GitStash| {
+        let proxy = proxy.clone();
+        let index = stash.index;
+        stack((
+            label(move || format!("stash@{{{index}}}")).style(move |s| {
+                s.margin_right(6.0)
+                    .color(config.get().color(LapceColor::EDITOR_DIM))
+                    .selectable(false)
+            }),
+            label(move || stash.message.clone()).style(|s| {
+                s.text_ellipsis()
+                    .flex_grow(1.0)
+                    .flex_basis(0.0)
+                    .min_width(0.0)
+                    .selectable(false)
+            }),
+        ))
+        .on_event_cont(EventListener::PointerDown, move |event| {
+            let Event::PointerDown(pointer_event) = event else {
+                return;
+            };
+            if !pointer_event.button.is_secondary() {
+                return;
+            }
+
+            let root = root.get_untracked();
+            let apply_proxy = proxy.clone();
+            let apply_root = root.clone();
+            let apply = move || {
+                apply_proxy.git_stash_apply(apply_root.clone(), index, false)
+            };
+            let pop_proxy = proxy.clone();
+            let pop_root = root.clone();
+            let pop =
+                move || pop_proxy.git_stash_apply(pop_root.clone(), index, true);
+            let drop_proxy = proxy.clone();
+            let drop = move || drop_proxy.git_stash_drop(root.clone(), index);
+            let menu = Menu::new("")
+                .entry(MenuItem::new("Apply Stash").action(apply))
+                .entry(MenuItem::new("Pop Stash").action(pop))
+                .entry(MenuItem::new("Drop Stash").action(drop));
+            show_context_menu(menu, None);
+        })
+        .style(move |s| {
+            s.padding_horiz(10.0)
+                .width_pct(100.0)
+                .items_center()
+                .hover(|s| {
+                    s.background(
+                        config.get().color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                    )
+                })
+        })
+    };
+
+    container({
+        scroll({
+            dyn_stack(move || stashes.get(), |stash| stash.clone(), stash_view)
+                .style(|s| s.line_height(1.6).flex_col().width_pct(100.0))
+        })
+        .style(|s| s.absolute().size_pct(100.0, 100.0))
+    })
+    .style(|s| s.size_pct(100.0, 100.0))
+}
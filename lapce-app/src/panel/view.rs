@@ -18,6 +18,7 @@
 
 use super::{
     debug_view::debug_panel,
+    git_history_view::git_history_panel,
     global_search_view::global_search_panel,
     kind::PanelKind,
     plugin_view::plugin_panel,
@@ -507,6 +508,9 @@ fn panel_view(
                     implementation_panel(window_tab_data.clone(), position)
                         .into_any()
                 }
+                PanelKind::GitHistory => {
+                    git_history_panel(window_tab_data.clone(), position).into_any()
+                }
             };
             view.style(|s| s.size_pct(100.0, 100.0))
         },
@@ -563,6 +567,7 @@ fn panel_picker(
                 PanelKind::DocumentSymbol => "Document Symbol",
                 PanelKind::References => "References",
                 PanelKind::Implementation => "Implementation",
+                PanelKind::GitHistory => "Git History",
             };
             let icon = p.svg_name();
             let is_active = {
@@ -8,10 +8,11 @@
 use floem::{ext_event::create_signal_from_channel, reactive::ReadSignal};
 use lapce_proxy::dispatch::Dispatcher;
 use lapce_rpc::{
-    core::{CoreHandler, CoreNotification, CoreRpcHandler},
-    plugin::VoltID,
+    core::{CoreHandler, CoreNotification, CoreRequest, CoreRpcHandler},
+    plugin::{LspServerConfig, VoltID},
     proxy::{ProxyRpcHandler, ProxyStatus},
     terminal::TermId,
+    RequestId,
 };
 use tracing::error;
 
@@ -28,6 +29,7 @@
 
 pub struct Proxy {
     pub tx: Sender<CoreNotification>,
+    pub request_tx: Sender<(RequestId, CoreRequest)>,
     pub term_tx: Sender<(TermId, TermEvent)>,
 }
 
@@ -36,6 +38,8 @@ pub struct ProxyData {
     pub proxy_rpc: ProxyRpcHandler,
     pub core_rpc: CoreRpcHandler,
     pub notification: ReadSignal<Option<CoreNotification>>,
+    /// Requests of the proxy, which waits for `core_rpc.handle_response`
+    pub request: ReadSignal<Option<(RequestId, CoreRequest)>>,
 }
 
 impl ProxyData {
@@ -50,6 +54,7 @@ pub fn new_proxy(
     disabled_volts: Vec<VoltID>,
     extra_plugin_paths: Vec<PathBuf>,
     plugin_configurations: HashMap<String, HashMap<String, serde_json::Value>>,
+    lsp_servers: HashMap<String, LspServerConfig>,
     term_tx: Sender<(TermId, TermEvent)>,
 ) -> ProxyData {
     let proxy_rpc = ProxyRpcHandler::new();
@@ -66,9 +71,11 @@ pub fn new_proxy(
                 });
                 proxy_rpc.initialize(
                     workspace.path.clone(),
+                    workspace.roots(),
                     disabled_volts,
                     extra_plugin_paths,
                     plugin_configurations,
+                    lsp_servers,
                     1,
                     1,
                 );
@@ -113,12 +120,17 @@ pub fn new_proxy(
     }
 
     let (tx, rx) = std::sync::mpsc::channel();
+    let (request_tx, request_rx) = std::sync::mpsc::channel();
     {
         let core_rpc = core_rpc.clone();
         std::thread::Builder::new()
             .name("CoreRpcHandler".to_owned())
             .spawn(move || {
-                let mut proxy = Proxy { tx, term_tx };
+                let mut proxy = Proxy {
+                    tx,
+                    request_tx,
+                    term_tx,
+                };
                 core_rpc.mainloop(&mut proxy);
                 core_rpc.notification(CoreNotification::ProxyStatus {
                     status: ProxyStatus::Disconnected,
@@ -128,11 +140,13 @@ pub fn new_proxy(
     };
 
     let notification = create_signal_from_channel(rx);
+    let request = create_signal_from_channel(request_rx);
 
     ProxyData {
         proxy_rpc,
         core_rpc,
         notification,
+        request,
     }
 }
 
@@ -152,11 +166,10 @@ fn handle_notification(&mut self, rpc: lapce_rpc::core::CoreNotification) {
         }
     }
 
-    fn handle_request(
-        &mut self,
-        _id: lapce_rpc::RequestId,
-        _rpc: lapce_rpc::core::CoreRequest,
-    ) {
+    fn handle_request(&mut self, id: RequestId, rpc: CoreRequest) {
+        if let Err(err) = self.request_tx.send((id, rpc)) {
+            tracing::error!("{:?}", err);
+        }
     }
 }
 
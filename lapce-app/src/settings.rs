@@ -20,7 +20,7 @@
 };
 use indexmap::IndexMap;
 use inflector::Inflector;
-use lapce_core::{buffer::rope_text::RopeText, mode::Mode};
+use lapce_core::{buffer::rope_text::RopeText, language::LapceLanguage, mode::Mode};
 use lapce_rpc::plugin::VoltID;
 use lapce_xi_rope::Rope;
 use serde::Serialize;
@@ -75,6 +75,8 @@ struct SettingsItem {
     filter_text: String,
     value: SettingsValue,
     serde_value: Value,
+    /// The value that `[language.<name>.editor]` sets for the current language
+    language_override: Option<String>,
     pos: RwSignal<Point>,
     size: RwSignal<Size>,
     // this is only the header that give an visual sepeartion between different type of settings
@@ -132,6 +134,7 @@ impl SettingsData {
     pub fn new(
         cx: Scope,
         installed_plugin: RwSignal<IndexMap<VoltID, InstalledVoltData>>,
+        language: ReadSignal<Option<LapceLanguage>>,
         common: Rc<CommonData>,
     ) -> Self {
         fn into_settings_map(
@@ -151,6 +154,12 @@ fn into_settings_map(
         let kinds = cx.create_rw_signal(im::Vector::new());
         cx.create_effect(move |_| {
             let config = config.get();
+            let language = language.get();
+            let language_overrides = language.and_then(|language| {
+                config
+                    .language_editor_overrides(language)
+                    .map(|overrides| (language, overrides))
+            });
 
             let mut data_items = im::Vector::new();
             let mut data_kinds = im::Vector::new();
@@ -190,6 +199,7 @@ fn into_settings_map(
                     description: "".to_string(),
                     value: SettingsValue::Empty,
                     serde_value: Value::Null,
+                    language_override: None,
                     pos,
                     size: cx.create_rw_signal(Size::ZERO),
                     header: true,
@@ -211,6 +221,23 @@ fn into_settings_map(
                         (SettingsValue::from(value.clone()), value)
                     };
 
+                    let language_override = if kind == "Editor" {
+                        language_overrides.and_then(|(language, overrides)| {
+                            let value = match overrides.get(&field)? {
+                                Value::String(s) => s.clone(),
+                                value => value.to_string(),
+                            };
+                            Some(format!(
+                                "Overridden for {}: {value}",
+                                language.name()
+                            ))
+                        })
+                    } else {
+                        None
+                    };
+
+                    let has_language_override = language_override.is_some();
+
                     let name = format!(
                         "{kind}: {}",
                         name.replace('_', " ").to_title_case()
@@ -229,9 +256,14 @@ fn into_settings_map(
                         pos: cx.create_rw_signal(Point::ZERO),
                         size: cx.create_rw_signal(Size::ZERO),
                         serde_value,
+                        language_override,
                         header: false,
                     });
                     item_height_accum += 50.0;
+                    if has_language_override {
+                        // The line that shows the overridden value
+                        item_height_accum += config.ui.font_size() as f64 * 1.8;
+                    }
                 }
             }
 
@@ -256,6 +288,7 @@ fn into_settings_map(
                         description: "".to_string(),
                         value: SettingsValue::Empty,
                         serde_value: Value::Null,
+                        language_override: None,
                         pos,
                         size: cx.create_rw_signal(Size::ZERO),
                         header: true,
@@ -295,6 +328,7 @@ fn into_settings_map(
                                 pos: cx.create_rw_signal(Point::ZERO),
                                 size: cx.create_rw_signal(Size::ZERO),
                                 serde_value: Value::Null,
+                                language_override: None,
                                 header: false,
                             };
                             local_items.push(item);
@@ -323,13 +357,15 @@ fn into_settings_map(
 
 pub fn settings_view(
     installed_plugins: RwSignal<IndexMap<VoltID, InstalledVoltData>>,
+    language: ReadSignal<Option<LapceLanguage>>,
     editors: Editors,
     common: Rc<CommonData>,
 ) -> impl View {
     let config = common.config;
 
     let cx = Scope::current();
-    let settings_data = SettingsData::new(cx, installed_plugins, common.clone());
+    let settings_data =
+        SettingsData::new(cx, installed_plugins, language, common.clone());
     let view_settings_data = settings_data.clone();
     let plugin_kinds = settings_data.plugin_kinds;
 
@@ -509,6 +545,7 @@ pub fn settings_view(
                                 item.kind.clone(),
                                 item.name.clone(),
                                 item.serde_value.clone(),
+                                item.language_override.clone(),
                             )
                         },
                         move |item| {
@@ -682,6 +719,9 @@ fn settings_item_view(
         }
     };
 
+    let language_override = item.language_override.clone();
+    let has_language_override = language_override.is_some();
+
     stack((
         label(move || item.name.clone()).style(move |s| {
             s.font_bold()
@@ -691,6 +731,15 @@ fn settings_item_view(
                 .line_height(1.8)
                 .font_size(config.get().ui.font_size() as f32 + 1.0)
         }),
+        label(move || language_override.clone().unwrap_or_default()).style(
+            move |s| {
+                s.min_width(0.0)
+                    .max_width_pct(100.0)
+                    .line_height(1.8)
+                    .color(config.get().color(LapceColor::EDITOR_DIM))
+                    .apply_if(!has_language_override, |s| s.hide())
+            },
+        ),
         stack((
             label(move || t!(&item.description.clone().to_string())).style(move |s| {
                 s.min_width(0.0)
@@ -1,15 +1,69 @@
 use core::fmt;
-use std::{fmt::Display, str::FromStr};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Write},
+    hash::{BuildHasher, Hasher},
+    path::Path,
+    str::FromStr,
+};
 
 use anyhow::Error;
+use chrono::{DateTime, Local};
+use indexmap::IndexMap;
+use lapce_core::{
+    language::LapceLanguage,
+    selection::{SelRegion, Selection},
+};
 use once_cell::sync::Lazy;
-use regex::Regex;
+use regex::{Captures, Regex, RegexBuilder};
+use serde::Deserialize;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum SnippetElement {
     Text(String),
     PlaceHolder(usize, Vec<SnippetElement>),
     Tabstop(usize),
+    /// `${1|one,two|}`, inserted as its first choice
+    Choice(usize, Vec<String>),
+    /// `$name`, `${name:default}` or `${name/regex/format/options}`, which is
+    /// replaced by its value before the snippet is inserted
+    Variable {
+        name: String,
+        default: Vec<SnippetElement>,
+        transform: Option<Transform>,
+    },
+    /// `${1/regex/format/options}`, which mirrors a tabstop with a transform
+    /// applied
+    TabstopTransform(usize, Transform),
+}
+
+/// The regex replacement of a variable or tabstop transform
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    pub regex: String,
+    pub format: Vec<FormatElement>,
+    /// Regex flags, `g` to replace every match rather than the first
+    pub options: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatElement {
+    Text(String),
+    /// `$1`, `${1}` or `${1:/upcase}`, a capture group with an optional case
+    /// change
+    Group(usize, Option<CaseChange>),
+    /// `${1:+if}`, `${1:?if:else}`, `${1:-else}` or `${1:else}`. Without an
+    /// `if` text, a matched group inserts itself.
+    Conditional(usize, Option<String>, Option<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseChange {
+    Upcase,
+    Downcase,
+    Capitalize,
+    Camelcase,
+    Pascalcase,
 }
 
 impl Display for SnippetElement {
@@ -26,6 +80,194 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                 f.write_str("}")
             }
             SnippetElement::Tabstop(tab) => write!(f, "${tab}"),
+            SnippetElement::Choice(tab, choices) => {
+                write!(f, "${{{tab}|")?;
+                for (i, choice) in choices.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_escaped(f, choice, &[',', '|'])?;
+                }
+                f.write_str("|}")
+            }
+            SnippetElement::Variable {
+                name,
+                default,
+                transform,
+            } => {
+                write!(f, "${{{name}")?;
+                if let Some(transform) = transform {
+                    fmt::Display::fmt(transform, f)?;
+                } else if !default.is_empty() {
+                    f.write_char(':')?;
+                    for child_snippet_elm in default {
+                        fmt::Display::fmt(child_snippet_elm, f)?;
+                    }
+                }
+                f.write_str("}")
+            }
+            SnippetElement::TabstopTransform(tab, transform) => {
+                write!(f, "${{{tab}{transform}}}")
+            }
+        }
+    }
+}
+
+impl Display for Transform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_char('/')?;
+        f.write_str(&self.regex.replace('/', "\\/"))?;
+        f.write_char('/')?;
+        for element in &self.format {
+            match element {
+                FormatElement::Text(text) => write_escaped(f, text, &['$', '/'])?,
+                FormatElement::Group(group, None) => write!(f, "${{{group}}}")?,
+                FormatElement::Group(group, Some(case)) => {
+                    write!(f, "${{{group}:/{}}}", case.name())?
+                }
+                FormatElement::Conditional(group, if_text, else_text) => {
+                    write!(f, "${{{group}:")?;
+                    match (if_text, else_text) {
+                        (Some(if_text), Some(else_text)) => {
+                            f.write_char('?')?;
+                            write_escaped(f, if_text, &[':', '}'])?;
+                            f.write_char(':')?;
+                            write_escaped(f, else_text, &['}'])?;
+                        }
+                        (Some(if_text), None) => {
+                            f.write_char('+')?;
+                            write_escaped(f, if_text, &['}'])?;
+                        }
+                        (None, else_text) => {
+                            f.write_char('-')?;
+                            write_escaped(
+                                f,
+                                else_text.as_deref().unwrap_or(""),
+                                &['}'],
+                            )?;
+                        }
+                    }
+                    f.write_char('}')?;
+                }
+            }
+        }
+        f.write_char('/')?;
+        f.write_str(&self.options)
+    }
+}
+
+/// Write `text` with backslashes before backslashes and the chars of `escaped`
+fn write_escaped(
+    f: &mut std::fmt::Formatter<'_>,
+    text: &str,
+    escaped: &[char],
+) -> fmt::Result {
+    for c in text.chars() {
+        if c == '\\' || escaped.contains(&c) {
+            f.write_char('\\')?;
+        }
+        f.write_char(c)?;
+    }
+    Ok(())
+}
+
+impl Transform {
+    /// Replace the first match of the regex in `text`, or every match with the
+    /// `g` option. An invalid regex leaves the text unchanged.
+    pub fn apply(&self, text: &str) -> String {
+        let Ok(regex) = RegexBuilder::new(&self.regex)
+            .case_insensitive(self.options.contains('i'))
+            .multi_line(self.options.contains('m'))
+            .dot_matches_new_line(self.options.contains('s'))
+            .build()
+        else {
+            return text.to_string();
+        };
+        let replacement = |caps: &Captures| {
+            self.format
+                .iter()
+                .map(|element| element.expand(caps))
+                .collect::<String>()
+        };
+        if self.options.contains('g') {
+            regex.replace_all(text, replacement).into_owned()
+        } else {
+            regex.replace(text, replacement).into_owned()
+        }
+    }
+}
+
+impl FormatElement {
+    fn expand(&self, caps: &Captures) -> String {
+        match self {
+            FormatElement::Text(text) => text.clone(),
+            FormatElement::Group(group, case) => {
+                let text = caps.get(*group).map(|m| m.as_str()).unwrap_or("");
+                match case {
+                    Some(case) => case.apply(text),
+                    None => text.to_string(),
+                }
+            }
+            FormatElement::Conditional(group, if_text, else_text) => {
+                match caps.get(*group).filter(|m| !m.as_str().is_empty()) {
+                    Some(m) => if_text.clone().unwrap_or_else(|| m.as_str().into()),
+                    None => else_text.clone().unwrap_or_default(),
+                }
+            }
+        }
+    }
+}
+
+impl CaseChange {
+    fn from_name(name: &str) -> Option<CaseChange> {
+        Some(match name {
+            "upcase" => CaseChange::Upcase,
+            "downcase" => CaseChange::Downcase,
+            "capitalize" => CaseChange::Capitalize,
+            "camelcase" => CaseChange::Camelcase,
+            "pascalcase" => CaseChange::Pascalcase,
+            _ => return None,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            CaseChange::Upcase => "upcase",
+            CaseChange::Downcase => "downcase",
+            CaseChange::Capitalize => "capitalize",
+            CaseChange::Camelcase => "camelcase",
+            CaseChange::Pascalcase => "pascalcase",
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        fn capitalize(word: &str) -> String {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        }
+
+        match self {
+            CaseChange::Upcase => text.to_uppercase(),
+            CaseChange::Downcase => text.to_lowercase(),
+            CaseChange::Capitalize => capitalize(text),
+            CaseChange::Camelcase | CaseChange::Pascalcase => {
+                let words = text
+                    .split(|c: char| !c.is_alphanumeric())
+                    .filter(|word| !word.is_empty());
+                words
+                    .enumerate()
+                    .map(|(i, word)| {
+                        if i == 0 && *self == CaseChange::Camelcase {
+                            word.to_lowercase()
+                        } else {
+                            capitalize(&word.to_lowercase())
+                        }
+                    })
+                    .collect()
+            }
         }
     }
 }
@@ -37,7 +279,13 @@ pub fn len(&self) -> usize {
             SnippetElement::PlaceHolder(_, elements) => {
                 elements.iter().map(|e| e.len()).sum()
             }
-            SnippetElement::Tabstop(_) => 0,
+            SnippetElement::Tabstop(_) | SnippetElement::TabstopTransform(..) => 0,
+            SnippetElement::Choice(_, choices) => {
+                choices.first().map(|c| c.len()).unwrap_or(0)
+            }
+            SnippetElement::Variable { default, .. } => {
+                default.iter().map(|e| e.len()).sum()
+            }
         }
     }
 
@@ -57,14 +305,22 @@ pub fn text(&self) -> String {
     fn write_text_to<Buffer: fmt::Write>(&self, buf: &mut Buffer) -> fmt::Result {
         match self {
             SnippetElement::Text(text) => buf.write_str(text),
-            SnippetElement::PlaceHolder(_, elements) => {
+            SnippetElement::PlaceHolder(_, elements)
+            | SnippetElement::Variable {
+                default: elements, ..
+            } => {
                 for child_snippet_elm in elements {
                     // call ourselves recursively
                     child_snippet_elm.write_text_to(buf)?;
                 }
                 fmt::Result::Ok(())
             }
-            SnippetElement::Tabstop(_) => fmt::Result::Ok(()),
+            SnippetElement::Choice(_, choices) => {
+                buf.write_str(choices.first().map(|c| c.as_str()).unwrap_or(""))
+            }
+            SnippetElement::Tabstop(_) | SnippetElement::TabstopTransform(..) => {
+                fmt::Result::Ok(())
+            }
         }
     }
 }
@@ -112,11 +368,29 @@ fn extract_elements(
             } else if let Some((ele, end)) = Self::extract_placeholder(s, pos) {
                 elements.push(ele);
                 pos = end;
+            } else if let Some((ele, end)) = Self::extract_choice(s, pos) {
+                elements.push(ele);
+                pos = end;
+            } else if let Some((ele, end)) = Self::extract_variable(s, pos) {
+                elements.push(ele);
+                pos = end;
+            } else if let Some((ele, end)) = Self::extract_tabstop_transform(s, pos)
+            {
+                elements.push(ele);
+                pos = end;
             } else if let Some((ele, end)) =
                 Self::extract_text(s, pos, escs, loose_escs)
             {
                 elements.push(ele);
                 pos = end;
+            } else if let Some(c @ ('$' | '\\')) = s[pos..].chars().next() {
+                // A `$` or `\` that starts nothing is inserted as is
+                if let Some(SnippetElement::Text(text)) = elements.last_mut() {
+                    text.push(c);
+                } else {
+                    elements.push(SnippetElement::Text(c.to_string()));
+                }
+                pos += 1;
             } else {
                 break;
             }
@@ -232,51 +506,696 @@ fn extract_text(
         Some((SnippetElement::Text(ele), end))
     }
 
+    /// Extract `${1|one,two|}`
+    fn extract_choice(s: &str, pos: usize) -> Option<(SnippetElement, usize)> {
+        static REGEX: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"^\$\{(\d+)\|").unwrap());
+
+        let caps = REGEX.captures(&s[pos..])?;
+        let tab = caps.get(1)?.as_str().parse::<usize>().ok()?;
+        let mut choices = Vec::new();
+        let mut pos = pos + caps.get(0)?.end();
+        loop {
+            let (choice, end) = text_until(s, pos, &[',', '|'])?;
+            choices.push(choice);
+            pos = end + 1;
+            if s[end..].starts_with('|') {
+                break;
+            }
+        }
+        if !s[pos..].starts_with('}') {
+            return None;
+        }
+        Some((SnippetElement::Choice(tab, choices), pos + 1))
+    }
+
+    /// Extract `$name`, `${name}`, `${name:default}` or
+    /// `${name/regex/format/options}`
+    fn extract_variable(s: &str, pos: usize) -> Option<(SnippetElement, usize)> {
+        static REGEX_FIRST: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"^\$([A-Za-z_][A-Za-z0-9_]*)").unwrap());
+        static REGEX_SECOND: Lazy<Regex> = Lazy::new(|| {
+            Regex::new(r"^\$\{([A-Za-z_][A-Za-z0-9_]*)([}:/])").unwrap()
+        });
+
+        let variable = |name: &str, default, transform| SnippetElement::Variable {
+            name: name.to_string(),
+            default,
+            transform,
+        };
+
+        if let Some(caps) = REGEX_FIRST.captures(&s[pos..]) {
+            let end = pos + caps.get(0)?.end();
+            return Some((variable(caps.get(1)?.as_str(), Vec::new(), None), end));
+        }
+
+        let caps = REGEX_SECOND.captures(&s[pos..])?;
+        let name = caps.get(1)?.as_str();
+        let end = pos + caps.get(0)?.end();
+        match caps.get(2)?.as_str() {
+            "}" => Some((variable(name, Vec::new(), None), end)),
+            ":" => {
+                let (default, end) =
+                    Self::extract_elements(s, end, &['$', '}', '\\'], &[]);
+                if !s[end..].starts_with('}') {
+                    return None;
+                }
+                Some((variable(name, default, None), end + 1))
+            }
+            _ => {
+                let (transform, end) = Self::extract_transform(s, end - 1)?;
+                Some((variable(name, Vec::new(), Some(transform)), end))
+            }
+        }
+    }
+
+    /// Extract `${1/regex/format/options}`
+    fn extract_tabstop_transform(
+        s: &str,
+        pos: usize,
+    ) -> Option<(SnippetElement, usize)> {
+        static REGEX: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"^\$\{(\d+)/").unwrap());
+
+        let caps = REGEX.captures(&s[pos..])?;
+        let tab = caps.get(1)?.as_str().parse::<usize>().ok()?;
+        let (transform, end) =
+            Self::extract_transform(s, pos + caps.get(0)?.end() - 1)?;
+        Some((SnippetElement::TabstopTransform(tab, transform), end))
+    }
+
+    /// Extract `/regex/format/options}` starting at the first `/`, returning
+    /// the position after the closing `}`
+    fn extract_transform(s: &str, pos: usize) -> Option<(Transform, usize)> {
+        // The regex keeps its escapes, except for the `\/` of a slash
+        let mut regex = String::new();
+        let mut chars = s[pos + 1..].char_indices();
+        let mut end = None;
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '/' => {
+                    end = Some(pos + 1 + i);
+                    break;
+                }
+                '\\' => match chars.next() {
+                    Some((_, '/')) => regex.push('/'),
+                    Some((_, next)) => {
+                        regex.push('\\');
+                        regex.push(next);
+                    }
+                    None => return None,
+                },
+                c => regex.push(c),
+            }
+        }
+
+        let (format, end) = Self::extract_format(s, end? + 1)?;
+        let (options, end) = text_until(s, end + 1, &['}'])?;
+        Some((
+            Transform {
+                regex,
+                format,
+                options,
+            },
+            end + 1,
+        ))
+    }
+
+    /// Extract the format of a transform, returning the position of the `/`
+    /// that ends it
+    fn extract_format(s: &str, pos: usize) -> Option<(Vec<FormatElement>, usize)> {
+        let mut elements = Vec::new();
+        let mut text = String::new();
+        let mut pos = pos;
+        loop {
+            let c = s[pos..].chars().next()?;
+            match c {
+                '/' => break,
+                '\\' => {
+                    let next = s[pos + 1..].chars().next()?;
+                    if matches!(next, '$' | '\\' | '/') {
+                        text.push(next);
+                        pos += 1 + next.len_utf8();
+                    } else {
+                        text.push('\\');
+                        pos += 1;
+                    }
+                }
+                '$' => match Self::extract_format_group(s, pos) {
+                    Some((element, end)) => {
+                        if !text.is_empty() {
+                            elements.push(FormatElement::Text(std::mem::take(
+                                &mut text,
+                            )));
+                        }
+                        elements.push(element);
+                        pos = end;
+                    }
+                    None => {
+                        text.push('$');
+                        pos += 1;
+                    }
+                },
+                c => {
+                    text.push(c);
+                    pos += c.len_utf8();
+                }
+            }
+        }
+        if !text.is_empty() {
+            elements.push(FormatElement::Text(text));
+        }
+        Some((elements, pos))
+    }
+
+    /// Extract `$1`, `${1}`, `${1:/upcase}` or a conditional like `${1:+if}`
+    fn extract_format_group(s: &str, pos: usize) -> Option<(FormatElement, usize)> {
+        static REGEX_FIRST: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"^\$(\d+)").unwrap());
+        static REGEX_SECOND: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"^\$\{(\d+)(\}|:)").unwrap());
+        static REGEX_CASE: Lazy<Regex> =
+            Lazy::new(|| Regex::new(r"^/([a-z]+)\}").unwrap());
+
+        if let Some(caps) = REGEX_FIRST.captures(&s[pos..]) {
+            let group = caps.get(1)?.as_str().parse::<usize>().ok()?;
+            return Some((
+                FormatElement::Group(group, None),
+                pos + caps.get(0)?.end(),
+            ));
+        }
+
+        let caps = REGEX_SECOND.captures(&s[pos..])?;
+        let group = caps.get(1)?.as_str().parse::<usize>().ok()?;
+        let end = pos + caps.get(0)?.end();
+        if caps.get(2)?.as_str() == "}" {
+            return Some((FormatElement::Group(group, None), end));
+        }
+
+        let rest = &s[end..];
+        if let Some(caps) = REGEX_CASE.captures(rest) {
+            let case = CaseChange::from_name(caps.get(1)?.as_str())?;
+            return Some((
+                FormatElement::Group(group, Some(case)),
+                end + caps.get(0)?.end(),
+            ));
+        }
+        let (if_text, else_text, end) = match rest.chars().next()? {
+            '+' => {
+                let (if_text, end) = text_until(s, end + 1, &['}'])?;
+                (Some(if_text), None, end)
+            }
+            '?' => {
+                let (if_text, end) = text_until(s, end + 1, &[':'])?;
+                let (else_text, end) = text_until(s, end + 1, &['}'])?;
+                (Some(if_text), Some(else_text), end)
+            }
+            '-' => {
+                let (else_text, end) = text_until(s, end + 1, &['}'])?;
+                (None, Some(else_text), end)
+            }
+            _ => {
+                let (else_text, end) = text_until(s, end, &['}'])?;
+                (None, Some(else_text), end)
+            }
+        };
+        Some((
+            FormatElement::Conditional(group, if_text, else_text),
+            end + 1,
+        ))
+    }
+
+    /// The text inserted for the snippet, with mirrors repeating the text of
+    /// their tabstop
     #[inline]
     pub fn text(&self) -> String {
-        let mut buf = String::new();
-        self.write_text_to(&mut buf)
-            .expect("Snippet::write_text_to function unexpectedly return error");
-        buf
+        let values = self.tab_values();
+        self.elements
+            .iter()
+            .map(|element| Self::element_text(element, &values))
+            .collect()
     }
 
-    #[inline]
-    fn write_text_to<Buffer: fmt::Write>(&self, buf: &mut Buffer) -> fmt::Result {
-        for snippet_element in self.elements.iter() {
-            snippet_element.write_text_to(buf)?
+    fn element_text(
+        element: &SnippetElement,
+        values: &HashMap<usize, String>,
+    ) -> String {
+        match element {
+            SnippetElement::Tabstop(tab) => {
+                values.get(tab).cloned().unwrap_or_default()
+            }
+            SnippetElement::TabstopTransform(tab, transform) => {
+                transform.apply(values.get(tab).map(|v| v.as_str()).unwrap_or(""))
+            }
+            SnippetElement::PlaceHolder(_, elements)
+            | SnippetElement::Variable {
+                default: elements, ..
+            } => elements
+                .iter()
+                .map(|element| Self::element_text(element, values))
+                .collect(),
+            SnippetElement::Text(_) | SnippetElement::Choice(..) => element.text(),
         }
-        fmt::Result::Ok(())
     }
 
+    /// The text of each tabstop with a placeholder or choices, taken from its
+    /// first occurrence
+    fn tab_values(&self) -> HashMap<usize, String> {
+        fn collect(
+            elements: &[SnippetElement],
+            values: &mut HashMap<usize, String>,
+        ) {
+            for element in elements {
+                match element {
+                    SnippetElement::PlaceHolder(tab, children) => {
+                        values.entry(*tab).or_insert_with(|| element.text());
+                        collect(children, values);
+                    }
+                    SnippetElement::Choice(tab, _) => {
+                        values.entry(*tab).or_insert_with(|| element.text());
+                    }
+                    SnippetElement::Variable { default, .. } => {
+                        collect(default, values);
+                    }
+                    SnippetElement::Text(_)
+                    | SnippetElement::Tabstop(_)
+                    | SnippetElement::TabstopTransform(..) => {}
+                }
+            }
+        }
+
+        let mut values = HashMap::new();
+        collect(&self.elements, &mut values);
+        values
+    }
+
+    /// The choices of each tabstop that has some
+    pub fn choices(&self) -> HashMap<usize, Vec<String>> {
+        fn collect(
+            elements: &[SnippetElement],
+            choices: &mut HashMap<usize, Vec<String>>,
+        ) {
+            for element in elements {
+                match element {
+                    SnippetElement::Choice(tab, items) => {
+                        choices.entry(*tab).or_insert_with(|| items.clone());
+                    }
+                    SnippetElement::PlaceHolder(_, children)
+                    | SnippetElement::Variable {
+                        default: children, ..
+                    } => collect(children, choices),
+                    SnippetElement::Text(_)
+                    | SnippetElement::Tabstop(_)
+                    | SnippetElement::TabstopTransform(..) => {}
+                }
+            }
+        }
+
+        let mut choices = HashMap::new();
+        collect(&self.elements, &mut choices);
+        choices
+    }
+
+    /// Replace the variables with their value, or their default when they have
+    /// no value. `value` returns `None` for unknown variables, which insert
+    /// their name.
+    pub fn resolve_variables(&mut self, value: &dyn Fn(&str) -> Option<String>) {
+        fn resolve(
+            elements: Vec<SnippetElement>,
+            value: &dyn Fn(&str) -> Option<String>,
+        ) -> Vec<SnippetElement> {
+            let mut resolved = Vec::new();
+            for element in elements {
+                match element {
+                    SnippetElement::Variable {
+                        name,
+                        default,
+                        transform,
+                    } => match value(&name) {
+                        Some(text) if !text.is_empty() => {
+                            resolved.push(SnippetElement::Text(match transform {
+                                Some(transform) => transform.apply(&text),
+                                None => text,
+                            }));
+                        }
+                        text => {
+                            if !default.is_empty() {
+                                resolved.extend(resolve(default, value));
+                            } else if text.is_none() {
+                                resolved.push(SnippetElement::Text(name));
+                            }
+                        }
+                    },
+                    SnippetElement::PlaceHolder(tab, children) => {
+                        resolved.push(SnippetElement::PlaceHolder(
+                            tab,
+                            resolve(children, value),
+                        ));
+                    }
+                    element => resolved.push(element),
+                }
+            }
+            resolved
+        }
+
+        self.elements = resolve(std::mem::take(&mut self.elements), value);
+    }
+
+    /// The ranges of the tabstops, placeholders and choices in the order they
+    /// appear, with the snippet inserted at `pos`
     #[inline]
     pub fn tabs(&self, pos: usize) -> Vec<(usize, (usize, usize))> {
-        Self::elements_tabs(&self.elements, pos)
+        let mut tabs = Vec::new();
+        Self::elements_ranges(
+            &self.elements,
+            pos,
+            &self.tab_values(),
+            &mut tabs,
+            &mut Vec::new(),
+        );
+        tabs
     }
 
-    pub fn elements_tabs(
+    /// The tabstops in the order they are visited, which is by number with
+    /// `$0` last, each with the ranges of all its occurrences
+    pub fn tab_stops(&self, pos: usize) -> Vec<SnippetTab> {
+        let mut tabs = Vec::new();
+        let mut transforms = Vec::new();
+        Self::elements_ranges(
+            &self.elements,
+            pos,
+            &self.tab_values(),
+            &mut tabs,
+            &mut transforms,
+        );
+
+        let mut numbers: Vec<usize> = tabs.iter().map(|(tab, _)| *tab).collect();
+        numbers.sort_by_key(|tab| (*tab == 0, *tab));
+        numbers.dedup();
+
+        let mut choices = self.choices();
+        numbers
+            .into_iter()
+            .map(|number| SnippetTab {
+                ranges: tabs
+                    .iter()
+                    .filter(|(tab, _)| *tab == number)
+                    .map(|(_, range)| *range)
+                    .collect(),
+                transforms: transforms
+                    .iter()
+                    .filter(|(tab, _, _)| *tab == number)
+                    .map(|(_, range, transform)| (*range, transform.clone()))
+                    .collect(),
+                choices: choices.remove(&number).unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// Collect the ranges of the tabstops and of the transformed mirrors of
+    /// `elements` inserted at `start`, returning where they end
+    fn elements_ranges(
         elements: &[SnippetElement],
         start: usize,
-    ) -> Vec<(usize, (usize, usize))> {
-        let mut tabs = Vec::new();
+        values: &HashMap<usize, String>,
+        tabs: &mut Vec<(usize, (usize, usize))>,
+        transforms: &mut Vec<(usize, (usize, usize), Transform)>,
+    ) -> usize {
         let mut pos = start;
         for el in elements {
-            match el {
-                SnippetElement::Text(t) => {
-                    pos += t.len();
-                }
+            pos = match el {
+                SnippetElement::Text(t) => pos + t.len(),
                 SnippetElement::PlaceHolder(tab, els) => {
-                    let placeholder_tabs = Self::elements_tabs(els, pos);
-                    let end = pos + els.iter().map(|e| e.len()).sum::<usize>();
+                    // The placeholder comes before the tabstops it contains
+                    let index = tabs.len();
+                    let end =
+                        Self::elements_ranges(els, pos, values, tabs, transforms);
+                    tabs.insert(index, (*tab, (pos, end)));
+                    end
+                }
+                SnippetElement::Variable { default, .. } => {
+                    Self::elements_ranges(default, pos, values, tabs, transforms)
+                }
+                SnippetElement::Tabstop(tab) | SnippetElement::Choice(tab, _) => {
+                    let end = pos + Self::element_text(el, values).len();
                     tabs.push((*tab, (pos, end)));
-                    tabs.extend_from_slice(&placeholder_tabs);
-                    pos = end;
+                    end
                 }
-                SnippetElement::Tabstop(tab) => {
-                    tabs.push((*tab, (pos, pos)));
+                SnippetElement::TabstopTransform(tab, transform) => {
+                    let end = pos + Self::element_text(el, values).len();
+                    transforms.push((*tab, (pos, end), transform.clone()));
+                    end
+                }
+            };
+        }
+        pos
+    }
+}
+
+/// Read up to the first unescaped char of `stops`, returning the text without
+/// its escapes and the position of that char
+fn text_until(s: &str, pos: usize, stops: &[char]) -> Option<(String, usize)> {
+    let mut text = String::new();
+    let mut chars = s[pos..].char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            if let Some(&(_, next)) = chars.peek() {
+                if next == '\\'
+                    || next == '$'
+                    || next == '}'
+                    || stops.contains(&next)
+                {
+                    text.push(next);
+                    chars.next();
+                    continue;
                 }
             }
         }
-        tabs
+        if stops.contains(&c) {
+            return Some((text, pos + i));
+        }
+        text.push(c);
+    }
+    None
+}
+
+/// A tabstop of a snippet being filled in
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnippetTab {
+    /// The ranges of the tabstop, more than one when it is mirrored
+    pub ranges: Vec<(usize, usize)>,
+    /// The ranges mirroring the tabstop through a transform, updated when the
+    /// tabstop is left
+    pub transforms: Vec<((usize, usize), Transform)>,
+    pub choices: Vec<String>,
+}
+
+impl SnippetTab {
+    pub fn contains(&self, offset: usize) -> bool {
+        self.ranges
+            .iter()
+            .any(|(start, end)| *start <= offset && offset <= *end)
+    }
+
+    /// A selection of every range, so that mirrors are edited together
+    pub fn selection(&self) -> Selection {
+        let mut selection = Selection::new();
+        for (start, end) in &self.ranges {
+            selection.add_region(SelRegion::new(*start, *end, None));
+        }
+        selection
+    }
+}
+
+/// The value of the snippet variables for the date and time
+pub fn time_variable(name: &str, now: &DateTime<Local>) -> Option<String> {
+    let format = match name {
+        "CURRENT_YEAR" => "%Y",
+        "CURRENT_YEAR_SHORT" => "%y",
+        "CURRENT_MONTH" => "%m",
+        "CURRENT_MONTH_NAME" => "%B",
+        "CURRENT_MONTH_NAME_SHORT" => "%b",
+        "CURRENT_DATE" => "%d",
+        "CURRENT_DAY_NAME" => "%A",
+        "CURRENT_DAY_NAME_SHORT" => "%a",
+        "CURRENT_HOUR" => "%H",
+        "CURRENT_MINUTE" => "%M",
+        "CURRENT_SECOND" => "%S",
+        "CURRENT_SECONDS_UNIX" => return Some(now.timestamp().to_string()),
+        "CURRENT_TIMEZONE_OFFSET" => "%:z",
+        _ => return None,
+    };
+    Some(now.format(format).to_string())
+}
+
+/// The value of the `RANDOM`, `RANDOM_HEX` and `UUID` snippet variables
+pub fn random_variable(name: &str) -> Option<String> {
+    fn random() -> u64 {
+        // Each `RandomState` is seeded differently
+        let mut hasher =
+            std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u128(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or(0),
+        );
+        hasher.finish()
+    }
+
+    match name {
+        "RANDOM" => Some(format!("{:06}", random() % 1_000_000)),
+        "RANDOM_HEX" => Some(format!("{:06x}", random() % 0x100_0000)),
+        "UUID" => {
+            let (high, low) = (random(), random());
+            // Version 4, variant 1
+            let high = (high & !0xf000) | 0x4000;
+            let low = (low & !(0b11 << 62)) | (0b10 << 62);
+            Some(format!(
+                "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+                high >> 32,
+                (high >> 16) & 0xffff,
+                high & 0xffff,
+                low >> 48,
+                low & 0xffff_ffff_ffff
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// A snippet from a snippet file rather than from a language server.
+///
+/// Snippet files use the same format as VS Code: a JSON object of snippets
+/// keyed by their name. A `<language>.json` file holds the snippets of that
+/// language, while the snippets of a `.code-snippets` file apply to every
+/// language unless they list some in `scope`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserSnippet {
+    pub name: String,
+    /// The words typed to offer the snippet in the completion list
+    pub prefixes: Vec<String>,
+    /// The snippet text, in the snippet syntax
+    pub body: String,
+    pub description: Option<String>,
+    /// The languages the snippet applies to, or `None` for all of them
+    pub languages: Option<Vec<LapceLanguage>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            OneOrMany::One(s) => vec![s],
+            OneOrMany::Many(v) => v,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SnippetDefinition {
+    prefix: Option<OneOrMany>,
+    body: OneOrMany,
+    description: Option<OneOrMany>,
+    /// Comma separated language names
+    scope: Option<String>,
+}
+
+impl UserSnippet {
+    /// Load the snippets of a `<language>.json` or `.code-snippets` file
+    pub fn load(path: &Path) -> Vec<UserSnippet> {
+        let language = match path.extension().and_then(|e| e.to_str()) {
+            Some("code-snippets") => None,
+            Some("json") => {
+                let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+                let Some(language) = LapceLanguage::from_name(name) else {
+                    tracing::error!(
+                        "Unknown language `{name}` of snippet file {path:?}"
+                    );
+                    return Vec::new();
+                };
+                Some(language)
+            }
+            _ => return Vec::new(),
+        };
+
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                tracing::error!("Failed to read snippet file {path:?}: {err}");
+                return Vec::new();
+            }
+        };
+        match Self::parse(&text, language) {
+            Ok(snippets) => snippets,
+            Err(err) => {
+                tracing::error!("Failed to parse snippet file {path:?}: {err}");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Load the snippet files of `dir`, in the order of their file names
+    pub fn load_dir(dir: &Path) -> Vec<UserSnippet> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.is_file())
+            .collect();
+        paths.sort();
+        paths.iter().flat_map(|path| Self::load(path)).collect()
+    }
+
+    /// Parse the snippets of a file, where `language` is the language of a
+    /// `<language>.json` file
+    pub fn parse(
+        text: &str,
+        language: Option<LapceLanguage>,
+    ) -> anyhow::Result<Vec<UserSnippet>> {
+        let definitions: IndexMap<String, SnippetDefinition> =
+            serde_json::from_str(text)?;
+        Ok(definitions
+            .into_iter()
+            .map(|(name, definition)| {
+                let languages = match (language, definition.scope) {
+                    (Some(language), _) => Some(vec![language]),
+                    (None, Some(scope)) => Some(
+                        scope
+                            .split(',')
+                            .filter_map(|name| LapceLanguage::from_name(name.trim()))
+                            .collect(),
+                    ),
+                    (None, None) => None,
+                };
+                UserSnippet {
+                    prefixes: definition
+                        .prefix
+                        .map(OneOrMany::into_vec)
+                        .unwrap_or_default(),
+                    body: definition.body.into_vec().join("\n"),
+                    description: definition
+                        .description
+                        .map(|d| d.into_vec().join("\n")),
+                    languages,
+                    name,
+                }
+            })
+            .collect())
+    }
+
+    pub fn applies_to(&self, language: LapceLanguage) -> bool {
+        self.languages
+            .as_ref()
+            .map(|languages| languages.contains(&language))
+            .unwrap_or(true)
     }
 }
 
@@ -856,4 +1775,138 @@ fn test_extract_text() {
             Snippet::extract_text(s, end + 1, &['$', '{', '}', '\\'], &[])
         );
     }
+
+    #[test]
+    fn test_snippet_grammar() {
+        use SnippetElement::*;
+
+        let s = "${1|one,t\\,wo|} $1 ${2:x} ${2/(.*)/${1:/upcase}/} $TM_FILENAME ${FOO:bar} cost: $";
+        let parsed = Snippet::from_str(s).unwrap();
+        assert_eq!(
+            parsed.elements[0],
+            Choice(1, vec!["one".into(), "t,wo".into()])
+        );
+        assert_eq!(
+            parsed.elements[8],
+            Variable {
+                name: "TM_FILENAME".into(),
+                default: Vec::new(),
+                transform: None,
+            }
+        );
+        assert_eq!(parsed.elements[11], Text(" cost: $".into()));
+        assert_eq!(parsed.text(), "one one x X  bar cost: $");
+        assert_eq!(
+            "${1|one,t\\,wo|} $1 ${2:x} ${2/(.*)/${1:/upcase}/} ${TM_FILENAME} ${FOO:bar} cost: $",
+            parsed.to_string()
+        );
+
+        // Mirrors are visited together, by number
+        let tabs = parsed.tab_stops(0);
+        assert_eq!(tabs.len(), 2);
+        assert_eq!(tabs[0].ranges, vec![(0, 3), (4, 7)]);
+        assert_eq!(tabs[0].choices, vec!["one", "t,wo"]);
+        assert_eq!(tabs[1].ranges, vec![(8, 9)]);
+        assert_eq!(tabs[1].transforms.len(), 1);
+        assert_eq!(tabs[1].transforms[0].0, (10, 11));
+
+        let mut parsed = parsed;
+        parsed.resolve_variables(&|name| match name {
+            "TM_FILENAME" => Some("main.rs".into()),
+            "FOO" => Some(String::new()),
+            _ => None,
+        });
+        assert_eq!(parsed.text(), "one one x X main.rs bar cost: $");
+
+        let parsed = Snippet::from_str("$0 ${3:c} $1").unwrap();
+        let tabs: Vec<_> =
+            parsed.tab_stops(0).into_iter().map(|t| t.ranges).collect();
+        assert_eq!(tabs, vec![vec![(3, 3)], vec![(1, 2)], vec![(0, 0)]]);
+    }
+
+    #[test]
+    fn test_transform() {
+        let transform = |s: &str| {
+            let parsed = Snippet::from_str(s).unwrap();
+            match &parsed.elements[0] {
+                SnippetElement::TabstopTransform(_, transform) => transform.clone(),
+                _ => panic!("not a transform: {s}"),
+            }
+        };
+
+        assert_eq!(transform("${1/a/b/g}").apply("banana"), "bbnbnb");
+        assert_eq!(transform("${1/a/b/}").apply("banana"), "bbnana");
+        assert_eq!(transform("${1/A/b/gi}").apply("aA"), "bb");
+        assert_eq!(
+            transform("${1/(\\w+) (\\w+)/${2:/capitalize} $1/}")
+                .apply("hello world"),
+            "World hello"
+        );
+        assert_eq!(
+            transform("${1/(.*)/${1:/camelcase}|${1:/pascalcase}/}")
+                .apply("foo-bar_baz"),
+            "fooBarBaz|FooBarBaz"
+        );
+        assert_eq!(
+            transform("${1/^(a)?.*$/${1:+yes}${1:?A:B}${1:-none}\\/\\$/}")
+                .apply("xyz"),
+            "Bnone/$"
+        );
+        assert_eq!(
+            transform("${1/^(a)?.*$/${1:+yes}${1:?A:B}${1:-none}/}").apply("abc"),
+            "yesAa"
+        );
+        // An invalid regex leaves the text as is
+        assert_eq!(transform("${1/(/x/}").apply("text"), "text");
+
+        let s = "${1/a\\/b/${1:/upcase}\\/${2:?x\\}:y}/gi}";
+        assert_eq!(Snippet::from_str(s).unwrap().to_string(), s);
+    }
+
+    #[test]
+    fn test_random_variable() {
+        let uuid = random_variable("UUID").unwrap();
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(&uuid[14..15], "4");
+        assert_eq!(random_variable("RANDOM").unwrap().len(), 6);
+        assert_eq!(random_variable("RANDOM_HEX").unwrap().len(), 6);
+        assert_eq!(random_variable("TM_FILENAME"), None);
+    }
+
+    #[test]
+    fn test_user_snippets() {
+        let text = r#"{
+            "For Loop": {
+                "prefix": ["for", "fori"],
+                "body": ["for ${1:i} in ${2:iter} {", "\t$0", "}"],
+                "description": "A for loop"
+            },
+            "Todo": {
+                "prefix": "todo",
+                "body": "// TODO: $0",
+                "scope": "rust, python, not-a-language"
+            },
+            "Header": {
+                "body": "Copyright"
+            }
+        }"#;
+
+        let snippets = UserSnippet::parse(text, None).unwrap();
+        assert_eq!(snippets.len(), 3);
+        assert_eq!(snippets[0].name, "For Loop");
+        assert_eq!(snippets[0].prefixes, vec!["for", "fori"]);
+        assert_eq!(snippets[0].body, "for ${1:i} in ${2:iter} {\n\t$0\n}");
+        assert_eq!(snippets[0].description.as_deref(), Some("A for loop"));
+        assert!(snippets[0].applies_to(LapceLanguage::Go));
+        assert_eq!(
+            snippets[1].languages,
+            Some(vec![LapceLanguage::Rust, LapceLanguage::Python])
+        );
+        assert!(!snippets[1].applies_to(LapceLanguage::Go));
+        assert!(snippets[2].prefixes.is_empty());
+
+        let snippets = UserSnippet::parse(text, Some(LapceLanguage::Go)).unwrap();
+        assert!(snippets.iter().all(|s| s.applies_to(LapceLanguage::Go)
+            && !s.applies_to(LapceLanguage::Rust)));
+    }
 }
@@ -1,28 +1,119 @@
-use std::{path::PathBuf, rc::Rc};
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use floem::{
     keyboard::Modifiers,
-    reactive::{RwSignal, Scope, SignalWith},
+    reactive::{RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
 };
 use indexmap::IndexMap;
 use lapce_core::mode::Mode;
-use lapce_rpc::source_control::FileDiff;
+use lapce_rpc::source_control::{FileDiff, GitStash};
 
 use crate::{
     command::{CommandExecuted, CommandKind},
     editor::EditorData,
     keypress::{condition::Condition, KeyPressFocus},
     main_split::Editors,
+    palette::kind::PaletteKind,
     window_tab::CommonData,
 };
 
+/// What is done with the reference or stash picked in the source control
+/// palettes, or with the name typed in them
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ScmAction {
+    #[default]
+    Checkout,
+    /// Pick the reference to create a branch from, then name the branch
+    CreateBranch,
+    /// Name the branch created from `from`
+    NameBranch {
+        from: String,
+    },
+    RenameBranch,
+    /// Type the new name of the branch `old`
+    NameRenamedBranch {
+        old: String,
+    },
+    DeleteBranch {
+        force: bool,
+    },
+    Merge,
+    /// Type the message of a new stash
+    Stash,
+    ApplyStash {
+        pop: bool,
+    },
+    DropStash,
+}
+
+impl ScmAction {
+    /// The palette that picks what the action is done with
+    pub fn palette_kind(&self) -> PaletteKind {
+        match self {
+            ScmAction::Checkout
+            | ScmAction::CreateBranch
+            | ScmAction::RenameBranch
+            | ScmAction::DeleteBranch { .. }
+            | ScmAction::Merge => PaletteKind::SCMReferences,
+            ScmAction::NameBranch { .. }
+            | ScmAction::NameRenamedBranch { .. }
+            | ScmAction::Stash => PaletteKind::SCMInput,
+            ScmAction::ApplyStash { .. } | ScmAction::DropStash => {
+                PaletteKind::SCMStashes
+            }
+        }
+    }
+
+    /// Whether only branches can be picked, rather than any reference
+    pub fn branches_only(&self) -> bool {
+        matches!(
+            self,
+            ScmAction::RenameBranch | ScmAction::DeleteBranch { .. }
+        )
+    }
+
+    pub fn placeholder(&self) -> &'static str {
+        match self {
+            ScmAction::Checkout => "",
+            ScmAction::CreateBranch => {
+                "Select a reference to create the branch from"
+            }
+            ScmAction::NameBranch { .. } => "Name of the new branch",
+            ScmAction::RenameBranch => "Select a branch to rename",
+            ScmAction::NameRenamedBranch { .. } => "New name of the branch",
+            ScmAction::DeleteBranch { .. } => "Select a branch to delete",
+            ScmAction::Merge => {
+                "Select a reference to merge into the current branch"
+            }
+            ScmAction::Stash => "Stash message (optional)",
+            ScmAction::ApplyStash { pop: false } => "Select a stash to apply",
+            ScmAction::ApplyStash { pop: true } => "Select a stash to pop",
+            ScmAction::DropStash => "Select a stash to drop",
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct SourceControlData {
-    // VCS modified files & whether they should be included in the next commit
-    pub file_diffs: RwSignal<IndexMap<PathBuf, (FileDiff, bool)>>,
+    /// VCS modified files that are not staged
+    pub file_diffs: RwSignal<IndexMap<PathBuf, FileDiff>>,
+    /// VCS modified files staged in the index, which make up the next commit
+    pub staged_diffs: RwSignal<IndexMap<PathBuf, FileDiff>>,
+    /// Whether the next commit replaces the last one
+    pub amend: RwSignal<bool>,
     pub branch: RwSignal<String>,
+    /// The workspace root folder the branch, branches, tags and stashes are of
+    pub root: RwSignal<Option<PathBuf>>,
+    /// How many commits the branch is ahead of and behind its upstream
+    pub ahead_behind: RwSignal<Option<(usize, usize)>>,
+    /// The current branch of each workspace root folder
+    pub root_branches: RwSignal<IndexMap<PathBuf, String>>,
     pub branches: RwSignal<im::Vector<String>>,
     pub tags: RwSignal<im::Vector<String>>,
+    pub stashes: RwSignal<im::Vector<GitStash>>,
     pub editor: EditorData,
     pub common: Rc<CommonData>,
 }
@@ -64,31 +155,26 @@ impl SourceControlData {
     pub fn new(cx: Scope, editors: Editors, common: Rc<CommonData>) -> Self {
         Self {
             file_diffs: cx.create_rw_signal(IndexMap::new()),
+            staged_diffs: cx.create_rw_signal(IndexMap::new()),
+            amend: cx.create_rw_signal(false),
             branch: cx.create_rw_signal("".to_string()),
+            root: cx.create_rw_signal(None),
+            ahead_behind: cx.create_rw_signal(None),
+            root_branches: cx.create_rw_signal(IndexMap::new()),
             branches: cx.create_rw_signal(im::Vector::new()),
             tags: cx.create_rw_signal(im::Vector::new()),
+            stashes: cx.create_rw_signal(im::Vector::new()),
             editor: editors.make_local(cx, common.clone()),
             common,
         }
     }
 
     pub fn commit(&self) {
-        let diffs: Vec<FileDiff> = self.file_diffs.with_untracked(|file_diffs| {
-            file_diffs
-                .iter()
-                .filter_map(
-                    |(_, (diff, checked))| {
-                        if *checked {
-                            Some(diff)
-                        } else {
-                            None
-                        }
-                    },
-                )
-                .cloned()
-                .collect()
-        });
-        if diffs.is_empty() {
+        let diffs: Vec<FileDiff> = self
+            .staged_diffs
+            .with_untracked(|staged_diffs| staged_diffs.values().cloned().collect());
+        let amend = self.amend.get_untracked();
+        if diffs.is_empty() && !amend {
             return;
         }
 
@@ -103,6 +189,88 @@ pub fn commit(&self) {
         }
 
         self.editor.reset();
-        self.common.proxy.git_commit(message.to_string(), diffs);
+        self.amend.set(false);
+        self.common
+            .proxy
+            .git_commit(message.to_string(), diffs, amend);
+    }
+
+    pub fn stage(&self, diffs: &[FileDiff]) {
+        let files = diffs.iter().flat_map(FileDiff::paths).collect();
+        self.common.proxy.git_stage_files(files);
+    }
+
+    pub fn unstage(&self, diffs: &[FileDiff]) {
+        let files = diffs.iter().flat_map(FileDiff::paths).collect();
+        self.common.proxy.git_unstage_files(files);
+    }
+
+    /// Stage the changes of the file at `path`
+    pub fn stage_path(&self, path: &Path) {
+        if let Some(diff) = self
+            .file_diffs
+            .with_untracked(|file_diffs| file_diffs.get(path).cloned())
+        {
+            self.stage(&[diff]);
+        }
+    }
+
+    /// Unstage the staged changes of the file at `path`
+    pub fn unstage_path(&self, path: &Path) {
+        if let Some(diff) = self
+            .staged_diffs
+            .with_untracked(|staged_diffs| staged_diffs.get(path).cloned())
+        {
+            self.unstage(&[diff]);
+        }
+    }
+}
+
+/// How long before `now` the unix `time` was, e.g. "3 days ago"
+pub fn time_ago(time: i64, now: i64) -> String {
+    const UNITS: [(i64, &str); 6] = [
+        (365 * 24 * 60 * 60, "year"),
+        (30 * 24 * 60 * 60, "month"),
+        (7 * 24 * 60 * 60, "week"),
+        (24 * 60 * 60, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+    ];
+
+    let elapsed = (now - time).max(0);
+    for (secs, unit) in UNITS {
+        let count = elapsed / secs;
+        if count > 0 {
+            let plural = if count == 1 { "" } else { "s" };
+            return format!("{count} {unit}{plural} ago");
+        }
+    }
+    "just now".to_string()
+}
+
+/// The unix `time` as a local date, e.g. "2024-01-31"
+pub fn local_date(time: i64) -> String {
+    chrono::DateTime::from_timestamp(time, 0)
+        .map(|date| {
+            date.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::time_ago;
+
+    #[test]
+    fn test_time_ago() {
+        let now = 1_700_000_000;
+        assert_eq!(time_ago(now - 30, now), "just now");
+        assert_eq!(time_ago(now - 60, now), "1 minute ago");
+        assert_eq!(time_ago(now - 3 * 60 * 60, now), "3 hours ago");
+        assert_eq!(time_ago(now - 8 * 24 * 60 * 60, now), "1 week ago");
+        assert_eq!(time_ago(now - 400 * 24 * 60 * 60, now), "1 year ago");
+        assert_eq!(time_ago(now + 10, now), "just now");
     }
 }
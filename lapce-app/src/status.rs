@@ -58,16 +58,29 @@ pub fn status(
     });
     let branch = source_control.branch;
     let file_diffs = source_control.file_diffs;
+    let staged_diffs = source_control.staged_diffs;
+    let ahead_behind = source_control.ahead_behind;
     let branch = move || {
-        format!(
+        let mut branch = format!(
             "{}{}",
             branch.get(),
-            if file_diffs.with(|diffs| diffs.is_empty()) {
+            if file_diffs.with(|diffs| diffs.is_empty())
+                && staged_diffs.with(|diffs| diffs.is_empty())
+            {
                 ""
             } else {
                 "*"
             }
-        )
+        );
+        if let Some((ahead, behind)) = ahead_behind.get() {
+            if behind > 0 {
+                branch.push_str(&format!(" {behind}↓"));
+            }
+            if ahead > 0 {
+                branch.push_str(&format!(" {ahead}↑"));
+            }
+        }
+        branch
     };
 
     let progresses = window_tab_data.progresses;
@@ -369,6 +382,18 @@ pub fn status(
                 palette_clone.run(PaletteKind::LineEnding);
             });
             let palette_clone = palette.clone();
+            let encoding_info = status_text(config, editor, move || {
+                if let Some(editor) = editor.get() {
+                    let doc = editor.doc_signal().get();
+                    doc.encoding.get()
+                } else {
+                    String::new()
+                }
+            })
+            .on_click_stop(move |_| {
+                palette_clone.run(PaletteKind::ReopenWithEncoding);
+            });
+            let palette_clone = palette.clone();
             let language_info = status_text(config, editor, move || {
                 if let Some(editor) = editor.get() {
                     let doc = editor.doc_signal().get();
@@ -380,7 +405,7 @@ pub fn status(
             .on_click_stop(move |_| {
                 palette_clone.run(PaletteKind::Language);
             });
-            (cursor_info, line_ending_info, language_info)
+            (cursor_info, line_ending_info, encoding_info, language_info)
         })
         .style(|s| {
             s.height_pct(100.0)
@@ -20,7 +20,7 @@
     register::Clipboard,
 };
 use lapce_rpc::{
-    dap_types::RunDebugConfig,
+    dap_types::{DebugRequest, RunDebugConfig},
     terminal::{TermId, TerminalProfile},
 };
 use parking_lot::RwLock;
@@ -411,8 +411,10 @@ fn new_raw_terminal(
 
             profile.environment = run_debug.env;
 
-            profile.command = Some(run_debug.program);
-            profile.arguments = run_debug.args;
+            if !run_debug.program.is_empty() {
+                profile.command = Some(run_debug.program);
+                profile.arguments = run_debug.args;
+            }
         }
 
         {
@@ -795,6 +797,9 @@ pub fn expand(
                 (command, args)
             } else if let Some(prelaunch) = prelaunch {
                 (prelaunch.program.clone(), prelaunch.args.clone())
+            } else if run_debug.request == DebugRequest::Attach {
+                // the program is already running, the terminal only gets a shell
+                (String::new(), None)
             } else {
                 (run_debug.program.clone(), run_debug.args.clone())
             };
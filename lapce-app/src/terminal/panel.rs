@@ -60,7 +60,7 @@ pub fn new(
         let tab_info = TerminalTabInfo { active: 0, tabs };
         let tab_info = cx.create_rw_signal(tab_info);
 
-        let debug = RunDebugData::new(cx, common.breakpoints);
+        let debug = RunDebugData::new(cx, main_split.editors, common.clone());
 
         let breakline = {
             let active_term = debug.active_term;
@@ -665,6 +665,7 @@ pub fn dap_stopped(
             .with_untracked(|daps| daps.get(dap_id).cloned());
         if let Some(dap) = dap {
             dap.stopped(self.cx, stopped, stack_frames, variables);
+            self.debug.evaluate_watches(&dap);
         }
         floem::action::focus_window();
     }
@@ -771,6 +772,8 @@ pub fn get_dap(&self, term_id: TermId, tracked: bool) -> Option<DapData> {
 
     pub fn dap_frame_scopes(&self, dap_id: DapId, frame_id: usize) {
         if let Some(dap) = self.debug.daps.get_untracked().get(&dap_id) {
+            dap.frame_id.set(Some(frame_id));
+            self.debug.evaluate_watches(dap);
             let variables = dap.variables;
             let send = create_ext_action(self.common.scope, move |result| {
                 if let Ok(ProxyResponse::DapGetScopesResponse { scopes }) = result {
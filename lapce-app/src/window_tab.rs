@@ -31,33 +31,40 @@
 use indexmap::IndexMap;
 use itertools::Itertools;
 use lapce_core::{
-    command::FocusCommand, cursor::CursorAffinity, directory::Directory, meta,
-    mode::Mode, register::Register,
+    command::FocusCommand,
+    cursor::{CursorAffinity, CursorMode},
+    directory::Directory,
+    meta,
+    mode::Mode,
+    register::Register,
 };
 use lapce_rpc::{
-    core::CoreNotification,
-    dap_types::{ConfigSource, RunDebugConfig},
+    core::{CoreNotification, CoreRequest, CoreResponse},
+    dap_types::{ConfigSource, DapId, DebugRequest, RunDebugConfig},
     file::{Naming, PathObject},
     plugin::PluginId,
     proxy::{ProxyResponse, ProxyRpcHandler, ProxyStatus},
     source_control::FileDiff,
     terminal::TermId,
-    RpcError,
+    RequestId, RpcError,
 };
 use lsp_types::{
-    CodeActionOrCommand, CodeLens, Diagnostic, ProgressParams, ProgressToken,
-    ShowMessageParams,
+    CodeActionOrCommand, CodeLens, Diagnostic, MessageActionItem, MessageType,
+    ProgressParams, ProgressToken, ShowMessageParams, ShowMessageRequestParams,
 };
+use serde::de::DeserializeOwned;
 use serde_json::Value;
 use tracing::{debug, error, event, Level};
 
 use crate::{
     about::AboutData,
     alert::{AlertBoxData, AlertButton},
+    breakpoint::BreakpointEditData,
     code_action::{CodeActionData, CodeActionStatus},
     command::{
-        CommandExecuted, CommandKind, InternalCommand, LapceCommand,
-        LapceWorkbenchCommand, WindowCommand,
+        CommandExecuted, CommandKind, GoToLineArgs, InsertTextArgs, InternalCommand,
+        LapceCommand, LapceWorkbenchCommand, OpenFileArgs, RunCommandsArgs,
+        ShellCommandArgs, WindowCommand,
     },
     completion::{CompletionData, CompletionStatus},
     config::LapceConfig,
@@ -66,8 +73,10 @@
     doc::DocContent,
     editor::location::{EditorLocation, EditorPosition},
     editor_tab::EditorTabChild,
+    ex_command::{ExCommand, ExCommandKind},
     file_explorer::data::FileExplorerData,
     find::Find,
+    git_history::GitHistoryData,
     global_search::GlobalSearchData,
     hover::HoverData,
     id::WindowTabId,
@@ -86,7 +95,7 @@
     plugin::PluginData,
     proxy::{new_proxy, ProxyData},
     rename::RenameData,
-    source_control::SourceControlData,
+    source_control::{ScmAction, SourceControlData},
     terminal::{
         event::{terminal_update_process, TermEvent, TermNotification},
         panel::TerminalPanelData,
@@ -102,6 +111,7 @@ pub enum Focus {
     Palette,
     CodeAction,
     Rename,
+    BreakpointEdit,
     AboutPopup,
     Panel(PanelKind),
 }
@@ -152,6 +162,7 @@ pub struct CommonData {
     pub proxy_status: RwSignal<Option<ProxyStatus>>,
     pub mouse_hover_timer: RwSignal<TimerToken>,
     pub breakpoints: RwSignal<BTreeMap<PathBuf, BTreeMap<usize, LapceBreakpoint>>>,
+    pub daps: RwSignal<im::HashMap<DapId, DapData>>,
     // the current focused view which will receive keyboard events
     pub keyboard_focus: RwSignal<Option<ViewId>>,
     pub window_common: Rc<WindowCommonData>,
@@ -180,7 +191,9 @@ pub struct WindowTabData {
     pub code_lens: RwSignal<Option<ViewId>>,
     pub source_control: SourceControlData,
     pub rename: RenameData,
+    pub breakpoint_edit: BreakpointEditData,
     pub global_search: GlobalSearchData,
+    pub git_history: GitHistoryData,
     pub call_hierarchy_data: CallHierarchyData,
     pub about_data: AboutData,
     pub alert_data: AlertBoxData,
@@ -229,7 +242,7 @@ fn run_command(
     ) -> CommandExecuted {
         match &command.kind {
             CommandKind::Workbench(cmd) => {
-                self.run_workbench_command(cmd.clone(), None);
+                self.run_workbench_command(cmd.clone(), command.data.clone());
             }
             CommandKind::Focus(cmd) => {
                 if self.common.focus.get_untracked() == Focus::Workbench {
@@ -334,6 +347,7 @@ pub fn new(
             all_disabled_volts,
             window_common.extra_plugin_paths.as_ref().clone(),
             config.plugins.clone(),
+            config.lsp.clone(),
             term_tx.clone(),
         );
         let (config, set_config) = cx.create_signal(Arc::new(config));
@@ -387,6 +401,7 @@ pub fn new(
             mouse_hover_timer: cx.create_rw_signal(TimerToken::INVALID),
             window_origin: cx.create_rw_signal(Point::ZERO),
             breakpoints: cx.create_rw_signal(BTreeMap::new()),
+            daps: cx.create_rw_signal(im::HashMap::new()),
             keyboard_focus: cx.create_rw_signal(None),
             window_common: window_common.clone(),
         });
@@ -510,7 +525,15 @@ pub fn new(
         }
 
         let rename = RenameData::new(cx, main_split.editors, common.clone());
+        let breakpoint_edit = BreakpointEditData::new(
+            cx,
+            main_split.editors,
+            terminal.debug.clone(),
+            common.clone(),
+        );
         let global_search = GlobalSearchData::new(cx, main_split.clone());
+        let git_history =
+            GitHistoryData::new(cx, main_split.clone(), source_control.branch);
 
         let plugin = PluginData::new(
             cx,
@@ -557,7 +580,9 @@ pub fn new(
             source_control,
             plugin,
             rename,
+            breakpoint_edit,
             global_search,
+            git_history,
             call_hierarchy_data: CallHierarchyData {
                 root: cx.create_rw_signal(None),
                 common: common.clone(),
@@ -580,6 +605,7 @@ pub fn new(
             let focus = window_tab_data.common.focus;
             let active_editor = window_tab_data.main_split.active_editor;
             let rename_active = window_tab_data.rename.active;
+            let breakpoint_edit_active = window_tab_data.breakpoint_edit.active;
             let internal_command = window_tab_data.common.internal_command;
             cx.create_effect(move |_| {
                 let focus = focus.get();
@@ -589,6 +615,41 @@ pub fn new(
                 if focus != Focus::Rename && rename_active.get_untracked() {
                     rename_active.set(false);
                 }
+                if focus != Focus::BreakpointEdit
+                    && breakpoint_edit_active.get_untracked()
+                {
+                    breakpoint_edit_active.set(false);
+                }
+            });
+        }
+
+        {
+            // The context keys of keymap `when` expressions that don't depend
+            // on the focused view
+            let keypress = window_tab_data.common.keypress.get_untracked();
+            let focus = window_tab_data.common.focus;
+            let config = window_tab_data.common.config;
+            let modal =
+                cx.create_memo(move |_| config.with(|config| config.core.modal));
+            let root_branches = window_tab_data.source_control.root_branches;
+            let terminal = window_tab_data.terminal.clone();
+            cx.create_effect(move |_| {
+                let focused_panel = match focus.get() {
+                    Focus::Panel(kind) => Some(<&str>::from(kind).to_string()),
+                    _ => None,
+                };
+                let debug_state = match terminal.get_active_dap(true) {
+                    Some(dap) if dap.stopped.get() => "stopped",
+                    Some(_) => "running",
+                    None => "inactive",
+                };
+                let git_repository = root_branches.with(|b| !b.is_empty());
+                let modal = modal.get();
+                keypress.set_context("focused_panel", focused_panel);
+                keypress.set_context("debug_state", Some(debug_state.to_string()));
+                keypress
+                    .set_context("git_repository", Some(git_repository.to_string()));
+                keypress.set_context("modal", Some(modal.to_string()));
             });
         }
 
@@ -626,6 +687,18 @@ pub fn new(
             });
         }
 
+        {
+            let window_tab_data = window_tab_data.clone();
+            let request = window_tab_data.proxy.request;
+            cx.create_effect(move |_| {
+                request.with(|request| {
+                    if let Some((id, rpc)) = request.as_ref() {
+                        window_tab_data.handle_core_request(*id, rpc);
+                    }
+                });
+            });
+        }
+
         window_tab_data
     }
 
@@ -659,6 +732,9 @@ pub fn reload_config(&self) {
                 change_plugins.push(key.clone());
             }
         }
+        if self.common.config.with_untracked(|c| c.lsp != config.lsp) {
+            self.common.proxy.update_lsp_servers(config.lsp.clone());
+        }
         self.set_config.set(Arc::new(config.clone()));
         if !change_plugins.is_empty() {
             self.common
@@ -697,7 +773,9 @@ pub fn run_lapce_command(&self, cmd: LapceCommand) {
             CommandKind::Scroll(_)
             | CommandKind::Focus(_)
             | CommandKind::Edit(_)
-            | CommandKind::Move(_) => {
+            | CommandKind::Move(_)
+            | CommandKind::MotionMode(_)
+            | CommandKind::MultiSelection(_) => {
                 if self.palette.status.get_untracked() != PaletteStatus::Inactive {
                     self.palette.run_command(&cmd, None, Modifiers::empty());
                 } else if let Some(editor_data) =
@@ -708,8 +786,6 @@ pub fn run_lapce_command(&self, cmd: LapceCommand) {
                     // TODO: dispatch to current focused view?
                 }
             }
-            CommandKind::MotionMode(_) => {}
-            CommandKind::MultiSelection(_) => {}
         }
     }
 
@@ -750,6 +826,8 @@ pub fn run_workbench_command(
                                     tracing::error!("No path");
                                     return;
                                 }),
+                                file: None,
+                                folders: Vec::new(),
                                 last_open: std::time::SystemTime::now()
                                     .duration_since(std::time::UNIX_EPOCH)
                                     .unwrap()
@@ -761,19 +839,65 @@ pub fn run_workbench_command(
                     });
                 }
             }
+            OpenWorkspaceFile => {
+                if !self.workspace.kind.is_remote() {
+                    let window_command = self.common.window_common.window_command;
+                    open_file(FileDialogOptions::new(), move |file| {
+                        let Some(path) = file.and_then(|mut file| file.path.pop())
+                        else {
+                            return;
+                        };
+                        match LapceWorkspace::from_workspace_file(
+                            LapceWorkspaceType::Local,
+                            &path,
+                        ) {
+                            Ok(mut workspace) => {
+                                workspace.last_open = std::time::SystemTime::now()
+                                    .duration_since(std::time::UNIX_EPOCH)
+                                    .unwrap()
+                                    .as_secs();
+                                window_command
+                                    .send(WindowCommand::SetWorkspace { workspace });
+                            }
+                            Err(err) => {
+                                tracing::error!(
+                                    "failed to open workspace file {path:?}: {err}"
+                                );
+                            }
+                        }
+                    });
+                }
+            }
             CloseFolder => {
                 if !self.workspace.kind.is_remote() {
                     let window_command = self.common.window_common.window_command;
                     let workspace = LapceWorkspace {
                         kind: LapceWorkspaceType::Local,
                         path: None,
+                        file: None,
+                        folders: Vec::new(),
                         last_open: 0,
                     };
                     window_command.send(WindowCommand::SetWorkspace { workspace });
                 }
             }
             OpenFile => {
-                if !self.workspace.kind.is_remote() {
+                if let Some(data) = data {
+                    let Some(args) =
+                        command_args::<OpenFileArgs>("open_file", Some(data))
+                    else {
+                        return;
+                    };
+                    let path = match self.workspace.path.as_ref() {
+                        Some(workspace) if args.path.is_relative() => {
+                            workspace.join(args.path)
+                        }
+                        _ => args.path,
+                    };
+                    self.common
+                        .internal_command
+                        .send(InternalCommand::OpenFile { path });
+                } else if !self.workspace.kind.is_remote() {
                     let internal_command = self.common.internal_command;
                     let options = FileDialogOptions::new();
                     open_file(options, move |file| {
@@ -926,6 +1050,25 @@ pub fn run_workbench_command(
                 self.main_split.export_theme();
             }
             ToggleInlayHints => {}
+            ToggleInlineBlame | ToggleBlameGutter => {
+                let config = self.common.config.get_untracked();
+                let (key, enabled) = if cmd == ToggleInlineBlame {
+                    ("blame-end-of-line", !config.editor.blame_end_of_line)
+                } else {
+                    ("blame-gutter", !config.editor.blame_gutter)
+                };
+                LapceConfig::update_file(
+                    "editor",
+                    key,
+                    toml_edit::Value::from(enabled),
+                );
+                if enabled {
+                    let docs = self.main_split.docs.get_untracked();
+                    for doc in docs.values() {
+                        doc.retrieve_blame();
+                    }
+                }
+            }
 
             // ==== Window ====
             ReloadWindow => {
@@ -1096,6 +1239,8 @@ pub fn run_workbench_command(
                         workspace: LapceWorkspace {
                             kind: LapceWorkspaceType::Local,
                             path: None,
+                            file: None,
+                            folders: Vec::new(),
                             last_open: 0,
                         },
                     },
@@ -1118,6 +1263,32 @@ pub fn run_workbench_command(
             PaletteCommand => {
                 self.palette.run(PaletteKind::Command);
             }
+            ExCommandLine => {
+                let visual =
+                    self.main_split.active_editor.get_untracked().is_some_and(
+                        |editor| {
+                            editor.cursor().with_untracked(|cursor| {
+                                matches!(cursor.mode, CursorMode::Visual { .. })
+                            })
+                        },
+                    );
+                self.palette.run_ex_command(visual);
+            }
+            RunExCommand => {
+                let Some(input) = data.as_ref().and_then(|data| data.as_str())
+                else {
+                    return;
+                };
+                if let Err(err) = self.run_ex_command(input) {
+                    self.show_message(
+                        "Ex Command",
+                        &ShowMessageParams {
+                            typ: MessageType::ERROR,
+                            message: format!(":{}: {err}", input.trim()),
+                        },
+                    );
+                }
+            }
             PaletteWorkspace => {
                 self.palette.run(PaletteKind::Workspace);
             }
@@ -1139,6 +1310,15 @@ pub fn run_workbench_command(
             ChangeFileLineEnding => {
                 self.palette.run(PaletteKind::LineEnding);
             }
+            ReopenWithEncoding => {
+                self.palette.run(PaletteKind::ReopenWithEncoding);
+            }
+            SaveWithEncoding => {
+                self.palette.run(PaletteKind::SaveWithEncoding);
+            }
+            InsertSnippet => {
+                self.palette.run(PaletteKind::Snippet);
+            }
             DiffFiles => self.palette.run(PaletteKind::DiffFiles),
 
             // ==== Running / Debugging ====
@@ -1339,13 +1519,177 @@ pub fn run_workbench_command(
                                 .git_discard_files_changes(vec![old_path]);
                             self.common.proxy.trash_path(new_path, Box::new(|_| {}));
                         }
+                        // Conflicts are resolved in the file rather than
+                        // discarded
+                        FileDiff::Conflicted(_) => {}
                     }
                 }
             }
             SourceControlDiscardWorkspaceChanges => {
                 // TODO:
             }
+            SourceControlStageActiveFile | SourceControlUnstageActiveFile => {
+                if let Some(editor_data) =
+                    self.main_split.active_editor.get_untracked()
+                {
+                    let path = editor_data.doc().content.with_untracked(|content| {
+                        match content {
+                            DocContent::File { path, .. } => Some(path.clone()),
+                            DocContent::History(history) => {
+                                Some(history.path.clone())
+                            }
+                            _ => None,
+                        }
+                    });
+                    if let Some(path) = path {
+                        if cmd == SourceControlStageActiveFile {
+                            self.source_control.stage_path(&path);
+                        } else {
+                            self.source_control.unstage_path(&path);
+                        }
+                    }
+                }
+            }
+            SourceControlStageHunk | SourceControlUnstageHunk => {
+                if let Some(editor_data) =
+                    self.main_split.active_editor.get_untracked()
+                {
+                    let doc = editor_data.doc();
+                    let offset = editor_data.cursor().with_untracked(|c| c.offset());
+                    let line =
+                        doc.buffer.with_untracked(|b| b.line_of_offset(offset));
+                    // Hunks are staged from the working file and unstaged from the
+                    // index version shown on the right of the staged changes
+                    match doc.content.get_untracked() {
+                        DocContent::File { path, .. }
+                            if cmd == SourceControlStageHunk =>
+                        {
+                            self.common.proxy.git_stage_hunk(path, line);
+                        }
+                        DocContent::History(history)
+                            if cmd == SourceControlUnstageHunk
+                                && history.version == "index" =>
+                        {
+                            self.common.proxy.git_unstage_hunk(history.path, line);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            ShowGitHistory => {
+                self.git_history.show_repository();
+                self.show_panel(PanelKind::GitHistory);
+            }
+            ShowFileHistory => {
+                let path = self.main_split.active_editor.get_untracked().and_then(
+                    |editor_data| {
+                        editor_data.doc().content.with_untracked(|content| {
+                            match content {
+                                DocContent::File { path, .. } => Some(path.clone()),
+                                DocContent::History(history) => {
+                                    Some(history.path.clone())
+                                }
+                                _ => None,
+                            }
+                        })
+                    },
+                );
+                if let Some(path) = path {
+                    self.git_history.show_file(path);
+                    self.show_panel(PanelKind::GitHistory);
+                }
+            }
+            SourceControlOpenBlameCommit => {
+                if let Some(editor_data) =
+                    self.main_split.active_editor.get_untracked()
+                {
+                    let doc = editor_data.doc();
+                    let offset = editor_data.cursor().with_untracked(|c| c.offset());
+                    let line =
+                        doc.buffer.with_untracked(|b| b.line_of_offset(offset));
+                    let Some(path) =
+                        doc.content.with_untracked(|c| c.path().cloned())
+                    else {
+                        return;
+                    };
+                    if let Some(hunk) = doc.blame_of_line(line) {
+                        if let Some(commit) = hunk.commit {
+                            self.main_split.open_commit_file_changes(
+                                path.clone(),
+                                path,
+                                commit,
+                            );
+                        }
+                        return;
+                    }
 
+                    // Blame isn't shown, so it has to be retrieved first
+                    let main_split = self.main_split.clone();
+                    let commit_path = path.clone();
+                    let send = create_ext_action(self.scope, move |result| {
+                        let Ok(ProxyResponse::GitBlameResponse { hunks }) = result
+                        else {
+                            return;
+                        };
+                        if let Some(commit) = hunks
+                            .into_iter()
+                            .find(|hunk| hunk.contains_line(line))
+                            .and_then(|hunk| hunk.commit)
+                        {
+                            main_split.open_commit_file_changes(
+                                commit_path.clone(),
+                                commit_path,
+                                commit,
+                            );
+                        }
+                    });
+                    self.common.proxy.git_blame(path, move |result| {
+                        send(result);
+                    });
+                }
+            }
+            SourceControlFetch => {
+                self.common.proxy.git_fetch();
+            }
+            SourceControlPull => {
+                self.common.proxy.git_pull(None);
+            }
+            SourceControlPullMerge => {
+                self.common.proxy.git_pull(Some(false));
+            }
+            SourceControlPullRebase => {
+                self.common.proxy.git_pull(Some(true));
+            }
+            SourceControlPush => {
+                self.common.proxy.git_push();
+            }
+            SourceControlCreateBranch => {
+                self.palette.run_scm(ScmAction::CreateBranch);
+            }
+            SourceControlRenameBranch => {
+                self.palette.run_scm(ScmAction::RenameBranch);
+            }
+            SourceControlDeleteBranch => {
+                self.palette.run_scm(ScmAction::DeleteBranch { force: false });
+            }
+            SourceControlForceDeleteBranch => {
+                self.palette.run_scm(ScmAction::DeleteBranch { force: true });
+            }
+            SourceControlMergeBranch => {
+                self.palette.run_scm(ScmAction::Merge);
+            }
+            SourceControlStash => {
+                self.palette.run_scm(ScmAction::Stash);
+            }
+            SourceControlStashApply => {
+                self.palette.run_scm(ScmAction::ApplyStash { pop: false });
+            }
+            SourceControlStashPop => {
+                self.palette.run_scm(ScmAction::ApplyStash { pop: true });
+            }
+            SourceControlStashDrop => {
+                self.palette.run_scm(ScmAction::DropStash);
+            }
             // ==== UI ====
             ShowAbout => {
                 self.about_data.open();
@@ -1430,6 +1774,12 @@ pub fn run_workbench_command(
                 self.main_split.next_error();
             }
             PreviousError => {}
+            NextSearchResult => {
+                self.global_search.step_result(true);
+            }
+            PreviousSearchResult => {
+                self.global_search.step_result(false);
+            }
             Quit => {
                 floem::quit_app();
             }
@@ -1530,11 +1880,16 @@ pub fn run_workbench_command(
                         let config = RunDebugConfig {
                             ty: None,
                             name,
+                            request: DebugRequest::Launch,
                             program,
                             args,
                             cwd: None,
                             env: None,
                             prelaunch: None,
+                            process_id: None,
+                            host: None,
+                            port: None,
+                            adapter_args: None,
                             debug_command: None,
                             dap_id: Default::default(),
                             tracing_output: false,
@@ -1572,6 +1927,94 @@ pub fn run_workbench_command(
                     } });
                 }
             }
+            GoToLine => {
+                let Some(args) =
+                    command_args::<GoToLineArgs>("go_to_line", data)
+                else {
+                    return;
+                };
+                let Some(editor_data) = self.main_split.active_editor.get_untracked()
+                else {
+                    return;
+                };
+                let Some(path) = editor_data
+                    .doc()
+                    .content
+                    .with_untracked(|c| c.path().cloned())
+                else {
+                    return;
+                };
+                let line = args.line.saturating_sub(1);
+                let position = match args.column {
+                    Some(column) => {
+                        EditorPosition::LineColumn(line, column.saturating_sub(1))
+                    }
+                    None => EditorPosition::Line(line),
+                };
+                self.common
+                    .internal_command
+                    .send(InternalCommand::JumpToLocation {
+                        location: EditorLocation {
+                            path,
+                            position: Some(position),
+                            scroll_offset: None,
+                            ignore_unconfirmed: false,
+                            same_editor_tab: false,
+                        },
+                    });
+            }
+            InsertText => {
+                let Some(args) =
+                    command_args::<InsertTextArgs>("insert_text", data)
+                else {
+                    return;
+                };
+                if let Some(editor_data) =
+                    self.main_split.active_editor.get_untracked()
+                {
+                    editor_data.insert_text(&args.text);
+                }
+            }
+            RunShellCommand => {
+                let Some(args) =
+                    command_args::<ShellCommandArgs>("run_shell_command", data)
+                else {
+                    return;
+                };
+                // Creates a terminal tab if there isn't one yet
+                self.show_panel(PanelKind::Terminal);
+                let terminal = self
+                    .terminal
+                    .active_tab(false)
+                    .and_then(|tab| tab.active_terminal(false));
+                if let Some(terminal) = terminal {
+                    self.common.proxy.terminal_write(
+                        terminal.term_id,
+                        format!("{}\r", args.command),
+                    );
+                }
+            }
+            RunCommands => {
+                let Some(args) =
+                    command_args::<RunCommandsArgs>("run_commands", data)
+                else {
+                    return;
+                };
+                let commands = self.common.keypress.get_untracked().commands;
+                for arg in args.commands {
+                    let Some(cmd) = commands.get(arg.command()) else {
+                        tracing::error!(
+                            "run_commands: unknown command {}",
+                            arg.command()
+                        );
+                        continue;
+                    };
+                    self.run_lapce_command(LapceCommand {
+                        kind: cmd.kind.clone(),
+                        data: arg.args().cloned().or_else(|| cmd.data.clone()),
+                    });
+                }
+            }
             AddRunDebugConfig => {
                 if let Some(editor_data) =
                     self.main_split.active_editor.get_untracked()
@@ -1579,6 +2022,14 @@ pub fn run_workbench_command(
                     editor_data.receive_char(DEFAULT_RUN_TOML);
                 }
             }
+            Fold | Unfold | ToggleFold | FoldAll | UnfoldAll | FoldLevel1
+            | FoldLevel2 | FoldLevel3 => {
+                if let Some(editor_data) =
+                    self.main_split.active_editor.get_untracked()
+                {
+                    editor_data.run_fold_command(&cmd);
+                }
+            }
 
         }
     }
@@ -1637,8 +2088,15 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
                     None,
                 );
             }
-            InternalCommand::OpenFileChanges { path } => {
-                self.main_split.open_file_changes(path);
+            InternalCommand::OpenFileChanges { path, staged } => {
+                self.main_split.open_file_changes(path, staged);
+            }
+            InternalCommand::OpenCommitFileChanges { path, commit } => {
+                self.main_split
+                    .open_commit_file_changes(path.clone(), path, commit);
+            }
+            InternalCommand::OpenMergeEditor { path } => {
+                self.main_split.open_merge_editor(path);
             }
             InternalCommand::ReloadFileExplorer => {
                 self.file_explorer.reload();
@@ -1856,7 +2314,9 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
                 self.main_split.run_code_action(plugin_id, action);
             }
             InternalCommand::ApplyWorkspaceEdit { edit } => {
-                self.main_split.apply_workspace_edit(&edit);
+                if let Err(e) = self.main_split.apply_workspace_edit(&edit) {
+                    tracing::error!("failed to apply workspace edit: {e}");
+                }
             }
             InternalCommand::SaveJumpLocation {
                 path,
@@ -1892,6 +2352,14 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
             } => {
                 self.rename.start(path, placeholder, start, position);
             }
+            InternalCommand::EditBreakpoint {
+                path,
+                line,
+                offset,
+                kind,
+            } => {
+                self.breakpoint_edit.start(path, line, offset, kind);
+            }
             InternalCommand::Search { pattern } => {
                 self.main_split.set_find_pattern(pattern);
             }
@@ -2085,32 +2553,68 @@ fn handle_core_notification(&self, rpc: &CoreNotification) {
             CoreNotification::ProxyStatus { status } => {
                 self.common.proxy_status.set(Some(status.to_owned()));
             }
-            CoreNotification::DiffInfo { diff } => {
-                self.source_control.branch.set(diff.head.clone());
-                self.source_control
-                    .branches
-                    .set(diff.branches.iter().cloned().collect());
-                self.source_control
-                    .tags
-                    .set(diff.tags.iter().cloned().collect());
-                self.source_control.file_diffs.update(|file_diffs| {
-                    *file_diffs = diff
-                        .diffs
-                        .iter()
-                        .cloned()
-                        .map(|diff| {
-                            let checked = file_diffs
-                                .get(diff.path())
-                                .map_or(true, |(_, c)| *c);
-                            (diff.path().clone(), (diff, checked))
-                        })
-                        .collect();
+            CoreNotification::DiffInfo { root, diff } => {
+                // The branch picker and status bar follow the primary root
+                if self
+                    .workspace
+                    .path
+                    .as_ref()
+                    .map_or(true, |path| path == root)
+                {
+                    self.source_control.root.set(Some(root.clone()));
+                    self.source_control.branch.set(diff.head.clone());
+                    self.source_control
+                        .branches
+                        .set(diff.branches.iter().cloned().collect());
+                    self.source_control
+                        .tags
+                        .set(diff.tags.iter().cloned().collect());
+                    self.source_control.ahead_behind.set(diff.ahead_behind);
+                    self.source_control
+                        .stashes
+                        .set(diff.stashes.iter().cloned().collect());
+                }
+                let roots = self.workspace.roots();
+                self.source_control.root_branches.update(|root_branches| {
+                    root_branches.insert(root.clone(), diff.head.clone());
+                    let position =
+                        |root: &PathBuf| roots.iter().position(|r| r == root);
+                    root_branches
+                        .sort_by(|a, _, b, _| position(a).cmp(&position(b)));
                 });
+                for (file_diffs, diffs) in [
+                    (self.source_control.file_diffs, &diff.diffs),
+                    (self.source_control.staged_diffs, &diff.staged),
+                ] {
+                    file_diffs.update(|file_diffs| {
+                        file_diffs.retain(|path, _| {
+                            self.workspace.root_of(path).as_ref() != Some(root)
+                        });
+                        file_diffs.extend(
+                            diffs
+                                .iter()
+                                .map(|diff| (diff.path().clone(), diff.clone())),
+                        );
+                    });
+                }
 
+                let conflicted =
+                    self.source_control.file_diffs.with_untracked(|file_diffs| {
+                        file_diffs
+                            .iter()
+                            .filter(|(_, diff)| {
+                                matches!(diff, FileDiff::Conflicted(_))
+                            })
+                            .map(|(path, _)| path.clone())
+                            .collect::<im::HashSet<_>>()
+                    });
                 let docs = self.main_split.docs.get_untracked();
-                for (_, doc) in docs {
+                for (path, doc) in docs {
+                    doc.set_conflicted(conflicted.contains(&path));
                     doc.retrieve_head();
                 }
+                self.main_split.conflicted.set(conflicted);
+                self.main_split.refresh_file_changes();
             }
             CoreNotification::CompletionResponse {
                 request_id,
@@ -2219,6 +2723,16 @@ fn handle_core_notification(&self, rpc: &CoreNotification) {
             CoreNotification::DapContinued { dap_id } => {
                 self.terminal.dap_continued(dap_id);
             }
+            CoreNotification::DapOutput { dap_id, output } => {
+                if let Some(dap) = self
+                    .terminal
+                    .debug
+                    .daps
+                    .with_untracked(|daps| daps.get(dap_id).cloned())
+                {
+                    dap.output(output);
+                }
+            }
             CoreNotification::DapBreakpointsResp {
                 path, breakpoints, ..
             } => {
@@ -2260,6 +2774,10 @@ fn handle_core_notification(&self, rpc: &CoreNotification) {
             CoreNotification::OpenFileChanged { path, content } => {
                 self.main_split.open_file_changed(path, content);
             }
+            CoreNotification::GlobalSearchPartial { search_id, matches } => {
+                self.global_search
+                    .partial_matches(*search_id, matches.clone());
+            }
             CoreNotification::VoltInstalled { volt, icon } => {
                 self.plugin.volt_installed(volt, icon);
             }
@@ -2272,6 +2790,25 @@ fn handle_core_notification(&self, rpc: &CoreNotification) {
             CoreNotification::ShowMessage { title, message } => {
                 self.show_message(title, message);
             }
+            CoreNotification::CodeLensRefresh {} => {
+                self.main_split.docs.with_untracked(|docs| {
+                    for doc in docs.values() {
+                        doc.get_code_lens();
+                    }
+                });
+            }
+            CoreNotification::InlayHintRefresh {} => {
+                self.main_split.docs.with_untracked(|docs| {
+                    for doc in docs.values() {
+                        doc.get_inlay_hints();
+                    }
+                });
+            }
+            CoreNotification::SetContext { key, value } => {
+                self.common.keypress.with_untracked(|keypress| {
+                    keypress.set_context(key, value.clone())
+                });
+            }
             CoreNotification::Log {
                 level,
                 message,
@@ -2343,6 +2880,9 @@ pub fn key_down<'a>(&self, event: impl Into<EventRef<'a>> + Copy) -> bool {
                 Some(keypress.key_down(event, &code_action))
             }
             Focus::Rename => Some(keypress.key_down(event, &self.rename)),
+            Focus::BreakpointEdit => {
+                Some(keypress.key_down(event, &self.breakpoint_edit))
+            }
             Focus::AboutPopup => Some(keypress.key_down(event, &self.about_data)),
             Focus::Panel(PanelKind::Terminal) => {
                 self.terminal.key_down(event, &keypress)
@@ -2350,12 +2890,18 @@ pub fn key_down<'a>(&self, event: impl Into<EventRef<'a>> + Copy) -> bool {
             Focus::Panel(PanelKind::Search) => {
                 Some(keypress.key_down(event, &self.global_search))
             }
+            Focus::Panel(PanelKind::GitHistory) => {
+                Some(keypress.key_down(event, &self.git_history))
+            }
             Focus::Panel(PanelKind::Plugin) => {
                 Some(keypress.key_down(event, &self.plugin))
             }
             Focus::Panel(PanelKind::SourceControl) => {
                 Some(keypress.key_down(event, &self.source_control))
             }
+            Focus::Panel(PanelKind::Debug) => {
+                Some(keypress.key_down(event, &self.terminal.debug))
+            }
             _ => None,
         };
 
@@ -2642,9 +3188,10 @@ fn toggle_panel_focus(&self, kind: PanelKind) {
                 // in those cases.
                 self.panel.is_panel_visible(&kind)
             }
-            PanelKind::Terminal | PanelKind::SourceControl | PanelKind::Search => {
-                self.is_panel_focused(kind)
-            }
+            PanelKind::Terminal
+            | PanelKind::SourceControl
+            | PanelKind::Search
+            | PanelKind::GitHistory => self.is_panel_focused(kind),
         };
         if should_hide {
             self.hide_panel(kind);
@@ -2744,9 +3291,22 @@ fn run_and_debug(
                 self.run_in_terminal(cx, mode, config, false);
             }
             RunDebugMode::Debug => {
+                let attach = config.request == DebugRequest::Attach;
+                if attach && config.process_id.is_none() && config.port.is_none() {
+                    // let the user pick the process to attach to first
+                    self.palette.attach_config.set(Some(config.clone()));
+                    self.palette.run(PaletteKind::DebugProcess);
+                    return;
+                }
+
                 if config.prelaunch.is_some() {
                     self.run_in_terminal(cx, mode, config, false);
                 } else {
+                    if attach {
+                        // the debugger won't ask for a terminal to run the
+                        // program in, so create the session's terminal here
+                        self.run_in_terminal(cx, mode, config, true);
+                    }
                     self.common.proxy.dap_start(
                         config.clone(),
                         self.terminal.debug.source_breakpoints(),
@@ -2810,17 +3370,17 @@ fn run_in_terminal(
     }
 
     pub fn open_paths(&self, paths: &[PathObject]) {
-        let (folders, files): (Vec<&PathObject>, Vec<&PathObject>) =
-            paths.iter().partition(|p| p.is_dir);
+        let (folders, files): (Vec<&PathObject>, Vec<&PathObject>) = paths
+            .iter()
+            .partition(|p| p.is_dir || LapceWorkspace::is_workspace_file(&p.path));
 
         for folder in folders {
             self.common.window_common.window_command.send(
                 WindowCommand::NewWorkspaceTab {
-                    workspace: LapceWorkspace {
-                        kind: self.workspace.kind.clone(),
-                        path: Some(folder.path.clone()),
-                        last_open: 0,
-                    },
+                    workspace: LapceWorkspace::from_path(
+                        self.workspace.kind.clone(),
+                        folder.path.clone(),
+                    ),
                     end: false,
                 },
             );
@@ -2851,6 +3411,8 @@ pub fn open_paths(&self, paths: &[PathObject]) {
     }
 
     pub fn show_alert(&self, title: String, msg: String, buttons: Vec<AlertButton>) {
+        // The alert being replaced counts as dismissed
+        self.alert_data.run_cancel();
         self.alert_data.title.set(title);
         self.alert_data.msg.set(msg);
         self.alert_data.buttons.set(buttons);
@@ -2889,12 +3451,182 @@ fn update_progress(&self, progress: &ProgressParams) {
         }
     }
 
+    /// Run a vim ex command typed in the ex command line
+    fn run_ex_command(&self, input: &str) -> anyhow::Result<()> {
+        let command = ExCommand::parse(input)?;
+        match &command.kind {
+            ExCommandKind::Edit { path } => {
+                let path = PathBuf::from(path);
+                let path = match self.workspace.path.as_ref() {
+                    Some(workspace) if path.is_relative() => workspace.join(path),
+                    _ => path,
+                };
+                self.common
+                    .internal_command
+                    .send(InternalCommand::OpenFile { path });
+                return Ok(());
+            }
+            ExCommandKind::NoHighlight => {
+                self.common.find.visual.set(false);
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let editor = self
+            .main_split
+            .active_editor
+            .get_untracked()
+            .ok_or_else(|| anyhow::anyhow!("no active editor"))?;
+        let selection = editor.selection_lines();
+        // The selection is taken by `'<,'>`, so visual mode is left as in vim
+        editor.cursor().update(|cursor| {
+            if let CursorMode::Visual { end, .. } = cursor.mode {
+                cursor.mode = CursorMode::Normal(end);
+            }
+        });
+        let tab_child = match editor.editor_tab_id.get_untracked() {
+            Some(editor_tab_id) => {
+                Some((editor_tab_id, EditorTabChild::Editor(editor.id())))
+            }
+            None => editor.diff_editor_id.get_untracked().map(
+                |(editor_tab_id, diff_editor_id)| {
+                    (editor_tab_id, EditorTabChild::DiffEditor(diff_editor_id))
+                },
+            ),
+        };
+
+        match &command.kind {
+            ExCommandKind::Write => {
+                editor.save(true, || {});
+            }
+            ExCommandKind::Quit { force } => {
+                if let Some((editor_tab_id, child)) = tab_child {
+                    self.main_split.editor_tab_child_close(
+                        editor_tab_id,
+                        child,
+                        *force,
+                    );
+                }
+            }
+            ExCommandKind::WriteQuit => {
+                let Some((editor_tab_id, child)) = tab_child else {
+                    return Ok(());
+                };
+                if editor.doc().is_pristine() {
+                    self.main_split.editor_tab_child_close(
+                        editor_tab_id,
+                        child,
+                        false,
+                    );
+                } else {
+                    let main_split = self.main_split.clone();
+                    editor.save(true, move || {
+                        main_split.editor_tab_child_close(
+                            editor_tab_id,
+                            child,
+                            false,
+                        );
+                    });
+                }
+            }
+            _ => {
+                let (current, last) = editor.doc().buffer.with_untracked(|buffer| {
+                    let offset = editor.cursor().with_untracked(|c| c.offset());
+                    (buffer.line_of_offset(offset), buffer.last_line())
+                });
+                let (start, end) =
+                    command.range().resolve(current, last, selection)?;
+                if command.kind == ExCommandKind::GoToLine {
+                    self.run_workbench_command(
+                        LapceWorkbenchCommand::GoToLine,
+                        Some(serde_json::json!({ "line": end + 1 })),
+                    );
+                } else {
+                    editor.run_ex_edit(&command.kind, start, end)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn show_message(&self, title: &str, message: &ShowMessageParams) {
         self.messages.update(|messages| {
             messages.push((title.to_string(), message.clone()));
         });
     }
 
+    /// Answer a request of the proxy, which is blocked until it gets the
+    /// response
+    fn handle_core_request(&self, id: RequestId, rpc: &CoreRequest) {
+        match rpc {
+            CoreRequest::ApplyWorkspaceEdit { edit } => {
+                let failure_reason =
+                    self.main_split.apply_workspace_edit(edit).err();
+                self.proxy.core_rpc.handle_response(
+                    id,
+                    Ok(CoreResponse::ApplyWorkspaceEditResponse {
+                        applied: failure_reason.is_none(),
+                        failure_reason,
+                    }),
+                );
+            }
+            CoreRequest::ShowMessageRequest { title, message } => {
+                self.show_message_request(id, title, message);
+            }
+        }
+    }
+
+    /// Show the message of a language server in the alert box, answering with
+    /// the action picked or with none when it's dismissed
+    fn show_message_request(
+        &self,
+        id: RequestId,
+        title: &str,
+        message: &ShowMessageRequestParams,
+    ) {
+        let core_rpc = self.proxy.core_rpc.clone();
+        let respond = move |action: Option<MessageActionItem>| {
+            core_rpc.handle_response(
+                id,
+                Ok(CoreResponse::ShowMessageRequestResponse { action }),
+            );
+        };
+
+        let actions = message.actions.clone().unwrap_or_default();
+        if actions.is_empty() {
+            self.show_message(
+                title,
+                &ShowMessageParams {
+                    typ: message.typ,
+                    message: message.message.clone(),
+                },
+            );
+            respond(None);
+            return;
+        }
+
+        let buttons = actions
+            .into_iter()
+            .map(|action| {
+                let alert_data = self.alert_data.clone();
+                let respond = respond.clone();
+                AlertButton {
+                    text: action.title.clone(),
+                    action: Rc::new(move || {
+                        alert_data.on_cancel.set(None);
+                        alert_data.active.set(false);
+                        respond(Some(action.clone()));
+                    }),
+                }
+            })
+            .collect();
+        self.show_alert(title.to_string(), message.message.clone(), buttons);
+        self.alert_data
+            .on_cancel
+            .set(Some(Rc::new(move || respond(None))));
+    }
+
     pub fn update_code_lens_id(&self, view_id: Option<ViewId>) {
         if let Some(Some(old_id)) = self.code_lens.try_update(|x| {
             let old = x.take();
@@ -2983,6 +3715,16 @@ pub fn call_hierarchy_incoming(&self, item_id: ViewId) {
     }
 }
 
+/// Deserialize the args of `command`, logging why when they're malformed
+fn command_args<T: DeserializeOwned>(
+    command: &str,
+    data: Option<Value>,
+) -> Option<T> {
+    serde_json::from_value(data?)
+        .map_err(|err| tracing::error!("{command}: invalid args: {err}"))
+        .ok()
+}
+
 /// Open path with the default application without blocking.
 fn open_uri(path: &Path) {
     match open::that(path) {
@@ -1,5 +1,10 @@
-use std::{collections::HashMap, fmt::Display, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
 
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use crate::{debug::LapceBreakpoint, main_split::SplitInfo, panel::data::PanelInfo};
@@ -101,21 +106,118 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
+/// The extension of files describing a multi-root workspace
+pub const WORKSPACE_FILE_EXTENSION: &str = "lapce-workspace";
+
+/// The content of a `.lapce-workspace` file, e.g.
+/// ```toml
+/// folders = ["lapce", "../floem"]
+/// ```
+/// Relative folders are resolved against the directory of the file.
+#[derive(Debug, Default, Deserialize)]
+struct WorkspaceFile {
+    #[serde(default)]
+    folders: Vec<PathBuf>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LapceWorkspace {
     pub kind: LapceWorkspaceType,
+    /// The primary root folder of the workspace
     pub path: Option<PathBuf>,
+    /// The `.lapce-workspace` file the workspace was opened from
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    /// All the root folders of a multi-root workspace, starting with `path`
+    #[serde(default)]
+    pub folders: Vec<PathBuf>,
     pub last_open: u64,
 }
 
 impl LapceWorkspace {
+    pub fn is_workspace_file(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|ext| ext == WORKSPACE_FILE_EXTENSION)
+    }
+
+    /// Open the multi-root workspace described by a `.lapce-workspace` file
+    pub fn from_workspace_file(
+        kind: LapceWorkspaceType,
+        file: &Path,
+    ) -> Result<Self> {
+        let content = std::fs::read_to_string(file)?;
+        let folders = parse_workspace_file(file, &content)?;
+        Ok(Self {
+            kind,
+            path: folders.first().cloned(),
+            file: Some(file.to_path_buf()),
+            folders,
+            last_open: 0,
+        })
+    }
+
+    /// Open `path` as a workspace, which is either a folder or a
+    /// `.lapce-workspace` file
+    pub fn from_path(kind: LapceWorkspaceType, path: PathBuf) -> Self {
+        if Self::is_workspace_file(&path) {
+            match Self::from_workspace_file(kind.clone(), &path) {
+                Ok(workspace) => return workspace,
+                Err(err) => {
+                    tracing::error!("failed to read workspace file: {err}");
+                }
+            }
+        }
+        Self {
+            kind,
+            path: Some(path),
+            ..Default::default()
+        }
+    }
+
+    /// The root folders of the workspace
+    pub fn roots(&self) -> Vec<PathBuf> {
+        if self.folders.is_empty() {
+            self.path.iter().cloned().collect()
+        } else {
+            self.folders.clone()
+        }
+    }
+
+    pub fn is_multi_root(&self) -> bool {
+        self.folders.len() > 1
+    }
+
+    /// The root folder that contains `path`, preferring the innermost one
+    /// when roots are nested
+    pub fn root_of(&self, path: &Path) -> Option<PathBuf> {
+        self.roots()
+            .into_iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+    }
+
+    /// The path relative to the root folder containing it, prefixed with
+    /// the name of that root in multi-root workspaces
+    pub fn relative_path(&self, path: &Path) -> PathBuf {
+        let Some(root) = self.root_of(path) else {
+            return path.to_path_buf();
+        };
+        let relative = path.strip_prefix(&root).unwrap_or(path);
+        match root.file_name() {
+            Some(name) if self.is_multi_root() => Path::new(name).join(relative),
+            _ => relative.to_path_buf(),
+        }
+    }
+
     pub fn display(&self) -> Option<String> {
-        let path = self.path.as_ref()?;
-        let path = path
-            .file_name()
-            .unwrap_or(path.as_os_str())
-            .to_string_lossy()
-            .to_string();
+        let path = match self.file.as_ref() {
+            Some(file) => file.file_stem().unwrap_or(file.as_os_str()),
+            None => {
+                let path = self.path.as_ref()?;
+                path.file_name().unwrap_or(path.as_os_str())
+            }
+        };
+        let path = path.to_string_lossy().to_string();
         let remote = match &self.kind {
             LapceWorkspaceType::Local => String::new(),
             LapceWorkspaceType::RemoteSSH(remote) => {
@@ -135,6 +237,8 @@ fn default() -> Self {
         Self {
             kind: LapceWorkspaceType::Local,
             path: None,
+            file: None,
+            folders: Vec::new(),
             last_open: 0,
         }
     }
@@ -146,14 +250,79 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             f,
             "{}:{}",
             self.kind,
-            self.path.as_ref().and_then(|p| p.to_str()).unwrap_or("")
+            self.file
+                .as_ref()
+                .or(self.path.as_ref())
+                .and_then(|p| p.to_str())
+                .unwrap_or("")
         )
     }
 }
 
+fn parse_workspace_file(file: &Path, content: &str) -> Result<Vec<PathBuf>> {
+    let workspace_file: WorkspaceFile = toml::from_str(content)?;
+    let dir = file.parent().unwrap_or(Path::new(""));
+    let mut folders: Vec<PathBuf> = Vec::new();
+    for folder in workspace_file.folders {
+        let folder = dir.join(folder);
+        let folder = folder.canonicalize().unwrap_or(folder);
+        if !folders.contains(&folder) {
+            folders.push(folder);
+        }
+    }
+    if folders.is_empty() {
+        anyhow::bail!("no folders listed in {}", file.display());
+    }
+    Ok(folders)
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct WorkspaceInfo {
     pub split: SplitInfo,
     pub panel: PanelInfo,
     pub breakpoints: HashMap<PathBuf, Vec<LapceBreakpoint>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{parse_workspace_file, LapceWorkspace, LapceWorkspaceType};
+
+    #[test]
+    fn test_workspace_file_folders() {
+        let file = Path::new("/nonexistent/project/app.lapce-workspace");
+        let folders = parse_workspace_file(
+            file,
+            r#"folders = ["app", "../lib", "app", "/abs/other"]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            folders,
+            vec![
+                PathBuf::from("/nonexistent/project/app"),
+                PathBuf::from("/nonexistent/project/../lib"),
+                PathBuf::from("/abs/other"),
+            ]
+        );
+        assert!(parse_workspace_file(file, "folders = []").is_err());
+
+        let workspace = LapceWorkspace {
+            kind: LapceWorkspaceType::Local,
+            path: folders.first().cloned(),
+            file: Some(file.to_path_buf()),
+            folders,
+            last_open: 0,
+        };
+        assert!(workspace.is_multi_root());
+        assert_eq!(
+            workspace.relative_path(Path::new("/abs/other/src/main.rs")),
+            PathBuf::from("other/src/main.rs")
+        );
+        assert_eq!(
+            workspace.root_of(Path::new("/nonexistent/project/app/Cargo.toml")),
+            Some(PathBuf::from("/nonexistent/project/app"))
+        );
+        assert_eq!(workspace.display().as_deref(), Some("app"));
+    }
+}
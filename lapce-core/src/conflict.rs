@@ -0,0 +1,176 @@
+use std::ops::Range;
+
+const START_MARKER: &str = "<<<<<<<";
+const BASE_MARKER: &str = "|||||||";
+const SEPARATOR_MARKER: &str = "=======";
+const END_MARKER: &str = ">>>>>>>";
+
+/// Which side of a merge conflict is kept when resolving it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// The change on the checked out branch
+    Current,
+    /// The change being merged in
+    Incoming,
+    /// The current change followed by the incoming change
+    Both,
+}
+
+/// The marker lines of a conflict block a merge left in a file, as line
+/// numbers
+///
+/// ```text
+/// <<<<<<< HEAD          start
+/// current lines
+/// ||||||| base          base, only with the `diff3` conflict style
+/// base lines
+/// =======               separator
+/// incoming lines
+/// >>>>>>> branch        end
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ConflictBlock {
+    pub start: usize,
+    pub base: Option<usize>,
+    pub separator: usize,
+    pub end: usize,
+}
+
+impl ConflictBlock {
+    /// The lines of the checked out branch
+    pub fn current_lines(&self) -> Range<usize> {
+        self.start + 1..self.base.unwrap_or(self.separator)
+    }
+
+    /// The lines of the common ancestor, if the markers include them
+    pub fn base_lines(&self) -> Option<Range<usize>> {
+        self.base.map(|base| base + 1..self.separator)
+    }
+
+    /// The lines being merged in
+    pub fn incoming_lines(&self) -> Range<usize> {
+        self.separator + 1..self.end
+    }
+
+    /// The lines that replace the whole block once resolved
+    pub fn kept_lines(&self, resolution: ConflictResolution) -> Vec<Range<usize>> {
+        match resolution {
+            ConflictResolution::Current => vec![self.current_lines()],
+            ConflictResolution::Incoming => vec![self.incoming_lines()],
+            ConflictResolution::Both => {
+                vec![self.current_lines(), self.incoming_lines()]
+            }
+        }
+    }
+
+    pub fn contains_line(&self, line: usize) -> bool {
+        self.start <= line && line <= self.end
+    }
+}
+
+fn is_marker(line: &str, marker: &str) -> bool {
+    match line.strip_prefix(marker) {
+        // A longer run of the marker character is content, not a marker
+        Some(rest) => rest.is_empty() || rest.starts_with(char::is_whitespace),
+        None => false,
+    }
+}
+
+/// Find the conflict blocks in the lines of a file, skipping incomplete ones
+pub fn parse_conflicts<S: AsRef<str>>(
+    lines: impl IntoIterator<Item = S>,
+) -> Vec<ConflictBlock> {
+    let mut blocks = Vec::new();
+    let mut start = None;
+    let mut base = None;
+    let mut separator = None;
+    for (i, line) in lines.into_iter().enumerate() {
+        let line = line.as_ref();
+        if is_marker(line, START_MARKER) {
+            start = Some(i);
+            base = None;
+            separator = None;
+        } else if start.is_none() {
+            continue;
+        } else if is_marker(line, BASE_MARKER)
+            && base.is_none()
+            && separator.is_none()
+        {
+            base = Some(i);
+        } else if is_marker(line, SEPARATOR_MARKER) && separator.is_none() {
+            separator = Some(i);
+        } else if is_marker(line, END_MARKER) {
+            if let (Some(start), Some(separator)) = (start, separator) {
+                blocks.push(ConflictBlock {
+                    start,
+                    base,
+                    separator,
+                    end: i,
+                });
+            }
+            start = None;
+            base = None;
+            separator = None;
+        }
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_conflicts() {
+        let text = "\
+a
+<<<<<<< HEAD
+ours
+=======
+theirs
+>>>>>>> feature
+b
+<<<<<<< HEAD
+||||||| base
+base
+=======
+theirs
+>>>>>>> feature
+<<<<<<< incomplete
+=======
+c";
+        let blocks = parse_conflicts(text.lines());
+        assert_eq!(
+            blocks,
+            vec![
+                ConflictBlock {
+                    start: 1,
+                    base: None,
+                    separator: 3,
+                    end: 5,
+                },
+                ConflictBlock {
+                    start: 7,
+                    base: Some(8),
+                    separator: 10,
+                    end: 12,
+                },
+            ]
+        );
+
+        assert_eq!(blocks[0].current_lines(), 2..3);
+        assert_eq!(blocks[0].incoming_lines(), 4..5);
+        assert_eq!(blocks[0].base_lines(), None);
+        assert_eq!(blocks[1].current_lines(), 8..8);
+        assert_eq!(blocks[1].base_lines(), Some(9..10));
+        assert_eq!(
+            blocks[0].kept_lines(ConflictResolution::Both),
+            vec![2..3, 4..5]
+        );
+
+        assert!(
+            parse_conflicts("<<<<<<<< not a marker\n=======\n>>>>>>>".lines())
+                .is_empty()
+        );
+    }
+}
@@ -142,6 +142,21 @@ pub fn config_directory() -> Option<PathBuf> {
         }
     }
 
+    /// The user's snippet files, next to the settings
+    pub fn snippets_directory() -> Option<PathBuf> {
+        if let Some(dir) = Self::config_directory() {
+            let dir = dir.join("snippets");
+            if !dir.exists() {
+                if let Err(err) = std::fs::create_dir(&dir) {
+                    tracing::error!("{:?}", err);
+                }
+            }
+            Some(dir)
+        } else {
+            None
+        }
+    }
+
     pub fn local_socket() -> Option<PathBuf> {
         Self::data_local_directory().map(|dir| dir.join("local.sock"))
     }
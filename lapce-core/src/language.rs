@@ -1,13 +1,15 @@
 use std::{
     collections::{hash_map::Entry, HashMap, HashSet},
     fmt::Write,
-    path::Path,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
 use lapce_rpc::style::{LineStyle, Style};
 use once_cell::sync::Lazy;
+use parking_lot::RwLock;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use strum_macros::{AsRefStr, Display, EnumMessage, EnumString, IntoStaticStr};
 use tracing::{event, Level};
 use tree_sitter::{Point, TreeCursor};
@@ -167,8 +169,9 @@ struct CommentProperties {
     multi_line_prefix: Option<&'static str>,
 }
 
-/// NOTE: Keep the enum variants "fieldless" so they can cast to usize as array
-/// indices into the LANGUAGES array.  See method `LapceLanguage::properties`.
+/// NOTE: Keep the built-in enum variants "fieldless", the LANGUAGES array holds
+/// their properties in variant order.  See method `LapceLanguage::properties`.
+/// The only exception is `User`, for languages declared at runtime.
 ///
 /// Do not assign values to the variants because the number of variants and
 /// number of elements in the LANGUAGES array change as different features
@@ -444,6 +447,298 @@ pub enum LapceLanguage {
     Yaml,
     #[strum(message = "Zig")]
     Zig,
+    /// A language declared at runtime, see [`LanguageDefinition`]
+    #[remain::unsorted]
+    #[strum(disabled)]
+    User(UserLanguageId),
+}
+
+/// The id of a language declared at runtime, which stays the same for a name
+/// when the declarations are reloaded.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug, PartialOrd, Ord)]
+pub struct UserLanguageId(usize);
+
+/// A language declared in `languages.toml` or contributed by a volt, so that
+/// languages and grammars can be added without recompiling.
+///
+/// When `name` matches a built-in language the definition extends it, otherwise
+/// a new language is added.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LanguageDefinition {
+    pub name: String,
+    /// File names that belong to this language, like `Dockerfile`
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// File name extensions that belong to this language, like `rs`
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// The token used when commenting out one line
+    pub comment: Option<String>,
+    /// The start and end tokens of a block comment
+    pub block_comment: Option<(String, String)>,
+    /// The indent unit, like `"  "` or `"\t"`
+    pub indent: Option<String>,
+    /// The grammar name, loaded from `libtree-sitter-<grammar>`
+    pub grammar: Option<String>,
+    /// The grammar fn name, loaded from `tree_sitter_<grammar-fn>`
+    pub grammar_fn: Option<String>,
+    /// The directory of the grammar library, instead of the grammars directory
+    pub grammar_dir: Option<PathBuf>,
+    /// The directory holding `highlights.scm` and `injections.scm`, instead of
+    /// the folder for the language in the queries directory
+    pub query_dir: Option<PathBuf>,
+}
+
+/// The contents of a `languages.toml` file, with one `[[language]]` table per
+/// definition
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LanguageDefinitions {
+    #[serde(default)]
+    language: Vec<LanguageDefinition>,
+}
+
+impl LanguageDefinition {
+    /// Read the `[[language]]` definitions of a file. Relative grammar and
+    /// query directories are resolved against the directory of the file.
+    pub fn load(path: &Path) -> Vec<LanguageDefinition> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                tracing::error!("Failed to read {path:?}: {err}");
+                return Vec::new();
+            }
+        };
+        let definitions = match toml::from_str::<LanguageDefinitions>(&content) {
+            Ok(definitions) => definitions.language,
+            Err(err) => {
+                tracing::error!("Failed to parse {path:?}: {err}");
+                return Vec::new();
+            }
+        };
+
+        let base = path.parent().unwrap_or(path);
+        definitions
+            .into_iter()
+            .map(|mut definition| {
+                definition.grammar_dir =
+                    definition.grammar_dir.map(|dir| base.join(dir));
+                definition.query_dir =
+                    definition.query_dir.map(|dir| base.join(dir));
+                definition
+            })
+            .collect()
+    }
+}
+
+/// A language declared at runtime, or a built-in language that a
+/// [`LanguageDefinition`] extends
+struct UserLanguage {
+    properties: &'static SyntaxProperties,
+    grammar_dir: Option<PathBuf>,
+    query_dir: Option<PathBuf>,
+}
+
+/// The language definitions are kept for the lifetime of the program, so their
+/// strings are leaked to fit the `'static` properties of the built-in
+/// languages. Each distinct value is only leaked once, so registering the same
+/// definitions again on a config reload doesn't leak any more memory.
+#[derive(Default)]
+struct Interner {
+    strs: HashSet<&'static str>,
+    lists: HashSet<&'static [&'static str]>,
+    properties: HashSet<&'static SyntaxProperties>,
+}
+
+impl Interner {
+    fn str(&mut self, s: &str) -> &'static str {
+        if let Some(s) = self.strs.get(s) {
+            return s;
+        }
+        let s: &'static str = Box::leak(s.to_string().into_boxed_str());
+        self.strs.insert(s);
+        s
+    }
+
+    fn list(
+        &mut self,
+        base: &'static [&'static str],
+        extra: impl Iterator<Item = String>,
+    ) -> &'static [&'static str] {
+        let mut list = base.to_vec();
+        list.extend(extra.map(|s| self.str(&s)));
+        if list.len() == base.len() {
+            return base;
+        }
+        if let Some(list) = self.lists.get(list.as_slice()) {
+            return list;
+        }
+        let list: &'static [&'static str] = Box::leak(list.into_boxed_slice());
+        self.lists.insert(list);
+        list
+    }
+
+    fn properties(
+        &mut self,
+        properties: SyntaxProperties,
+    ) -> &'static SyntaxProperties {
+        if let Some(properties) = self.properties.get(&properties) {
+            return properties;
+        }
+        let properties: &'static SyntaxProperties = Box::leak(Box::new(properties));
+        self.properties.insert(properties);
+        properties
+    }
+}
+
+#[derive(Default)]
+struct UserLanguages {
+    /// The definitions that `languages` was built from
+    definitions: Vec<LanguageDefinition>,
+    /// The names of every language added at runtime, indexed by
+    /// `UserLanguageId`
+    names: Vec<&'static str>,
+    /// The lowercase `names`, which are their language ids for language servers
+    language_ids: Vec<&'static str>,
+    /// In declaration order, so that they take precedence in `from_path`
+    languages: Vec<UserLanguage>,
+    interner: Interner,
+}
+
+impl UserLanguages {
+    fn get(&self, language: &LapceLanguage) -> Option<&UserLanguage> {
+        self.languages.iter().find(|l| l.properties.id == *language)
+    }
+
+    fn find_by_path(
+        &self,
+        filename: Option<&str>,
+        extension: Option<&str>,
+    ) -> Option<LapceLanguage> {
+        self.languages
+            .iter()
+            .find(|l| l.properties.matches(filename, extension))
+            .map(|l| l.properties.id)
+    }
+
+    fn find_by_name(&self, name: &str) -> Option<LapceLanguage> {
+        let i = self
+            .names
+            .iter()
+            .position(|n| n.eq_ignore_ascii_case(name))?;
+        Some(LapceLanguage::User(UserLanguageId(i)))
+    }
+
+    fn name(&self, id: UserLanguageId) -> &'static str {
+        self.names.get(id.0).copied().unwrap_or_default()
+    }
+
+    fn language_id(&self, id: UserLanguageId) -> Option<&'static str> {
+        self.language_ids.get(id.0).copied()
+    }
+
+    /// Replace the languages declared at runtime with `definitions`
+    fn register(&mut self, definitions: Vec<LanguageDefinition>) {
+        if self.definitions == definitions {
+            return;
+        }
+
+        self.languages.clear();
+        for definition in &definitions {
+            let builtin = LapceLanguage::from_str(&definition.name.to_lowercase())
+                .ok()
+                .map(|language| *language.builtin_properties());
+            let mut properties = match builtin {
+                Some(properties) => properties,
+                None => {
+                    let i = match self
+                        .names
+                        .iter()
+                        .position(|n| n.eq_ignore_ascii_case(&definition.name))
+                    {
+                        Some(i) => i,
+                        None => {
+                            self.names.push(self.interner.str(&definition.name));
+                            self.language_ids.push(
+                                self.interner.str(&definition.name.to_lowercase()),
+                            );
+                            self.names.len() - 1
+                        }
+                    };
+                    let name = definition.name.to_lowercase();
+                    let grammar = definition.grammar.as_deref().unwrap_or(&name);
+                    SyntaxProperties {
+                        id: LapceLanguage::User(UserLanguageId(i)),
+                        comment: comment_properties!(),
+                        indent: Indent::space(4),
+                        files: &[],
+                        extensions: &[],
+                        tree_sitter: TreeSitterProperties {
+                            grammar: Some(self.interner.str(grammar)),
+                            grammar_fn: Some(self.interner.str(grammar)),
+                            query: Some(self.interner.str(&name)),
+                            ..TreeSitterProperties::DEFAULT
+                        },
+                    }
+                }
+            };
+
+            let interner = &mut self.interner;
+            properties.files =
+                interner.list(properties.files, definition.files.iter().cloned());
+            properties.extensions = interner.list(
+                properties.extensions,
+                definition
+                    .extensions
+                    .iter()
+                    .map(|e| e.trim_start_matches('.').to_lowercase()),
+            );
+            if let Some(comment) = &definition.comment {
+                properties.comment.single_line_start = Some(interner.str(comment));
+            }
+            if let Some((start, end)) = &definition.block_comment {
+                properties.comment.multi_line_start = Some(interner.str(start));
+                properties.comment.multi_line_end = Some(interner.str(end));
+            }
+            if let Some(indent) = &definition.indent {
+                properties.indent = interner.str(indent);
+            }
+            if let Some(grammar) = &definition.grammar {
+                properties.tree_sitter.grammar = Some(interner.str(grammar));
+            }
+            if let Some(grammar_fn) = &definition.grammar_fn {
+                properties.tree_sitter.grammar_fn = Some(interner.str(grammar_fn));
+            }
+
+            // A later definition of the same language replaces the earlier one
+            self.languages.retain(|l| l.properties.id != properties.id);
+            self.languages.push(UserLanguage {
+                properties: interner.properties(properties),
+                grammar_dir: definition.grammar_dir.clone(),
+                query_dir: definition.query_dir.clone(),
+            });
+        }
+        self.definitions = definitions;
+    }
+}
+
+static USER_LANGUAGES: Lazy<RwLock<UserLanguages>> =
+    Lazy::new(|| RwLock::new(UserLanguages::default()));
+
+static BUILTIN_LANGUAGE_INDEX: Lazy<HashMap<LapceLanguage, usize>> =
+    Lazy::new(|| {
+        LANGUAGES
+            .iter()
+            .enumerate()
+            .map(|(i, properties)| (properties.id, i))
+            .collect()
+    });
+
+impl SyntaxProperties {
+    fn matches(&self, filename: Option<&str>, extension: Option<&str>) -> bool {
+        self.files.iter().any(|f| Some(*f) == filename)
+            || self.extensions.iter().any(|e| Some(*e) == extension)
+    }
 }
 
 /// NOTE: Elements in the array must be in the same order as the enum variants of
@@ -1689,15 +1984,14 @@ pub fn from_path_raw(path: &Path) -> Option<LapceLanguage> {
             .and_then(|s| s.to_str().map(|s| s.to_lowercase()));
         // NOTE: This is a linear search.  It is assumed that this function
         // isn't called in any tight loop.
+        if let Some(language) = USER_LANGUAGES
+            .read()
+            .find_by_path(filename, extension.as_deref())
+        {
+            return Some(language);
+        }
         for properties in LANGUAGES {
-            if properties.files.iter().any(|f| Some(*f) == filename) {
-                return Some(properties.id);
-            }
-            if properties
-                .extensions
-                .iter()
-                .any(|e| Some(*e) == extension.as_deref())
-            {
+            if properties.matches(filename, extension.as_deref()) {
                 return Some(properties.id);
             }
         }
@@ -1706,6 +2000,10 @@ pub fn from_path_raw(path: &Path) -> Option<LapceLanguage> {
     }
 
     pub fn from_name(name: &str) -> Option<LapceLanguage> {
+        if let Some(language) = USER_LANGUAGES.read().find_by_name(name) {
+            return Some(language);
+        }
+
         match LapceLanguage::from_str(name.to_lowercase().as_str()) {
             Ok(v) => Some(v),
             Err(e) => {
@@ -1723,29 +2021,52 @@ pub fn languages() -> Vec<&'static str> {
                 langs.push(lang)
             }
         }
+        let user_languages = USER_LANGUAGES.read();
+        for l in &user_languages.languages {
+            if let LapceLanguage::User(id) = l.properties.id {
+                langs.push(user_languages.name(id));
+            }
+        }
         langs
     }
 
-    // NOTE: Instead of using `&LANGUAGES[*self as usize]` directly, the
-    // `debug_assertion` gives better feedback should something has gone wrong
-    // badly.
-    fn properties(&self) -> &SyntaxProperties {
-        let i = *self as usize;
-        let l = &LANGUAGES[i];
-        debug_assert!(
-            l.id == *self,
-            "LANGUAGES[{i}]: Setting::id mismatch: {:?} != {:?}",
-            l.id,
-            self
-        );
-        l
+    /// Replace the languages declared at runtime with `definitions`. Documents
+    /// pick up the changes when their language is detected again.
+    pub fn register(definitions: Vec<LanguageDefinition>) {
+        USER_LANGUAGES.write().register(definitions);
+    }
+
+    fn builtin_properties(&self) -> &'static SyntaxProperties {
+        match BUILTIN_LANGUAGE_INDEX.get(self) {
+            Some(i) => &LANGUAGES[*i],
+            None => &LANGUAGES[0],
+        }
+    }
+
+    fn properties(&self) -> &'static SyntaxProperties {
+        if let Some(language) = USER_LANGUAGES.read().get(self) {
+            return language.properties;
+        }
+        self.builtin_properties()
     }
 
     pub fn name(&self) -> &'static str {
+        if let LapceLanguage::User(id) = self {
+            return USER_LANGUAGES.read().name(*id);
+        }
         strum::EnumMessage::get_message(self).unwrap_or(self.into())
     }
 
-    pub fn sticky_header_tags(&self) -> &[&'static str] {
+    /// The language id that language servers get for a language declared at
+    /// runtime, which is its lowercase name
+    pub fn language_id(&self) -> Option<&'static str> {
+        match self {
+            LapceLanguage::User(id) => USER_LANGUAGES.read().language_id(*id),
+            _ => None,
+        }
+    }
+
+    pub fn sticky_header_tags(&self) -> &'static [&'static str] {
         self.properties().tree_sitter.sticky_headers
     }
 
@@ -1756,7 +2077,7 @@ pub fn comment_token(&self) -> &'static str {
             .unwrap_or_default()
     }
 
-    pub fn indent_unit(&self) -> &str {
+    pub fn indent_unit(&self) -> &'static str {
         self.properties().indent
     }
 
@@ -1764,7 +2085,12 @@ fn get_grammar(&self) -> Option<tree_sitter::Language> {
         let grammar_name = self.grammar_name();
         let grammar_fn_name = self.grammar_fn_name();
 
-        if let Some(grammars_dir) = Directory::grammars_directory() {
+        let grammars_dir = USER_LANGUAGES
+            .read()
+            .get(self)
+            .and_then(|l| l.grammar_dir.clone())
+            .or_else(Directory::grammars_directory);
+        if let Some(grammars_dir) = grammars_dir {
             match self::load_grammar(&grammar_name, &grammar_fn_name, &grammars_dir)
             {
                 Ok(grammar) => {
@@ -1807,6 +2133,29 @@ fn grammar_fn_name(&self) -> String {
 
     fn get_grammar_query(&self) -> (String, String) {
         let query_name = self.query_name();
+        let query_dir = USER_LANGUAGES
+            .read()
+            .get(self)
+            .and_then(|l| l.query_dir.clone());
+
+        // Queries of a declared language can inherit from the ones in the
+        // user config dir
+        if let Some(query_dir) = query_dir {
+            let queries_dir =
+                Directory::queries_directory().unwrap_or_else(|| query_dir.clone());
+            return (
+                read_query_file(
+                    &query_dir.join(Self::HIGHLIGHTS_QUERIES_FILE_NAME),
+                    &queries_dir,
+                    Self::HIGHLIGHTS_QUERIES_FILE_NAME,
+                ),
+                read_query_file(
+                    &query_dir.join(Self::HIGHLIGHTS_INJECTIONS_FILE_NAME),
+                    &queries_dir,
+                    Self::HIGHLIGHTS_INJECTIONS_FILE_NAME,
+                ),
+            );
+        }
 
         // Try reading highlights from user config dir
         if let Some(queries_dir) = Directory::queries_directory() {
@@ -2011,11 +2360,16 @@ pub(crate) fn walk_tree_bracket_ast(
 }
 
 fn read_grammar_query(queries_dir: &Path, name: &str, kind: &str) -> String {
+    let file = queries_dir.join(name).join(kind);
+    read_query_file(&file, queries_dir, kind)
+}
+
+/// Read a query file, resolving its `inherits` from `queries_dir`
+fn read_query_file(file: &Path, queries_dir: &Path, kind: &str) -> String {
     static INHERITS_REGEX: Lazy<Regex> =
         Lazy::new(|| Regex::new(r";+\s*inherits\s*:?\s*([a-z_,()-]+)\s*").unwrap());
 
-    let file = queries_dir.join(name).join(kind);
-    let query = std::fs::read_to_string(&file).unwrap_or_else(|err| {
+    let query = std::fs::read_to_string(file).unwrap_or_else(|err| {
         tracing::event!(
             tracing::Level::WARN,
             "Failed to read queries at: {file:?}, {err}"
@@ -2044,11 +2398,52 @@ fn read_grammar_query(queries_dir: &Path, name: &str, kind: &str) -> String {
 mod tests {
     use std::path::PathBuf;
 
-    use super::LapceLanguage;
+    use super::{LanguageDefinition, LapceLanguage, UserLanguages};
 
     #[test]
     fn test_lanaguage_from_path() {
         let l = LapceLanguage::from_path(&PathBuf::new().join("test.rs"));
         assert_eq!(l, LapceLanguage::Rust);
     }
+
+    #[test]
+    fn test_register_languages() {
+        let definitions = vec![
+            LanguageDefinition {
+                name: "Koka".to_string(),
+                extensions: vec!["kk".to_string()],
+                comment: Some("//".to_string()),
+                ..Default::default()
+            },
+            LanguageDefinition {
+                name: "toml".to_string(),
+                files: vec!["Cargo.lock".to_string()],
+                ..Default::default()
+            },
+        ];
+        let mut languages = UserLanguages::default();
+        languages.register(definitions.clone());
+
+        let koka = languages.find_by_path(Some("main.kk"), Some("kk")).unwrap();
+        let LapceLanguage::User(id) = koka else {
+            panic!("Koka should be a new language");
+        };
+        assert_eq!(languages.find_by_name("koka"), Some(koka));
+        assert_eq!(languages.name(id), "Koka");
+        assert_eq!(languages.language_id(id), Some("koka"));
+        let properties = languages.get(&koka).unwrap().properties;
+        assert_eq!(properties.comment.single_line_start, Some("//"));
+
+        let l = languages.find_by_path(Some("Cargo.lock"), Some("lock"));
+        assert_eq!(l, Some(LapceLanguage::Toml));
+        let l = languages.find_by_path(Some("Cargo.toml"), Some("toml"));
+        assert_eq!(l, Some(LapceLanguage::Toml));
+
+        // Registering the same definitions again reuses what was leaked before
+        languages.register(Vec::new());
+        assert_eq!(languages.find_by_path(Some("main.kk"), Some("kk")), None);
+        languages.register(definitions);
+        let again = languages.get(&koka).unwrap().properties;
+        assert!(std::ptr::eq(properties, again));
+    }
 }
@@ -1,11 +1,13 @@
 #![allow(clippy::manual_clamp)]
 
+pub mod conflict;
 pub mod directory;
 pub mod encoding;
 pub mod language;
 pub mod lens;
 pub mod meta;
 pub mod rope_text_pos;
+pub mod search;
 pub mod style;
 pub mod syntax;
 // This is primarily being re-exported to avoid changing every single usage
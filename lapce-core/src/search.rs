@@ -0,0 +1,117 @@
+use std::ops::Range;
+
+use grep_matcher::{Captures, Matcher, NoError};
+use grep_regex::{RegexMatcher, RegexMatcherBuilder};
+use lapce_rpc::proxy::SearchMatch;
+
+/// Build the matcher used by both the global search and the global replace, so
+/// that a replace always finds the same matches the search reported.
+pub fn build_search_matcher(
+    pattern: &str,
+    case_sensitive: bool,
+    whole_word: bool,
+    is_regex: bool,
+) -> Result<RegexMatcher, grep_regex::Error> {
+    let mut matcher = RegexMatcherBuilder::new();
+    let matcher = matcher.case_insensitive(!case_sensitive).word(whole_word);
+    if is_regex {
+        matcher.build(pattern)
+    } else {
+        matcher.build_literals(&[&regex::escape(pattern)])
+    }
+}
+
+/// Find the replacements for the given matches within a single line.
+///
+/// Returns the byte range within the line that should be replaced along with
+/// the text to replace it with. Capture groups are only expanded in the
+/// replacement when searching with a regex.
+pub fn replace_in_line(
+    matcher: &RegexMatcher,
+    line: &str,
+    replacement: &str,
+    is_regex: bool,
+    matches: &[&SearchMatch],
+) -> Result<Vec<(Range<usize>, String)>, NoError> {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let mut edits = Vec::new();
+    let mut caps = matcher.new_captures()?;
+    matcher.captures_iter(line.as_bytes(), &mut caps, |caps| {
+        let Some(m) = caps.get(0) else {
+            return true;
+        };
+        // Empty matches are never reported by the search
+        if m.is_empty()
+            || !matches
+                .iter()
+                .any(|search_match| search_match.start == m.start())
+        {
+            return true;
+        }
+        let new_text = if is_regex {
+            let mut new_text = Vec::new();
+            caps.interpolate(
+                |name| matcher.capture_index(name),
+                line.as_bytes(),
+                replacement.as_bytes(),
+                &mut new_text,
+            );
+            String::from_utf8_lossy(&new_text).into_owned()
+        } else {
+            replacement.to_string()
+        };
+        edits.push((m.start()..m.end(), new_text));
+        true
+    })?;
+    Ok(edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search_match(line: usize, start: usize, end: usize) -> SearchMatch {
+        SearchMatch {
+            line,
+            start,
+            end,
+            line_content: String::new(),
+            line_content_start: 0,
+            full_line: None,
+        }
+    }
+
+    #[test]
+    fn test_replace_in_line() {
+        let line = "foo foobar foo_x foo\n";
+        let matcher = build_search_matcher("foo", true, true, false).unwrap();
+        let (first, last) = (search_match(1, 0, 3), search_match(1, 17, 20));
+        assert_eq!(
+            replace_in_line(&matcher, line, "$1", false, &[&first, &last]).unwrap(),
+            vec![(0..3, "$1".to_string()), (17..20, "$1".to_string())]
+        );
+        // a dismissed match is left alone
+        assert_eq!(
+            replace_in_line(&matcher, line, "bar", false, &[&last]).unwrap(),
+            vec![(17..20, "bar".to_string())]
+        );
+
+        let matcher =
+            build_search_matcher(r"(\w+)=(\d+)", true, false, true).unwrap();
+        let matches = [search_match(1, 0, 3), search_match(1, 5, 8)];
+        let matches = matches.iter().collect::<Vec<_>>();
+        assert_eq!(
+            replace_in_line(&matcher, "a=1, b=2", "$2=$1", true, &matches).unwrap(),
+            vec![(0..3, "1=a".to_string()), (5..8, "2=b".to_string())]
+        );
+
+        // lazy patterns match as they do in the search, not up to the end
+        let matcher = build_search_matcher(r"a(.+?)b", true, false, true).unwrap();
+        let matches = [search_match(1, 0, 3)];
+        let matches = matches.iter().collect::<Vec<_>>();
+        assert_eq!(
+            replace_in_line(&matcher, "axbxb", "<$1>", true, &matches).unwrap(),
+            vec![(0..3, "<x>".to_string())]
+        );
+    }
+}
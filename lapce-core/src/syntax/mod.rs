@@ -8,7 +8,7 @@
 
 use std::{
     cell::RefCell,
-    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet, VecDeque},
     hash::{Hash, Hasher},
     mem,
     path::Path,
@@ -1168,6 +1168,50 @@ pub fn sticky_headers(&self, offset: usize) -> Option<Vec<usize>> {
         Some(offsets)
     }
 
+    /// The foldable line ranges of the syntax tree, as `(start_line, end_line)`
+    /// where folding hides the lines after `start_line` up to and including
+    /// `end_line`. A closing bracket that ends a node is left visible.
+    pub fn folding_ranges(&self) -> Option<Vec<(usize, usize)>> {
+        let tree = self.layers.as_ref()?.try_tree()?;
+        let mut ranges: BTreeMap<usize, usize> = BTreeMap::new();
+
+        let mut cursor = tree.walk();
+        if !cursor.goto_first_child() {
+            return Some(Vec::new());
+        }
+        loop {
+            let node = cursor.node();
+            let start = node.start_position().row;
+            let end = node.end_position();
+            // a node ending with a newline ends at the start of the next line
+            let mut end_line = if end.column == 0 && end.row > start {
+                end.row - 1
+            } else {
+                end.row
+            };
+            if let Some(last) = node.child(node.child_count().saturating_sub(1)) {
+                if matches!(last.kind(), "}" | ")" | "]")
+                    && last.start_position().row == end_line
+                {
+                    end_line = end_line.saturating_sub(1);
+                }
+            }
+            if node.is_named() && end_line > start {
+                let range_end = ranges.entry(start).or_insert(end_line);
+                *range_end = (*range_end).max(end_line);
+            }
+
+            if cursor.goto_first_child() {
+                continue;
+            }
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return Some(ranges.into_iter().collect());
+                }
+            }
+        }
+    }
+
     pub fn find_enclosing_parentheses(
         &self,
         offset: usize,
@@ -9,12 +9,16 @@
 };
 
 use anyhow::{anyhow, Result};
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
 use floem_editor_core::buffer::rope_text::CharIndicesJoin;
-use lapce_core::encoding::offset_utf8_to_utf16;
-use lapce_rpc::buffer::BufferId;
+use lapce_core::{encoding::offset_utf8_to_utf16, language::LapceLanguage};
+use lapce_rpc::buffer::{BufferId, EditorConfigProperties};
 use lapce_xi_rope::{interval::IntervalBounds, rope::Rope, RopeDelta};
 use lsp_types::*;
 
+use crate::editorconfig;
+
 #[derive(Clone)]
 pub struct Buffer {
     pub language_id: &'static str,
@@ -24,12 +28,31 @@ pub struct Buffer {
     pub path: PathBuf,
     pub rev: u64,
     pub mod_time: Option<SystemTime>,
+    /// The encoding the file is written with when saving
+    pub encoding: &'static Encoding,
+    /// Whether the file starts with a byte order mark, which is kept on save
+    pub bom: bool,
+    pub editorconfig: EditorConfigProperties,
 }
 
 impl Buffer {
     pub fn new(id: BufferId, path: PathBuf) -> Buffer {
-        let (s, read_only) = match load_file(&path) {
-            Ok(s) => (s, false),
+        let editorconfig = editorconfig::resolve(&path);
+        // A new file is created with the charset from `.editorconfig`
+        let (mut encoding, mut bom) = editorconfig.charset.unwrap_or((UTF_8, false));
+        // while an existing one that doesn't decode with it is most likely not
+        // covered by it
+        let file = match editorconfig.charset {
+            Some((charset, _)) => load_file_with_encoding(&path, Some(charset))
+                .or_else(|_| load_file_with_encoding(&path, None)),
+            None => load_file_with_encoding(&path, None),
+        };
+        let (s, read_only) = match file {
+            Ok(file) => {
+                encoding = file.encoding;
+                bom = file.bom;
+                (file.content, false)
+            }
             Err(err) => {
                 use std::io::ErrorKind;
                 match err.downcast_ref::<std::io::Error>() {
@@ -59,9 +82,38 @@ pub fn new(id: BufferId, path: PathBuf) -> Buffer {
             language_id,
             rev,
             mod_time,
+            encoding,
+            bom,
+            editorconfig: editorconfig.properties,
         }
     }
 
+    /// Read the file again with the given encoding, which is then also used for
+    /// saving. The buffer content itself is updated by the edits that follow.
+    pub fn reopen_with_encoding(&mut self, encoding: &str) -> Result<String> {
+        let encoding = encoding_from_name(encoding)?;
+        let file = load_file_with_encoding(&self.path, Some(encoding))?;
+        self.encoding = file.encoding;
+        self.bom = file.bom;
+        self.read_only = false;
+        Ok(file.content)
+    }
+
+    pub fn set_encoding(&mut self, encoding: &str) -> Result<()> {
+        let encoding = encoding_from_name(encoding)?;
+        if encoding != self.encoding {
+            // A byte order mark is what most tools expect to tell UTF-16 apart,
+            // while it is rather a nuisance for UTF-8
+            let bom = encoding == UTF_16LE || encoding == UTF_16BE;
+            // Fail right away rather than on the next save when the content
+            // can't be represented
+            encode_text(&self.rope.to_string(), encoding, bom)?;
+            self.encoding = encoding;
+            self.bom = bom;
+        }
+        Ok(())
+    }
+
     pub fn save(&mut self, rev: u64, create_parents: bool) -> Result<()> {
         if self.read_only {
             return Err(anyhow!("can't save to read only file"));
@@ -85,6 +137,18 @@ pub fn save(&mut self, rev: u64, create_parents: bool) -> Result<()> {
         };
         let new_file = !path.exists();
 
+        // Encode before touching the file, so that text which can't be
+        // represented in the encoding doesn't leave a truncated file behind
+        let encoded = if self.encoding == UTF_8 {
+            None
+        } else {
+            Some(encode_text(
+                &self.rope.to_string(),
+                self.encoding,
+                self.bom,
+            )?)
+        };
+
         let bak_file_path = &path.with_extension(bak_extension);
         if !new_file {
             fs::copy(&path, bak_file_path)?;
@@ -101,8 +165,15 @@ pub fn save(&mut self, rev: u64, create_parents: bool) -> Result<()> {
             .write(true)
             .truncate(true)
             .open(&path)?;
-        for chunk in self.rope.iter_chunks(..self.rope.len()) {
-            f.write_all(chunk.as_bytes())?;
+        if let Some(encoded) = encoded {
+            f.write_all(&encoded)?;
+        } else {
+            if self.bom {
+                f.write_all(UTF8_BOM)?;
+            }
+            for chunk in self.rope.iter_chunks(..self.rope.len()) {
+                f.write_all(chunk.as_bytes())?;
+            }
         }
 
         self.mod_time = get_mod_time(&path);
@@ -192,25 +263,156 @@ pub fn is_empty(&self) -> bool {
     }
 }
 
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// The content of a file decoded to utf8, along with how it was encoded
+pub struct DecodedFile {
+    pub content: String,
+    pub encoding: &'static Encoding,
+    pub bom: bool,
+}
+
 pub fn load_file(path: &Path) -> Result<String> {
     read_path_to_string(path)
 }
 
-pub fn read_path_to_string<P: AsRef<Path>>(path: P) -> Result<String> {
-    let path = path.as_ref();
-
+/// Read a file with the given encoding, or with the one detected from its
+/// content if there is none
+pub fn load_file_with_encoding(
+    path: &Path,
+    encoding: Option<&'static Encoding>,
+) -> Result<DecodedFile> {
     let mut file = File::open(path)?;
     // Read the file in as bytes
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)?;
 
-    // Parse the file contents as utf8
-    let contents = String::from_utf8(buffer)?;
+    decode_bytes(&buffer, encoding)
+}
 
-    Ok(contents.to_string())
+pub fn read_path_to_string<P: AsRef<Path>>(path: P) -> Result<String> {
+    Ok(load_file_with_encoding(path.as_ref(), None)?.content)
+}
+
+pub fn encoding_from_name(name: &str) -> Result<&'static Encoding> {
+    Encoding::for_label(name.as_bytes())
+        .ok_or_else(|| anyhow!("unknown encoding {name}"))
+}
+
+pub fn decode_bytes(
+    bytes: &[u8],
+    encoding: Option<&'static Encoding>,
+) -> Result<DecodedFile> {
+    let (encoding, bom_len) = match encoding {
+        Some(encoding) => {
+            let bom_len = Encoding::for_bom(bytes)
+                .filter(|(bom_encoding, _)| *bom_encoding == encoding)
+                .map_or(0, |(_, len)| len);
+            (encoding, bom_len)
+        }
+        None => match Encoding::for_bom(bytes) {
+            Some((encoding, len)) => (encoding, len),
+            None => (detect_encoding(bytes)?, 0),
+        },
+    };
+
+    let (content, had_errors) =
+        encoding.decode_without_bom_handling(&bytes[bom_len..]);
+    if had_errors {
+        return Err(anyhow!("the file is not valid {}", encoding.name()));
+    }
+
+    Ok(DecodedFile {
+        content: content.into_owned(),
+        encoding,
+        bom: bom_len > 0,
+    })
+}
+
+/// Guess the encoding of text without a byte order mark
+fn detect_encoding(bytes: &[u8]) -> Result<&'static Encoding> {
+    if std::str::from_utf8(bytes).is_ok() {
+        return Ok(UTF_8);
+    }
+    if let Some(encoding) = detect_utf16(bytes) {
+        return Ok(encoding);
+    }
+    // Nul bytes don't show up in text in any of the other encodings
+    if bytes.contains(&0) {
+        return Err(anyhow!("binary file"));
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    Ok(detector.guess(None, true))
+}
+
+/// Text that is mostly ASCII has a nul byte in every other position when it is
+/// UTF-16, which tells which byte order it uses
+fn detect_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(4096) & !1];
+    if sample.is_empty() {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let even_nuls = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_nuls = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|b| **b == 0)
+        .count();
+    if odd_nuls * 2 > pairs && even_nuls * 20 < pairs {
+        Some(UTF_16LE)
+    } else if even_nuls * 2 > pairs && odd_nuls * 20 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+pub fn encode_text(
+    text: &str,
+    encoding: &'static Encoding,
+    bom: bool,
+) -> Result<Vec<u8>> {
+    // encoding_rs only decodes UTF-16, so it is encoded by hand
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        let mut bytes = Vec::with_capacity(text.len() * 2 + 2);
+        let units = bom.then_some(0xFEFF).into_iter().chain(text.encode_utf16());
+        for unit in units {
+            if encoding == UTF_16LE {
+                bytes.extend_from_slice(&unit.to_le_bytes());
+            } else {
+                bytes.extend_from_slice(&unit.to_be_bytes());
+            }
+        }
+        return Ok(bytes);
+    }
+
+    let (encoded, _, unmappable) = encoding.encode(text);
+    if unmappable {
+        return Err(anyhow!(
+            "the file contains characters that can't be saved as {}",
+            encoding.name()
+        ));
+    }
+    let mut bytes = Vec::with_capacity(encoded.len() + 3);
+    if bom && encoding == UTF_8 {
+        bytes.extend_from_slice(UTF8_BOM);
+    }
+    bytes.extend_from_slice(&encoded);
+    Ok(bytes)
 }
 
 pub fn language_id_from_path(path: &Path) -> Option<&'static str> {
+    // Languages declared in `languages.toml` or by a volt
+    if let Some(language_id) =
+        LapceLanguage::from_path_raw(path).and_then(|l| l.language_id())
+    {
+        return Some(language_id);
+    }
+
     // recommended language_id values
     // https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#textDocumentItem
     Some(match path.extension() {
@@ -347,3 +549,55 @@ pub fn get_mod_time<P: AsRef<Path>>(path: P) -> Option<SystemTime> {
         .and_then(|meta| meta.modified())
         .ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use encoding_rs::{SHIFT_JIS, UTF_16LE, UTF_8, WINDOWS_1252};
+
+    use super::{decode_bytes, encode_text};
+
+    #[test]
+    fn test_decode_bom() {
+        let file = decode_bytes(b"\xFF\xFEh\x00i\x00", None).unwrap();
+        assert_eq!(file.content, "hi");
+        assert_eq!(file.encoding, UTF_16LE);
+        assert!(file.bom);
+
+        let file = decode_bytes(b"\xEF\xBB\xBFhi", None).unwrap();
+        assert_eq!(file.content, "hi");
+        assert_eq!(file.encoding, UTF_8);
+        assert!(file.bom);
+    }
+
+    #[test]
+    fn test_detect_encoding() {
+        let file = decode_bytes(b"h\x00e\x00l\x00l\x00o\x00", None).unwrap();
+        assert_eq!(file.content, "hello");
+        assert_eq!(file.encoding, UTF_16LE);
+        assert!(!file.bom);
+
+        let file = decode_bytes(b"caf\xE9 cr\xE8me br\xFBl\xE9e", None).unwrap();
+        assert_eq!(file.content, "café crème brûlée");
+        assert_eq!(file.encoding, WINDOWS_1252);
+
+        assert!(decode_bytes(
+            b"\x7FELF\x02\x01\x01\x00\x00\x00\xB7\x00\x3E\x00",
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let text = "こんにちは";
+        for (encoding, bom) in [(UTF_8, true), (UTF_16LE, true), (SHIFT_JIS, false)]
+        {
+            let bytes = encode_text(text, encoding, bom).unwrap();
+            let file = decode_bytes(&bytes, Some(encoding)).unwrap();
+            assert_eq!(file.content, text);
+            assert_eq!(file.bom, bom);
+        }
+
+        assert!(encode_text("こんにちは", WINDOWS_1252, false).is_err());
+    }
+}
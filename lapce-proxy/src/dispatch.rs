@@ -1,4 +1,5 @@
 use std::{
+    cell::{Cell, RefCell},
     collections::{HashMap, HashSet},
     fs, io,
     path::{Path, PathBuf},
@@ -7,19 +8,26 @@
         Arc,
     },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use alacritty_terminal::{event::WindowSize, event_loop::Msg};
 use anyhow::{anyhow, Context, Result};
 use crossbeam_channel::Sender;
 use git2::{
-    build::CheckoutBuilder, DiffOptions, ErrorCode::NotFound, Oid, Repository,
+    build::CheckoutBuilder, BranchType, DiffOptions, ErrorCode::NotFound, Oid,
+    Repository, StashFlags,
 };
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use grep_matcher::Matcher;
-use grep_regex::RegexMatcherBuilder;
+use grep_regex::RegexMatcher;
 use grep_searcher::{sinks::UTF8, SearcherBuilder};
 use indexmap::IndexMap;
+use lapce_core::{
+    directory::Directory,
+    language::LapceLanguage,
+    search::{build_search_matcher, replace_in_line},
+};
 use lapce_rpc::{
     buffer::BufferId,
     core::{CoreNotification, CoreRpcHandler, FileChanged},
@@ -29,7 +37,7 @@
         ProxyHandler, ProxyNotification, ProxyRequest, ProxyResponse,
         ProxyRpcHandler, SearchMatch,
     },
-    source_control::{DiffInfo, FileDiff},
+    source_control::{BlameHunk, DiffInfo, FileDiff, GitCommit, GitStash},
     style::{LineStyle, SemanticStyles},
     terminal::TermId,
     RequestId, RpcError,
@@ -37,14 +45,21 @@
 use lapce_xi_rope::Rope;
 use lsp_types::{
     notification::{Cancel, Notification},
-    CancelParams, MessageType, NumberOrString, Position, Range, ShowMessageParams,
-    TextDocumentItem, Url,
+    CancelParams, MessageType, NumberOrString, Position, ProgressParams,
+    ProgressParamsValue, Range, ShowMessageParams, TextDocumentItem, Url,
+    WorkDoneProgress, WorkDoneProgressBegin, WorkDoneProgressEnd,
+    WorkDoneProgressReport,
 };
 use parking_lot::Mutex;
 
 use crate::{
-    buffer::{get_mod_time, load_file, Buffer},
-    plugin::{catalog::PluginCatalog, PluginCatalogRpcHandler},
+    buffer::{
+        encode_text, get_mod_time, load_file_with_encoding, Buffer, DecodedFile,
+    },
+    plugin::{
+        catalog::PluginCatalog, dap, wasi::find_language_definitions,
+        PluginCatalogRpcHandler,
+    },
     terminal::{Terminal, TerminalSender},
     watcher::{FileWatcher, Notify, WatchToken},
 };
@@ -54,6 +69,8 @@
 
 pub struct Dispatcher {
     workspace: Option<PathBuf>,
+    /// All the root folders of the workspace, starting with `workspace`
+    workspace_folders: Vec<PathBuf>,
     pub proxy_rpc: ProxyRpcHandler,
     core_rpc: CoreRpcHandler,
     catalog_rpc: PluginCatalogRpcHandler,
@@ -70,33 +87,53 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
         match rpc {
             Initialize {
                 workspace,
+                workspace_folders,
                 disabled_volts,
                 extra_plugin_paths,
                 plugin_configurations,
+                lsp_servers,
                 window_id,
                 tab_id,
             } => {
                 self.window_id = window_id;
                 self.tab_id = tab_id;
                 self.workspace = workspace;
+                self.workspace_folders = if workspace_folders.is_empty() {
+                    self.workspace.iter().cloned().collect()
+                } else {
+                    workspace_folders
+                };
                 self.file_watcher.notify(FileWatchNotifier::new(
-                    self.workspace.clone(),
+                    self.workspace_folders.clone(),
                     self.core_rpc.clone(),
                     self.proxy_rpc.clone(),
                 ));
-                if let Some(workspace) = self.workspace.as_ref() {
-                    self.file_watcher
-                        .watch(workspace, true, WORKSPACE_EVENT_TOKEN);
+                for folder in self.workspace_folders.iter() {
+                    self.file_watcher.watch(folder, true, WORKSPACE_EVENT_TOKEN);
                 }
 
                 let plugin_rpc = self.catalog_rpc.clone();
                 let workspace = self.workspace.clone();
+                let workspace_folders = self.workspace_folders.clone();
                 thread::spawn(move || {
+                    // The language servers are told the language id of the
+                    // languages declared at runtime
+                    let languages_file = Directory::config_directory()
+                        .map(|dir| dir.join("languages.toml"))
+                        .filter(|path| path.exists());
+                    LapceLanguage::register(find_language_definitions(
+                        &disabled_volts,
+                        &extra_plugin_paths,
+                        languages_file.as_deref(),
+                    ));
+
                     let mut plugin = PluginCatalog::new(
                         workspace,
+                        workspace_folders,
                         disabled_volts,
                         extra_plugin_paths,
                         plugin_configurations,
+                        lsp_servers,
                         plugin_rpc.clone(),
                     );
                     plugin_rpc.mainloop(&mut plugin);
@@ -122,11 +159,14 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                         if get_mod_time(&buffer.path) == buffer.mod_time {
                             return;
                         }
-                        match load_file(&buffer.path) {
-                            Ok(content) => {
+                        match load_file_with_encoding(
+                            &buffer.path,
+                            Some(buffer.encoding),
+                        ) {
+                            Ok(file) => {
                                 self.core_rpc.open_file_changed(
                                     path,
-                                    FileChanged::Change(content),
+                                    FileChanged::Change(file.content),
                                 );
                             }
                             Err(err) => {
@@ -179,6 +219,11 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                     tracing::error!("{:?}", err);
                 }
             }
+            UpdateLspServers { servers } => {
+                if let Err(err) = self.catalog_rpc.update_lsp_servers(servers) {
+                    tracing::error!("{:?}", err);
+                }
+            }
             NewTerminal { term_id, profile } => {
                 let mut terminal = match Terminal::new(term_id, profile, 50, 10) {
                     Ok(terminal) => terminal,
@@ -332,9 +377,21 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                     tracing::error!("{:?}", err);
                 }
             }
-            GitCommit { message, diffs } => {
-                if let Some(workspace) = self.workspace.as_ref() {
-                    match git_commit(workspace, &message, diffs) {
+            GitCommit {
+                message,
+                diffs,
+                amend,
+            } => {
+                let mut diffs =
+                    self.group_by_root(diffs, |diff: &FileDiff| diff.path());
+                if diffs.is_empty() && amend {
+                    // Amending only the message of the last commit
+                    if let Some(workspace) = self.workspace.clone() {
+                        diffs.insert(workspace, Vec::new());
+                    }
+                }
+                for root in diffs.into_keys() {
+                    match git_commit(&root, &message, amend) {
                         Ok(()) => (),
                         Err(e) => {
                             self.core_rpc.show_message(
@@ -356,10 +413,60 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                     }
                 }
             }
+            GitCreateBranch { root, name, from } => {
+                self.run_git_operation(
+                    root.as_deref(),
+                    "Git Create Branch failure",
+                    |workspace| git_create_branch(workspace, &name, &from),
+                );
+            }
+            GitRenameBranch { root, old, new } => {
+                self.run_git_operation(
+                    root.as_deref(),
+                    "Git Rename Branch failure",
+                    |workspace| git_rename_branch(workspace, &old, &new),
+                );
+            }
+            GitDeleteBranch { root, name, force } => {
+                self.run_git_operation(
+                    root.as_deref(),
+                    "Git Delete Branch failure",
+                    |workspace| git_delete_branch(workspace, &name, force),
+                );
+            }
+            GitMerge { root, reference } => {
+                self.run_git_operation(
+                    root.as_deref(),
+                    "Git Merge failure",
+                    |workspace| git_merge(workspace, &reference),
+                );
+            }
+            GitStashSave { root, message } => {
+                self.run_git_operation(
+                    root.as_deref(),
+                    "Git Stash failure",
+                    |workspace| git_stash_save(workspace, &message),
+                );
+            }
+            GitStashApply { root, index, pop } => {
+                self.run_git_operation(
+                    root.as_deref(),
+                    "Git Stash Apply failure",
+                    |workspace| git_stash_apply(workspace, index, pop),
+                );
+            }
+            GitStashDrop { root, index } => {
+                self.run_git_operation(
+                    root.as_deref(),
+                    "Git Stash Drop failure",
+                    |workspace| git_stash_drop(workspace, index),
+                );
+            }
             GitDiscardFilesChanges { files } => {
-                if let Some(workspace) = self.workspace.as_ref() {
+                let files = self.group_by_root(files, |file: &PathBuf| file);
+                for (root, files) in files {
                     match git_discard_files_changes(
-                        workspace,
+                        &root,
                         files.iter().map(AsRef::as_ref),
                     ) {
                         Ok(()) => (),
@@ -368,13 +475,48 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                 }
             }
             GitDiscardWorkspaceChanges {} => {
-                if let Some(workspace) = self.workspace.as_ref() {
-                    match git_discard_workspace_changes(workspace) {
+                for folder in self.workspace_folders.iter() {
+                    match git_discard_workspace_changes(folder) {
                         Ok(()) => (),
                         Err(e) => eprintln!("{e:?}"),
                     }
                 }
             }
+            GitStageFiles { files } => {
+                let files = self.group_by_root(files, |file: &PathBuf| file);
+                for (root, files) in files {
+                    if let Err(e) = git_stage_files(&root, &files) {
+                        self.show_git_error("Git Stage failure", &e);
+                    }
+                }
+            }
+            GitUnstageFiles { files } => {
+                let files = self.group_by_root(files, |file: &PathBuf| file);
+                for (root, files) in files {
+                    if let Err(e) = git_unstage_files(&root, &files) {
+                        self.show_git_error("Git Unstage failure", &e);
+                    }
+                }
+            }
+            GitStageHunk { path, line } => {
+                if let Some(root) = self.workspace_root(&path) {
+                    // Stage what's in the editor, which may not be saved yet
+                    let content = self
+                        .buffers
+                        .get(&path)
+                        .map(|buffer| buffer.rope.to_string());
+                    if let Err(e) = git_stage_hunk(root, &path, line, content) {
+                        self.show_git_error("Git Stage failure", &e);
+                    }
+                }
+            }
+            GitUnstageHunk { path, line } => {
+                if let Some(root) = self.workspace_root(&path) {
+                    if let Err(e) = git_unstage_hunk(root, &path, line) {
+                        self.show_git_error("Git Unstage failure", &e);
+                    }
+                }
+            }
             GitInit {} => {
                 if let Some(workspace) = self.workspace.as_ref() {
                     match git_init(workspace) {
@@ -383,6 +525,18 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                     }
                 }
             }
+            GitFetch {} => {
+                self.spawn_git_remote_operation("Git Fetch failure", git_fetch);
+            }
+            GitPull { rebase } => {
+                self.spawn_git_remote_operation(
+                    "Git Pull failure",
+                    move |workspace, core_rpc| git_pull(workspace, core_rpc, rebase),
+                );
+            }
+            GitPush {} => {
+                self.spawn_git_remote_operation("Git Push failure", git_push);
+            }
             LspCancel { id } => {
                 self.catalog_rpc.send_notification(
                     None,
@@ -405,6 +559,8 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 let buffer = Buffer::new(buffer_id, path.clone());
                 let content = buffer.rope.to_string();
                 let read_only = buffer.read_only;
+                let encoding = buffer.encoding.name().to_string();
+                let editorconfig = buffer.editorconfig.clone();
                 self.catalog_rpc.did_open_document(
                     &path,
                     buffer.language_id.to_string(),
@@ -415,11 +571,16 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 self.buffers.insert(path, buffer);
                 self.respond_rpc(
                     id,
-                    Ok(ProxyResponse::NewBufferResponse { content, read_only }),
+                    Ok(ProxyResponse::NewBufferResponse {
+                        content,
+                        read_only,
+                        encoding,
+                        editorconfig,
+                    }),
                 );
             }
             BufferHead { path } => {
-                let result = if let Some(workspace) = self.workspace.as_ref() {
+                let result = if let Some(workspace) = self.workspace_root(&path) {
                     let result = file_get_head(workspace, &path);
                     if let Ok((_blob_id, content)) = result {
                         Ok(ProxyResponse::BufferHeadResponse {
@@ -440,48 +601,191 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 };
                 self.respond_rpc(id, result);
             }
+            BufferIndex { path } => {
+                let result = if let Some(workspace) = self.workspace_root(&path) {
+                    file_get_index(workspace, &path)
+                        .map(|content| ProxyResponse::BufferHeadResponse {
+                            version: "index".to_string(),
+                            content,
+                        })
+                        .map_err(|_| RpcError {
+                            code: 0,
+                            message: "can't get file index".to_string(),
+                        })
+                } else {
+                    Err(RpcError {
+                        code: 0,
+                        message: "no workspace set".to_string(),
+                    })
+                };
+                self.respond_rpc(id, result);
+            }
+            BufferRevision { path, revision } => {
+                let result = if let Some(workspace) = self.workspace_root(&path) {
+                    file_get_revision(workspace, &path, &revision)
+                        .map(|content| ProxyResponse::BufferHeadResponse {
+                            version: revision,
+                            content,
+                        })
+                        .map_err(|e| RpcError {
+                            code: 0,
+                            message: e.to_string(),
+                        })
+                } else {
+                    Err(RpcError {
+                        code: 0,
+                        message: "no workspace set".to_string(),
+                    })
+                };
+                self.respond_rpc(id, result);
+            }
             GlobalSearch {
                 pattern,
                 case_sensitive,
                 whole_word,
                 is_regex,
+                include,
+                exclude,
+                search_ignored,
+                max_results,
+                search_id,
             } => {
                 static WORKER_ID: AtomicU64 = AtomicU64::new(0);
                 let our_id = WORKER_ID.fetch_add(1, Ordering::SeqCst) + 1;
 
-                let workspace = self.workspace.clone();
+                let filter = match SearchFilter::new(&include, &exclude) {
+                    Ok(filter) => Arc::new(filter),
+                    Err(err) => {
+                        self.respond_rpc(
+                            id,
+                            Err(RpcError {
+                                code: 0,
+                                message: err.to_string(),
+                            }),
+                        );
+                        return;
+                    }
+                };
+
+                let workspace = self.workspace_folders.clone();
+                // Open buffers inside of the workspace are already covered by
+                // walking the workspace
                 let buffers = self
                     .buffers
-                    .iter()
-                    .map(|p| p.0)
+                    .keys()
+                    .filter(|p| !workspace.iter().any(|w| p.starts_with(w)))
                     .cloned()
                     .collect::<Vec<PathBuf>>();
                 let proxy_rpc = self.proxy_rpc.clone();
+                let core_rpc = self.core_rpc.clone();
 
                 // Perform the search on another thread to avoid blocking the proxy thread
                 thread::spawn(move || {
+                    let workspace_paths = workspace.iter().flat_map(|w| {
+                        let root = w.clone();
+                        let filter = filter.clone();
+                        filter
+                            .walk(w, search_ignored)
+                            .flatten()
+                            .map(|p| p.into_path())
+                            .filter(move |p| {
+                                filter
+                                    .is_included(p.strip_prefix(&root).unwrap_or(p))
+                            })
+                    });
+                    let buffer_paths =
+                        buffers.iter().filter(|p| filter.is_included(p)).cloned();
                     proxy_rpc.handle_response(
                         id,
                         search_in_path(
                             our_id,
                             &WORKER_ID,
-                            workspace
-                                .iter()
-                                .flat_map(|w| ignore::Walk::new(w).flatten())
-                                .chain(
-                                    buffers.iter().flat_map(|p| {
-                                        ignore::Walk::new(p).flatten()
-                                    }),
-                                )
-                                .map(|p| p.into_path()),
+                            workspace_paths.chain(buffer_paths),
                             &pattern,
                             case_sensitive,
                             whole_word,
                             is_regex,
+                            max_results,
+                            search_id,
+                            &core_rpc,
                         ),
                     );
                 });
             }
+            GlobalReplace {
+                pattern,
+                replacement,
+                case_sensitive,
+                whole_word,
+                is_regex,
+                matches,
+            } => {
+                let matcher = match build_search_matcher(
+                    &pattern,
+                    case_sensitive,
+                    whole_word,
+                    is_regex,
+                ) {
+                    Ok(matcher) => matcher,
+                    Err(err) => {
+                        self.respond_rpc(
+                            id,
+                            Err(RpcError {
+                                code: 0,
+                                message: err.to_string(),
+                            }),
+                        );
+                        return;
+                    }
+                };
+
+                // Files that are open get their edits sent back to the editor so
+                // that they can be undone, the rest are rewritten on disk.
+                let mut edits = IndexMap::new();
+                let mut closed = Vec::new();
+                for (path, matches) in matches {
+                    if let Some(buffer) = self.buffers.get(&path) {
+                        let file_edits = replace_edits_in_buffer(
+                            buffer,
+                            &matcher,
+                            &replacement,
+                            is_regex,
+                            &matches,
+                        );
+                        if !file_edits.is_empty() {
+                            edits.insert(path, file_edits);
+                        }
+                    } else {
+                        closed.push((path, matches));
+                    }
+                }
+
+                let proxy_rpc = self.proxy_rpc.clone();
+                thread::spawn(move || {
+                    let mut written = Vec::new();
+                    for (path, matches) in closed {
+                        match replace_in_file(
+                            &path,
+                            &matcher,
+                            &replacement,
+                            is_regex,
+                            &matches,
+                        ) {
+                            Ok(true) => written.push(path),
+                            Ok(false) => {}
+                            Err(err) => {
+                                tracing::error!(
+                                    "failed to replace in {path:?}: {err:?}"
+                                );
+                            }
+                        }
+                    }
+                    proxy_rpc.handle_response(
+                        id,
+                        Ok(ProxyResponse::GlobalReplaceResponse { edits, written }),
+                    );
+                });
+            }
             CompletionResolve {
                 plugin_id,
                 completion_item,
@@ -529,7 +833,7 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 );
             }
             GitGetRemoteFileUrl { file } => {
-                if let Some(workspace) = self.workspace.as_ref() {
+                if let Some(workspace) = self.workspace_root(&file) {
                     match git_get_remote_file_url(workspace, &file) {
                         Ok(s) => self.proxy_rpc.handle_response(
                             id,
@@ -539,6 +843,73 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                     }
                 }
             }
+            GitBlame { path } => {
+                let Some(workspace) = self.workspace_root(&path).cloned() else {
+                    self.respond_rpc(
+                        id,
+                        Err(RpcError {
+                            code: 0,
+                            message: "no workspace set".to_string(),
+                        }),
+                    );
+                    return;
+                };
+                // Blame what's in the editor, which may not be saved yet
+                let content = self
+                    .buffers
+                    .get(&path)
+                    .map(|buffer| buffer.rope.to_string());
+                let proxy_rpc = self.proxy_rpc.clone();
+                thread::spawn(move || {
+                    let result = git_blame(&workspace, &path, content.as_deref())
+                        .map(|hunks| ProxyResponse::GitBlameResponse { hunks })
+                        .map_err(|e| RpcError {
+                            code: 0,
+                            message: e.to_string(),
+                        });
+                    proxy_rpc.handle_response(id, result);
+                });
+            }
+            GitLog { path, query, limit } => {
+                let Some(workspace) = self.repo_folder(path.as_deref()) else {
+                    self.respond_rpc(
+                        id,
+                        Err(RpcError {
+                            code: 0,
+                            message: "no workspace set".to_string(),
+                        }),
+                    );
+                    return;
+                };
+                let proxy_rpc = self.proxy_rpc.clone();
+                thread::spawn(move || {
+                    let result = git_log(&workspace, path.as_deref(), &query, limit)
+                        .map(|commits| ProxyResponse::GitLogResponse { commits })
+                        .map_err(|e| RpcError {
+                            code: 0,
+                            message: e.to_string(),
+                        });
+                    proxy_rpc.handle_response(id, result);
+                });
+            }
+            GitCommitFiles { commit, path } => {
+                let result = if let Some(workspace) =
+                    self.repo_folder(path.as_deref())
+                {
+                    git_commit_files(&workspace, &commit)
+                        .map(|files| ProxyResponse::GitCommitFilesResponse { files })
+                        .map_err(|e| RpcError {
+                            code: 0,
+                            message: e.to_string(),
+                        })
+                } else {
+                    Err(RpcError {
+                        code: 0,
+                        message: "no workspace set".to_string(),
+                    })
+                };
+                self.respond_rpc(id, result);
+            }
             GetDefinition {
                 request_id,
                 path,
@@ -764,10 +1135,11 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 );
             }
             GetFiles { .. } => {
-                let workspace = self.workspace.clone();
+                let workspace_folders = self.workspace_folders.clone();
                 let proxy_rpc = self.proxy_rpc.clone();
                 thread::spawn(move || {
-                    let result = if let Some(workspace) = workspace {
+                    let mut items = Vec::new();
+                    for workspace in workspace_folders {
                         let git_folder =
                             ignore::overrides::OverrideBuilder::new(&workspace)
                                 .add("!.git/")
@@ -788,7 +1160,6 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                                 .build(),
                         };
 
-                        let mut items = Vec::new();
                         for path in walker.flatten() {
                             if let Some(file_type) = path.file_type() {
                                 if file_type.is_file() {
@@ -796,11 +1167,11 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                                 }
                             }
                         }
-                        Ok(ProxyResponse::GetFilesResponse { items })
-                    } else {
-                        Ok(ProxyResponse::GetFilesResponse { items: Vec::new() })
-                    };
-                    proxy_rpc.handle_response(id, result);
+                    }
+                    proxy_rpc.handle_response(
+                        id,
+                        Ok(ProxyResponse::GetFilesResponse { items }),
+                    );
                 });
             }
             GetOpenFilesContent {} => {
@@ -868,6 +1239,56 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                     });
                 self.respond_rpc(id, result);
             }
+            ReopenWithEncoding { path, encoding } => {
+                let result = self
+                    .buffers
+                    .get_mut(&path)
+                    .ok_or_else(|| anyhow!("buffer not found"))
+                    .and_then(|buffer| buffer.reopen_with_encoding(&encoding));
+                let result = match result {
+                    Ok(content) => {
+                        Ok(ProxyResponse::ReopenWithEncodingResponse { content })
+                    }
+                    Err(err) => {
+                        self.core_rpc.show_message(
+                            "Reopen with Encoding".to_owned(),
+                            ShowMessageParams {
+                                typ: MessageType::ERROR,
+                                message: err.to_string(),
+                            },
+                        );
+                        Err(RpcError {
+                            code: 0,
+                            message: err.to_string(),
+                        })
+                    }
+                };
+                self.respond_rpc(id, result);
+            }
+            SetEncoding { path, encoding } => {
+                let result = self
+                    .buffers
+                    .get_mut(&path)
+                    .ok_or_else(|| anyhow!("buffer not found"))
+                    .and_then(|buffer| buffer.set_encoding(&encoding));
+                let result = match result {
+                    Ok(()) => Ok(ProxyResponse::Success {}),
+                    Err(err) => {
+                        self.core_rpc.show_message(
+                            "Save with Encoding".to_owned(),
+                            ShowMessageParams {
+                                typ: MessageType::ERROR,
+                                message: err.to_string(),
+                            },
+                        );
+                        Err(RpcError {
+                            code: 0,
+                            message: err.to_string(),
+                        })
+                    }
+                };
+                self.respond_rpc(id, result);
+            }
             SaveBufferAs {
                 buffer_id,
                 path,
@@ -1120,6 +1541,42 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                         );
                     });
             }
+            DapEvaluate {
+                dap_id,
+                expression,
+                frame_id,
+                context,
+            } => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                self.catalog_rpc.dap_evaluate(
+                    dap_id,
+                    expression,
+                    frame_id,
+                    context,
+                    move |result| {
+                        proxy_rpc.handle_response(
+                            id,
+                            result.map(|result| {
+                                ProxyResponse::DapEvaluateResponse { result }
+                            }),
+                        );
+                    },
+                );
+            }
+            DapListProcesses {} => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                thread::spawn(move || {
+                    let result = dap::list_processes()
+                        .map(|processes| ProxyResponse::DapListProcessesResponse {
+                            processes,
+                        })
+                        .map_err(|e| RpcError {
+                            code: 0,
+                            message: e.to_string(),
+                        });
+                    proxy_rpc.handle_response(id, result);
+                });
+            }
             GetCodeLens { path } => {
                 let proxy_rpc = self.proxy_rpc.clone();
                 self.catalog_rpc
@@ -1214,6 +1671,7 @@ pub fn new(core_rpc: CoreRpcHandler, proxy_rpc: ProxyRpcHandler) -> Self {
 
         Self {
             workspace: None,
+            workspace_folders: Vec::new(),
             proxy_rpc,
             core_rpc,
             catalog_rpc: plugin_rpc,
@@ -1229,6 +1687,116 @@ fn respond_rpc(&self, id: RequestId, result: Result<ProxyResponse, RpcError>) {
         self.proxy_rpc.handle_response(id, result);
     }
 
+    /// The workspace root folder containing `path`
+    fn workspace_root(&self, path: &Path) -> Option<&PathBuf> {
+        workspace_root(&self.workspace_folders, path)
+    }
+
+    /// The workspace folder containing `path`, or the main workspace folder
+    /// when there's no path
+    fn repo_folder(&self, path: Option<&Path>) -> Option<PathBuf> {
+        match path {
+            Some(path) => self.workspace_root(path).cloned(),
+            None => self.workspace.clone(),
+        }
+    }
+
+    /// Group items by the workspace root folder containing their path,
+    /// dropping the ones outside of the workspace
+    fn group_by_root<T>(
+        &self,
+        items: Vec<T>,
+        path: impl Fn(&T) -> &PathBuf,
+    ) -> IndexMap<PathBuf, Vec<T>> {
+        let mut groups: IndexMap<PathBuf, Vec<T>> = IndexMap::new();
+        for item in items {
+            if let Some(root) = self.workspace_root(path(&item)) {
+                groups.entry(root.clone()).or_default().push(item);
+            }
+        }
+        groups
+    }
+
+    /// The workspace root folders that are in a git repository, with only the
+    /// first one of the roots sharing a repository
+    fn git_roots(&self) -> Vec<PathBuf> {
+        let mut workdirs = HashSet::new();
+        self.workspace_folders
+            .iter()
+            .filter(|folder| {
+                Repository::discover(folder)
+                    .ok()
+                    .and_then(|repo| repo.workdir().map(Path::to_path_buf))
+                    .is_some_and(|workdir| workdirs.insert(workdir))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Run a git operation talking to a remote for the repository of each
+    /// workspace root folder on its own thread, as it can take a while, and
+    /// show the errors of the ones that fail
+    fn spawn_git_remote_operation(
+        &self,
+        failure_title: &'static str,
+        f: impl Fn(&Path, &CoreRpcHandler) -> Result<()> + Send + 'static,
+    ) {
+        let roots = self.git_roots();
+        let core_rpc = self.core_rpc.clone();
+        thread::spawn(move || {
+            for root in &roots {
+                if let Err(e) = f(root, &core_rpc) {
+                    let message = if roots.len() > 1 {
+                        format!("{}: {e}", root.display())
+                    } else {
+                        e.to_string()
+                    };
+                    core_rpc.show_message(
+                        failure_title.to_owned(),
+                        ShowMessageParams {
+                            typ: MessageType::ERROR,
+                            message,
+                        },
+                    );
+                }
+            }
+        });
+    }
+
+    /// Run a git operation on the repository of the workspace root folder `root`,
+    /// or of the workspace without one, showing its error if it fails
+    fn run_git_operation(
+        &self,
+        root: Option<&Path>,
+        failure_title: &str,
+        f: impl FnOnce(&Path) -> Result<()>,
+    ) {
+        let Some(workspace) = self.repo_folder(root) else {
+            return;
+        };
+        if let Err(e) = f(&workspace) {
+            self.core_rpc.show_message(
+                failure_title.to_owned(),
+                ShowMessageParams {
+                    typ: MessageType::ERROR,
+                    message: e.to_string(),
+                },
+            );
+        }
+    }
+
+    /// Log the error of a git operation and show it to the user
+    fn show_git_error(&self, failure_title: &str, e: &anyhow::Error) {
+        tracing::error!("{failure_title}: {e:?}");
+        self.core_rpc.show_message(
+            failure_title.to_owned(),
+            ShowMessageParams {
+                typ: MessageType::ERROR,
+                message: e.to_string(),
+            },
+        );
+    }
+
     fn get_buffer_or_insert(&mut self, path: PathBuf) -> &mut Buffer {
         self.buffers
             .entry(path.clone())
@@ -1239,9 +1807,10 @@ fn get_buffer_or_insert(&mut self, path: PathBuf) -> &mut Buffer {
 struct FileWatchNotifier {
     core_rpc: CoreRpcHandler,
     proxy_rpc: ProxyRpcHandler,
-    workspace: Option<PathBuf>,
+    workspace_folders: Vec<PathBuf>,
     workspace_fs_change_handler: Arc<Mutex<Option<Sender<bool>>>>,
-    last_diff: Arc<Mutex<DiffInfo>>,
+    /// The last diff sent to the editor for each workspace root folder
+    last_diffs: Arc<Mutex<HashMap<PathBuf, DiffInfo>>>,
 }
 
 impl Notify for FileWatchNotifier {
@@ -1252,26 +1821,24 @@ fn notify(&self, events: Vec<(WatchToken, notify::Event)>) {
 
 impl FileWatchNotifier {
     fn new(
-        workspace: Option<PathBuf>,
+        workspace_folders: Vec<PathBuf>,
         core_rpc: CoreRpcHandler,
         proxy_rpc: ProxyRpcHandler,
     ) -> Self {
         let notifier = Self {
-            workspace,
+            workspace_folders,
             core_rpc,
             proxy_rpc,
             workspace_fs_change_handler: Arc::new(Mutex::new(None)),
-            last_diff: Arc::new(Mutex::new(DiffInfo::default())),
+            last_diffs: Arc::new(Mutex::new(HashMap::new())),
         };
 
-        if let Some(workspace) = notifier.workspace.clone() {
+        if !notifier.workspace_folders.is_empty() {
+            let workspace_folders = notifier.workspace_folders.clone();
             let core_rpc = notifier.core_rpc.clone();
-            let last_diff = notifier.last_diff.clone();
+            let last_diffs = notifier.last_diffs.clone();
             thread::spawn(move || {
-                if let Some(diff) = git_diff_new(&workspace) {
-                    core_rpc.diff_info(diff.clone());
-                    *last_diff.lock() = diff;
-                }
+                send_diff_infos(&workspace_folders, &core_rpc, &last_diffs);
             });
         }
 
@@ -1337,8 +1904,8 @@ fn handle_workspace_fs_event(&self, event: notify::Event) {
 
         let local_handler = self.workspace_fs_change_handler.clone();
         let core_rpc = self.core_rpc.clone();
-        let workspace = self.workspace.clone().unwrap();
-        let last_diff = self.last_diff.clone();
+        let workspace_folders = self.workspace_folders.clone();
+        let last_diffs = self.last_diffs.clone();
         thread::spawn(move || {
             thread::sleep(Duration::from_millis(500));
 
@@ -1356,18 +1923,41 @@ fn handle_workspace_fs_event(&self, event: notify::Event) {
             if explorer_change {
                 core_rpc.workspace_file_change();
             }
-            if let Some(diff) = git_diff_new(&workspace) {
-                let mut last_diff = last_diff.lock();
-                if diff != *last_diff {
-                    core_rpc.diff_info(diff.clone());
-                    *last_diff = diff;
-                }
-            }
+            send_diff_infos(&workspace_folders, &core_rpc, &last_diffs);
         });
         *handler = Some(sender);
     }
 }
 
+/// Send the git status of each workspace root folder that changed since the
+/// last time it was sent
+fn send_diff_infos(
+    workspace_folders: &[PathBuf],
+    core_rpc: &CoreRpcHandler,
+    last_diffs: &Mutex<HashMap<PathBuf, DiffInfo>>,
+) {
+    for folder in workspace_folders {
+        if let Some(diff) = git_diff_new(folder) {
+            let mut last_diffs = last_diffs.lock();
+            if last_diffs.get(folder) != Some(&diff) {
+                core_rpc.diff_info(folder.clone(), diff.clone());
+                last_diffs.insert(folder.clone(), diff);
+            }
+        }
+    }
+}
+
+/// The innermost workspace root folder containing `path`
+fn workspace_root<'a>(
+    workspace_folders: &'a [PathBuf],
+    path: &Path,
+) -> Option<&'a PathBuf> {
+    workspace_folders
+        .iter()
+        .filter(|folder| path.starts_with(folder))
+        .max_by_key(|folder| folder.components().count())
+}
+
 #[derive(Clone, Debug)]
 pub struct DiffHunk {
     pub old_start: u32,
@@ -1377,6 +1967,19 @@ pub struct DiffHunk {
     pub header: String,
 }
 
+impl DiffHunk {
+    /// Whether the hunk changes the 0-based `line` of the new text, where a hunk
+    /// only removing lines is around the lines on either side of the removal
+    pub fn contains_new_line(&self, line: usize) -> bool {
+        let line = line as u32 + 1;
+        if self.new_lines == 0 {
+            line == self.new_start || line == self.new_start + 1
+        } else {
+            self.new_start <= line && line < self.new_start + self.new_lines
+        }
+    }
+}
+
 fn git_init(workspace_path: &Path) -> Result<()> {
     if Repository::discover(workspace_path).is_err() {
         Repository::init(workspace_path)?;
@@ -1384,33 +1987,29 @@ fn git_init(workspace_path: &Path) -> Result<()> {
     Ok(())
 }
 
-fn git_commit(
-    workspace_path: &Path,
-    message: &str,
-    diffs: Vec<FileDiff>,
-) -> Result<()> {
+/// Commit what is staged in the index, or with `amend` replace the last commit
+/// with it
+fn git_commit(workspace_path: &Path, message: &str, amend: bool) -> Result<()> {
     let repo = Repository::discover(workspace_path)?;
     let mut index = repo.index()?;
-    for diff in diffs {
-        match diff {
-            FileDiff::Modified(p) | FileDiff::Added(p) => {
-                index.add_path(p.strip_prefix(workspace_path)?)?;
-            }
-            FileDiff::Renamed(a, d) => {
-                index.add_path(a.strip_prefix(workspace_path)?)?;
-                index.remove_path(d.strip_prefix(workspace_path)?)?;
-            }
-            FileDiff::Deleted(p) => {
-                index.remove_path(p.strip_prefix(workspace_path)?)?;
-            }
-        }
-    }
-    index.write()?;
     let tree = index.write_tree()?;
     let tree = repo.find_tree(tree)?;
 
     match repo.signature() {
         Ok(signature) => {
+            if amend {
+                let head = repo.head()?.peel_to_commit()?;
+                head.amend(
+                    Some("HEAD"),
+                    None,
+                    Some(&signature),
+                    None,
+                    Some(message),
+                    Some(&tree),
+                )?;
+                return Ok(());
+            }
+
             let parents = repo
                 .head()
                 .and_then(|head| Ok(vec![head.peel_to_commit()?]))
@@ -1439,6 +2038,186 @@ fn git_commit(
     }
 }
 
+/// The path of `path` relative to the working directory of `repo`
+fn repo_relative_path(repo: &Repository, path: &Path) -> Result<PathBuf> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("bare repository has no working directory"))?;
+    Ok(path.strip_prefix(workdir)?.to_path_buf())
+}
+
+fn git_stage_files(workspace_path: &Path, files: &[PathBuf]) -> Result<()> {
+    let repo = Repository::discover(workspace_path)?;
+    let mut index = repo.index()?;
+    for path in files {
+        let relative = repo_relative_path(&repo, path)?;
+        if path.exists() {
+            index.add_path(&relative)?;
+        } else {
+            index.remove_path(&relative)?;
+        }
+    }
+    index.write()?;
+    Ok(())
+}
+
+fn git_unstage_files(workspace_path: &Path, files: &[PathBuf]) -> Result<()> {
+    let repo = Repository::discover(workspace_path)?;
+    let relative = files
+        .iter()
+        .map(|path| repo_relative_path(&repo, path))
+        .collect::<Result<Vec<_>>>()?;
+    match repo.head().and_then(|head| head.peel_to_commit()) {
+        Ok(head) => {
+            repo.reset_default(Some(head.as_object()), relative)?;
+        }
+        Err(_) => {
+            // Nothing has been committed yet, so unstaging removes from the index
+            let mut index = repo.index()?;
+            for path in relative {
+                index.remove_path(&path)?;
+            }
+            index.write()?;
+        }
+    }
+    Ok(())
+}
+
+/// Stage the change around the 0-based `line` of the working file, given its
+/// current content if it's open
+fn git_stage_hunk(
+    workspace_path: &Path,
+    path: &Path,
+    line: usize,
+    content: Option<String>,
+) -> Result<()> {
+    let repo = Repository::discover(workspace_path)?;
+    let relative = repo_relative_path(&repo, path)?;
+    let mut index = repo.index()?;
+    let Some(entry) = index.get_path(&relative, 0) else {
+        // A new file is staged as a whole
+        index.add_path(&relative)?;
+        index.write()?;
+        return Ok(());
+    };
+
+    let staged = blob_content(&repo, entry.id)?;
+    let working = match content {
+        Some(content) => content,
+        None => fs::read_to_string(path)?,
+    };
+    let hunk = diff_hunks(&staged, &working)?
+        .into_iter()
+        .find(|hunk| hunk.contains_new_line(line))
+        .ok_or_else(|| anyhow!("no unstaged change at line {}", line + 1))?;
+    let content = replace_hunk_lines(
+        &staged,
+        (hunk.old_start, hunk.old_lines),
+        &working,
+        (hunk.new_start, hunk.new_lines),
+    );
+    index.add_frombuffer(&entry, content.as_bytes())?;
+    index.write()?;
+    Ok(())
+}
+
+/// Unstage the staged change around the 0-based `line` of the file in the index
+fn git_unstage_hunk(workspace_path: &Path, path: &Path, line: usize) -> Result<()> {
+    let repo = Repository::discover(workspace_path)?;
+    let relative = repo_relative_path(&repo, path)?;
+    let head = repo
+        .head()
+        .and_then(|head| head.peel_to_tree())
+        .and_then(|tree| tree.get_path(&relative));
+    let Ok(head) = head else {
+        // A file that isn't committed yet is unstaged as a whole
+        return git_unstage_files(workspace_path, &[path.to_path_buf()]);
+    };
+
+    let mut index = repo.index()?;
+    let entry = index
+        .get_path(&relative, 0)
+        .ok_or_else(|| anyhow!("{} is not in the index", relative.display()))?;
+    let committed = blob_content(&repo, head.id())?;
+    let staged = blob_content(&repo, entry.id)?;
+    let hunk = diff_hunks(&committed, &staged)?
+        .into_iter()
+        .find(|hunk| hunk.contains_new_line(line))
+        .ok_or_else(|| anyhow!("no staged change at line {}", line + 1))?;
+    let content = replace_hunk_lines(
+        &staged,
+        (hunk.new_start, hunk.new_lines),
+        &committed,
+        (hunk.old_start, hunk.old_lines),
+    );
+    index.add_frombuffer(&entry, content.as_bytes())?;
+    index.write()?;
+    Ok(())
+}
+
+fn blob_content(repo: &Repository, id: Oid) -> Result<String> {
+    let blob = repo.find_blob(id)?;
+    let content = std::str::from_utf8(blob.content())
+        .with_context(|| "content bytes to string")?
+        .to_string();
+    Ok(content)
+}
+
+/// The changed line ranges between two texts, without context lines
+fn diff_hunks(old: &str, new: &str) -> Result<Vec<DiffHunk>> {
+    let mut options = DiffOptions::new();
+    options.context_lines(0);
+    let patch = git2::Patch::from_buffers(
+        old.as_bytes(),
+        None,
+        new.as_bytes(),
+        None,
+        Some(&mut options),
+    )?;
+    let mut hunks = Vec::new();
+    for i in 0..patch.num_hunks() {
+        let (hunk, _) = patch.hunk(i)?;
+        hunks.push(DiffHunk {
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+            header: String::from_utf8_lossy(hunk.header()).to_string(),
+        });
+    }
+    Ok(hunks)
+}
+
+/// Replace the lines of `base` in the `(start, lines)` range with the lines of
+/// `other` in its range, with the ranges as git gives them in hunks
+fn replace_hunk_lines(
+    base: &str,
+    (base_start, base_lines): (u32, u32),
+    other: &str,
+    (other_start, other_lines): (u32, u32),
+) -> String {
+    // The start of an empty range is the line before it, otherwise it's 1-based
+    let index = |start: u32, lines: u32| {
+        if lines == 0 {
+            start as usize
+        } else {
+            start.saturating_sub(1) as usize
+        }
+    };
+    let base: Vec<&str> = base.split_inclusive('\n').collect();
+    let other: Vec<&str> = other.split_inclusive('\n').collect();
+    let base_start = index(base_start, base_lines).min(base.len());
+    let base_end = (base_start + base_lines as usize).min(base.len());
+    let other_start = index(other_start, other_lines).min(other.len());
+    let other_end = (other_start + other_lines as usize).min(other.len());
+
+    let mut content = String::new();
+    content.extend(base[..base_start].iter().copied());
+    content.extend(other[other_start..other_end].iter().copied());
+    content.extend(base[base_end..].iter().copied());
+    content
+}
+
 fn git_checkout(workspace_path: &Path, reference: &str) -> Result<()> {
     let repo = Repository::discover(workspace_path)?;
     let (object, reference) = repo.revparse_ext(reference)?;
@@ -1447,6 +2226,444 @@ fn git_checkout(workspace_path: &Path, reference: &str) -> Result<()> {
     Ok(())
 }
 
+/// Reports the progress of a git operation talking to a remote as work done
+/// progress, which the editor shows in the status bar
+struct GitProgress<'a> {
+    core_rpc: &'a CoreRpcHandler,
+    token: NumberOrString,
+    percentage: Cell<Option<u32>>,
+}
+
+impl<'a> GitProgress<'a> {
+    fn begin(core_rpc: &'a CoreRpcHandler, title: String) -> Self {
+        static GIT_PROGRESS_ID: AtomicU64 = AtomicU64::new(0);
+        let id = GIT_PROGRESS_ID.fetch_add(1, Ordering::Relaxed);
+        let token = NumberOrString::String(format!("lapce-git-{id}"));
+        core_rpc.work_done_progress(ProgressParams {
+            token: token.clone(),
+            value: ProgressParamsValue::WorkDone(WorkDoneProgress::Begin(
+                WorkDoneProgressBegin {
+                    title,
+                    cancellable: Some(false),
+                    message: None,
+                    percentage: Some(0),
+                },
+            )),
+        });
+        Self {
+            core_rpc,
+            token,
+            percentage: Cell::new(Some(0)),
+        }
+    }
+
+    fn report(&self, message: &str, current: usize, total: usize) {
+        let percentage = (current * 100).checked_div(total).map(|p| p as u32);
+        // Only send when the percentage changes, as the callbacks come often
+        if percentage == self.percentage.get() {
+            return;
+        }
+        self.percentage.set(percentage);
+        self.core_rpc.work_done_progress(ProgressParams {
+            token: self.token.clone(),
+            value: ProgressParamsValue::WorkDone(WorkDoneProgress::Report(
+                WorkDoneProgressReport {
+                    cancellable: Some(false),
+                    message: Some(format!("{message} {current}/{total}")),
+                    percentage,
+                },
+            )),
+        });
+    }
+}
+
+impl Drop for GitProgress<'_> {
+    fn drop(&mut self) {
+        self.core_rpc.work_done_progress(ProgressParams {
+            token: self.token.clone(),
+            value: ProgressParamsValue::WorkDone(WorkDoneProgress::End(
+                WorkDoneProgressEnd { message: None },
+            )),
+        });
+    }
+}
+
+/// The callbacks for talking to a remote, reporting the transfer to `progress`
+/// and taking credentials from the ssh agent or the git credential helpers
+fn git_remote_callbacks<'a>(
+    repo: &Repository,
+    progress: &'a GitProgress,
+) -> git2::RemoteCallbacks<'a> {
+    let config = repo.config().ok();
+    let mut tried = git2::CredentialType::empty();
+    let mut callbacks = git2::RemoteCallbacks::new();
+    callbacks.credentials(move |url, username_from_url, allowed| {
+        // Each kind of credential is tried once, as libgit2 keeps asking for
+        // credentials while the ones given are refused
+        let username = username_from_url.unwrap_or("git");
+        if allowed.contains(git2::CredentialType::USERNAME)
+            && !tried.contains(git2::CredentialType::USERNAME)
+        {
+            tried |= git2::CredentialType::USERNAME;
+            return git2::Cred::username(username);
+        }
+        if allowed.contains(git2::CredentialType::SSH_KEY)
+            && !tried.contains(git2::CredentialType::SSH_KEY)
+        {
+            tried |= git2::CredentialType::SSH_KEY;
+            return git2::Cred::ssh_key_from_agent(username);
+        }
+        if allowed.contains(git2::CredentialType::USER_PASS_PLAINTEXT)
+            && !tried.contains(git2::CredentialType::USER_PASS_PLAINTEXT)
+        {
+            tried |= git2::CredentialType::USER_PASS_PLAINTEXT;
+            if let Some(config) = config.as_ref() {
+                return git2::Cred::credential_helper(
+                    config,
+                    url,
+                    username_from_url,
+                );
+            }
+        }
+        if allowed.contains(git2::CredentialType::DEFAULT)
+            && !tried.contains(git2::CredentialType::DEFAULT)
+        {
+            tried |= git2::CredentialType::DEFAULT;
+            return git2::Cred::default();
+        }
+        Err(git2::Error::from_str(&format!(
+            "authentication failed for {url}, \
+             check your ssh agent or git credential helper"
+        )))
+    });
+    callbacks.transfer_progress(|stats| {
+        if stats.received_objects() < stats.total_objects() {
+            progress.report(
+                "Receiving objects",
+                stats.received_objects(),
+                stats.total_objects(),
+            );
+        } else {
+            progress.report(
+                "Resolving deltas",
+                stats.indexed_deltas(),
+                stats.total_deltas(),
+            );
+        }
+        true
+    });
+    callbacks.push_transfer_progress(|current, total, _bytes| {
+        progress.report("Writing objects", current, total);
+    });
+    callbacks
+}
+
+/// The current branch and the name of the remote it is pulled from and pushed
+/// to, which is `origin` or the only remote when it has no upstream yet
+fn git_branch_remote(repo: &Repository) -> Result<(git2::Branch<'_>, String)> {
+    let head = repo.head()?;
+    if !head.is_branch() {
+        return Err(anyhow!("HEAD is not on a branch"));
+    }
+    let refname = head.name().ok_or_else(|| anyhow!("invalid branch name"))?;
+    let remote = match repo.branch_upstream_remote(refname) {
+        Ok(remote) => remote
+            .as_str()
+            .ok_or_else(|| anyhow!("invalid remote name"))?
+            .to_string(),
+        Err(_) => {
+            let remotes = repo.remotes()?;
+            let remotes = remotes.iter().flatten().collect::<Vec<_>>();
+            if remotes.contains(&"origin") {
+                "origin".to_string()
+            } else if let [remote] = remotes.as_slice() {
+                remote.to_string()
+            } else {
+                return Err(anyhow!("no remote to use for the current branch"));
+            }
+        }
+    };
+    Ok((git2::Branch::wrap(head), remote))
+}
+
+fn git_fetch(workspace_path: &Path, core_rpc: &CoreRpcHandler) -> Result<()> {
+    let repo = Repository::discover(workspace_path)?;
+    let (_, remote) = git_branch_remote(&repo)?;
+    git_fetch_remote(&repo, &remote, core_rpc)
+}
+
+fn git_fetch_remote(
+    repo: &Repository,
+    remote: &str,
+    core_rpc: &CoreRpcHandler,
+) -> Result<()> {
+    let mut remote = repo.find_remote(remote)?;
+    let progress = GitProgress::begin(
+        core_rpc,
+        format!("Fetching from {}", remote.name().unwrap_or_default()),
+    );
+    let mut options = git2::FetchOptions::new();
+    options.remote_callbacks(git_remote_callbacks(repo, &progress));
+    // No refspecs to fetch the ones configured for the remote
+    remote.fetch(&[] as &[&str], Some(&mut options), None)?;
+    Ok(())
+}
+
+/// Fetch and integrate the upstream of the current branch, rebasing onto it
+/// when `rebase` is true, merging it when false and following the `pull.rebase`
+/// config when not given
+fn git_pull(
+    workspace_path: &Path,
+    core_rpc: &CoreRpcHandler,
+    rebase: Option<bool>,
+) -> Result<()> {
+    let repo = Repository::discover(workspace_path)?;
+    let (branch, remote) = git_branch_remote(&repo)?;
+    git_fetch_remote(&repo, &remote, core_rpc)?;
+
+    let upstream = branch.upstream().map_err(|_| {
+        anyhow!(
+            "the branch {} has no upstream to pull from",
+            branch.name().ok().flatten().unwrap_or_default()
+        )
+    })?;
+    let upstream_commit = repo.reference_to_annotated_commit(upstream.get())?;
+    let (analysis, _) = repo.merge_analysis(&[&upstream_commit])?;
+    if analysis.is_up_to_date() {
+        return Ok(());
+    }
+
+    let rebase = rebase.unwrap_or_else(|| {
+        repo.config()
+            .and_then(|config| config.get_bool("pull.rebase"))
+            .unwrap_or(false)
+    });
+    if rebase && !analysis.is_fast_forward() {
+        let signature = repo.signature()?;
+        let mut rebase = repo.rebase(None, Some(&upstream_commit), None, None)?;
+        while let Some(operation) = rebase.next() {
+            operation?;
+            if repo.index()?.has_conflicts() {
+                rebase.abort()?;
+                return Err(anyhow!(
+                    "the rebase stopped on conflicts and was aborted, \
+                     pull with merge to resolve them"
+                ));
+            }
+            if let Err(e) = rebase.commit(None, &signature, None) {
+                // A commit already in the upstream has nothing left to apply
+                if e.code() != git2::ErrorCode::Applied {
+                    rebase.abort()?;
+                    return Err(e.into());
+                }
+            }
+        }
+        rebase.finish(Some(&signature))?;
+        return Ok(());
+    }
+
+    let message = format!(
+        "Merge remote-tracking branch '{}'",
+        upstream.name().ok().flatten().unwrap_or_default()
+    );
+    git_merge_commit(&repo, &upstream_commit, "pull", &message)
+}
+
+/// Merge `their` into HEAD, fast-forwarding when possible and otherwise
+/// committing the merge with `message`
+fn git_merge_commit(
+    repo: &Repository,
+    their: &git2::AnnotatedCommit,
+    reflog_action: &str,
+    message: &str,
+) -> Result<()> {
+    let (analysis, _) = repo.merge_analysis(&[their])?;
+    if analysis.is_up_to_date() {
+        return Ok(());
+    }
+
+    if analysis.is_fast_forward() {
+        // Check out the merged tree before moving the branch, so changes in the
+        // working tree are kept unless the merged commits change them too
+        let target = repo.find_object(their.id(), None)?;
+        repo.checkout_tree(&target, Some(CheckoutBuilder::new().safe()))?;
+        repo.head()?
+            .set_target(their.id(), &format!("{reflog_action}: fast-forward"))?;
+        return Ok(());
+    }
+
+    repo.merge(&[their], None, Some(CheckoutBuilder::new().safe()))?;
+    let mut index = repo.index()?;
+    if index.has_conflicts() {
+        // The merge is left in progress for the conflicts to be resolved
+        return Err(anyhow!(
+            "the merge has conflicts, resolve them and commit the result"
+        ));
+    }
+    let signature = repo.signature()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let their_head = repo.find_commit(their.id())?;
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &[&head, &their_head],
+    )?;
+    repo.cleanup_state()?;
+    Ok(())
+}
+
+/// Merge `reference`, which can be a branch, a tag or a commit, into the
+/// current branch
+fn git_merge(workspace_path: &Path, reference: &str) -> Result<()> {
+    let repo = Repository::discover(workspace_path)?;
+    let (object, git_reference) = repo.revparse_ext(reference)?;
+    let (their, kind) = match git_reference {
+        Some(git_reference) => {
+            let kind = if git_reference.is_branch() {
+                "branch"
+            } else if git_reference.is_remote() {
+                "remote-tracking branch"
+            } else if git_reference.is_tag() {
+                "tag"
+            } else {
+                "commit"
+            };
+            (repo.reference_to_annotated_commit(&git_reference)?, kind)
+        }
+        None => {
+            let commit = object.peel_to_commit()?;
+            (repo.find_annotated_commit(commit.id())?, "commit")
+        }
+    };
+    let message = format!("Merge {kind} '{reference}'");
+    git_merge_commit(&repo, &their, "merge", &message)
+}
+
+/// Create the branch `name` at `from` and check it out
+fn git_create_branch(workspace_path: &Path, name: &str, from: &str) -> Result<()> {
+    let repo = Repository::discover(workspace_path)?;
+    let (object, reference) = repo.revparse_ext(from)?;
+    let commit = object.peel_to_commit()?;
+    let mut branch = repo.branch(name, &commit, false)?;
+    // A branch made from a remote branch tracks it, as with `git checkout -b`
+    if let Some(remote) = reference
+        .as_ref()
+        .filter(|reference| reference.is_remote())
+        .and_then(|reference| reference.shorthand())
+    {
+        branch.set_upstream(Some(remote))?;
+    }
+
+    let refname = branch
+        .get()
+        .name()
+        .ok_or_else(|| anyhow!("invalid branch name"))?
+        .to_string();
+    if let Err(e) = repo.checkout_tree(commit.as_object(), None) {
+        // Don't leave behind a branch that couldn't be checked out
+        let _ = branch.delete();
+        return Err(e.into());
+    }
+    repo.set_head(&refname)?;
+    Ok(())
+}
+
+fn git_rename_branch(workspace_path: &Path, old: &str, new: &str) -> Result<()> {
+    let repo = Repository::discover(workspace_path)?;
+    repo.find_branch(old, BranchType::Local)?
+        .rename(new, false)?;
+    Ok(())
+}
+
+/// Delete the local branch `name`, refusing to unless `force` when its commits
+/// are not in the current branch, as they could be lost
+fn git_delete_branch(workspace_path: &Path, name: &str, force: bool) -> Result<()> {
+    let repo = Repository::discover(workspace_path)?;
+    let mut branch = repo.find_branch(name, BranchType::Local)?;
+    if !force {
+        let tip = branch
+            .get()
+            .target()
+            .ok_or_else(|| anyhow!("invalid branch {name}"))?;
+        let head = repo.head()?.peel_to_commit()?.id();
+        if tip != head && !repo.graph_descendant_of(head, tip)? {
+            return Err(anyhow!(
+                "the branch {name} is not fully merged into the current branch"
+            ));
+        }
+    }
+    branch.delete()?;
+    Ok(())
+}
+
+fn git_stash_save(workspace_path: &Path, message: &str) -> Result<()> {
+    let mut repo = Repository::discover(workspace_path)?;
+    let signature = repo.signature()?;
+    let message = Some(message).filter(|message| !message.is_empty());
+    repo.stash_save2(&signature, message, Some(StashFlags::INCLUDE_UNTRACKED))?;
+    Ok(())
+}
+
+fn git_stash_apply(workspace_path: &Path, index: usize, pop: bool) -> Result<()> {
+    let mut repo = Repository::discover(workspace_path)?;
+    if pop {
+        repo.stash_pop(index, None)?;
+    } else {
+        repo.stash_apply(index, None)?;
+    }
+    Ok(())
+}
+
+fn git_stash_drop(workspace_path: &Path, index: usize) -> Result<()> {
+    let mut repo = Repository::discover(workspace_path)?;
+    repo.stash_drop(index)?;
+    Ok(())
+}
+
+fn git_push(workspace_path: &Path, core_rpc: &CoreRpcHandler) -> Result<()> {
+    let repo = Repository::discover(workspace_path)?;
+    let (mut branch, remote_name) = git_branch_remote(&repo)?;
+    let refname = branch
+        .get()
+        .name()
+        .ok_or_else(|| anyhow!("invalid branch name"))?
+        .to_string();
+    let mut remote = repo.find_remote(&remote_name)?;
+
+    let rejected = RefCell::new(None);
+    {
+        let progress =
+            GitProgress::begin(core_rpc, format!("Pushing to {remote_name}"));
+        let mut callbacks = git_remote_callbacks(&repo, &progress);
+        callbacks.push_update_reference(|refname, status| {
+            if let Some(status) = status {
+                *rejected.borrow_mut() =
+                    Some(format!("{refname} was rejected: {status}"));
+            }
+            Ok(())
+        });
+        let mut options = git2::PushOptions::new();
+        options.remote_callbacks(callbacks);
+        remote.push(&[format!("{refname}:{refname}")], Some(&mut options))?;
+    }
+    if let Some(rejected) = rejected.into_inner() {
+        return Err(anyhow!(rejected));
+    }
+
+    if branch.upstream().is_err() {
+        let name = branch
+            .name()?
+            .ok_or_else(|| anyhow!("invalid branch name"))?
+            .to_string();
+        branch.set_upstream(Some(&format!("{remote_name}/{name}")))?;
+    }
+    Ok(())
+}
+
 fn git_discard_files_changes<'a>(
     workspace_path: &Path,
     files: impl Iterator<Item = &'a Path>,
@@ -1488,31 +2705,44 @@ fn git_discard_workspace_changes(workspace_path: &Path) -> Result<()> {
 }
 
 fn git_delta_format(
-    workspace_path: &Path,
+    workdir: &Path,
     delta: &git2::DiffDelta,
 ) -> Option<(git2::Delta, git2::Oid, PathBuf)> {
     match delta.status() {
         git2::Delta::Added | git2::Delta::Untracked => Some((
             git2::Delta::Added,
             delta.new_file().id(),
-            delta.new_file().path().map(|p| workspace_path.join(p))?,
+            delta.new_file().path().map(|p| workdir.join(p))?,
         )),
         git2::Delta::Deleted => Some((
             git2::Delta::Deleted,
             delta.old_file().id(),
-            delta.old_file().path().map(|p| workspace_path.join(p))?,
+            delta.old_file().path().map(|p| workdir.join(p))?,
         )),
         git2::Delta::Modified => Some((
             git2::Delta::Modified,
             delta.new_file().id(),
-            delta.new_file().path().map(|p| workspace_path.join(p))?,
+            delta.new_file().path().map(|p| workdir.join(p))?,
         )),
         _ => None,
     }
 }
 
 fn git_diff_new(workspace_path: &Path) -> Option<DiffInfo> {
-    let repo = Repository::discover(workspace_path).ok()?;
+    let mut repo = Repository::discover(workspace_path).ok()?;
+    // The diff paths are relative to the repository, which the workspace may
+    // only be a folder of
+    let workdir = repo.workdir()?.to_path_buf();
+
+    let mut stashes = Vec::new();
+    let _ = repo.stash_foreach(|index, message, _| {
+        stashes.push(GitStash {
+            index,
+            message: message.to_string(),
+        });
+        true
+    });
+
     let name = match repo.head() {
         Ok(head) => head.shorthand()?.to_string(),
         _ => "(No branch)".to_owned(),
@@ -1543,7 +2773,7 @@ fn git_diff_new(workspace_path: &Path) -> Option<DiffInfo> {
         )
         .ok()?;
     for delta in diff.deltas() {
-        if let Some(delta) = git_delta_format(workspace_path, &delta) {
+        if let Some(delta) = git_delta_format(&workdir, &delta) {
             deltas.push(delta);
         }
     }
@@ -1553,17 +2783,61 @@ fn git_diff_new(workspace_path: &Path) -> Option<DiffInfo> {
         _ => Oid::zero(),
     };
 
+    let mut staged_deltas = Vec::new();
     let cached_diff = repo
         .diff_tree_to_index(repo.find_tree(oid).ok().as_ref(), None, None)
         .ok();
 
     if let Some(cached_diff) = cached_diff {
         for delta in cached_diff.deltas() {
-            if let Some(delta) = git_delta_format(workspace_path, &delta) {
-                deltas.push(delta);
+            if let Some(delta) = git_delta_format(&workdir, &delta) {
+                staged_deltas.push(delta);
             }
         }
     }
+
+    // A conflicted file is listed once as such, rather than as changed in both
+    // the index and the working tree
+    let conflicted = git_conflicted_paths(&repo);
+    let mut diffs: Vec<FileDiff> = conflicted
+        .iter()
+        .cloned()
+        .map(FileDiff::Conflicted)
+        .collect();
+    diffs.extend(
+        git_file_diffs(&deltas)
+            .into_iter()
+            .filter(|diff| !conflicted.contains(diff.path())),
+    );
+    let mut staged = git_file_diffs(&staged_deltas);
+    staged.retain(|diff| !conflicted.contains(diff.path()));
+
+    Some(DiffInfo {
+        head: name,
+        branches,
+        tags,
+        diffs,
+        staged,
+        ahead_behind: git_ahead_behind(&repo),
+        stashes,
+    })
+}
+
+/// How many commits the current branch is ahead of and behind its upstream
+fn git_ahead_behind(repo: &Repository) -> Option<(usize, usize)> {
+    let head = repo.head().ok()?;
+    if !head.is_branch() {
+        return None;
+    }
+    let branch = git2::Branch::wrap(head);
+    let upstream = branch.upstream().ok()?;
+    repo.graph_ahead_behind(branch.get().target()?, upstream.get().target()?)
+        .ok()
+}
+
+/// Turn the formatted deltas into file diffs, pairing a deleted and an added
+/// file with the same content into a rename
+fn git_file_diffs(deltas: &[(git2::Delta, git2::Oid, PathBuf)]) -> Vec<FileDiff> {
     let mut renames = Vec::new();
     let mut renamed_deltas = HashSet::new();
 
@@ -1603,14 +2877,38 @@ fn git_diff_new(workspace_path: &Path) -> Option<DiffInfo> {
         FileDiff::Modified(p)
         | FileDiff::Added(p)
         | FileDiff::Renamed(p, _)
-        | FileDiff::Deleted(p) => p.clone(),
+        | FileDiff::Deleted(p)
+        | FileDiff::Conflicted(p) => p.clone(),
     });
-    Some(DiffInfo {
-        head: name,
-        branches,
-        tags,
-        diffs: file_diffs,
-    })
+    file_diffs
+}
+
+/// The files a merge, rebase or stash left unresolved conflicts in
+fn git_conflicted_paths(repo: &Repository) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let Some(workdir) = repo.workdir() else {
+        return paths;
+    };
+    let Ok(index) = repo.index() else {
+        return paths;
+    };
+    let Ok(conflicts) = index.conflicts() else {
+        return paths;
+    };
+    for conflict in conflicts.flatten() {
+        let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor)
+        else {
+            continue;
+        };
+        if let Ok(path) = std::str::from_utf8(&entry.path) {
+            let path = workdir.join(path);
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    paths.sort();
+    paths
 }
 
 fn file_get_head(workspace_path: &Path, path: &Path) -> Result<(String, String)> {
@@ -1626,6 +2924,246 @@ fn file_get_head(workspace_path: &Path, path: &Path) -> Result<(String, String)>
     Ok((id, content))
 }
 
+fn file_get_index(workspace_path: &Path, path: &Path) -> Result<String> {
+    let repo = Repository::discover(workspace_path)?;
+    let relative = repo_relative_path(&repo, path)?;
+    let entry = repo
+        .index()?
+        .get_path(&relative, 0)
+        .ok_or_else(|| anyhow!("{} is not in the index", relative.display()))?;
+    blob_content(&repo, entry.id)
+}
+
+/// The content of the file at `revision`, which is empty when the file doesn't
+/// exist there, like before the commit adding it
+///
+/// The revisions `:1`, `:2` and `:3` are the common ancestor, the current and
+/// the incoming version of a conflicted file, as staged in the index.
+fn file_get_revision(
+    workspace_path: &Path,
+    path: &Path,
+    revision: &str,
+) -> Result<String> {
+    let repo = Repository::discover(workspace_path)?;
+    let relative = repo_relative_path(&repo, path)?;
+    if let Some(stage) = revision
+        .strip_prefix(':')
+        .and_then(|stage| stage.parse::<i32>().ok())
+    {
+        let index = repo.index()?;
+        return match index.get_path(&relative, stage) {
+            Some(entry) => blob_content(&repo, entry.id),
+            // One side of the merge may have deleted the file
+            None if (1..=3).any(|s| index.get_path(&relative, s).is_some()) => {
+                Ok(String::new())
+            }
+            None => Err(anyhow!("{} has no conflicts", relative.display())),
+        };
+    }
+    let Ok(tree) = repo
+        .revparse_single(revision)
+        .and_then(|object| object.peel_to_tree())
+    else {
+        return Ok(String::new());
+    };
+    match tree.get_path(&relative) {
+        Ok(entry) => blob_content(&repo, entry.id()),
+        Err(e) if e.code() == NotFound => Ok(String::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The commits reachable from `HEAD`, newest first, whose message or author
+/// contains `query`, limited to the ones changing `path` when given
+fn git_log(
+    workspace_path: &Path,
+    path: Option<&Path>,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<GitCommit>> {
+    let repo = Repository::discover(workspace_path)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("bare repository has no working directory"))?;
+    let mut path = path
+        .map(|path| repo_relative_path(&repo, path))
+        .transpose()?;
+    let query = query.to_lowercase();
+
+    let mut revwalk = repo.revwalk()?;
+    // Children before their parents, so renames are followed in order
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+    if revwalk.push_head().is_err() {
+        // There are no commits yet
+        return Ok(Vec::new());
+    }
+
+    let mut commits = Vec::new();
+    for id in revwalk {
+        if commits.len() >= limit {
+            break;
+        }
+        let commit = repo.find_commit(id?)?;
+        let commit_path = path.clone();
+        if let Some(path) = path.as_mut() {
+            if !git_commit_changed_path(&repo, &commit, path)? {
+                continue;
+            }
+        }
+
+        let author = commit.author();
+        let name = author.name().unwrap_or_default();
+        let email = author.email().unwrap_or_default();
+        let message = commit.message().unwrap_or_default().trim_end();
+        if !query.is_empty()
+            && ![message, name, email]
+                .iter()
+                .any(|text| text.to_lowercase().contains(&query))
+        {
+            continue;
+        }
+        commits.push(GitCommit {
+            id: commit.id().to_string(),
+            author: name.to_string(),
+            email: email.to_string(),
+            time: author.when().seconds(),
+            message: message.to_string(),
+            path: commit_path.map(|path| workdir.join(path)),
+        });
+    }
+    Ok(commits)
+}
+
+/// Whether `commit` changed the file at the repository relative `path`,
+/// which is set to the file's previous path if the commit renamed it
+fn git_commit_changed_path(
+    repo: &Repository,
+    commit: &git2::Commit,
+    path: &mut PathBuf,
+) -> Result<bool> {
+    let tree = commit.tree()?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let new_id = tree.get_path(path).ok().map(|entry| entry.id());
+    let old_id = parent_tree
+        .as_ref()
+        .and_then(|tree| tree.get_path(path).ok())
+        .map(|entry| entry.id());
+    if new_id == old_id {
+        return Ok(false);
+    }
+
+    if let (Some(_), None, Some(parent_tree)) = (new_id, old_id, &parent_tree) {
+        // The file may have been added by renaming another one
+        let mut diff =
+            repo.diff_tree_to_tree(Some(parent_tree), Some(&tree), None)?;
+        diff.find_similar(Some(git2::DiffFindOptions::new().renames(true)))?;
+        let old_path = diff.deltas().find_map(|delta| {
+            if delta.status() == git2::Delta::Renamed
+                && delta.new_file().path() == Some(path.as_path())
+            {
+                delta.old_file().path().map(|p| p.to_path_buf())
+            } else {
+                None
+            }
+        });
+        if let Some(old_path) = old_path {
+            *path = old_path;
+        }
+    }
+    Ok(true)
+}
+
+/// The files changed by `commit` compared to its first parent
+fn git_commit_files(workspace_path: &Path, commit: &str) -> Result<Vec<FileDiff>> {
+    let repo = Repository::discover(workspace_path)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("bare repository has no working directory"))?;
+    let commit = repo.revparse_single(commit)?.peel_to_commit()?;
+    let tree = commit.tree()?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let mut diff =
+        repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+    diff.find_similar(Some(git2::DiffFindOptions::new().renames(true)))?;
+
+    let mut files = Vec::new();
+    for delta in diff.deltas() {
+        let new = delta.new_file().path().map(|p| workdir.join(p));
+        let old = delta.old_file().path().map(|p| workdir.join(p));
+        let file = match (delta.status(), new, old) {
+            (git2::Delta::Added, Some(new), _) => FileDiff::Added(new),
+            (git2::Delta::Deleted, _, Some(old)) => FileDiff::Deleted(old),
+            (git2::Delta::Renamed, Some(new), Some(old)) => {
+                FileDiff::Renamed(new, old)
+            }
+            (_, Some(new), _) => FileDiff::Modified(new),
+            _ => continue,
+        };
+        files.push(file);
+    }
+    Ok(files)
+}
+
+/// The commits that last changed the lines of the file, blaming `content` when
+/// given as the file may have unsaved changes
+fn git_blame(
+    workspace_path: &Path,
+    path: &Path,
+    content: Option<&str>,
+) -> Result<Vec<BlameHunk>> {
+    let repo = Repository::discover(workspace_path)?;
+    let relative = repo_relative_path(&repo, path)?;
+    let blame = repo.blame_file(&relative, None)?;
+    let blame = match content {
+        Some(content) => blame.blame_buffer(content.as_bytes())?,
+        None => blame,
+    };
+
+    let mut commits = HashMap::new();
+    let mut hunks = Vec::new();
+    for hunk in blame.iter() {
+        let start_line = hunk.final_start_line().saturating_sub(1);
+        let line_count = hunk.lines_in_hunk();
+        let id = hunk.final_commit_id();
+        if id.is_zero() {
+            hunks.push(BlameHunk {
+                start_line,
+                line_count,
+                commit: None,
+                author: "Not Committed Yet".to_string(),
+                email: String::new(),
+                time: 0,
+                message: String::new(),
+            });
+            continue;
+        }
+
+        let commit = match commits.entry(id) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(repo.find_commit(id)?)
+            }
+        };
+        let author = commit.author();
+        hunks.push(BlameHunk {
+            start_line,
+            line_count,
+            commit: Some(id.to_string()),
+            author: author.name().unwrap_or_default().to_string(),
+            email: author.email().unwrap_or_default().to_string(),
+            time: author.when().seconds(),
+            message: commit.message().unwrap_or_default().trim_end().to_string(),
+        });
+    }
+    Ok(hunks)
+}
+
 fn git_get_remote_file_url(workspace_path: &Path, file: &Path) -> Result<String> {
     let repo = Repository::discover(workspace_path)?;
     let head = repo.head()?;
@@ -1671,6 +3209,82 @@ fn git_get_remote_file_url(workspace_path: &Path, file: &Path) -> Result<String>
     Ok(url)
 }
 
+/// Which files a global search looks at, built from the include and exclude
+/// globs given by the user
+struct SearchFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl SearchFilter {
+    fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: search_glob_set(include)?,
+            exclude: search_glob_set(exclude)?,
+        })
+    }
+
+    fn is_included(&self, path: &Path) -> bool {
+        self.include.as_ref().map_or(true, |set| set.is_match(path))
+            && !self.is_excluded(path)
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        self.exclude.as_ref().is_some_and(|set| set.is_match(path))
+    }
+
+    /// Walk the files under `root`, skipping the excluded folders entirely
+    fn walk(self: &Arc<Self>, root: &Path, search_ignored: bool) -> ignore::Walk {
+        let filter = self.clone();
+        let root_path = root.to_path_buf();
+        ignore::WalkBuilder::new(root)
+            .git_ignore(!search_ignored)
+            .git_global(!search_ignored)
+            .git_exclude(!search_ignored)
+            .ignore(!search_ignored)
+            .parents(!search_ignored)
+            .filter_entry(move |entry| {
+                let path = entry
+                    .path()
+                    .strip_prefix(&root_path)
+                    .unwrap_or(entry.path());
+                path.as_os_str().is_empty() || !filter.is_excluded(path)
+            })
+            .build()
+    }
+}
+
+/// Build a glob set from user written globs.
+///
+/// A glob that doesn't start with `/` or `**` matches at any depth, and a glob
+/// naming a folder also matches everything inside of it, so `target` leaves out
+/// every `target` folder.
+fn search_glob_set(globs: &[String]) -> Result<Option<GlobSet>> {
+    let mut builder = GlobSetBuilder::new();
+    let mut empty = true;
+    for glob in globs.iter().map(|g| g.trim()).filter(|g| !g.is_empty()) {
+        let glob = glob.trim_start_matches("./").trim_end_matches('/');
+        let glob = if let Some(glob) = glob.strip_prefix('/') {
+            glob.to_string()
+        } else if glob.starts_with("**") {
+            glob.to_string()
+        } else {
+            format!("**/{glob}")
+        };
+        builder.add(Glob::new(&glob)?);
+        builder.add(Glob::new(&format!("{glob}/**"))?);
+        empty = false;
+    }
+    if empty {
+        return Ok(None);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// How often matches are sent back to the core while a search is running
+const SEARCH_PARTIAL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[allow(clippy::too_many_arguments)]
 fn search_in_path(
     id: u64,
     current_id: &AtomicU64,
@@ -1679,21 +3293,22 @@ fn search_in_path(
     case_sensitive: bool,
     whole_word: bool,
     is_regex: bool,
+    max_results: Option<usize>,
+    search_id: u64,
+    core_rpc: &CoreRpcHandler,
 ) -> Result<ProxyResponse, RpcError> {
     let mut matches = IndexMap::new();
-    let mut matcher = RegexMatcherBuilder::new();
-    let matcher = matcher.case_insensitive(!case_sensitive).word(whole_word);
-    let matcher = if is_regex {
-        matcher.build(pattern)
-    } else {
-        matcher.build_literals(&[&regex::escape(pattern)])
-    };
-    let matcher = matcher.map_err(|_| RpcError {
-        code: 0,
-        message: "can't build matcher".to_string(),
-    })?;
+    let matcher =
+        build_search_matcher(pattern, case_sensitive, whole_word, is_regex)
+            .map_err(|_| RpcError {
+                code: 0,
+                message: "can't build matcher".to_string(),
+            })?;
     let mut searcher = SearcherBuilder::new().build();
 
+    let mut total = 0;
+    let mut limit_reached = false;
+    let mut last_sent = Instant::now();
     for path in paths {
         if current_id.load(Ordering::SeqCst) != id {
             return Err(RpcError {
@@ -1703,6 +3318,7 @@ fn search_in_path(
         }
 
         if path.is_file() {
+            let remaining = max_results.map(|max| max.saturating_sub(total));
             let mut line_matches = Vec::new();
             if let Err(err) = searcher.search_path(
                 &matcher,
@@ -1712,47 +3328,524 @@ fn search_in_path(
                         return Ok(false);
                     }
 
-                    let mymatch = matcher.find(line.as_bytes())?.unwrap();
-                    let line = if line.len() > 200 {
-                        // Shorten the line to avoid sending over absurdly long-lines
-                        // (such as in minified javascript)
-                        // Note that the start/end are column based, not absolute from the
-                        // start of the file.
-                        let left_keep = line[..mymatch.start()]
-                            .chars()
-                            .rev()
-                            .take(100)
-                            .map(|c| c.len_utf8())
-                            .sum::<usize>();
-                        let right_keep = line[mymatch.end()..]
-                            .chars()
-                            .take(100)
-                            .map(|c| c.len_utf8())
-                            .sum::<usize>();
-                        let display_range =
-                            mymatch.start() - left_keep..mymatch.end() + right_keep;
-                        line[display_range].to_string()
-                    } else {
-                        line.to_string()
-                    };
-                    line_matches.push(SearchMatch {
-                        line: lnum as usize,
-                        start: mymatch.start(),
-                        end: mymatch.end(),
-                        line_content: line,
-                    });
-                    Ok(true)
+                    matcher.find_iter(line.as_bytes(), |mymatch| {
+                        if mymatch.is_empty() {
+                            return true;
+                        }
+                        let (line_content, line_content_start) = if line.len() > 200
+                        {
+                            // Shorten the line to avoid sending over absurdly long-lines
+                            // (such as in minified javascript)
+                            // Note that the start/end are column based, not absolute from the
+                            // start of the file.
+                            let left_keep = line[..mymatch.start()]
+                                .chars()
+                                .rev()
+                                .take(100)
+                                .map(|c| c.len_utf8())
+                                .sum::<usize>();
+                            let right_keep = line[mymatch.end()..]
+                                .chars()
+                                .take(100)
+                                .map(|c| c.len_utf8())
+                                .sum::<usize>();
+                            let display_range = mymatch.start() - left_keep
+                                ..mymatch.end() + right_keep;
+                            (
+                                line[display_range].to_string(),
+                                mymatch.start() - left_keep,
+                            )
+                        } else {
+                            (line.to_string(), 0)
+                        };
+                        line_matches.push(SearchMatch {
+                            line: lnum as usize,
+                            start: mymatch.start(),
+                            end: mymatch.end(),
+                            full_line: (line_content.len() < line.len())
+                                .then(|| line.to_string()),
+                            line_content,
+                            line_content_start,
+                        });
+                        true
+                    })?;
+                    Ok(remaining.map_or(true, |r| line_matches.len() < r))
                 }),
             ) {
                 {
                     tracing::error!("{:?}", err);
                 }
             }
+            if let Some(remaining) = remaining {
+                if line_matches.len() >= remaining {
+                    line_matches.truncate(remaining);
+                    limit_reached = true;
+                }
+            }
+            total += line_matches.len();
             if !line_matches.is_empty() {
                 matches.insert(path.clone(), line_matches);
             }
+            if limit_reached {
+                break;
+            }
+        }
+
+        // Send what was found so far, so that results show up while the rest
+        // of a large workspace is still being searched
+        if !matches.is_empty() && last_sent.elapsed() >= SEARCH_PARTIAL_INTERVAL {
+            core_rpc.global_search_partial(search_id, std::mem::take(&mut matches));
+            last_sent = Instant::now();
         }
     }
 
-    Ok(ProxyResponse::GlobalSearchResponse { matches })
+    Ok(ProxyResponse::GlobalSearchResponse {
+        matches,
+        limit_reached,
+    })
+}
+
+/// Group the matches by their line, which is 1-based as reported by the searcher.
+fn matches_by_line(matches: &[SearchMatch]) -> IndexMap<usize, Vec<&SearchMatch>> {
+    let mut lines: IndexMap<usize, Vec<&SearchMatch>> = IndexMap::new();
+    for m in matches {
+        lines.entry(m.line).or_default().push(m);
+    }
+    lines
+}
+
+fn replace_edits_in_buffer(
+    buffer: &Buffer,
+    matcher: &RegexMatcher,
+    replacement: &str,
+    is_regex: bool,
+    matches: &[SearchMatch],
+) -> Vec<lsp_types::TextEdit> {
+    let mut edits = Vec::new();
+    for (line, line_matches) in matches_by_line(matches) {
+        let line = line.saturating_sub(1);
+        if line > buffer.line_of_offset(buffer.len()) {
+            continue;
+        }
+        let line_offset = buffer.offset_of_line(line);
+        let content = buffer.line_to_cow(line);
+        let line_edits = match replace_in_line(
+            matcher,
+            &content,
+            replacement,
+            is_regex,
+            &line_matches,
+        ) {
+            Ok(line_edits) => line_edits,
+            Err(err) => {
+                tracing::error!("{:?}", err);
+                continue;
+            }
+        };
+        for (range, new_text) in line_edits {
+            edits.push(lsp_types::TextEdit {
+                range: Range {
+                    start: buffer.offset_to_position(line_offset + range.start),
+                    end: buffer.offset_to_position(line_offset + range.end),
+                },
+                new_text,
+            });
+        }
+    }
+    edits
+}
+
+/// Apply the replacements to a file that isn't open, writing it back to disk.
+///
+/// Returns whether anything was replaced.
+fn replace_in_file(
+    path: &Path,
+    matcher: &RegexMatcher,
+    replacement: &str,
+    is_regex: bool,
+    matches: &[SearchMatch],
+) -> Result<bool> {
+    let DecodedFile {
+        content,
+        encoding,
+        bom,
+    } = load_file_with_encoding(path, None)?;
+    let lines = matches_by_line(matches);
+
+    let mut replaced = false;
+    let mut new_content = String::with_capacity(content.len());
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        let Some(line_matches) = lines.get(&(i + 1)) else {
+            new_content.push_str(line);
+            continue;
+        };
+        let mut last = 0;
+        for (range, new_text) in
+            replace_in_line(matcher, line, replacement, is_regex, line_matches)?
+        {
+            new_content.push_str(&line[last..range.start]);
+            new_content.push_str(&new_text);
+            last = range.end;
+            replaced = true;
+        }
+        new_content.push_str(&line[last..]);
+    }
+
+    if replaced {
+        fs::write(path, encode_text(&new_content, encoding, bom)?)?;
+    }
+    Ok(replaced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_hunk_lines() {
+        let base = "a\nb\nc\nd\n";
+        let other = "a\nB\nc\nd\ne\n";
+        // a changed line
+        assert_eq!(
+            replace_hunk_lines(base, (2, 1), other, (2, 1)),
+            "a\nB\nc\nd\n"
+        );
+        // an added line at the end
+        assert_eq!(
+            replace_hunk_lines(base, (4, 0), other, (5, 1)),
+            "a\nb\nc\nd\ne\n"
+        );
+        // a removed line
+        assert_eq!(replace_hunk_lines(base, (3, 1), "", (2, 0)), "a\nb\nd\n");
+    }
+
+    /// Commit `file` with `content` on the current branch of `repo`
+    fn commit_file(repo: &Repository, content: &str) {
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Lapce").unwrap();
+        config.set_str("user.email", "lapce@example.com").unwrap();
+        fs::write(repo.workdir().unwrap().join("file"), content).unwrap();
+        let mut index = repo.index().unwrap();
+        // the operations under test change the index on disk
+        index.read(false).unwrap();
+        index.add_path(Path::new("file")).unwrap();
+        index.write().unwrap();
+        git_commit(repo.workdir().unwrap(), content, false).unwrap();
+    }
+
+    #[test]
+    fn test_git_push_fetch_pull() {
+        let dir = tempfile::tempdir().unwrap();
+        let core_rpc = CoreRpcHandler::new();
+        let remote = dir.path().join("remote.git");
+        Repository::init_bare(&remote).unwrap();
+
+        let first = dir.path().join("first");
+        let first_repo = Repository::init(&first).unwrap();
+        first_repo
+            .remote("origin", remote.to_str().unwrap())
+            .unwrap();
+        commit_file(&first_repo, "a");
+        git_push(&first, &core_rpc).unwrap();
+        // the push sets the upstream of the branch
+        assert_eq!(git_ahead_behind(&first_repo), Some((0, 0)));
+
+        let second = dir.path().join("second");
+        let second_repo =
+            Repository::clone(remote.to_str().unwrap(), &second).unwrap();
+        commit_file(&second_repo, "b");
+        assert_eq!(git_ahead_behind(&second_repo), Some((1, 0)));
+        git_push(&second, &core_rpc).unwrap();
+
+        git_fetch(&first, &core_rpc).unwrap();
+        assert_eq!(git_ahead_behind(&first_repo), Some((0, 1)));
+        git_pull(&first, &core_rpc, None).unwrap();
+        assert_eq!(git_ahead_behind(&first_repo), Some((0, 0)));
+        assert_eq!(fs::read_to_string(first.join("file")).unwrap(), "b");
+    }
+
+    #[test]
+    fn test_git_blame() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let head = || repo.head().unwrap().target().unwrap().to_string();
+        commit_file(&repo, "a\nb\n");
+        let first = head();
+        commit_file(&repo, "a\nc\n");
+        let second = head();
+        let path = repo.workdir().unwrap().join("file");
+
+        let hunks = git_blame(dir.path(), &path, Some("a\nc\nd\n")).unwrap();
+        let lines = hunks
+            .iter()
+            .map(|hunk| (hunk.start_line, hunk.line_count, hunk.commit.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                (0, 1, Some(first.clone())),
+                (1, 1, Some(second)),
+                (2, 1, None)
+            ]
+        );
+
+        assert_eq!(
+            file_get_revision(dir.path(), &path, &first).unwrap(),
+            "a\nb\n"
+        );
+        // the file doesn't exist before the first commit
+        assert_eq!(
+            file_get_revision(dir.path(), &path, &format!("{first}^")).unwrap(),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_git_log_follows_renames() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let workdir = repo.workdir().unwrap().to_path_buf();
+        commit_file(&repo, "first\n");
+        commit_file(&repo, "second\n");
+
+        fs::rename(workdir.join("file"), workdir.join("renamed")).unwrap();
+        let mut index = repo.index().unwrap();
+        index.remove_path(Path::new("file")).unwrap();
+        index.add_path(Path::new("renamed")).unwrap();
+        index.write().unwrap();
+        git_commit(&workdir, "rename", false).unwrap();
+
+        fs::write(workdir.join("other"), "other\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("other")).unwrap();
+        index.write().unwrap();
+        git_commit(&workdir, "other", false).unwrap();
+
+        let commits = git_log(dir.path(), None, "", 10).unwrap();
+        assert_eq!(commits.len(), 4);
+
+        let renamed = workdir.join("renamed");
+        let commits = git_log(dir.path(), Some(&renamed), "", 10).unwrap();
+        let history = commits
+            .iter()
+            .map(|c| (c.summary(), c.path.clone().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            history,
+            vec![
+                ("rename", renamed.clone()),
+                ("second", workdir.join("file")),
+                ("first", workdir.join("file")),
+            ]
+        );
+
+        let commits = git_log(dir.path(), Some(&renamed), "SEC", 10).unwrap();
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].summary(), "second");
+
+        let rename = git_log(dir.path(), None, "rename", 1).unwrap();
+        assert_eq!(
+            git_commit_files(dir.path(), &rename[0].id).unwrap(),
+            vec![FileDiff::Renamed(renamed, workdir.join("file"))]
+        );
+    }
+
+    #[test]
+    fn test_git_branches_and_stashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let workdir = repo.workdir().unwrap().to_path_buf();
+        let head = || repo.head().unwrap().shorthand().unwrap().to_string();
+        let read_file = || fs::read_to_string(workdir.join("file")).unwrap();
+        commit_file(&repo, "a");
+        let main = head();
+
+        git_create_branch(dir.path(), "feature", "HEAD").unwrap();
+        assert_eq!(head(), "feature");
+        commit_file(&repo, "b");
+        git_checkout(dir.path(), &main).unwrap();
+        assert_eq!(read_file(), "a");
+        // the commit on the branch would be lost
+        assert!(git_delete_branch(dir.path(), "feature", false).is_err());
+
+        git_merge(dir.path(), "feature").unwrap();
+        assert_eq!(read_file(), "b");
+        git_delete_branch(dir.path(), "feature", false).unwrap();
+        git_rename_branch(dir.path(), &main, "trunk").unwrap();
+        assert_eq!(head(), "trunk");
+
+        git_create_branch(dir.path(), "side", "trunk").unwrap();
+        fs::write(workdir.join("other"), "other").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("other")).unwrap();
+        index.write().unwrap();
+        git_commit(&workdir, "other", false).unwrap();
+        git_checkout(dir.path(), "trunk").unwrap();
+        commit_file(&repo, "c");
+        git_merge(dir.path(), "side").unwrap();
+        let merge = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(merge.parent_count(), 2);
+        assert_eq!(merge.summary(), Some("Merge branch 'side'"));
+        assert!(workdir.join("other").exists());
+
+        fs::write(workdir.join("file"), "d").unwrap();
+        git_stash_save(dir.path(), "wip").unwrap();
+        assert_eq!(read_file(), "c");
+        let stashes = git_diff_new(dir.path()).unwrap().stashes;
+        assert_eq!(stashes.len(), 1);
+        assert!(stashes[0].message.ends_with("wip"));
+
+        git_stash_apply(dir.path(), 0, false).unwrap();
+        assert_eq!(read_file(), "d");
+        git_stash_drop(dir.path(), 0).unwrap();
+        assert!(git_diff_new(dir.path()).unwrap().stashes.is_empty());
+    }
+
+    #[test]
+    fn test_git_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let path = repo.workdir().unwrap().join("file");
+        commit_file(&repo, "a\n");
+        let main = repo.head().unwrap().shorthand().unwrap().to_string();
+        git_create_branch(dir.path(), "feature", "HEAD").unwrap();
+        commit_file(&repo, "b\n");
+        git_checkout(dir.path(), &main).unwrap();
+        commit_file(&repo, "c\n");
+
+        assert!(git_merge(dir.path(), "feature").is_err());
+        let diff = git_diff_new(dir.path()).unwrap();
+        assert_eq!(diff.diffs, vec![FileDiff::Conflicted(path.clone())]);
+        assert!(diff.staged.is_empty());
+        let revision = |revision| file_get_revision(dir.path(), &path, revision);
+        assert_eq!(revision(":1").unwrap(), "a\n");
+        assert_eq!(revision(":2").unwrap(), "c\n");
+        assert_eq!(revision(":3").unwrap(), "b\n");
+
+        fs::write(&path, "b\nc\n").unwrap();
+        git_stage_files(dir.path(), &[path.clone()]).unwrap();
+        let diff = git_diff_new(dir.path()).unwrap();
+        assert_eq!(diff.staged, vec![FileDiff::Modified(path.clone())]);
+        assert!(revision(":2").is_err());
+    }
+
+    fn search_match(line: usize, start: usize, end: usize) -> SearchMatch {
+        SearchMatch {
+            line,
+            start,
+            end,
+            line_content: String::new(),
+            line_content_start: 0,
+            full_line: None,
+        }
+    }
+
+    #[test]
+    fn test_replace_in_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        fs::write(&path, "one two\ntwo one\r\nTwo\n").unwrap();
+        let matcher = build_search_matcher("two", false, false, false).unwrap();
+
+        let matches = [search_match(2, 0, 3), search_match(3, 0, 3)];
+        assert!(replace_in_file(&path, &matcher, "2", false, &matches).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one two\n2 one\r\n2\n");
+        assert!(!replace_in_file(&path, &matcher, "2", false, &[]).unwrap());
+    }
+
+    #[test]
+    fn test_search_every_match_on_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        let long_line = format!("{}foo{}", "x ".repeat(150), " y".repeat(150));
+        fs::write(&path, format!("foo bar foo foo\n{long_line}\n")).unwrap();
+
+        let result = search_in_path(
+            1,
+            &AtomicU64::new(1),
+            [path.clone()].into_iter(),
+            "foo",
+            true,
+            false,
+            false,
+            None,
+            1,
+            &CoreRpcHandler::new(),
+        );
+        let Ok(ProxyResponse::GlobalSearchResponse { matches, .. }) = result else {
+            panic!("search failed");
+        };
+        let matches = &matches[&path];
+        let columns = matches
+            .iter()
+            .map(|m| (m.line, m.start, m.end))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            vec![(1, 0, 3), (1, 8, 11), (1, 12, 15), (2, 300, 303)]
+        );
+        // the long line is shortened around the match
+        let long = &matches[3];
+        assert!(long.line_content.len() < long_line.len());
+        assert_eq!(&long.line_content[long.content_range()], "foo");
+    }
+
+    #[test]
+    fn test_search_filter_and_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("target")).unwrap();
+        fs::write(root.join("a.rs"), "foo\nfoo\n").unwrap();
+        fs::write(root.join("b.rs"), "foo\n").unwrap();
+        fs::write(root.join("c.txt"), "foo\n").unwrap();
+        fs::write(root.join("target").join("d.rs"), "foo\n").unwrap();
+
+        let filter = Arc::new(
+            SearchFilter::new(&["*.rs".to_string()], &["target".into()]).unwrap(),
+        );
+        let search = |current_id: u64, max_results: Option<usize>| {
+            let paths = filter
+                .walk(root, false)
+                .flatten()
+                .map(|p| p.into_path())
+                .filter(|p| filter.is_included(p.strip_prefix(root).unwrap()));
+            search_in_path(
+                1,
+                &AtomicU64::new(current_id),
+                paths,
+                "foo",
+                true,
+                false,
+                false,
+                max_results,
+                1,
+                &CoreRpcHandler::new(),
+            )
+        };
+
+        let Ok(ProxyResponse::GlobalSearchResponse {
+            matches,
+            limit_reached,
+        }) = search(1, None)
+        else {
+            panic!("search failed");
+        };
+        let mut paths = matches.keys().cloned().collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(paths, vec![root.join("a.rs"), root.join("b.rs")]);
+        assert!(!limit_reached);
+
+        let Ok(ProxyResponse::GlobalSearchResponse {
+            matches,
+            limit_reached,
+        }) = search(1, Some(2))
+        else {
+            panic!("search failed");
+        };
+        assert_eq!(matches.values().map(|m| m.len()).sum::<usize>(), 2);
+        assert!(limit_reached);
+
+        // a search that was replaced by a newer one stops early
+        assert!(search(2, None).is_err());
+    }
 }
@@ -0,0 +1,127 @@
+use std::path::Path;
+
+use ec4rs::property::{
+    Charset, EndOfLine as EcEndOfLine, FinalNewline, IndentSize, IndentStyle,
+    TabWidth, TrimTrailingWs,
+};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use lapce_rpc::buffer::{EditorConfigProperties, EndOfLine};
+
+/// The `.editorconfig` settings that apply to a file
+#[derive(Default)]
+pub struct EditorConfig {
+    pub properties: EditorConfigProperties,
+    /// The encoding of the file, along with whether it starts with a byte order
+    /// mark
+    pub charset: Option<(&'static Encoding, bool)>,
+}
+
+/// Resolve the `.editorconfig` files in the folders above `path`
+pub fn resolve(path: &Path) -> EditorConfig {
+    let mut properties = match ec4rs::properties_of(path) {
+        Ok(properties) => properties,
+        Err(err) => {
+            tracing::error!("{:?}", err);
+            return EditorConfig::default();
+        }
+    };
+    // Derives `indent_size` and `tab_width` from each other when only one is set
+    properties.use_fallbacks();
+
+    let indent_with_tabs = properties
+        .get::<IndentStyle>()
+        .ok()
+        .map(|style| matches!(style, IndentStyle::Tabs));
+    let tab_width = match properties.get::<TabWidth>() {
+        Ok(TabWidth::Value(width)) => Some(width),
+        _ => None,
+    };
+    let indent_size = match properties.get::<IndentSize>() {
+        Ok(IndentSize::Value(size)) => Some(size),
+        Ok(IndentSize::UseTabWidth) => tab_width,
+        _ => None,
+    };
+    let end_of_line = properties.get::<EcEndOfLine>().ok().map(|eol| match eol {
+        EcEndOfLine::Lf => EndOfLine::Lf,
+        EcEndOfLine::CrLf => EndOfLine::CrLf,
+        EcEndOfLine::Cr => EndOfLine::Cr,
+    });
+    let trim_trailing_whitespace = match properties.get::<TrimTrailingWs>() {
+        Ok(TrimTrailingWs::Value(trim)) => Some(trim),
+        _ => None,
+    };
+    let insert_final_newline = match properties.get::<FinalNewline>() {
+        Ok(FinalNewline::Value(insert)) => Some(insert),
+        _ => None,
+    };
+    let charset = properties
+        .get::<Charset>()
+        .ok()
+        .map(|charset| match charset {
+            // encoding_rs decodes Latin-1 as its superset Windows 1252, as
+            // browsers do
+            Charset::Latin1 => (WINDOWS_1252, false),
+            Charset::Utf8 => (UTF_8, false),
+            Charset::Utf8Bom => (UTF_8, true),
+            Charset::Utf16Be => (UTF_16BE, true),
+            Charset::Utf16Le => (UTF_16LE, true),
+        });
+
+    EditorConfig {
+        properties: EditorConfigProperties {
+            indent_with_tabs,
+            indent_size,
+            tab_width,
+            end_of_line,
+            trim_trailing_whitespace,
+            insert_final_newline,
+        },
+        charset,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(".editorconfig"),
+            "root = true\n\n\
+             [*]\nindent_style = tab\nend_of_line = crlf\n\n\
+             [*.py]\nindent_style = space\nindent_size = 2\n\
+             charset = utf-8-bom\ntrim_trailing_whitespace = true\n\n\
+             [*.md]\nindent_style = unset\nindent_size = 2\n",
+        )
+        .unwrap();
+
+        let config = resolve(&dir.path().join("main.py"));
+        assert_eq!(
+            config.properties,
+            EditorConfigProperties {
+                indent_with_tabs: Some(false),
+                indent_size: Some(2),
+                tab_width: Some(2),
+                end_of_line: Some(EndOfLine::CrLf),
+                trim_trailing_whitespace: Some(true),
+                insert_final_newline: None,
+            }
+        );
+        assert_eq!(config.charset, Some((UTF_8, true)));
+
+        let config = resolve(&dir.path().join("main.rs"));
+        assert_eq!(config.properties.indent_with_tabs, Some(true));
+        assert_eq!(config.properties.indent_size, None);
+        assert_eq!(config.charset, None);
+
+        // Only the size, which the editor applies to the style of the file
+        let config = resolve(&dir.path().join("README.md"));
+        assert_eq!(config.properties.indent_with_tabs, None);
+        assert_eq!(config.properties.indent_size, Some(2));
+        assert_eq!(config.properties.tab_width, Some(2));
+    }
+}
@@ -1,7 +1,7 @@
 use std::{
     borrow::Cow,
-    collections::HashMap,
-    path::PathBuf,
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc,
@@ -9,9 +9,11 @@
     thread,
 };
 
+use anyhow::{anyhow, Result};
 use lapce_rpc::{
+    core::LogLevel,
     dap_types::{self, DapId, DapServer, SetBreakpointsResponse},
-    plugin::{PluginId, VoltID, VoltInfo, VoltMetadata},
+    plugin::{LspServerConfig, PluginId, VoltID, VoltInfo, VoltMetadata},
     proxy::ProxyResponse,
     style::LineStyle,
     RpcError,
@@ -19,8 +21,9 @@
 use lapce_xi_rope::{Rope, RopeDelta};
 use lsp_types::{
     notification::DidOpenTextDocument, request::Request, DidOpenTextDocumentParams,
-    MessageType, SemanticTokens, ShowMessageParams, TextDocumentIdentifier,
-    TextDocumentItem, VersionedTextDocumentIdentifier,
+    DocumentFilter, DocumentSelector, MessageType, SemanticTokens,
+    ShowMessageParams, TextDocumentIdentifier, TextDocumentItem, Url,
+    VersionedTextDocumentIdentifier,
 };
 use parking_lot::Mutex;
 use psp_types::Notification;
@@ -28,8 +31,9 @@
 
 use super::{
     dap::{DapClient, DapRpcHandler, DebuggerData},
+    lsp::LspClient,
     psp::{ClonableCallback, PluginServerRpc, PluginServerRpcHandler, RpcCallback},
-    wasi::{load_all_volts, start_volt},
+    wasi::{load_all_volts, start_volt, unflatten_map},
     PluginCatalogNotification, PluginCatalogRpcHandler,
 };
 use crate::plugin::{
@@ -38,31 +42,41 @@
 
 pub struct PluginCatalog {
     workspace: Option<PathBuf>,
+    workspace_folders: Vec<PathBuf>,
     plugin_rpc: PluginCatalogRpcHandler,
     plugins: HashMap<PluginId, PluginServerRpcHandler>,
     daps: HashMap<DapId, DapRpcHandler>,
     debuggers: HashMap<String, DebuggerData>,
     plugin_configurations: HashMap<String, HashMap<String, serde_json::Value>>,
     unactivated_volts: HashMap<VoltID, VoltMetadata>,
+    /// The language servers declared in the `[lsp.<name>]` settings
+    lsp_servers: HashMap<String, LspServerConfig>,
+    /// The names of the servers of `lsp_servers` that were started
+    started_lsp_servers: HashSet<String>,
     open_files: HashMap<PathBuf, String>,
 }
 
 impl PluginCatalog {
     pub fn new(
         workspace: Option<PathBuf>,
+        workspace_folders: Vec<PathBuf>,
         disabled_volts: Vec<VoltID>,
         extra_plugin_paths: Vec<PathBuf>,
         plugin_configurations: HashMap<String, HashMap<String, serde_json::Value>>,
+        lsp_servers: HashMap<String, LspServerConfig>,
         plugin_rpc: PluginCatalogRpcHandler,
     ) -> Self {
         let plugin = Self {
             workspace,
+            workspace_folders,
             plugin_rpc: plugin_rpc.clone(),
             plugin_configurations,
             plugins: HashMap::new(),
             daps: HashMap::new(),
             debuggers: HashMap::new(),
             unactivated_volts: HashMap::new(),
+            lsp_servers,
+            started_lsp_servers: HashSet::new(),
             open_files: HashMap::new(),
         };
 
@@ -204,15 +218,20 @@ pub fn shutdown_volt(
     fn start_unactivated_volts(&mut self, to_be_activated: Vec<VoltID>) {
         for id in to_be_activated.iter() {
             let workspace = self.workspace.clone();
+            let workspace_folders = self.workspace_folders.clone();
             if let Some(meta) = self.unactivated_volts.remove(id) {
                 let configurations =
                     self.plugin_configurations.get(&meta.name).cloned();
                 tracing::debug!("{:?} {:?}", id, configurations);
                 let plugin_rpc = self.plugin_rpc.clone();
                 thread::spawn(move || {
-                    if let Err(err) =
-                        start_volt(workspace, configurations, plugin_rpc, meta)
-                    {
+                    if let Err(err) = start_volt(
+                        workspace,
+                        workspace_folders,
+                        configurations,
+                        plugin_rpc,
+                        meta,
+                    ) {
                         tracing::error!("{:?}", err);
                     }
                 });
@@ -239,7 +258,7 @@ fn check_unactivated_volts(&mut self) {
                     return Some(id.clone());
                 }
 
-                if let Some(workspace) = self.workspace.as_ref() {
+                if !self.workspace_folders.is_empty() {
                     if let Some(globs) = meta
                         .activation
                         .as_ref()
@@ -259,10 +278,15 @@ fn check_unactivated_volts(&mut self) {
                         match builder.build() {
                             Ok(matcher) => {
                                 if !matcher.is_empty() {
-                                    for entry in walkdir::WalkDir::new(workspace)
-                                        .into_iter()
-                                        .flatten()
-                                    {
+                                    let entries = self
+                                        .workspace_folders
+                                        .iter()
+                                        .flat_map(|folder| {
+                                            walkdir::WalkDir::new(folder)
+                                                .into_iter()
+                                                .flatten()
+                                        });
+                                    for entry in entries {
                                         if matcher.is_match(entry.path()) {
                                             return Some(id.clone());
                                         }
@@ -282,6 +306,77 @@ fn check_unactivated_volts(&mut self) {
         self.start_unactivated_volts(to_be_activated);
     }
 
+    /// Start the servers declared in the settings that handle one of the open
+    /// files
+    fn start_lsp_servers(&mut self) {
+        let to_be_started: Vec<String> = self
+            .lsp_servers
+            .iter()
+            .filter(|(name, server)| {
+                !self.started_lsp_servers.contains(*name)
+                    && self.open_files.iter().any(|(path, language_id)| {
+                        lsp_server_handles(server, language_id, path)
+                    })
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in to_be_started {
+            let server = self.lsp_servers[&name].clone();
+            self.started_lsp_servers.insert(name.clone());
+            let workspace = self.workspace.clone();
+            let workspace_folders = self.workspace_folders.clone();
+            let plugin_rpc = self.plugin_rpc.clone();
+            thread::spawn(move || {
+                let result = lsp_server_uri(&server.command).and_then(|uri| {
+                    LspClient::start(
+                        plugin_rpc.clone(),
+                        lsp_server_document_selector(&server),
+                        workspace,
+                        workspace_folders,
+                        lsp_server_volt_id(&name),
+                        name.clone(),
+                        None,
+                        None,
+                        None,
+                        uri,
+                        server.args,
+                        server.env,
+                        server.initialization_options,
+                        server.settings,
+                    )
+                });
+                if let Err(err) = result {
+                    plugin_rpc.core_rpc.log(
+                        LogLevel::Error,
+                        format!("failed to start language server {name}: {err}"),
+                        Some(format!(
+                            "lapce_proxy::plugin::catalog::lsp::{name}::start"
+                        )),
+                    );
+                }
+            });
+        }
+    }
+
+    /// Stop the server declared in the settings as `name`, if it was started
+    fn stop_lsp_server(&mut self, name: &str) {
+        if !self.started_lsp_servers.remove(name) {
+            return;
+        }
+        let volt_id = lsp_server_volt_id(name);
+        let ids: Vec<PluginId> = self
+            .plugins
+            .iter()
+            .filter(|(_, plugin)| plugin.volt_id == volt_id)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ids {
+            if let Some(plugin) = self.plugins.remove(&id) {
+                plugin.shutdown();
+            }
+        }
+    }
+
     pub fn handle_did_open_text_document(&mut self, document: TextDocumentItem) {
         match document.uri.to_file_path() {
             Ok(path) => {
@@ -309,6 +404,7 @@ pub fn handle_did_open_text_document(&mut self, document: TextDocumentItem) {
             })
             .collect();
         self.start_unactivated_volts(to_be_activated);
+        self.start_lsp_servers();
 
         let path = document.uri.to_file_path().ok();
         for (_, plugin) in self.plugins.iter() {
@@ -383,6 +479,23 @@ pub fn format_semantic_tokens(
         }
     }
 
+    pub fn plugin_configuration(
+        &self,
+        volt_id: &VoltID,
+        f: Box<dyn RpcCallback<Value, RpcError>>,
+    ) {
+        let configuration = if *volt_id == lsp_server_volt_id(&volt_id.name) {
+            self.lsp_servers
+                .get(&volt_id.name)
+                .and_then(|server| server.settings.clone())
+        } else {
+            self.plugin_configurations
+                .get(&volt_id.name)
+                .map(unflatten_map)
+        };
+        f.call(Ok(configuration.unwrap_or_default()));
+    }
+
     pub fn dap_variable(
         &self,
         dap_id: DapId,
@@ -477,6 +590,31 @@ pub fn dap_get_scopes(
         }
     }
 
+    pub fn dap_evaluate(
+        &self,
+        dap_id: DapId,
+        expression: String,
+        frame_id: Option<usize>,
+        context: dap_types::EvaluateContext,
+        f: Box<dyn RpcCallback<dap_types::EvaluateResponse, RpcError>>,
+    ) {
+        if let Some(dap) = self.daps.get(&dap_id) {
+            dap.evaluate_async(
+                expression,
+                frame_id,
+                context,
+                |result: Result<dap_types::EvaluateResponse, RpcError>| {
+                    f.call(result)
+                },
+            );
+        } else {
+            f.call(Err(RpcError {
+                code: 0,
+                message: "plugin doesn't exist".to_string(),
+            }));
+        }
+    }
+
     pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
         use PluginCatalogNotification::*;
         match notification {
@@ -492,6 +630,21 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
                 tracing::debug!("UpdatePluginConfigs {:?}", configs);
                 self.plugin_configurations = configs;
             }
+            UpdateLspServers(servers) => {
+                tracing::debug!("UpdateLspServers {:?}", servers);
+                // Changed servers are restarted with their new settings
+                let changed: Vec<String> = self
+                    .lsp_servers
+                    .iter()
+                    .filter(|(name, server)| servers.get(*name) != Some(*server))
+                    .map(|(name, _)| name.clone())
+                    .collect();
+                for name in changed {
+                    self.stop_lsp_server(&name);
+                }
+                self.lsp_servers = servers;
+                self.start_lsp_servers();
+            }
             PluginServerLoaded(plugin) => {
                 // TODO: check if the server has did open registered
                 match self.plugin_rpc.proxy_rpc.get_open_files_content() {
@@ -534,14 +687,19 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
             InstallVolt(volt) => {
                 tracing::debug!("InstallVolt {:?}", volt);
                 let workspace = self.workspace.clone();
+                let workspace_folders = self.workspace_folders.clone();
                 let configurations =
                     self.plugin_configurations.get(&volt.name).cloned();
                 let catalog_rpc = self.plugin_rpc.clone();
                 catalog_rpc.stop_volt(volt.clone());
                 thread::spawn(move || {
-                    if let Err(err) =
-                        install_volt(catalog_rpc, workspace, configurations, volt)
-                    {
+                    if let Err(err) = install_volt(
+                        catalog_rpc,
+                        workspace,
+                        workspace_folders,
+                        configurations,
+                        volt,
+                    ) {
                         tracing::error!("{:?}", err);
                     }
                 });
@@ -621,7 +779,7 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
                                     tracing::error!("{:?}", err);
                                 }
 
-                                if let Err(err) = dap_rpc.launch(&config) {
+                                if let Err(err) = dap_rpc.start_debuggee(&config) {
                                     tracing::error!("{:?}", err);
                                 }
                             }
@@ -758,3 +916,97 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
         }
     }
 }
+
+/// Servers declared in the settings are told apart from volts by this author
+fn lsp_server_volt_id(name: &str) -> VoltID {
+    VoltID {
+        author: "settings".to_string(),
+        name: name.to_string(),
+    }
+}
+
+/// The uri `LspClient` runs `command` from, a `file` uri for an absolute path
+/// and a `urn` for a program looked up in `PATH`
+fn lsp_server_uri(command: &str) -> Result<Url> {
+    if Path::new(command).is_absolute() {
+        Url::from_file_path(command)
+            .map_err(|_| anyhow!("invalid server path {command}"))
+    } else {
+        Ok(Url::parse(&format!("urn:{command}"))?)
+    }
+}
+
+fn lsp_server_document_selector(server: &LspServerConfig) -> DocumentSelector {
+    let languages = server.languages.iter().map(|language| DocumentFilter {
+        language: Some(language.clone()),
+        scheme: None,
+        pattern: None,
+    });
+    let globs = server.globs.iter().map(|glob| DocumentFilter {
+        language: None,
+        scheme: None,
+        pattern: Some(glob.clone()),
+    });
+    languages.chain(globs).collect()
+}
+
+/// Whether the document at `path` is handled by `server`, which is started
+/// once such a document is open
+fn lsp_server_handles(
+    server: &LspServerConfig,
+    language_id: &str,
+    path: &Path,
+) -> bool {
+    server
+        .languages
+        .iter()
+        .any(|language| language == language_id)
+        || server.globs.iter().any(|glob| {
+            globset::Glob::new(glob)
+                .map(|glob| glob.compile_matcher().is_match(path))
+                .unwrap_or(false)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use lapce_rpc::plugin::LspServerConfig;
+
+    use super::{lsp_server_handles, lsp_server_uri};
+
+    #[test]
+    fn test_lsp_server_settings() {
+        let server: LspServerConfig = toml::from_str(
+            r#"
+command = "my-server"
+args = ["--stdio"]
+env = { RUST_LOG = "debug" }
+languages = ["rust"]
+globs = ["**/*.tmpl"]
+initialization-options = { cache = false }
+"#,
+        )
+        .unwrap();
+        assert_eq!(server.args, vec!["--stdio".to_string()]);
+        assert_eq!(server.env["RUST_LOG"], "debug");
+        assert_eq!(
+            server.initialization_options,
+            Some(serde_json::json!({ "cache": false }))
+        );
+        assert!(server.settings.is_none());
+
+        assert!(lsp_server_handles(&server, "rust", Path::new("/a/main.rs")));
+        assert!(lsp_server_handles(
+            &server,
+            "html",
+            Path::new("/a/b/page.tmpl")
+        ));
+        assert!(!lsp_server_handles(&server, "go", Path::new("/a/main.go")));
+
+        let uri = lsp_server_uri("my-server").unwrap();
+        assert_eq!(uri.scheme(), "urn");
+        assert_eq!(uri.path(), "my-server");
+    }
+}
@@ -14,16 +14,18 @@
 use crossbeam_channel::{Receiver, Sender};
 use lapce_rpc::{
     dap_types::{
-        self, ConfigurationDone, Continue, ContinueArguments, ContinueResponse,
-        DapEvent, DapId, DapPayload, DapRequest, DapResponse, DapServer,
-        DebuggerCapabilities, Disconnect, Initialize, Launch, Next, NextArguments,
-        Pause, PauseArguments, Request, RunDebugConfig, RunInTerminal,
-        RunInTerminalArguments, RunInTerminalResponse, Scope, Scopes,
-        ScopesArguments, ScopesResponse, SetBreakpoints, SetBreakpointsArguments,
-        SetBreakpointsResponse, Source, SourceBreakpoint, StackTrace,
-        StackTraceArguments, StackTraceResponse, StepIn, StepInArguments, StepOut,
-        StepOutArguments, Terminate, ThreadId, Threads, ThreadsResponse, Variable,
-        Variables, VariablesArguments, VariablesResponse,
+        self, Attach, ConfigurationDone, Continue, ContinueArguments,
+        ContinueResponse, DapEvent, DapId, DapPayload, DapRequest, DapResponse,
+        DapServer, DebugRequest, DebuggerCapabilities, Disconnect, Evaluate,
+        EvaluateArguments, EvaluateContext, EvaluateResponse, Initialize, Launch,
+        Next, NextArguments, Pause, PauseArguments, ProcessInfo, Request,
+        RunDebugConfig, RunInTerminal, RunInTerminalArguments,
+        RunInTerminalResponse, Scope, Scopes, ScopesArguments, ScopesResponse,
+        SetBreakpoints, SetBreakpointsArguments, SetBreakpointsResponse, Source,
+        SourceBreakpoint, StackTrace, StackTraceArguments, StackTraceResponse,
+        StepIn, StepInArguments, StepOut, StepOutArguments, Terminate, ThreadId,
+        Threads, ThreadsResponse, Variable, Variables, VariablesArguments,
+        VariablesResponse,
     },
     terminal::TermId,
     RpcError,
@@ -306,7 +308,13 @@ fn handle_host_event(&mut self, event: &DapEvent) -> Result<()> {
                 }
             }
             DapEvent::Thread { .. } => {}
-            DapEvent::Output(_) => {}
+            DapEvent::Output(output) => {
+                if output.category.as_deref() != Some("telemetry") {
+                    self.plugin_rpc
+                        .core_rpc
+                        .dap_output(self.config.dap_id, output.clone());
+                }
+            }
             DapEvent::Breakpoint { .. } => {}
             DapEvent::Module { .. } => {}
             DapEvent::LoadedSource { .. } => {}
@@ -343,14 +351,21 @@ pub(crate) fn initialize(&mut self) -> Result<()> {
         Ok(())
     }
 
+    /// Whether the session is stopped with a terminate request rather than
+    /// by disconnecting. Terminating would kill a process we attached to, so
+    /// attach sessions always disconnect.
+    fn stops_with_terminate(&self) -> bool {
+        self.config.request == DebugRequest::Launch
+            && self
+                .capabilities
+                .as_ref()
+                .and_then(|c| c.supports_terminate_request)
+                .unwrap_or(false)
+    }
+
     fn stop(&self) {
         let dap_rpc = self.dap_rpc.clone();
-        if self
-            .capabilities
-            .as_ref()
-            .and_then(|c| c.supports_terminate_request)
-            .unwrap_or(false)
-        {
+        if self.stops_with_terminate() {
             thread::spawn(move || {
                 if let Err(err) = dap_rpc.terminate() {
                     tracing::error!("{:?}", err);
@@ -366,19 +381,13 @@ fn stop(&self) {
     }
 
     // check if the DAP was restared when we received terminated or disconnected
-    // if the DAP doesn't suports terminate request, then we also need to wait for
-    // disconnected
+    // if the DAP doesn't suports terminate request, or we attached to the process,
+    // then we also need to wait for disconnected
     fn check_restart(&mut self) -> Result<()> {
         if !self.restarted {
             return Ok(());
         }
-        if !self
-            .capabilities
-            .as_ref()
-            .and_then(|c| c.supports_terminate_request)
-            .unwrap_or(false)
-            && !self.disconnected
-        {
+        if !self.stops_with_terminate() && !self.disconnected {
             return Ok(());
         }
 
@@ -394,7 +403,7 @@ fn check_restart(&mut self) -> Result<()> {
         let dap_rpc = self.dap_rpc.clone();
         let config = self.config.clone();
         thread::spawn(move || {
-            if let Err(err) = dap_rpc.launch(&config) {
+            if let Err(err) = dap_rpc.start_debuggee(&config) {
                 tracing::error!("{:?}", err);
             }
         });
@@ -618,6 +627,15 @@ pub fn handle_server_message(&self, message_str: &str) {
         }
     }
 
+    /// Start debugging with either a `launch` or an `attach` request,
+    /// depending on what the config asks for
+    pub fn start_debuggee(&self, config: &RunDebugConfig) -> Result<()> {
+        match config.request {
+            DebugRequest::Launch => self.launch(config),
+            DebugRequest::Attach => self.attach(config),
+        }
+    }
+
     pub fn launch(&self, config: &RunDebugConfig) -> Result<()> {
         let params = serde_json::json!({
             "program": config.program,
@@ -626,12 +644,36 @@ pub fn launch(&self, config: &RunDebugConfig) -> Result<()> {
             "runInTerminal": true,
             "env": config.env
         });
+        let params = with_adapter_args(params, config);
         let _resp = self
             .request::<Launch>(params)
             .map_err(|e| anyhow!(e.message))?;
         Ok(())
     }
 
+    pub fn attach(&self, config: &RunDebugConfig) -> Result<()> {
+        let mut params = serde_json::Map::new();
+        if let Some(pid) = config.process_id {
+            params.insert("processId".to_string(), pid.into());
+            // lldb based adapters call it `pid`
+            params.insert("pid".to_string(), pid.into());
+        }
+        if let Some(host) = config.host.as_ref() {
+            params.insert("host".to_string(), host.clone().into());
+        }
+        if let Some(port) = config.port {
+            params.insert("port".to_string(), port.into());
+        }
+        if let Some(cwd) = config.cwd.as_ref() {
+            params.insert("cwd".to_string(), cwd.clone().into());
+        }
+        let params = with_adapter_args(Value::Object(params), config);
+        let _resp = self
+            .request::<Attach>(params)
+            .map_err(|e| anyhow!(e.message))?;
+        Ok(())
+    }
+
     pub fn stop(&self) {
         if let Err(err) = self.rpc_tx.send(DapRpc::Stop) {
             tracing::error!("{:?}", err);
@@ -820,4 +862,143 @@ pub fn step_out(&self, thread_id: ThreadId) {
 
         self.request_async::<StepOut>(args, move |_| {});
     }
+
+    pub fn evaluate_async(
+        &self,
+        expression: String,
+        frame_id: Option<usize>,
+        context: EvaluateContext,
+        f: impl RpcCallback<EvaluateResponse, RpcError> + 'static,
+    ) {
+        let args = EvaluateArguments {
+            expression,
+            frame_id,
+            context: Some(context),
+            format: None,
+        };
+
+        self.request_async::<Evaluate>(args, f);
+    }
+}
+
+/// Merge the adapter specific fields of the config into the request params,
+/// overriding the ones we filled in ourselves
+fn with_adapter_args(mut params: Value, config: &RunDebugConfig) -> Value {
+    if let (Some(params), Some(adapter_args)) =
+        (params.as_object_mut(), config.adapter_args.as_ref())
+    {
+        for (key, value) in adapter_args {
+            params.insert(key.clone(), value.clone());
+        }
+    }
+    params
+}
+
+/// List the running processes, for picking the one to attach the debugger to
+pub fn list_processes() -> Result<Vec<ProcessInfo>> {
+    #[cfg(not(target_os = "windows"))]
+    let output = Command::new("ps")
+        .args(["-A", "-o", "pid=", "-o", "args="])
+        .output()?;
+
+    #[cfg(target_os = "windows")]
+    let output = {
+        let mut process = Command::new("tasklist");
+        process.args(["/fo", "csv", "/nh"]);
+        // CREATE_NO_WINDOW
+        std::os::windows::process::CommandExt::creation_flags(
+            &mut process,
+            0x08000000,
+        );
+        process.output()?
+    };
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "failed to list processes: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let own_pid = std::process::id();
+    let processes = stdout
+        .lines()
+        .filter_map(parse_process_line)
+        .filter(|p| p.pid != own_pid)
+        .collect();
+    Ok(processes)
+}
+
+/// Parse a line of `ps -o pid= -o args=`
+#[cfg(not(target_os = "windows"))]
+fn parse_process_line(line: &str) -> Option<ProcessInfo> {
+    let (pid, command) = line.trim().split_once(char::is_whitespace)?;
+    let pid = pid.parse().ok()?;
+    let command = command.trim().to_string();
+    let program = command.split_whitespace().next()?;
+    let name = std::path::Path::new(program)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(program)
+        .to_string();
+    Some(ProcessInfo { pid, name, command })
+}
+
+/// Parse a line of `tasklist /fo csv /nh`, e.g. `"lapce.exe","1234",...`
+#[cfg(target_os = "windows")]
+fn parse_process_line(line: &str) -> Option<ProcessInfo> {
+    let mut fields = line.split("\",\"").map(|f| f.trim_matches('"'));
+    let name = fields.next()?.to_string();
+    let pid = fields.next()?.parse().ok()?;
+    Some(ProcessInfo {
+        pid,
+        command: name.clone(),
+        name,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use lapce_rpc::dap_types::{DebugRequest, RunDebugConfig};
+    use serde_json::json;
+
+    use super::with_adapter_args;
+
+    #[test]
+    fn test_adapter_args() {
+        let config: RunDebugConfig = toml::from_str(
+            r#"
+            name = "attach"
+            request = "attach"
+            process-id = 42
+            [adapter-args]
+            justMyCode = false
+            pathMappings = [{ localRoot = "src", remoteRoot = "/app" }]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.request, DebugRequest::Attach);
+        assert_eq!(config.process_id, Some(42));
+
+        let params = with_adapter_args(json!({ "justMyCode": true }), &config);
+        assert_eq!(
+            params,
+            json!({
+                "justMyCode": false,
+                "pathMappings": [{ "localRoot": "src", "remoteRoot": "/app" }],
+            })
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_parse_process_line() {
+        let process =
+            super::parse_process_line("  4242 /usr/bin/python3 -m http.server")
+                .unwrap();
+        assert_eq!(process.pid, 4242);
+        assert_eq!(process.name, "python3");
+        assert_eq!(process.command, "/usr/bin/python3 -m http.server");
+    }
 }
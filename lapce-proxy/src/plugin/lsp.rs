@@ -1,6 +1,7 @@
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 use std::{
+    collections::HashMap,
     io::{BufRead, BufReader, BufWriter, Write},
     path::{Path, PathBuf},
     process::{self, Child, Command, Stdio},
@@ -18,7 +19,7 @@
 };
 use lapce_xi_rope::Rope;
 use lsp_types::{
-    notification::{Initialized, Notification},
+    notification::{DidChangeConfiguration, Initialized, Notification},
     request::{Initialize, Request},
     *,
 };
@@ -64,6 +65,8 @@ pub struct LspClient {
     workspace: Option<PathBuf>,
     host: PluginHostHandler,
     options: Option<Value>,
+    /// Sent with `workspace/didChangeConfiguration` after initializing
+    settings: Option<Value>,
 }
 
 impl PluginServerHandler for LspClient {
@@ -174,6 +177,7 @@ fn new(
         plugin_rpc: PluginCatalogRpcHandler,
         document_selector: DocumentSelector,
         workspace: Option<PathBuf>,
+        workspace_folders: Vec<PathBuf>,
         volt_id: VoltID,
         volt_display_name: String,
         spawned_by: Option<PluginId>,
@@ -181,7 +185,9 @@ fn new(
         pwd: Option<PathBuf>,
         server_uri: Url,
         args: Vec<String>,
+        env: HashMap<String, String>,
         options: Option<Value>,
+        settings: Option<Value>,
     ) -> Result<Self> {
         let server = match server_uri.scheme() {
             "file" => {
@@ -200,7 +206,7 @@ fn new(
             _ => return Err(anyhow!("uri not supported")),
         };
 
-        let mut process = Self::process(workspace.as_ref(), &server, &args)?;
+        let mut process = Self::process(workspace.as_ref(), &server, &args, &env)?;
         let stdin = process.stdin.take().unwrap();
         let stdout = process.stdout.take().unwrap();
         let stderr = process.stderr.take().unwrap();
@@ -302,6 +308,7 @@ fn new(
 
         let host = PluginHostHandler::new(
             workspace.clone(),
+            workspace_folders,
             pwd,
             volt_id,
             volt_display_name,
@@ -318,6 +325,7 @@ fn new(
             workspace,
             host,
             options,
+            settings,
         })
     }
 
@@ -326,6 +334,7 @@ pub fn start(
         plugin_rpc: PluginCatalogRpcHandler,
         document_selector: DocumentSelector,
         workspace: Option<PathBuf>,
+        workspace_folders: Vec<PathBuf>,
         volt_id: VoltID,
         volt_display_name: String,
         spawned_by: Option<PluginId>,
@@ -333,12 +342,15 @@ pub fn start(
         pwd: Option<PathBuf>,
         server_uri: Url,
         args: Vec<String>,
+        env: HashMap<String, String>,
         options: Option<Value>,
+        settings: Option<Value>,
     ) -> Result<PluginId> {
         let mut lsp = Self::new(
             plugin_rpc,
             document_selector,
             workspace,
+            workspace_folders,
             volt_id,
             volt_display_name,
             spawned_by,
@@ -346,7 +358,9 @@ pub fn start(
             pwd,
             server_uri,
             args,
+            env,
             options,
+            settings,
         )?;
         let plugin_id = lsp.server_rpc.plugin_id;
 
@@ -366,16 +380,11 @@ fn initialize(&mut self) {
         #[allow(deprecated)]
         let params = InitializeParams {
             process_id: Some(process::id()),
-            root_uri: root_uri.clone(),
+            root_uri,
             initialization_options: self.options.clone(),
             capabilities: client_capabilities(),
             trace: Some(TraceValue::Verbose),
-            workspace_folders: root_uri.map(|uri| {
-                vec![WorkspaceFolder {
-                    name: uri.as_str().to_string(),
-                    uri,
-                }]
-            }),
+            workspace_folders: self.host.lsp_workspace_folders(),
             client_info: Some(ClientInfo {
                 name: meta::NAME.to_owned(),
                 version: Some(meta::VERSION.to_owned()),
@@ -402,6 +411,15 @@ fn initialize(&mut self) {
                     None,
                     false,
                 );
+                if let Some(settings) = self.settings.clone() {
+                    self.server_rpc.server_notification(
+                        DidChangeConfiguration::METHOD,
+                        DidChangeConfigurationParams { settings },
+                        None,
+                        None,
+                        false,
+                    );
+                }
                 if self
                     .plugin_rpc
                     .plugin_server_loaded(self.server_rpc.clone())
@@ -440,13 +458,14 @@ fn process(
         workspace: Option<&PathBuf>,
         server: &str,
         args: &[String],
+        env: &HashMap<String, String>,
     ) -> Result<Child> {
         let mut process = Command::new(server);
         if let Some(workspace) = workspace {
             process.current_dir(workspace);
         }
 
-        process.args(args);
+        process.args(args).envs(env);
 
         #[cfg(target_os = "windows")]
         let process = process.creation_flags(0x08000000);
@@ -24,7 +24,7 @@
 use lapce_rpc::{
     core::CoreRpcHandler,
     dap_types::{self, DapId, RunDebugConfig, SourceBreakpoint, ThreadId},
-    plugin::{PluginId, VoltInfo, VoltMetadata},
+    plugin::{LspServerConfig, PluginId, VoltID, VoltInfo, VoltMetadata},
     proxy::ProxyRpcHandler,
     style::LineStyle,
     terminal::TermId,
@@ -47,7 +47,8 @@
     ClientCapabilities, CodeAction, CodeActionCapabilityResolveSupport,
     CodeActionClientCapabilities, CodeActionContext, CodeActionKind,
     CodeActionKindLiteralSupport, CodeActionLiteralSupport, CodeActionParams,
-    CodeActionResponse, CodeLens, CodeLensParams, CompletionClientCapabilities,
+    CodeActionResponse, CodeLens, CodeLensParams,
+    CodeLensWorkspaceClientCapabilities, CompletionClientCapabilities,
     CompletionItem, CompletionItemCapability,
     CompletionItemCapabilityResolveSupport, CompletionParams, CompletionResponse,
     Diagnostic, DocumentFormattingParams, DocumentSymbolClientCapabilities,
@@ -55,7 +56,8 @@
     FoldingRangeClientCapabilities, FoldingRangeParams, FormattingOptions,
     GotoCapability, GotoDefinitionParams, GotoDefinitionResponse, Hover,
     HoverClientCapabilities, HoverParams, InlayHint, InlayHintClientCapabilities,
-    InlayHintParams, InlineCompletionClientCapabilities, InlineCompletionParams,
+    InlayHintParams, InlayHintWorkspaceClientCapabilities,
+    InlineCompletionClientCapabilities, InlineCompletionParams,
     InlineCompletionResponse, InlineCompletionTriggerKind, Location, MarkupKind,
     MessageActionItemCapabilities, ParameterInformationSettings,
     PartialResultParams, Position, PrepareRenameResponse,
@@ -128,9 +130,22 @@ pub enum PluginCatalogRpc {
             >,
         >,
     },
+    DapEvaluate {
+        dap_id: DapId,
+        expression: String,
+        frame_id: Option<usize>,
+        context: dap_types::EvaluateContext,
+        f: Box<dyn RpcCallback<dap_types::EvaluateResponse, RpcError>>,
+    },
     DidOpenTextDocument {
         document: TextDocumentItem,
     },
+    /// The configuration of a volt, or the settings of a server declared in
+    /// `[lsp.<name>]`
+    PluginConfiguration {
+        volt_id: VoltID,
+        f: Box<dyn RpcCallback<Value, RpcError>>,
+    },
     DidChangeTextDocument {
         language_id: String,
         document: VersionedTextDocumentIdentifier,
@@ -155,6 +170,7 @@ pub enum PluginCatalogRpc {
 #[allow(clippy::large_enum_variant)]
 pub enum PluginCatalogNotification {
     UpdatePluginConfigs(HashMap<String, HashMap<String, serde_json::Value>>),
+    UpdateLspServers(HashMap<String, LspServerConfig>),
     UnactivatedVolts(Vec<VoltMetadata>),
     PluginServerLoaded(PluginServerRpcHandler),
     InstallVolt(VoltInfo),
@@ -305,6 +321,9 @@ pub fn mainloop(&self, plugin: &mut PluginCatalog) {
                 PluginCatalogRpc::DidOpenTextDocument { document } => {
                     plugin.handle_did_open_text_document(document);
                 }
+                PluginCatalogRpc::PluginConfiguration { volt_id, f } => {
+                    plugin.plugin_configuration(&volt_id, f);
+                }
                 PluginCatalogRpc::DidSaveTextDocument {
                     language_id,
                     path,
@@ -347,6 +366,15 @@ pub fn mainloop(&self, plugin: &mut PluginCatalog) {
                 } => {
                     plugin.dap_get_scopes(dap_id, frame_id, f);
                 }
+                PluginCatalogRpc::DapEvaluate {
+                    dap_id,
+                    expression,
+                    frame_id,
+                    context,
+                    f,
+                } => {
+                    plugin.dap_evaluate(dap_id, expression, frame_id, context, f);
+                }
                 PluginCatalogRpc::Shutdown => {
                     return;
                 }
@@ -501,6 +529,21 @@ pub fn format_semantic_tokens(
         }
     }
 
+    pub fn plugin_configuration(
+        &self,
+        volt_id: VoltID,
+        f: impl FnOnce(Result<Value, RpcError>) + Send + 'static,
+    ) {
+        if let Err(err) =
+            self.plugin_tx.send(PluginCatalogRpc::PluginConfiguration {
+                volt_id,
+                f: Box::new(f),
+            })
+        {
+            tracing::error!("{:?}", err);
+        }
+    }
+
     pub fn did_save_text_document(&self, path: &Path, text: Rope) {
         let text_document =
             TextDocumentIdentifier::new(Url::from_file_path(path).unwrap());
@@ -1326,6 +1369,15 @@ pub fn update_plugin_configs(
         ))
     }
 
+    pub fn update_lsp_servers(
+        &self,
+        servers: HashMap<String, LspServerConfig>,
+    ) -> Result<()> {
+        self.catalog_notification(PluginCatalogNotification::UpdateLspServers(
+            servers,
+        ))
+    }
+
     pub fn install_volt(&self, volt: VoltInfo) -> Result<()> {
         self.catalog_notification(PluginCatalogNotification::InstallVolt(volt))
     }
@@ -1506,6 +1558,25 @@ pub fn dap_get_scopes(
         }
     }
 
+    pub fn dap_evaluate(
+        &self,
+        dap_id: DapId,
+        expression: String,
+        frame_id: Option<usize>,
+        context: dap_types::EvaluateContext,
+        f: impl FnOnce(Result<dap_types::EvaluateResponse, RpcError>) + Send + 'static,
+    ) {
+        if let Err(err) = self.plugin_tx.send(PluginCatalogRpc::DapEvaluate {
+            dap_id,
+            expression,
+            frame_id,
+            context,
+            f: Box::new(f),
+        }) {
+            tracing::error!("{:?}", err);
+        }
+    }
+
     pub fn register_debugger_type(
         &self,
         debugger_type: String,
@@ -1603,6 +1674,7 @@ pub fn download_volt(volt: &VoltInfo) -> Result<VoltMetadata> {
 pub fn install_volt(
     catalog_rpc: PluginCatalogRpcHandler,
     workspace: Option<PathBuf>,
+    workspace_folders: Vec<PathBuf>,
     configurations: Option<HashMap<String, serde_json::Value>>,
     volt: VoltInfo,
 ) -> Result<()> {
@@ -1616,9 +1688,13 @@ pub fn install_volt(
     let local_catalog_rpc = catalog_rpc.clone();
     let local_meta = meta.clone();
 
-    if let Err(err) =
-        start_volt(workspace, configurations, local_catalog_rpc, local_meta)
-    {
+    if let Err(err) = start_volt(
+        workspace,
+        workspace_folders,
+        configurations,
+        local_catalog_rpc,
+        local_meta,
+    ) {
         tracing::error!("{:?}", err);
     }
     let icon = volt_icon(&meta);
@@ -1768,7 +1844,7 @@ fn client_capabilities() -> ClientCapabilities {
             folding_range: Some(FoldingRangeClientCapabilities {
                 dynamic_registration: Some(false),
                 range_limit: None,
-                line_folding_only: Some(false),
+                line_folding_only: Some(true),
                 folding_range_kind: None,
                 folding_range: None,
             }),
@@ -1787,8 +1863,15 @@ fn client_capabilities() -> ClientCapabilities {
             symbol: Some(WorkspaceSymbolClientCapabilities {
                 ..Default::default()
             }),
-            configuration: Some(false),
+            apply_edit: Some(true),
+            configuration: Some(true),
             workspace_folders: Some(true),
+            code_lens: Some(CodeLensWorkspaceClientCapabilities {
+                refresh_support: Some(true),
+            }),
+            inlay_hint: Some(InlayHintWorkspaceClientCapabilities {
+                refresh_support: Some(true),
+            }),
             ..Default::default()
         }),
         experimental: Some(experimental.into()),
@@ -17,7 +17,7 @@
 use lapce_core::{encoding::offset_utf16_to_utf8, rope_text_pos::RopeTextPosition};
 use lapce_rpc::{
     core::{CoreRpcHandler, ServerStatusParams},
-    plugin::{PluginId, VoltID},
+    plugin::{PluginId, SetContext, SetContextParams, VoltID},
     style::{LineStyle, Style},
     RpcError,
 };
@@ -29,25 +29,27 @@
         ShowMessage,
     },
     request::{
-        CallHierarchyIncomingCalls, CallHierarchyPrepare, CodeActionRequest,
-        CodeActionResolveRequest, CodeLensRequest, CodeLensResolve, Completion,
-        DocumentSymbolRequest, FoldingRangeRequest, Formatting, GotoDefinition,
-        GotoImplementation, GotoTypeDefinition, HoverRequest, Initialize,
+        ApplyWorkspaceEdit, CallHierarchyIncomingCalls, CallHierarchyPrepare,
+        CodeActionRequest, CodeActionResolveRequest, CodeLensRefresh,
+        CodeLensRequest, CodeLensResolve, Completion, DocumentSymbolRequest,
+        FoldingRangeRequest, Formatting, GotoDefinition, GotoImplementation,
+        GotoTypeDefinition, HoverRequest, Initialize, InlayHintRefreshRequest,
         InlayHintRequest, InlineCompletionRequest, PrepareRenameRequest, References,
         RegisterCapability, Rename, ResolveCompletionItem, SelectionRangeRequest,
-        SemanticTokensFullRequest, SignatureHelpRequest, WorkDoneProgressCreate,
-        WorkspaceSymbolRequest,
+        SemanticTokensFullRequest, ShowMessageRequest, SignatureHelpRequest,
+        WorkDoneProgressCreate, WorkspaceConfiguration, WorkspaceSymbolRequest,
     },
-    CancelParams, CodeActionProviderCapability, DidChangeTextDocumentParams,
-    DidSaveTextDocumentParams, DocumentSelector, FoldingRangeProviderCapability,
-    HoverProviderCapability, ImplementationProviderCapability, InitializeResult,
-    LogMessageParams, MessageType, OneOf, ProgressParams, PublishDiagnosticsParams,
-    Range, Registration, RegistrationParams, SemanticTokens, SemanticTokensLegend,
+    ApplyWorkspaceEditParams, CancelParams, CodeActionProviderCapability,
+    ConfigurationParams, DidChangeTextDocumentParams, DidSaveTextDocumentParams,
+    DocumentSelector, FoldingRangeProviderCapability, HoverProviderCapability,
+    ImplementationProviderCapability, InitializeResult, LogMessageParams,
+    MessageType, OneOf, ProgressParams, PublishDiagnosticsParams, Range,
+    Registration, RegistrationParams, SemanticTokens, SemanticTokensLegend,
     SemanticTokensServerCapabilities, ServerCapabilities, ShowMessageParams,
-    TextDocumentContentChangeEvent, TextDocumentIdentifier,
-    TextDocumentSaveRegistrationOptions, TextDocumentSyncCapability,
-    TextDocumentSyncKind, TextDocumentSyncSaveOptions,
-    VersionedTextDocumentIdentifier,
+    ShowMessageRequestParams, TextDocumentContentChangeEvent,
+    TextDocumentIdentifier, TextDocumentSaveRegistrationOptions,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncSaveOptions,
+    Url, VersionedTextDocumentIdentifier, WorkspaceFolder,
 };
 use parking_lot::Mutex;
 use psp_types::{
@@ -623,6 +625,8 @@ pub struct PluginHostHandler {
     volt_display_name: String,
     pwd: Option<PathBuf>,
     pub(crate) workspace: Option<PathBuf>,
+    /// All the root folders of the workspace, starting with `workspace`
+    pub(crate) workspace_folders: Vec<PathBuf>,
     document_selector: Vec<DocumentFilter>,
     core_rpc: CoreRpcHandler,
     catalog_rpc: PluginCatalogRpcHandler,
@@ -639,6 +643,7 @@ impl PluginHostHandler {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         workspace: Option<PathBuf>,
+        workspace_folders: Vec<PathBuf>,
         pwd: Option<PathBuf>,
         volt_id: VoltID,
         volt_display_name: String,
@@ -654,6 +659,7 @@ pub fn new(
         Self {
             pwd,
             workspace,
+            workspace_folders,
             volt_id,
             volt_display_name,
             document_selector,
@@ -666,6 +672,24 @@ pub fn new(
         }
     }
 
+    /// The `workspaceFolders` sent to the server when initializing it
+    pub fn lsp_workspace_folders(&self) -> Option<Vec<WorkspaceFolder>> {
+        let folders: Vec<WorkspaceFolder> = self
+            .workspace_folders
+            .iter()
+            .filter_map(|folder| Url::from_directory_path(folder).ok())
+            .map(|uri| WorkspaceFolder {
+                name: uri.as_str().to_string(),
+                uri,
+            })
+            .collect();
+        if folders.is_empty() {
+            None
+        } else {
+            Some(folders)
+        }
+    }
+
     pub fn document_supported(
         &self,
         language_id: Option<&str>,
@@ -965,6 +989,68 @@ pub fn process_request(
                 self.register_capabilities(params.registrations);
                 resp.send_null();
             }
+            ApplyWorkspaceEdit::METHOD => {
+                let params: ApplyWorkspaceEditParams =
+                    serde_json::from_value(serde_json::to_value(params)?)?;
+                let core_rpc = self.core_rpc.clone();
+                // The server keeps being served while the editor applies it
+                thread::spawn(move || {
+                    match core_rpc.apply_workspace_edit(params.edit) {
+                        Ok(response) => {
+                            resp.send(response);
+                        }
+                        Err(err) => {
+                            resp.send_err(err.code, err.message);
+                        }
+                    }
+                });
+            }
+            WorkspaceConfiguration::METHOD => {
+                let params: ConfigurationParams =
+                    serde_json::from_value(serde_json::to_value(params)?)?;
+                self.catalog_rpc.plugin_configuration(
+                    self.volt_id.clone(),
+                    move |result| {
+                        let configuration = result.unwrap_or_default();
+                        let items: Vec<Value> = params
+                            .items
+                            .iter()
+                            .map(|item| {
+                                configuration_section(
+                                    &configuration,
+                                    item.section.as_deref(),
+                                )
+                            })
+                            .collect();
+                        resp.send(items);
+                    },
+                );
+            }
+            ShowMessageRequest::METHOD => {
+                let params: ShowMessageRequestParams =
+                    serde_json::from_value(serde_json::to_value(params)?)?;
+                let title = format!("Plugin: {}", self.volt_display_name);
+                let core_rpc = self.core_rpc.clone();
+                // Waiting for the user to pick an action mustn't block the server
+                thread::spawn(move || {
+                    match core_rpc.show_message_request(title, params) {
+                        Ok(action) => {
+                            resp.send(action);
+                        }
+                        Err(err) => {
+                            resp.send_err(err.code, err.message);
+                        }
+                    }
+                });
+            }
+            CodeLensRefresh::METHOD => {
+                self.core_rpc.code_lens_refresh();
+                resp.send_null();
+            }
+            InlayHintRefreshRequest::METHOD => {
+                self.core_rpc.inlay_hint_refresh();
+                resp.send_null();
+            }
             ExecuteProcess::METHOD => {
                 let params: ExecuteProcessParams =
                     serde_json::from_value(serde_json::to_value(params)?)?;
@@ -992,6 +1078,7 @@ pub fn process_request(
                 let params: StartLspServerParams =
                     serde_json::from_value(serde_json::to_value(params)?)?;
                 let workspace = self.workspace.clone();
+                let workspace_folders = self.workspace_folders.clone();
                 let pwd = self.pwd.clone();
                 let catalog_rpc = self.catalog_rpc.clone();
                 let volt_id = self.volt_id.clone();
@@ -1006,6 +1093,7 @@ pub fn process_request(
                         catalog_rpc,
                         params.document_selector,
                         workspace,
+                        workspace_folders,
                         volt_id,
                         volt_display_name,
                         Some(spawned_by),
@@ -1013,7 +1101,9 @@ pub fn process_request(
                         pwd,
                         params.server_uri,
                         params.server_args,
+                        HashMap::new(),
                         params.options,
+                        None,
                     ) {
                         tracing::error!("{:?}", err);
                     }
@@ -1106,6 +1196,7 @@ pub fn handle_notification(
                 let params: StartLspServerParams =
                     serde_json::from_value(serde_json::to_value(params)?)?;
                 let workspace = self.workspace.clone();
+                let workspace_folders = self.workspace_folders.clone();
                 let pwd = self.pwd.clone();
                 let catalog_rpc = self.catalog_rpc.clone();
                 let volt_id = self.volt_id.clone();
@@ -1115,6 +1206,7 @@ pub fn handle_notification(
                         catalog_rpc,
                         params.document_selector,
                         workspace,
+                        workspace_folders,
                         volt_id,
                         volt_display_name,
                         None,
@@ -1122,7 +1214,9 @@ pub fn handle_notification(
                         pwd,
                         params.server_uri,
                         params.server_args,
+                        HashMap::new(),
                         params.options,
+                        None,
                     ) {
                         tracing::error!("{:?}", err);
                     }
@@ -1160,6 +1254,18 @@ pub fn handle_notification(
                     serde_json::from_value(serde_json::to_value(params)?)?;
                 self.catalog_rpc.core_rpc.cancel(params);
             }
+            SetContext::METHOD => {
+                let params: SetContextParams =
+                    serde_json::from_value(serde_json::to_value(params)?)?;
+                let value = match params.value {
+                    Value::Null => None,
+                    Value::String(value) => Some(value),
+                    value => Some(value.to_string()),
+                };
+                // Kept apart from the built-in keys and the ones of other volts
+                let key = format!("{}.{}", self.volt_id.name, params.key);
+                self.catalog_rpc.core_rpc.set_context(key, value);
+            }
             "experimental/serverStatus" => {
                 let param: ServerStatusParams =
                     serde_json::from_value(serde_json::to_value(params)?)?;
@@ -1327,6 +1433,19 @@ pub fn handle_spawned_plugin_loaded(&mut self, plugin_id: PluginId) {
     }
 }
 
+/// The part of `configuration` named by a dotted `section`, or `null` if it
+/// doesn't exist
+fn configuration_section(configuration: &Value, section: Option<&str>) -> Value {
+    let Some(section) = section.filter(|section| !section.is_empty()) else {
+        return configuration.clone();
+    };
+    section
+        .split('.')
+        .try_fold(configuration, |value, key| value.get(key))
+        .cloned()
+        .unwrap_or_default()
+}
+
 /// Information that a plugin associates with a spawned language server.
 struct SpawnedLspInfo {
     /// The response sender to use when the lsp is initialized.
@@ -1436,3 +1555,26 @@ fn semantic_tokens_legend(
         ) => &options.semantic_tokens_options.legend,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::configuration_section;
+
+    #[test]
+    fn test_configuration_section() {
+        let configuration = json!({
+            "rust-analyzer": { "cargo": { "features": "all" } },
+        });
+        assert_eq!(configuration_section(&configuration, None), configuration);
+        assert_eq!(
+            configuration_section(&configuration, Some("rust-analyzer.cargo")),
+            json!({ "features": "all" })
+        );
+        assert_eq!(
+            configuration_section(&configuration, Some("rust-analyzer.check")),
+            json!(null)
+        );
+    }
+}
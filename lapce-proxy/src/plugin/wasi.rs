@@ -13,7 +13,7 @@
 
 use anyhow::{anyhow, Result};
 use jsonrpc_lite::{Id, Params};
-use lapce_core::directory::Directory;
+use lapce_core::{directory::Directory, language::LanguageDefinition};
 use lapce_rpc::{
     plugin::{PluginId, VoltID, VoltInfo, VoltMetadata},
     style::LineStyle,
@@ -24,7 +24,7 @@
     notification::Initialized, request::Initialize, DocumentFilter,
     InitializeParams, InitializedParams, TextDocumentContentChangeEvent,
     TextDocumentIdentifier, Url, VersionedTextDocumentIdentifier,
-    WorkDoneProgressParams, WorkspaceFolder,
+    WorkDoneProgressParams,
 };
 use parking_lot::Mutex;
 use psp_types::{Notification, Request};
@@ -205,18 +205,13 @@ fn initialize(&mut self) {
             InitializeParams {
                 process_id: Some(process::id()),
                 root_path: None,
-                root_uri: root_uri.clone(),
+                root_uri,
                 capabilities: client_capabilities(),
                 trace: None,
                 client_info: None,
                 locale: None,
                 initialization_options: configurations,
-                workspace_folders: root_uri.map(|uri| {
-                    vec![WorkspaceFolder {
-                        name: uri.as_str().to_string(),
-                        uri,
-                    }]
-                }),
+                workspace_folders: self.host.lsp_workspace_folders(),
                 work_done_progress_params: WorkDoneProgressParams::default(),
             },
             None,
@@ -328,6 +323,31 @@ pub fn find_all_volts(extra_plugin_paths: &[PathBuf]) -> Vec<VoltMetadata> {
     plugins
 }
 
+/// The `[[language]]` definitions of the enabled volts, followed by the ones of
+/// `languages_file` so that the user's definition of a language replaces the
+/// one of a volt.
+pub fn find_language_definitions(
+    disabled_volts: &[VoltID],
+    extra_plugin_paths: &[PathBuf],
+    languages_file: Option<&Path>,
+) -> Vec<LanguageDefinition> {
+    let mut definitions = Vec::new();
+    for meta in find_all_volts(extra_plugin_paths) {
+        if disabled_volts.contains(&meta.id()) {
+            continue;
+        }
+        if let Some(languages) = meta.languages.as_ref() {
+            for path in languages {
+                definitions.extend(LanguageDefinition::load(Path::new(path)));
+            }
+        }
+    }
+    if let Some(path) = languages_file {
+        definitions.extend(LanguageDefinition::load(path));
+    }
+    definitions
+}
+
 /// Returns an instance of "VoltMetadata" or an error if there is no file in the path,
 /// the contents of the file cannot be read into a string, or the content read cannot
 /// be converted to an instance of "VoltMetadata".
@@ -362,6 +382,8 @@ pub fn find_all_volts(extra_plugin_paths: &[PathBuf]) -> Vec<VoltMetadata> {
 ///         wasm: None,
 ///         color_themes: None,
 ///         icon_themes: None,
+///         languages: None,
+///         snippets: None,
 ///         dir: parent_path.canonicalize().ok(),
 ///         activation: None,
 ///         config: None
@@ -400,6 +422,22 @@ pub fn load_volt(path: &Path) -> Result<VoltMetadata> {
             })
             .collect()
     });
+    meta.languages = meta.languages.as_ref().map(|languages| {
+        languages
+            .iter()
+            .filter_map(|file| {
+                Some(path.join(file).canonicalize().ok()?.to_str()?.to_string())
+            })
+            .collect()
+    });
+    meta.snippets = meta.snippets.as_ref().map(|snippets| {
+        snippets
+            .iter()
+            .filter_map(|file| {
+                Some(path.join(file).canonicalize().ok()?.to_str()?.to_string())
+            })
+            .collect()
+    });
 
     Ok(meta)
 }
@@ -418,6 +456,7 @@ pub fn enable_volt(
 
 pub fn start_volt(
     workspace: Option<PathBuf>,
+    workspace_folders: Vec<PathBuf>,
     configurations: Option<HashMap<String, serde_json::Value>>,
     plugin_rpc: PluginCatalogRpcHandler,
     meta: VoltMetadata,
@@ -564,6 +603,7 @@ pub fn start_volt(
         id,
         host: PluginHostHandler::new(
             workspace,
+            workspace_folders,
             meta.dir.clone(),
             meta.id(),
             meta.display_name.clone(),
@@ -611,7 +651,9 @@ fn wasi_read_string(stdout: &Arc<RwLock<WasiPipe>>) -> Result<String> {
     Ok(buf)
 }
 
-fn unflatten_map(map: &HashMap<String, serde_json::Value>) -> serde_json::Value {
+pub(crate) fn unflatten_map(
+    map: &HashMap<String, serde_json::Value>,
+) -> serde_json::Value {
     let mut new = serde_json::json!({});
     for (key, value) in map.iter() {
         let mut current = new.as_object_mut().unwrap();
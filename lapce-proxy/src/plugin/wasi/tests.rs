@@ -143,6 +143,8 @@ fn test_load_volt() {
             wasm: wasm_path,
             color_themes: Some(color_themes_pathes),
             icon_themes: Some(icon_themes_pathes),
+            languages: None,
+            snippets: None,
             dir: parent_path.canonicalize().ok(),
             activation: None,
             config: None
@@ -203,6 +205,8 @@ fn test_load_volt() {
             wasm: wasm_path,
             color_themes: Some(color_themes_pathes),
             icon_themes: Some(icon_themes_pathes),
+            languages: None,
+            snippets: None,
             dir: parent_path.canonicalize().ok(),
             activation: None,
             config: None
@@ -229,6 +233,8 @@ fn test_load_volt() {
             wasm: None,
             color_themes: Some(Vec::new()),
             icon_themes: Some(Vec::new()),
+            languages: None,
+            snippets: None,
             dir: parent_path.canonicalize().ok(),
             activation: None,
             config: None
@@ -12,6 +12,62 @@ pub fn next() -> Self {
     }
 }
 
+/// The encodings a file can be reopened or saved with, as the name used over the
+/// rpc along with a description to show to the user
+pub const ENCODINGS: &[(&str, &str)] = &[
+    ("UTF-8", "UTF-8"),
+    ("UTF-16LE", "UTF-16 LE"),
+    ("UTF-16BE", "UTF-16 BE"),
+    ("windows-1252", "Western (Windows 1252, Latin-1)"),
+    ("ISO-8859-15", "Western (ISO 8859-15)"),
+    ("macintosh", "Western (Mac Roman)"),
+    ("windows-1250", "Central European (Windows 1250)"),
+    ("ISO-8859-2", "Central European (ISO 8859-2)"),
+    ("windows-1251", "Cyrillic (Windows 1251)"),
+    ("ISO-8859-5", "Cyrillic (ISO 8859-5)"),
+    ("KOI8-R", "Cyrillic (KOI8-R)"),
+    ("KOI8-U", "Cyrillic (KOI8-U)"),
+    ("IBM866", "Cyrillic (CP 866)"),
+    ("windows-1253", "Greek (Windows 1253)"),
+    ("ISO-8859-7", "Greek (ISO 8859-7)"),
+    ("windows-1254", "Turkish (Windows 1254)"),
+    ("windows-1255", "Hebrew (Windows 1255)"),
+    ("windows-1256", "Arabic (Windows 1256)"),
+    ("windows-1257", "Baltic (Windows 1257)"),
+    ("windows-1258", "Vietnamese (Windows 1258)"),
+    ("windows-874", "Thai (Windows 874)"),
+    ("Shift_JIS", "Japanese (Shift JIS)"),
+    ("EUC-JP", "Japanese (EUC-JP)"),
+    ("ISO-2022-JP", "Japanese (ISO 2022-JP)"),
+    ("EUC-KR", "Korean (EUC-KR)"),
+    ("GBK", "Simplified Chinese (GBK)"),
+    ("gb18030", "Simplified Chinese (GB 18030)"),
+    ("Big5", "Traditional Chinese (Big5)"),
+];
+
+/// The `.editorconfig` properties that apply to a file. A property is `None`
+/// when no `.editorconfig` sets it.
+///
+/// The `charset` property is applied by the proxy when reading and saving the
+/// file, so it is not part of this.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EditorConfigProperties {
+    /// Whether to indent with tabs rather than spaces
+    pub indent_with_tabs: Option<bool>,
+    pub indent_size: Option<usize>,
+    pub tab_width: Option<usize>,
+    pub end_of_line: Option<EndOfLine>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EndOfLine {
+    Lf,
+    CrLf,
+    Cr,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewBufferResponse {
     pub content: String,
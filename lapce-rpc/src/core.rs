@@ -8,9 +8,11 @@
 };
 
 use crossbeam_channel::{Receiver, Sender};
+use indexmap::IndexMap;
 use lsp_types::{
-    CancelParams, CompletionResponse, LogMessageParams, ProgressParams,
-    PublishDiagnosticsParams, ShowMessageParams, SignatureHelp,
+    ApplyWorkspaceEditResponse, CancelParams, CompletionResponse, LogMessageParams,
+    MessageActionItem, ProgressParams, PublishDiagnosticsParams, ShowMessageParams,
+    ShowMessageRequestParams, SignatureHelp, WorkspaceEdit,
 };
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
@@ -21,7 +23,7 @@
     },
     file::PathObject,
     plugin::{PluginId, VoltInfo, VoltMetadata},
-    proxy::ProxyStatus,
+    proxy::{ProxyStatus, SearchMatch},
     source_control::DiffInfo,
     terminal::TermId,
     RequestId, RpcError, RpcMessage,
@@ -86,6 +88,18 @@ pub enum CoreNotification {
     LspCancel {
         params: CancelParams,
     },
+    /// A language server asked for the code lenses of the open files to be
+    /// requested again
+    CodeLensRefresh {},
+    /// A language server asked for the inlay hints of the open files to be
+    /// requested again
+    InlayHintRefresh {},
+    /// A volt set a context key for keymap `when` expressions, or unset it if
+    /// `value` is `None`. The key is already prefixed with the volt name.
+    SetContext {
+        key: String,
+        value: Option<String>,
+    },
     HomeDir {
         path: PathBuf,
     },
@@ -106,6 +120,8 @@ pub enum CoreNotification {
         only_installing: bool,
     },
     DiffInfo {
+        /// The workspace root folder the diff belongs to
+        root: PathBuf,
         diff: DiffInfo,
     },
     UpdateTerminal {
@@ -141,20 +157,49 @@ pub enum CoreNotification {
     DapContinued {
         dap_id: DapId,
     },
+    DapOutput {
+        dap_id: DapId,
+        output: dap_types::Output,
+    },
     DapBreakpointsResp {
         dap_id: DapId,
         path: PathBuf,
         breakpoints: Vec<dap_types::Breakpoint>,
     },
+    /// Matches found by a global search that is still running
+    GlobalSearchPartial {
+        search_id: u64,
+        matches: IndexMap<PathBuf, Vec<SearchMatch>>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum CoreRequest {}
+#[serde(rename_all = "snake_case")]
+#[serde(tag = "method", content = "params")]
+pub enum CoreRequest {
+    /// A `workspace/applyEdit` request of a language server
+    ApplyWorkspaceEdit { edit: WorkspaceEdit },
+    /// A `window/showMessageRequest` of a language server, answered with the
+    /// action the user picked
+    ShowMessageRequest {
+        title: String,
+        message: ShowMessageRequestParams,
+    },
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[serde(tag = "method", content = "params")]
-pub enum CoreResponse {}
+pub enum CoreResponse {
+    ApplyWorkspaceEditResponse {
+        applied: bool,
+        /// Why the edit wasn't applied
+        failure_reason: Option<String>,
+    },
+    ShowMessageRequestResponse {
+        action: Option<MessageActionItem>,
+    },
+}
 
 pub type CoreMessage = RpcMessage<CoreRequest, CoreNotification, CoreResponse>;
 
@@ -237,6 +282,43 @@ pub fn request(&self, request: CoreRequest) -> Result<CoreResponse, RpcError> {
         })
     }
 
+    /// Apply `edit` to the workspace, blocking until the editor is done
+    pub fn apply_workspace_edit(
+        &self,
+        edit: WorkspaceEdit,
+    ) -> Result<ApplyWorkspaceEditResponse, RpcError> {
+        match self.request(CoreRequest::ApplyWorkspaceEdit { edit })? {
+            CoreResponse::ApplyWorkspaceEditResponse {
+                applied,
+                failure_reason,
+            } => Ok(ApplyWorkspaceEditResponse {
+                applied,
+                failure_reason,
+                failed_change: None,
+            }),
+            _ => Err(RpcError {
+                code: 0,
+                message: "unexpected response".to_string(),
+            }),
+        }
+    }
+
+    /// Show `message` with its actions, blocking until the user picks one or
+    /// dismisses it
+    pub fn show_message_request(
+        &self,
+        title: String,
+        message: ShowMessageRequestParams,
+    ) -> Result<Option<MessageActionItem>, RpcError> {
+        match self.request(CoreRequest::ShowMessageRequest { title, message })? {
+            CoreResponse::ShowMessageRequestResponse { action } => Ok(action),
+            _ => Err(RpcError {
+                code: 0,
+                message: "unexpected response".to_string(),
+            }),
+        }
+    }
+
     pub fn shutdown(&self) {
         if let Err(err) = self.tx.send(CoreRpc::Shutdown) {
             tracing::error!("{:?}", err);
@@ -254,8 +336,8 @@ pub fn workspace_file_change(&self) {
         self.notification(CoreNotification::WorkspaceFileChange);
     }
 
-    pub fn diff_info(&self, diff: DiffInfo) {
-        self.notification(CoreNotification::DiffInfo { diff });
+    pub fn diff_info(&self, root: PathBuf, diff: DiffInfo) {
+        self.notification(CoreNotification::DiffInfo { root, diff });
     }
 
     pub fn open_file_changed(&self, path: PathBuf, content: FileChanged) {
@@ -337,6 +419,18 @@ pub fn show_message(&self, title: String, message: ShowMessageParams) {
         self.notification(CoreNotification::ShowMessage { title, message });
     }
 
+    pub fn code_lens_refresh(&self) {
+        self.notification(CoreNotification::CodeLensRefresh {});
+    }
+
+    pub fn inlay_hint_refresh(&self) {
+        self.notification(CoreNotification::InlayHintRefresh {});
+    }
+
+    pub fn set_context(&self, key: String, value: Option<String>) {
+        self.notification(CoreNotification::SetContext { key, value });
+    }
+
     pub fn log_message(&self, message: LogMessageParams, target: String) {
         self.notification(CoreNotification::LogMessage { message, target });
     }
@@ -386,6 +480,10 @@ pub fn dap_continued(&self, dap_id: DapId) {
         self.notification(CoreNotification::DapContinued { dap_id });
     }
 
+    pub fn dap_output(&self, dap_id: DapId, output: dap_types::Output) {
+        self.notification(CoreNotification::DapOutput { dap_id, output });
+    }
+
     pub fn dap_breakpoints_resp(
         &self,
         dap_id: DapId,
@@ -399,6 +497,17 @@ pub fn dap_breakpoints_resp(
         });
     }
 
+    pub fn global_search_partial(
+        &self,
+        search_id: u64,
+        matches: IndexMap<PathBuf, Vec<SearchMatch>>,
+    ) {
+        self.notification(CoreNotification::GlobalSearchPartial {
+            search_id,
+            matches,
+        });
+    }
+
     pub fn home_dir(&self, path: PathBuf) {
         self.notification(CoreNotification::HomeDir { path });
     }
@@ -48,11 +48,25 @@ pub struct RunDebugConfig {
     #[serde(rename = "type")]
     pub ty: Option<String>,
     pub name: String,
+    /// Whether the debugger launches the program or attaches to a running one
+    #[serde(default)]
+    pub request: DebugRequest,
+    #[serde(default)]
     pub program: String,
     pub args: Option<Vec<String>>,
     pub cwd: Option<String>,
     pub env: Option<HashMap<String, String>>,
     pub prelaunch: Option<RunDebugProgram>,
+    /// The process to attach to. If neither this nor `port` is set, the
+    /// process is picked from a list of the running processes.
+    pub process_id: Option<u32>,
+    /// The host of a debug server to attach to
+    pub host: Option<String>,
+    /// The port of a debug server to attach to
+    pub port: Option<u16>,
+    /// Adapter specific fields which are passed as they are in the
+    /// `launch` or `attach` request, e.g. `stopOnEntry` or `justMyCode`
+    pub adapter_args: Option<serde_json::Map<String, Value>>,
     #[serde(skip)]
     pub debug_command: Option<Vec<String>>,
     #[serde(skip)]
@@ -63,6 +77,22 @@ pub struct RunDebugConfig {
     pub config_source: ConfigSource,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DebugRequest {
+    #[default]
+    Launch,
+    Attach,
+}
+
+/// A process running on the proxy's machine which a debugger can attach to
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    pub command: String,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
 pub enum ConfigSource {
     #[default]
@@ -500,6 +530,14 @@ impl Request for Launch {
     const COMMAND: &'static str = "launch";
 }
 
+pub enum Attach {}
+
+impl Request for Attach {
+    type Arguments = Value;
+    type Result = Value;
+    const COMMAND: &'static str = "attach";
+}
+
 #[derive(Debug, Default, PartialEq, Eq, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RunInTerminalResponse {
@@ -873,3 +911,49 @@ impl Request for StepOut {
     type Result = ();
     const COMMAND: &'static str = "stepOut";
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EvaluateContext {
+    Watch,
+    Repl,
+    Hover,
+    Clipboard,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluateArguments {
+    pub expression: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_id: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<EvaluateContext>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<ValueFormat>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluateResponse {
+    pub result: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub ty: Option<String>,
+    #[serde(default)]
+    pub variables_reference: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub named_variables: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indexed_variables: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_reference: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum Evaluate {}
+
+impl Request for Evaluate {
+    type Arguments = EvaluateArguments;
+    type Result = EvaluateResponse;
+    const COMMAND: &'static str = "evaluate";
+}
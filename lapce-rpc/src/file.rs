@@ -301,6 +301,53 @@ fn cmp(&self, other: &Self) -> Ordering {
 }
 
 impl FileNodeItem {
+    /// Create the node holding the root folders of a multi-root workspace.
+    /// It has an empty path, is always open and isn't shown itself.
+    pub fn workspace_roots(roots: &[PathBuf]) -> Self {
+        let children = roots
+            .iter()
+            .map(|root| {
+                (
+                    root.clone(),
+                    FileNodeItem {
+                        path: root.clone(),
+                        is_dir: true,
+                        read: false,
+                        open: false,
+                        children: HashMap::new(),
+                        children_open_count: 0,
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        FileNodeItem {
+            path: PathBuf::new(),
+            is_dir: true,
+            read: true,
+            open: true,
+            children_open_count: children.len(),
+            children,
+        }
+    }
+
+    /// Whether this node holds the root folders of a multi-root workspace
+    pub fn is_workspace_roots(&self) -> bool {
+        self.path.as_os_str().is_empty()
+    }
+
+    /// The root folder containing `path`, if this node holds the root
+    /// folders of a multi-root workspace
+    fn workspace_root_of(&self, path: &Path) -> Option<PathBuf> {
+        if !self.is_workspace_roots() || path == self.path {
+            return None;
+        }
+        self.children
+            .keys()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+            .cloned()
+    }
+
     /// Collect the children, sorted by name.
     /// Note: this will be empty if the directory has not been read.
     pub fn sorted_children(&self) -> Vec<&FileNodeItem> {
@@ -359,12 +406,20 @@ fn ancestors_rev<'a>(
 
     /// Recursively get the node at `path`.
     pub fn get_file_node(&self, path: &Path) -> Option<&FileNodeItem> {
+        if self.is_workspace_roots() && path != self.path {
+            let root = self.workspace_root_of(path)?;
+            return self.children.get(&root)?.get_file_node(path);
+        }
         self.ancestors_rev(path)?
             .try_fold(self, |node, path| node.children.get(path))
     }
 
     /// Recursively get the (mutable) node at `path`.
     pub fn get_file_node_mut(&mut self, path: &Path) -> Option<&mut FileNodeItem> {
+        if self.is_workspace_roots() && path != self.path {
+            let root = self.workspace_root_of(path)?;
+            return self.children.get_mut(&root)?.get_file_node_mut(path);
+        }
         self.ancestors_rev(path)?
             .try_fold(self, |node, path| node.children.get_mut(path))
     }
@@ -375,9 +430,7 @@ pub fn remove_child(&mut self, path: &Path) -> Option<FileNodeItem> {
         let parent = path.parent()?;
         let node = self.get_file_node_mut(parent)?;
         let node = node.children.remove(path)?;
-        for p in path.ancestors() {
-            self.update_node_count(p);
-        }
+        self.update_node_count_recursive(path);
 
         Some(node)
     }
@@ -397,9 +450,7 @@ pub fn add_child(&mut self, path: &Path, is_dir: bool) -> Option<()> {
                 children_open_count: 0,
             },
         );
-        for p in path.ancestors() {
-            self.update_node_count(p);
-        }
+        self.update_node_count_recursive(path);
 
         Some(())
     }
@@ -417,15 +468,16 @@ pub fn set_item_children(
             node.children = children;
         }
 
-        for p in path.ancestors() {
-            self.update_node_count(p);
-        }
+        self.update_node_count_recursive(path);
     }
 
     pub fn update_node_count_recursive(&mut self, path: &Path) {
         for current_path in path.ancestors() {
             self.update_node_count(current_path);
         }
+        if self.is_workspace_roots() {
+            self.update_node_count(Path::new(""));
+        }
     }
 
     pub fn update_node_count(&mut self, path: &Path) -> Option<()> {
@@ -55,6 +55,31 @@ pub struct VoltConfig {
     pub description: String,
 }
 
+/// A language server declared in a `[lsp.<name>]` settings section, which is
+/// started without a volt
+#[derive(Deserialize, Clone, Debug, Serialize, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct LspServerConfig {
+    /// The program to run, either a path or a name looked up in `PATH`
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Extra environment variables of the server process
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// The language ids of the documents the server handles
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// Globs matching the paths of the documents the server handles
+    #[serde(default)]
+    pub globs: Vec<String>,
+    /// Sent as the `initializationOptions` of the `initialize` request
+    pub initialization_options: Option<Value>,
+    /// Sent with `workspace/didChangeConfiguration` once the server is
+    /// initialized
+    pub settings: Option<Value>,
+}
+
 #[derive(Deserialize, Clone, Debug, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub struct VoltMetadata {
@@ -68,6 +93,10 @@ pub struct VoltMetadata {
     pub wasm: Option<String>,
     pub color_themes: Option<Vec<String>>,
     pub icon_themes: Option<Vec<String>>,
+    /// Files with `[[language]]` definitions
+    pub languages: Option<Vec<String>>,
+    /// Snippet files, named `<language>.json` or ending in `.code-snippets`
+    pub snippets: Option<Vec<String>>,
     pub dir: Option<PathBuf>,
     pub activation: Option<VoltActivation>,
     pub config: Option<HashMap<String, VoltConfig>>,
@@ -140,6 +169,26 @@ fn from(volt: &VoltInfo) -> Self {
     }
 }
 
+/// Sent by a volt to set a context key that keymap `when` expressions can
+/// compare against. The key is namespaced by the name of the volt, so that
+/// `key` set by the volt `my-volt` is `my-volt.key` in `when` expressions and
+/// can't override the built-in keys.
+#[derive(Debug)]
+pub enum SetContext {}
+
+impl lsp_types::notification::Notification for SetContext {
+    type Params = SetContextParams;
+    const METHOD: &'static str = "lapce/setContext";
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetContextParams {
+    /// The key, without the volt name it gets prefixed with
+    pub key: String,
+    /// A string, number or boolean, or `null` to unset the key
+    pub value: Value,
+}
+
 #[cfg(test)]
 mod tests {
     use super::{VoltID, VoltInfo, VoltMetadata};
@@ -157,6 +206,8 @@ fn test_volt_metadata_id() {
             wasm: None,
             color_themes: None,
             icon_themes: None,
+            languages: None,
+            snippets: None,
             dir: std::env::current_dir().unwrap().canonicalize().ok(),
             activation: None,
             config: None,
@@ -195,6 +246,8 @@ fn test_volt_metadata_info() {
             wasm: None,
             color_themes: None,
             icon_themes: None,
+            languages: None,
+            snippets: None,
             dir: std::env::current_dir().unwrap().canonicalize().ok(),
             activation: None,
             config: None,
@@ -23,12 +23,12 @@
 
 use super::plugin::VoltID;
 use crate::{
-    buffer::BufferId,
+    buffer::{BufferId, EditorConfigProperties},
     dap_types::{self, DapId, RunDebugConfig, SourceBreakpoint, ThreadId},
     file::{FileNodeItem, PathObject},
     file_line::FileLine,
-    plugin::{PluginId, VoltInfo, VoltMetadata},
-    source_control::FileDiff,
+    plugin::{LspServerConfig, PluginId, VoltInfo, VoltMetadata},
+    source_control::{BlameHunk, FileDiff, GitCommit},
     style::SemanticStyles,
     terminal::{TermId, TerminalProfile},
     RequestId, RpcError, RpcMessage,
@@ -50,10 +50,35 @@ pub enum ProxyStatus {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchMatch {
+    /// The line of the match, starting from 1
     pub line: usize,
+    /// The start of the match, as a byte column within the whole line
     pub start: usize,
+    /// The end of the match, as a byte column within the whole line
     pub end: usize,
+    /// The content of the line, which is shortened around the match for very
+    /// long lines
     pub line_content: String,
+    /// The byte column within the whole line that `line_content` starts at
+    #[serde(default)]
+    pub line_content_start: usize,
+    /// The whole line when `line_content` is shortened, which a replacement is
+    /// previewed against
+    #[serde(default)]
+    pub full_line: Option<String>,
+}
+
+impl SearchMatch {
+    /// The range of the match within `line_content`
+    pub fn content_range(&self) -> std::ops::Range<usize> {
+        self.start.saturating_sub(self.line_content_start)
+            ..self.end.saturating_sub(self.line_content_start)
+    }
+
+    /// The whole line of the match
+    pub fn line(&self) -> &str {
+        self.full_line.as_deref().unwrap_or(&self.line_content)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,11 +92,41 @@ pub enum ProxyRequest {
     BufferHead {
         path: PathBuf,
     },
+    /// The content of the file staged in the git index
+    BufferIndex {
+        path: PathBuf,
+    },
+    /// The content of the file at a git revision, like a commit id
+    BufferRevision {
+        path: PathBuf,
+        revision: String,
+    },
     GlobalSearch {
         pattern: String,
         case_sensitive: bool,
         whole_word: bool,
         is_regex: bool,
+        /// Globs of the files to search in, every file is searched when empty
+        include: Vec<String>,
+        /// Globs of the files and folders to leave out of the search
+        exclude: Vec<String>,
+        /// Whether to also search the files that are ignored by `.gitignore`
+        /// and similar files
+        search_ignored: bool,
+        /// Stop searching once this many matches have been found
+        max_results: Option<usize>,
+        /// Identifies the search in the partial results sent while it runs
+        search_id: u64,
+    },
+    GlobalReplace {
+        pattern: String,
+        replacement: String,
+        case_sensitive: bool,
+        whole_word: bool,
+        is_regex: bool,
+        /// The matches to replace, as returned by a previous global search.
+        /// Matches that were excluded by the user are left out.
+        matches: IndexMap<PathBuf, Vec<SearchMatch>>,
     },
     CompletionResolve {
         plugin_id: PluginId,
@@ -97,6 +152,24 @@ pub enum ProxyRequest {
     GitGetRemoteFileUrl {
         file: PathBuf,
     },
+    /// The commit that last changed each line of the file
+    GitBlame {
+        path: PathBuf,
+    },
+    /// The commits reachable from `HEAD`, newest first, whose message or author
+    /// contains `query`. With a `path` only the commits changing that file are
+    /// listed, following its renames.
+    GitLog {
+        path: Option<PathBuf>,
+        query: String,
+        limit: usize,
+    },
+    /// The files changed by a commit compared to its first parent, in the
+    /// repository of `path` or of the workspace
+    GitCommitFiles {
+        commit: String,
+        path: Option<PathBuf>,
+    },
     GetReferences {
         path: PathBuf,
         position: Position,
@@ -189,6 +262,16 @@ pub enum ProxyRequest {
         /// Whether to create the parent directories if they do not exist.
         create_parents: bool,
     },
+    /// Read an open file again, decoding it with the given encoding
+    ReopenWithEncoding {
+        path: PathBuf,
+        encoding: String,
+    },
+    /// Change the encoding an open file is written with when it is saved
+    SetEncoding {
+        path: PathBuf,
+        encoding: String,
+    },
     CreateFile {
         path: PathBuf,
     },
@@ -217,6 +300,13 @@ pub enum ProxyRequest {
         dap_id: DapId,
         frame_id: usize,
     },
+    DapEvaluate {
+        dap_id: DapId,
+        expression: String,
+        frame_id: Option<usize>,
+        context: dap_types::EvaluateContext,
+    },
+    DapListProcesses {},
     ReferencesResolve {
         items: Vec<Location>,
     },
@@ -228,10 +318,16 @@ pub enum ProxyRequest {
 pub enum ProxyNotification {
     Initialize {
         workspace: Option<PathBuf>,
+        /// All the root folders of a multi-root workspace, including `workspace`
+        #[serde(default)]
+        workspace_folders: Vec<PathBuf>,
         disabled_volts: Vec<VoltID>,
         /// Paths to extra plugins that should be loaded
         extra_plugin_paths: Vec<PathBuf>,
         plugin_configurations: HashMap<String, HashMap<String, serde_json::Value>>,
+        /// The language servers declared in the `[lsp.<name>]` settings
+        #[serde(default)]
+        lsp_servers: HashMap<String, LspServerConfig>,
         window_id: usize,
         tab_id: usize,
     },
@@ -261,6 +357,9 @@ pub enum ProxyNotification {
     UpdatePluginConfigs {
         configs: HashMap<String, HashMap<String, serde_json::Value>>,
     },
+    UpdateLspServers {
+        servers: HashMap<String, LspServerConfig>,
+    },
     NewTerminal {
         term_id: TermId,
         profile: TerminalProfile,
@@ -283,15 +382,85 @@ pub enum ProxyNotification {
     GitCommit {
         message: String,
         diffs: Vec<FileDiff>,
+        /// Replace the last commit instead of creating a new one
+        #[serde(default)]
+        amend: bool,
     },
     GitCheckout {
         reference: String,
     },
+    /// Create a branch named `name` at `from`, which can be any reference or
+    /// commit, and check it out. Like the other branch and stash operations it
+    /// acts on the repository of the workspace root folder `root`, or of the
+    /// workspace without one.
+    GitCreateBranch {
+        root: Option<PathBuf>,
+        name: String,
+        from: String,
+    },
+    GitRenameBranch {
+        root: Option<PathBuf>,
+        old: String,
+        new: String,
+    },
+    /// Delete the local branch `name`, which unless `force` has to be merged into
+    /// the current branch
+    GitDeleteBranch {
+        root: Option<PathBuf>,
+        name: String,
+        force: bool,
+    },
+    /// Merge `reference` into the current branch
+    GitMerge {
+        root: Option<PathBuf>,
+        reference: String,
+    },
+    /// Save the changes of the working tree and the index, untracked files
+    /// included, in a new stash and revert them
+    GitStashSave {
+        root: Option<PathBuf>,
+        message: String,
+    },
+    /// Apply the stash at `index`, and remove it from the stash list if `pop`
+    GitStashApply {
+        root: Option<PathBuf>,
+        index: usize,
+        pop: bool,
+    },
+    GitStashDrop {
+        root: Option<PathBuf>,
+        index: usize,
+    },
     GitDiscardFilesChanges {
         files: Vec<PathBuf>,
     },
     GitDiscardWorkspaceChanges {},
+    GitStageFiles {
+        files: Vec<PathBuf>,
+    },
+    GitUnstageFiles {
+        files: Vec<PathBuf>,
+    },
+    /// Stage the change of the working file around `line`
+    GitStageHunk {
+        path: PathBuf,
+        line: usize,
+    },
+    /// Unstage the staged change around `line` of the file in the index
+    GitUnstageHunk {
+        path: PathBuf,
+        line: usize,
+    },
     GitInit {},
+    /// Fetch from the remote of the current branch
+    GitFetch {},
+    /// Fetch and integrate the upstream of the current branch, by rebasing
+    /// onto it or merging it, or as the `pull.rebase` git config says
+    GitPull {
+        rebase: Option<bool>,
+    },
+    /// Push the current branch, setting its upstream if it has none
+    GitPush {},
     LspCancel {
         id: i32,
     },
@@ -360,9 +529,24 @@ pub enum ProxyResponse {
     GitGetRemoteFileUrl {
         file_url: String,
     },
+    GitBlameResponse {
+        hunks: Vec<BlameHunk>,
+    },
+    GitLogResponse {
+        commits: Vec<GitCommit>,
+    },
+    GitCommitFilesResponse {
+        files: Vec<FileDiff>,
+    },
     NewBufferResponse {
         content: String,
         read_only: bool,
+        /// The name of the encoding the file was decoded with
+        encoding: String,
+        editorconfig: EditorConfigProperties,
+    },
+    ReopenWithEncodingResponse {
+        content: String,
     },
     BufferHeadResponse {
         version: String,
@@ -452,7 +636,17 @@ pub enum ProxyResponse {
         items: Vec<TextDocumentItem>,
     },
     GlobalSearchResponse {
+        /// The matches that weren't already sent as partial results
         matches: IndexMap<PathBuf, Vec<SearchMatch>>,
+        /// Whether the search stopped early because of `max_results`
+        limit_reached: bool,
+    },
+    GlobalReplaceResponse {
+        /// Edits for files that are open in a buffer, which are applied by the
+        /// editor so that they go through the undo history
+        edits: IndexMap<PathBuf, Vec<TextEdit>>,
+        /// Files that were not open and were written directly to disk
+        written: Vec<PathBuf>,
     },
     DapVariableResponse {
         varialbes: Vec<dap_types::Variable>,
@@ -460,6 +654,12 @@ pub enum ProxyResponse {
     DapGetScopesResponse {
         scopes: Vec<(dap_types::Scope, Vec<dap_types::Variable>)>,
     },
+    DapEvaluateResponse {
+        result: dap_types::EvaluateResponse,
+    },
+    DapListProcessesResponse {
+        processes: Vec<dap_types::ProcessInfo>,
+    },
     CreatePathResponse {
         path: PathBuf,
     },
@@ -601,14 +801,61 @@ pub fn git_init(&self) {
         self.notification(ProxyNotification::GitInit {});
     }
 
-    pub fn git_commit(&self, message: String, diffs: Vec<FileDiff>) {
-        self.notification(ProxyNotification::GitCommit { message, diffs });
+    pub fn git_commit(&self, message: String, diffs: Vec<FileDiff>, amend: bool) {
+        self.notification(ProxyNotification::GitCommit {
+            message,
+            diffs,
+            amend,
+        });
     }
 
     pub fn git_checkout(&self, reference: String) {
         self.notification(ProxyNotification::GitCheckout { reference });
     }
 
+    pub fn git_create_branch(
+        &self,
+        root: Option<PathBuf>,
+        name: String,
+        from: String,
+    ) {
+        self.notification(ProxyNotification::GitCreateBranch { root, name, from });
+    }
+
+    pub fn git_rename_branch(
+        &self,
+        root: Option<PathBuf>,
+        old: String,
+        new: String,
+    ) {
+        self.notification(ProxyNotification::GitRenameBranch { root, old, new });
+    }
+
+    pub fn git_delete_branch(
+        &self,
+        root: Option<PathBuf>,
+        name: String,
+        force: bool,
+    ) {
+        self.notification(ProxyNotification::GitDeleteBranch { root, name, force });
+    }
+
+    pub fn git_merge(&self, root: Option<PathBuf>, reference: String) {
+        self.notification(ProxyNotification::GitMerge { root, reference });
+    }
+
+    pub fn git_stash_save(&self, root: Option<PathBuf>, message: String) {
+        self.notification(ProxyNotification::GitStashSave { root, message });
+    }
+
+    pub fn git_stash_apply(&self, root: Option<PathBuf>, index: usize, pop: bool) {
+        self.notification(ProxyNotification::GitStashApply { root, index, pop });
+    }
+
+    pub fn git_stash_drop(&self, root: Option<PathBuf>, index: usize) {
+        self.notification(ProxyNotification::GitStashDrop { root, index });
+    }
+
     pub fn install_volt(&self, volt: VoltInfo) {
         self.notification(ProxyNotification::InstallVolt { volt });
     }
@@ -636,20 +883,25 @@ pub fn shutdown(&self) {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize(
         &self,
         workspace: Option<PathBuf>,
+        workspace_folders: Vec<PathBuf>,
         disabled_volts: Vec<VoltID>,
         extra_plugin_paths: Vec<PathBuf>,
         plugin_configurations: HashMap<String, HashMap<String, serde_json::Value>>,
+        lsp_servers: HashMap<String, LspServerConfig>,
         window_id: usize,
         tab_id: usize,
     ) {
         self.notification(ProxyNotification::Initialize {
             workspace,
+            workspace_folders,
             disabled_volts,
             extra_plugin_paths,
             plugin_configurations,
+            lsp_servers,
             window_id,
             tab_id,
         });
@@ -716,6 +968,19 @@ pub fn get_buffer_head(&self, path: PathBuf, f: impl ProxyCallback + 'static) {
         self.request_async(ProxyRequest::BufferHead { path }, f);
     }
 
+    pub fn get_buffer_index(&self, path: PathBuf, f: impl ProxyCallback + 'static) {
+        self.request_async(ProxyRequest::BufferIndex { path }, f);
+    }
+
+    pub fn get_buffer_revision(
+        &self,
+        path: PathBuf,
+        revision: String,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::BufferRevision { path, revision }, f);
+    }
+
     pub fn create_file(&self, path: PathBuf, f: impl ProxyCallback + 'static) {
         self.request_async(ProxyRequest::CreateFile { path }, f);
     }
@@ -781,12 +1046,36 @@ pub fn save_buffer_as(
         );
     }
 
+    pub fn reopen_with_encoding(
+        &self,
+        path: PathBuf,
+        encoding: String,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::ReopenWithEncoding { path, encoding }, f);
+    }
+
+    pub fn set_encoding(
+        &self,
+        path: PathBuf,
+        encoding: String,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::SetEncoding { path, encoding }, f);
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn global_search(
         &self,
         pattern: String,
         case_sensitive: bool,
         whole_word: bool,
         is_regex: bool,
+        include: Vec<String>,
+        exclude: Vec<String>,
+        search_ignored: bool,
+        max_results: Option<usize>,
+        search_id: u64,
         f: impl ProxyCallback + 'static,
     ) {
         self.request_async(
@@ -795,6 +1084,35 @@ pub fn global_search(
                 case_sensitive,
                 whole_word,
                 is_regex,
+                include,
+                exclude,
+                search_ignored,
+                max_results,
+                search_id,
+            },
+            f,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn global_replace(
+        &self,
+        pattern: String,
+        replacement: String,
+        case_sensitive: bool,
+        whole_word: bool,
+        is_regex: bool,
+        matches: IndexMap<PathBuf, Vec<SearchMatch>>,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(
+            ProxyRequest::GlobalReplace {
+                pattern,
+                replacement,
+                case_sensitive,
+                whole_word,
+                is_regex,
+                matches,
             },
             f,
         );
@@ -1052,6 +1370,29 @@ pub fn git_get_remote_file_url(
         self.request_async(ProxyRequest::GitGetRemoteFileUrl { file }, f);
     }
 
+    pub fn git_blame(&self, path: PathBuf, f: impl ProxyCallback + 'static) {
+        self.request_async(ProxyRequest::GitBlame { path }, f);
+    }
+
+    pub fn git_log(
+        &self,
+        path: Option<PathBuf>,
+        query: String,
+        limit: usize,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::GitLog { path, query, limit }, f);
+    }
+
+    pub fn git_commit_files(
+        &self,
+        commit: String,
+        path: Option<PathBuf>,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::GitCommitFiles { commit, path }, f);
+    }
+
     pub fn rename(
         &self,
         path: PathBuf,
@@ -1101,6 +1442,10 @@ pub fn update_plugin_configs(
         self.notification(ProxyNotification::UpdatePluginConfigs { configs });
     }
 
+    pub fn update_lsp_servers(&self, servers: HashMap<String, LspServerConfig>) {
+        self.notification(ProxyNotification::UpdateLspServers { servers });
+    }
+
     pub fn git_discard_files_changes(&self, files: Vec<PathBuf>) {
         self.notification(ProxyNotification::GitDiscardFilesChanges { files });
     }
@@ -1109,6 +1454,34 @@ pub fn git_discard_workspace_changes(&self) {
         self.notification(ProxyNotification::GitDiscardWorkspaceChanges {});
     }
 
+    pub fn git_stage_files(&self, files: Vec<PathBuf>) {
+        self.notification(ProxyNotification::GitStageFiles { files });
+    }
+
+    pub fn git_unstage_files(&self, files: Vec<PathBuf>) {
+        self.notification(ProxyNotification::GitUnstageFiles { files });
+    }
+
+    pub fn git_stage_hunk(&self, path: PathBuf, line: usize) {
+        self.notification(ProxyNotification::GitStageHunk { path, line });
+    }
+
+    pub fn git_unstage_hunk(&self, path: PathBuf, line: usize) {
+        self.notification(ProxyNotification::GitUnstageHunk { path, line });
+    }
+
+    pub fn git_fetch(&self) {
+        self.notification(ProxyNotification::GitFetch {});
+    }
+
+    pub fn git_pull(&self, rebase: Option<bool>) {
+        self.notification(ProxyNotification::GitPull { rebase });
+    }
+
+    pub fn git_push(&self) {
+        self.notification(ProxyNotification::GitPush {});
+    }
+
     pub fn get_selection_range(
         &self,
         path: PathBuf,
@@ -1211,6 +1584,29 @@ pub fn dap_get_scopes(
     ) {
         self.request_async(ProxyRequest::DapGetScopes { dap_id, frame_id }, f);
     }
+
+    pub fn dap_evaluate(
+        &self,
+        dap_id: DapId,
+        expression: String,
+        frame_id: Option<usize>,
+        context: dap_types::EvaluateContext,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(
+            ProxyRequest::DapEvaluate {
+                dap_id,
+                expression,
+                frame_id,
+                context,
+            },
+            f,
+        );
+    }
+
+    pub fn dap_list_processes(&self, f: impl ProxyCallback + 'static) {
+        self.request_async(ProxyRequest::DapListProcesses {}, f);
+    }
 }
 
 impl Default for ProxyRpcHandler {
@@ -7,7 +7,25 @@ pub struct DiffInfo {
     pub head: String,
     pub branches: Vec<String>,
     pub tags: Vec<String>,
+    /// The changes in the working tree that are not staged
     pub diffs: Vec<FileDiff>,
+    /// The changes staged in the index, which the next commit is made of
+    #[serde(default)]
+    pub staged: Vec<FileDiff>,
+    /// How many commits the current branch is ahead of and behind its upstream,
+    /// if it has one
+    #[serde(default)]
+    pub ahead_behind: Option<(usize, usize)>,
+    /// The saved stashes, the most recent first
+    #[serde(default)]
+    pub stashes: Vec<GitStash>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct GitStash {
+    /// The position in the stash list, which is `0` for the most recent stash
+    pub index: usize,
+    pub message: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -16,6 +34,9 @@ pub enum FileDiff {
     Added(PathBuf),
     Deleted(PathBuf),
     Renamed(PathBuf, PathBuf),
+    /// A file a merge, rebase or stash left conflicts in, which aren't resolved
+    /// until the file is staged
+    Conflicted(PathBuf),
 }
 
 impl FileDiff {
@@ -24,7 +45,19 @@ pub fn path(&self) -> &PathBuf {
             FileDiff::Modified(p)
             | FileDiff::Added(p)
             | FileDiff::Deleted(p)
-            | FileDiff::Renamed(_, p) => p,
+            | FileDiff::Renamed(_, p)
+            | FileDiff::Conflicted(p) => p,
+        }
+    }
+
+    /// All the paths changed, which for a rename are both the new and old path
+    pub fn paths(&self) -> Vec<PathBuf> {
+        match self {
+            FileDiff::Modified(p)
+            | FileDiff::Added(p)
+            | FileDiff::Deleted(p)
+            | FileDiff::Conflicted(p) => vec![p.clone()],
+            FileDiff::Renamed(new, old) => vec![new.clone(), old.clone()],
         }
     }
 
@@ -34,6 +67,7 @@ pub fn kind(&self) -> FileDiffKind {
             FileDiff::Added(_) => FileDiffKind::Added,
             FileDiff::Deleted(_) => FileDiffKind::Deleted,
             FileDiff::Renamed(_, _) => FileDiffKind::Renamed,
+            FileDiff::Conflicted(_) => FileDiffKind::Conflicted,
         }
     }
 }
@@ -44,4 +78,63 @@ pub enum FileDiffKind {
     Added,
     Deleted,
     Renamed,
+    Conflicted,
+}
+
+/// Lines of a file last changed by the same commit
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlameHunk {
+    /// The first line of the hunk, 0-based
+    pub start_line: usize,
+    pub line_count: usize,
+    /// The commit id, `None` for the lines that aren't committed yet
+    pub commit: Option<String>,
+    pub author: String,
+    pub email: String,
+    /// The time of the commit, in seconds since the unix epoch
+    pub time: i64,
+    pub message: String,
+}
+
+impl BlameHunk {
+    pub fn contains_line(&self, line: usize) -> bool {
+        self.start_line <= line && line < self.start_line + self.line_count
+    }
+
+    /// The abbreviated commit id
+    pub fn short_commit(&self) -> Option<&str> {
+        self.commit
+            .as_deref()
+            .map(|commit| &commit[..commit.len().min(7)])
+    }
+
+    /// The first line of the commit message
+    pub fn summary(&self) -> &str {
+        self.message.lines().next().unwrap_or_default()
+    }
+}
+
+/// A commit in the history of a repository or of a file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GitCommit {
+    pub id: String,
+    pub author: String,
+    pub email: String,
+    /// The time of the commit, in seconds since the unix epoch
+    pub time: i64,
+    pub message: String,
+    /// The path the file had in this commit, for the history of a file
+    pub path: Option<PathBuf>,
+}
+
+impl GitCommit {
+    /// The abbreviated commit id
+    pub fn short_id(&self) -> &str {
+        &self.id[..self.id.len().min(7)]
+    }
+
+    /// The first line of the commit message
+    pub fn summary(&self) -> &str {
+        self.message.lines().next().unwrap_or_default()
+    }
 }